@@ -1,8 +1,8 @@
-use crate::error::Result;
-use crate::schema::{Engram, Connection};
-use crate::storage::Storage;
-use crate::index::SearchIndex;
-use crate::query::{EngramQuery, QueryService};
+use engram_lite::error::Result;
+use engram_lite::schema::{Engram, Connection};
+use engram_lite::storage::{Embedding, SerializationFormat, Storage};
+use engram_lite::index::{ImportanceIndex, SearchIndex};
+use engram_lite::query::{EngramQuery, QueryService};
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use rand::{Rng, SeedableRng};
@@ -129,7 +129,7 @@ pub fn benchmark_engram_retrieval(
         let id = &engram_ids[idx];
         
         // Retrieve the engram
-        if let Some(_) = storage.get_engram(id)? {
+        if storage.get_engram(id)?.is_some() {
             found += 1;
         }
     }
@@ -199,7 +199,7 @@ pub fn benchmark_connections(
     let mut found = 0;
     
     for id in &connection_ids {
-        if let Some(_) = storage.get_connection(id)? {
+        if storage.get_connection(id)?.is_some() {
             found += 1;
         }
     }
@@ -296,6 +296,122 @@ pub fn benchmark_traversal(
         .with_metric("avg_connections_per_traversal", avg_connections))
 }
 
+/// Benchmark for importance index ingestion and access tracking.
+///
+/// Inserts `count` engrams with random importance scores and records an access for each.
+/// Run it at increasing sizes to check that the average cost per operation stays
+/// logarithmic rather than growing linearly with the index size.
+pub fn benchmark_importance_index(count: usize) -> Result<BenchmarkResult> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let engrams: Vec<Engram> = (0..count)
+        .map(|i| {
+            let mut engram = Engram::new(format!("Importance engram {}", i), "benchmark".to_string(), 0.8, None);
+            engram.importance = rng.gen_range(0.0..1.0);
+            engram
+        })
+        .collect();
+    
+    let mut index = ImportanceIndex::new();
+    let start = Instant::now();
+    
+    for engram in &engrams {
+        index.add_engram(engram)?;
+    }
+    let insert_time = start.elapsed();
+    
+    for engram in &engrams {
+        index.record_access(&engram.id)?;
+    }
+    let total_time = start.elapsed();
+    let access_time = total_time - insert_time;
+    
+    let iterations = count * 2;
+    let ops_per_sec = iterations as f64 / total_time.as_secs_f64();
+    
+    Ok(BenchmarkResult::new(&format!("Importance Index ({} engrams)", count), iterations, total_time)
+        .with_metric("operations_per_second", ops_per_sec)
+        .with_metric("avg_insert_us", insert_time.as_secs_f64() * 1e6 / count as f64)
+        .with_metric("avg_record_access_us", access_time.as_secs_f64() * 1e6 / count as f64))
+}
+
+/// Benchmark rebuilding a `SearchIndex` sequentially versus with `add_engrams_parallel`.
+///
+/// Records the time of each rebuild over the same `count` engrams and the parallel speedup.
+pub fn benchmark_index_rebuild(count: usize) -> Result<BenchmarkResult> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let topics = ["climate", "ocean", "glacier", "policy", "energy", "forest", "research", "model"];
+    let engrams: Vec<Engram> = (0..count)
+        .map(|i| {
+            let content = format!(
+                "Engram {} about {} and {} observed in the {} study",
+                i,
+                topics[rng.gen_range(0..topics.len())],
+                topics[rng.gen_range(0..topics.len())],
+                topics[rng.gen_range(0..topics.len())],
+            );
+            let mut engram = Engram::new(content, "benchmark".to_string(), rng.gen_range(0.0..1.0), None);
+            engram.importance = rng.gen_range(0.0..1.0);
+            engram
+        })
+        .collect();
+    
+    let start = Instant::now();
+    let mut sequential = SearchIndex::new();
+    for engram in &engrams {
+        sequential.add_engram(engram)?;
+    }
+    let sequential_time = start.elapsed();
+    
+    let start = Instant::now();
+    let mut parallel = SearchIndex::new();
+    parallel.add_engrams_parallel(&engrams)?;
+    let parallel_time = start.elapsed();
+    
+    Ok(BenchmarkResult::new(&format!("Index Rebuild ({} engrams)", count), count, parallel_time)
+        .with_metric("sequential_ms", sequential_time.as_secs_f64() * 1e3)
+        .with_metric("parallel_ms", parallel_time.as_secs_f64() * 1e3)
+        .with_metric("speedup", sequential_time.as_secs_f64() / parallel_time.as_secs_f64()))
+}
+
+/// Compare record sizes and encode/decode time of JSON and MessagePack for engrams and embeddings
+pub fn benchmark_serialization_formats(count: usize, dimensions: usize) -> Result<Vec<BenchmarkResult>> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let engrams: Vec<Engram> = (0..count)
+        .map(|i| Engram::new(format!("Serialization engram {}", i), "benchmark".to_string(), rng.gen_range(0.0..1.0), None))
+        .collect();
+    let embeddings: Vec<Embedding> = (0..count)
+        .map(|_| {
+            let vector = (0..dimensions).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            Embedding::create(vector, "benchmark".to_string(), dimensions, HashMap::new())
+        })
+        .collect();
+    
+    let mut results = Vec::new();
+    
+    for format in [SerializationFormat::Json, SerializationFormat::MessagePack] {
+        let start = Instant::now();
+        let mut engram_bytes = 0;
+        let mut embedding_bytes = 0;
+        
+        for (engram, embedding) in engrams.iter().zip(&embeddings) {
+            let encoded = format.encode(engram)?;
+            engram_bytes += encoded.len();
+            let _: Engram = SerializationFormat::decode(&encoded)?;
+            
+            let encoded = format.encode(embedding)?;
+            embedding_bytes += encoded.len();
+            let _: Embedding = SerializationFormat::decode(&encoded)?;
+        }
+        let total_time = start.elapsed();
+        
+        results.push(BenchmarkResult::new(&format!("Serialization ({:?})", format), count * 2, total_time)
+            .with_metric("avg_engram_bytes", engram_bytes as f64 / count as f64)
+            .with_metric("avg_embedding_bytes", embedding_bytes as f64 / count as f64));
+    }
+    
+    Ok(results)
+}
+
 /// Run all benchmarks and return the results
 pub fn run_all_benchmarks(storage: &Storage, index: &SearchIndex) -> Result<Vec<BenchmarkResult>> {
     println!("Starting benchmarks...");
@@ -331,13 +447,32 @@ pub fn run_all_benchmarks(storage: &Storage, index: &SearchIndex) -> Result<Vec<
     println!("Benchmarking graph traversal...");
     let traversal_result = benchmark_traversal(&service, &engram_ids, 500)?;
     
-    Ok(vec![
+    // Importance index scaling: per-operation cost should barely move across a 10x size increase
+    println!("Benchmarking importance index scaling...");
+    let importance_small = benchmark_importance_index(10_000)?;
+    let importance_large = benchmark_importance_index(100_000)?;
+    
+    // Sequential versus parallel search index rebuild
+    println!("Benchmarking index rebuild...");
+    let rebuild_result = benchmark_index_rebuild(100_000)?;
+    
+    // Record sizes under each storage serialization format
+    println!("Benchmarking serialization formats...");
+    let serialization_results = benchmark_serialization_formats(engram_count, 384)?;
+    
+    let mut results = vec![
         storage_result,
         retrieval_result,
         connection_result,
         search_result,
         traversal_result,
-    ])
+        importance_small,
+        importance_large,
+        rebuild_result,
+    ];
+    results.extend(serialization_results);
+    
+    Ok(results)
 }
 
 #[cfg(test)]
@@ -1,12 +1,18 @@
+use engram_lite::embedding::{Embedding, EmbeddingService, HnswIndex};
 use engram_lite::error::Result;
 use engram_lite::graph::MemoryGraph;
+use engram_lite::index::{ForgettingPolicy, SearchIndex};
+use engram_lite::query::{recompute_importance_from_graph, QueryEngine};
 use engram_lite::schema::{Agent, Collection, Connection, Engram};
 use engram_lite::storage::Storage;
+use engram_lite::utils::{CorsPolicy, RateLimiter};
+use engram_lite::vector_search::{embed_missing_rate_limited, verify_vector_index, DEFAULT_EMBED_BATCH_SIZE};
 use std::collections::HashSet;
 use std::env;
 use std::io::{self, Write};
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
 
 struct EngramCli {
     storage: Storage,
@@ -66,6 +72,10 @@ impl EngramCli {
                 "filter-by-confidence" => self.filter_by_confidence(args)?,
                 "stats" => self.show_stats()?,
                 "compact" => self.compact_database(args)?,
+                "forget" => self.forget(args)?,
+                "pin" => self.set_pinned(args, true)?,
+                "unpin" => self.set_pinned(args, false)?,
+                "recompute-importance" => self.recompute_importance(args)?,
                 "refresh" => self.refresh_memory_graph()?,
                 "export" => self.export(args)?,
                 "import" => self.import(args)?,
@@ -109,6 +119,11 @@ impl EngramCli {
         println!("  delete-context <id>                    - Delete a context");
         println!("  stats                                  - Show system statistics");
         println!("  compact                                - Compact the database to reclaim space");
+        println!("  forget <max-importance>;<max-access>;<idle-days>[;<max-items>] [--apply [--compact]]");
+        println!("                                         - List forgetting candidates with reasons (dry run unless --apply)");
+        println!("  pin <id>                               - Exempt an engram from forgetting");
+        println!("  unpin <id>                             - Make a pinned engram forgettable again");
+        println!("  recompute-importance [<blend>]         - Blend importance with graph centrality (default blend 0.5)");
         println!("  refresh                                - Reload memory graph from storage");
         
         println!("\nImport/Export Commands:");
@@ -155,6 +170,28 @@ impl EngramCli {
             println!("Source: {}", engram.source);
             println!("Confidence: {}", engram.confidence);
             println!("Timestamp: {}", engram.timestamp);
+            
+            let mut collection_names = Vec::new();
+            for collection_id in self.storage.collections_for_engram(&engram.id)? {
+                if let Some(collection) = self.storage.get_collection(&collection_id)? {
+                    collection_names.push(collection.name);
+                }
+            }
+            collection_names.sort();
+            if !collection_names.is_empty() {
+                println!("Collections: {}", collection_names.join(", "));
+            }
+            
+            let mut context_names = Vec::new();
+            for context_id in self.storage.contexts_for_engram(&engram.id)? {
+                if let Some(context) = self.storage.get_context(&context_id)? {
+                    context_names.push(context.name);
+                }
+            }
+            context_names.sort();
+            if !context_names.is_empty() {
+                println!("Contexts: {}", context_names.join(", "));
+            }
         } else {
             println!("Engram not found with ID: {}", id);
         }
@@ -603,6 +640,151 @@ impl EngramCli {
         Ok(())
     }
     
+    fn recompute_importance(&mut self, args: &str) -> Result<()> {
+        let blend = match args.trim() {
+            "" => 0.5,
+            value => match value.parse::<f64>() {
+                Ok(blend) => blend,
+                Err(_) => {
+                    println!("Usage: recompute-importance [<blend>]");
+                    return Ok(());
+                }
+            },
+        };
+        
+        let mut engrams = Vec::new();
+        for id in self.storage.list_engrams()? {
+            if let Some(engram) = self.storage.get_engram(&id)? {
+                engrams.push(engram);
+            }
+        }
+        let mut index = SearchIndex::new();
+        index.add_engrams_parallel(&engrams)?;
+        
+        let updated = recompute_importance_from_graph(&self.storage, &mut index, blend)?;
+        println!("Updated importance of {} engrams from graph centrality (blend {})", updated, blend);
+        
+        // Keep the in-memory graph in step with the new scores
+        self.refresh_memory_graph()
+    }
+    
+    fn set_pinned(&mut self, args: &str, pinned: bool) -> Result<()> {
+        let id = args.trim();
+        if id.is_empty() {
+            println!("Usage: {} <engram-id>", if pinned { "pin" } else { "unpin" });
+            return Ok(());
+        }
+        
+        let mut engram = match self.storage.get_engram(&id.to_string())? {
+            Some(engram) => engram,
+            None => {
+                println!("Engram not found with ID: {}", id);
+                return Ok(());
+            }
+        };
+        
+        // Forgetting indexes are rebuilt from storage, so storing the flag is enough
+        engram.set_pinned(pinned);
+        self.storage.put_engram(&engram)?;
+        
+        if pinned {
+            println!("Pinned engram {}; it will never be forgotten", id);
+        } else {
+            println!("Unpinned engram {}", id);
+        }
+        Ok(())
+    }
+    
+    fn forget(&mut self, args: &str) -> Result<()> {
+        let usage = "Usage: forget <max-importance>;<max-access>;<idle-days>[;<max-items>] [--apply [--compact]]";
+        
+        let mut args = args.trim();
+        let mut apply = false;
+        let mut compact = false;
+        loop {
+            if let Some(rest) = args.strip_suffix("--apply") {
+                apply = true;
+                args = rest.trim();
+            } else if let Some(rest) = args.strip_suffix("--compact") {
+                compact = true;
+                args = rest.trim();
+            } else {
+                break;
+            }
+        }
+        
+        let parts: Vec<&str> = args.split(';').map(|part| part.trim()).collect();
+        if parts.len() < 3 {
+            println!("{}", usage);
+            return Ok(());
+        }
+        
+        let (max_importance, max_access_count, min_idle_days) = match (
+            parts[0].parse::<f64>(),
+            parts[1].parse::<u32>(),
+            parts[2].parse::<u64>(),
+        ) {
+            (Ok(importance), Ok(access), Ok(idle)) => (importance, access, idle),
+            _ => {
+                println!("{}", usage);
+                return Ok(());
+            }
+        };
+        let max_items = parts.get(3).and_then(|value| value.parse::<usize>().ok()).unwrap_or(100);
+        
+        // Build a search index over the stored engrams for the policy to evaluate
+        let mut engrams = Vec::new();
+        for id in self.storage.list_engrams()? {
+            if let Some(engram) = self.storage.get_engram(&id)? {
+                engrams.push(engram);
+            }
+        }
+        let mut index = SearchIndex::new();
+        index.add_engrams_parallel(&engrams)?;
+        
+        let mut engine = QueryEngine::new(&self.storage, &index);
+        engine.set_forgetting_policy(Some(ForgettingPolicy::Hybrid {
+            max_importance,
+            max_access_count,
+            min_idle_seconds: min_idle_days * 24 * 60 * 60,
+            max_items,
+        }));
+        
+        let candidates = engine.explain_forgetting_candidates();
+        if candidates.is_empty() {
+            println!("No engrams match the forgetting policy");
+            return Ok(());
+        }
+        
+        println!("Forgetting candidates ({}):", candidates.len());
+        for candidate in &candidates {
+            println!("  {} (score {:.2}): {}", candidate.id, candidate.score, candidate.reason);
+        }
+        
+        if !apply {
+            println!("Dry run: no engrams were deleted. Re-run with --apply to forget them.");
+            return Ok(());
+        }
+        
+        if compact {
+            let (forgotten, report) = engine.apply_forgetting_with_compaction()?;
+            println!("Forgot {} engrams", forgotten);
+            println!(
+                "Compaction reclaimed {} bytes ({} -> {})",
+                report.bytes_reclaimed(), report.bytes_before, report.bytes_after
+            );
+        } else {
+            let forgotten = engine.apply_forgetting()?;
+            println!("Forgot {} engrams", forgotten);
+        }
+        drop(engine);
+        
+        // Refresh the memory graph to maintain consistency
+        self.refresh_memory_graph()?;
+        
+        Ok(())
+    }
+    
     fn export(&self, args: &str) -> Result<()> {
         let parts: Vec<&str> = args.split(';').collect();
         let file_path = parts[0].trim();
@@ -727,19 +909,106 @@ fn show_help() {
     println!("  web             Start web server mode");
     println!("  demo            Populate database with demo data for a multi-agent coding project");
     println!("  docs            Start documentation server with mkdocs");
+    println!("  verify          Report unreadable records and check the vector index against stored embeddings");
+    println!("  embed-missing   Generate and store embeddings for engrams that have none");
     println!("  help            Show this help message");
     
     println!("\nOPTIONS:");
     println!("  --db-path PATH  Path to the database directory (default: ./engram_db)");
     println!("  --port PORT     Port for web server (default: 3000)");
+    println!("  --dev           With web: accept cross-origin requests from any origin");
+    println!("  --cors-origins LIST  With web: comma-separated origins allowed cross-origin");
+    println!("                  (default: ENGRAM_CORS_ORIGINS, otherwise none)");
+    println!("  --fix           With verify: delete embeddings of engrams that no longer exist");
+    println!("  --batch-size N  With embed-missing: engrams per embedding call (default: 32)");
+    println!("  --rate-limit N  With embed-missing: at most N engrams embedded per minute");
     
     println!("\nEXAMPLES:");
     println!("  engramlt                   # Start CLI mode with default settings");
     println!("  engramlt cli --db-path /path/to/db");
     println!("  engramlt tui               # Start TUI mode with default settings");
     println!("  engramlt web --port 8080   # Start web server on port 8080");
+    println!("  engramlt web --dev         # Start web server accepting requests from any origin");
     println!("  engramlt demo --db-path /path/to/db   # Populate with demo data");
     println!("  engramlt docs              # Start documentation server");
+    println!("  engramlt verify --fix      # Verify embeddings and clean up orphans");
+    println!("  engramlt embed-missing --rate-limit 60   # Backfill embeddings, 60 per minute");
+}
+
+fn verify_database(db_path: &str, fix: bool) -> Result<()> {
+    let storage = Storage::new(db_path)?;
+    
+    let health = storage.scan_health()?;
+    println!("Record health:");
+    println!("  Records scanned:      {}", health.records_scanned);
+    println!("  Unreadable records:   {}", health.unreadable_count());
+    for (cf, records) in &health.unreadable {
+        for record in records {
+            println!("    [{}] {}: {}", cf, record.key, record.error);
+        }
+    }
+    
+    // Build the vector index the same way it is built at startup: from the stored
+    // embeddings of every live engram
+    let mut index: Option<HnswIndex> = None;
+    for id in storage.list_engrams()? {
+        if let Some(stored) = storage.get_embedding(&id)? {
+            let embedding = Embedding {
+                vector: stored.vector,
+                model: stored.model,
+                dimensions: stored.dimensions,
+                metadata: stored.metadata,
+            };
+            let index = index.get_or_insert_with(|| HnswIndex::new(embedding.dimensions));
+            if let Err(e) = index.add(&id, embedding) {
+                println!("  Could not index embedding for {}: {}", id, e);
+            }
+        }
+    }
+    
+    let index = index.unwrap_or_else(|| HnswIndex::new(0));
+    let report = verify_vector_index(&storage, &index)?;
+    
+    println!("Vector index verification:");
+    println!("  Indexed embeddings:   {}", index.len());
+    println!("  Missing from index:   {}", report.missing_from_index.len());
+    for id in &report.missing_from_index {
+        println!("    {}", id);
+    }
+    println!("  Missing from storage: {}", report.missing_from_storage.len());
+    for id in &report.missing_from_storage {
+        println!("    {}", id);
+    }
+    
+    let orphaned = storage.list_orphaned_embeddings()?;
+    println!("  Orphaned embeddings:  {}", orphaned.len());
+    
+    if fix && !orphaned.is_empty() {
+        for id in &orphaned {
+            storage.delete_embedding(id)?;
+            storage.delete_reduced_embedding(id)?;
+        }
+        println!("Deleted {} orphaned embeddings", orphaned.len());
+    } else if report.is_consistent() && orphaned.is_empty() {
+        println!("Vector index is consistent with storage");
+    }
+    
+    Ok(())
+}
+
+fn embed_missing_embeddings(db_path: &str, batch_size: usize, per_minute: Option<usize>) -> Result<()> {
+    let storage = Storage::new(db_path)?;
+    let service = EmbeddingService::new();
+    let rate_limiter = per_minute
+        .filter(|limit| *limit > 0)
+        .map(|limit| RateLimiter::new(limit as u32, Duration::from_secs(60)));
+    
+    // Indexes built at startup load stored embeddings, so this index only needs the new ones
+    let mut index = HnswIndex::new(service.get_dimensions());
+    let embedded = embed_missing_rate_limited(&storage, &service, &mut index, batch_size, rate_limiter.as_ref())?;
+    
+    println!("Embedded {} engrams", embedded);
+    Ok(())
 }
 
 fn run_docs_server() -> Result<()> {
@@ -861,7 +1130,19 @@ fn main() -> Result<()> {
         "web" => {
             // Start Web server mode
             println!("Starting Web server mode...");
-            if let Err(e) = web::start_server(&db_path, port) {
+            // Allow every origin only in development; otherwise use the configured allowlist
+            let cors_policy = if args.iter().any(|arg| arg == "--dev") {
+                CorsPolicy::permissive()
+            } else {
+                let origins = args.iter()
+                    .position(|arg| arg == "--cors-origins")
+                    .and_then(|i| args.get(i + 1));
+                match origins {
+                    Some(origins) => CorsPolicy::from_env().with_allowed_origins(CorsPolicy::parse_list(origins)),
+                    None => CorsPolicy::from_env(),
+                }
+            };
+            if let Err(e) = web::start_server(&db_path, port, cors_policy) {
                 eprintln!("Error: {}", e);
             }
         },
@@ -878,6 +1159,22 @@ fn main() -> Result<()> {
                 eprintln!("Error starting documentation server: {}", e);
             }
         },
+        "verify" => {
+            let fix = args.iter().any(|arg| arg == "--fix");
+            if let Err(e) = verify_database(&db_path, fix) {
+                eprintln!("Error verifying database: {}", e);
+            }
+        },
+        "embed-missing" => {
+            let option = |name: &str| args.iter()
+                .position(|arg| arg == name)
+                .and_then(|i| args.get(i + 1))
+                .and_then(|value| value.parse::<usize>().ok());
+            let batch_size = option("--batch-size").unwrap_or(DEFAULT_EMBED_BATCH_SIZE);
+            if let Err(e) = embed_missing_embeddings(&db_path, batch_size, option("--rate-limit")) {
+                eprintln!("Error embedding engrams: {}", e);
+            }
+        },
         "help" => {
             show_help();
         },
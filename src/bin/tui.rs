@@ -8,6 +8,8 @@ use engram_lite::{
     error::Result,
 };
 #[cfg(feature = "tui")]
+use engram_lite::utils;
+#[cfg(feature = "tui")]
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout, Rect},
@@ -122,12 +124,7 @@ impl EngramTui {
         
         // Load connections
         let connection_ids = self.storage.list_connections()?;
-        self.connections.clear();
-        for id in connection_ids {
-            if let Some(connection) = self.storage.get_connection(&id)? {
-                self.connections.push(connection);
-            }
-        }
+        self.connections = self.storage.get_connections(&connection_ids)?.into_iter().flatten().collect();
         
         // Load collections
         let collection_ids = self.storage.list_collections()?;
@@ -483,7 +480,8 @@ fn render_dashboard(f: &mut Frame, app: &EngramTui, area: Rect) {
         .map(|engram| {
             ListItem::new(Line::from(vec![
                 Span::raw(format!("[{}] ", engram.id)),
-                Span::styled(&engram.content, Style::default().fg(Color::Yellow)),
+                Span::styled(utils::truncate_display(&engram.content, utils::DEFAULT_DISPLAY_LENGTH),
+                    Style::default().fg(Color::Yellow)),
             ]))
         })
         .collect();
@@ -536,11 +534,7 @@ fn render_engrams(f: &mut Frame, app: &EngramTui, area: Rect) {
             };
             ListItem::new(Line::from(vec![
                 Span::styled(format!("[{}] {}", engram.id, 
-                    if engram.content.len() > 30 {
-                        format!("{}...", &engram.content[..30])
-                    } else {
-                        engram.content.clone()
-                    }
+                    utils::truncate_display(&engram.content, utils::DEFAULT_DISPLAY_LENGTH)
                 ), style),
             ]))
         })
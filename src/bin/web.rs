@@ -1,23 +1,38 @@
 use actix_cors::Cors;
 use actix_files as fs;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder, middleware};
-use engram_lite::error::Result as EngramResult;
+use actix_web::{http::header, web, App, HttpRequest, HttpResponse, HttpServer, Responder, middleware};
+use engram_lite::error::{EngramError, Result as EngramResult};
 use engram_lite::graph::MemoryGraph;
-use engram_lite::storage::Storage;
-use engram_lite::schema::{Engram, Connection, Collection, Agent};
-use engram_lite::index::SearchIndex;
+use engram_lite::storage::{BatchDeleteReport, EngramExpansion, PaginationMeta, Storage, StorageConfig};
+use engram_lite::schema::{Engram, EngramId, Connection, Collection, Agent, Context, SchemaPolicy};
+use engram_lite::index::{sorted_ids, ForgettingPolicy, SearchIndex};
+use engram_lite::query::{dedupe_by_content, explain_relationship, recompute_importance_from_graph, CancellationToken, EngramQuery, QueryEngine, RetrievalWeights, TraversalEngine, DEFAULT_MAX_RESULT_SIZE, MAX_TRAVERSAL_DEPTH};
+use engram_lite::embedding::{EmbeddingModel, EmbeddingService};
+use engram_lite::utils::{self, CorsPolicy};
+use engram_lite::vector_search::{HybridQuery, HybridSearchEngine, QueryExpander, VectorIndex, VectorQuery, DEFAULT_MIN_SIMILARITY};
 use serde::{Serialize, Deserialize};
-use std::collections::HashSet;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 use tera::{Tera, Context as TeraContext};
 
+/// Default number of `/api/embed` calls allowed per minute, overridable with ENGRAM_EMBED_RATE_LIMIT
+const DEFAULT_EMBED_RATE_LIMIT: u32 = 60;
+
+/// Page size for paginated listings that are given a cursor but no limit
+const DEFAULT_PAGE_SIZE: usize = 50;
+
 // Application state
 struct AppState {
-    db_path: String,
     storage: Arc<Storage>,
     memory_graph: Arc<RwLock<MemoryGraph>>,
     search_index: Arc<RwLock<SearchIndex>>,
+    vector_index: Arc<VectorIndex>,
     templates: Tera,
+    max_result_size: usize,
+    /// One service per model so the embedding cache is shared across requests
+    embedding_services: Mutex<HashMap<EmbeddingModel, Arc<EmbeddingService>>>,
+    /// Bounds calls to `/api/embed`, which may hit a paid API
+    embed_rate_limiter: utils::RateLimiter,
 }
 
 // Define data transfer objects for API
@@ -26,6 +41,12 @@ struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
     error: Option<String>,
+    /// Problems that did not fail the request, e.g. records that could not be read
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    /// Position of this page in a paginated listing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<PaginationMeta>,
 }
 
 // Request DTOs
@@ -33,8 +54,13 @@ struct ApiResponse<T> {
 struct CreateEngramRequest {
     content: String,
     source: String,
-    confidence: f64,
+    /// Certainty of the content; defaults to the storage's schema policy
+    confidence: Option<f64>,
     metadata: Option<serde_json::Map<String, serde_json::Value>>,
+    /// ID of the agent creating the engram
+    created_by: Option<String>,
+    /// Seconds until the engram expires and is removed by the TTL sweep
+    ttl_seconds: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -42,8 +68,13 @@ struct CreateConnectionRequest {
     source_id: String,
     target_id: String,
     connection_type: String,
-    weight: f64,
+    /// Strength of the connection; defaults to the embedding similarity of the two engrams
+    weight: Option<f64>,
+    /// Certainty that the relationship exists; defaults to the storage's schema policy
+    confidence: Option<f64>,
     metadata: Option<serde_json::Map<String, serde_json::Value>>,
+    /// ID of the agent creating the connection
+    created_by: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -67,6 +98,138 @@ struct QueryRequest {
     source: Option<String>,
     min_confidence: Option<f64>,
     limit: Option<usize>,
+    /// Rank results by combined relevance, importance and recency with these weights
+    weights: Option<RetrievalWeights>,
+}
+
+#[derive(Deserialize)]
+struct QueryOptions {
+    /// Expand the text query with keywords from semantically similar engrams
+    expand: Option<bool>,
+    /// Abort the query after this many milliseconds
+    timeout_ms: Option<u64>,
+    /// Collapse ranked results with identical content into the highest-scored one
+    dedupe: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct RetrieveRequest {
+    query: String,
+    /// Token budget for the assembled context (default 2000)
+    max_tokens: Option<usize>,
+    /// Minimum hybrid search score for an engram to be included (default 0.0)
+    min_score: Option<f32>,
+    /// Maximum number of search results considered (default 20)
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct RetrieveOptions {
+    /// HNSW search width for the semantic component; higher is more accurate but slower
+    accuracy: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ConnectionsQuery {
+    min_weight: Option<f64>,
+    max_weight: Option<f64>,
+    min_confidence: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct EngramMembershipsResponse {
+    collections: Vec<Collection>,
+    contexts: Vec<Context>,
+}
+
+#[derive(Deserialize)]
+struct BatchDeleteRequest {
+    ids: Vec<String>,
+    /// Also remove every connection to or from a deleted engram (default true)
+    cascade: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct BatchDeleteResponse {
+    engrams_deleted: usize,
+    connections_deleted: usize,
+    not_found: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct EngramDetailQuery {
+    /// Comma-separated related records to embed: connections, collections
+    include: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    /// Maximum number of items per page (default DEFAULT_PAGE_SIZE)
+    limit: Option<usize>,
+    /// The `meta.cursor` of the previous page; also accepted as `after`
+    #[serde(alias = "after")]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SimilarQuery {
+    /// Maximum number of results (default 10)
+    limit: Option<usize>,
+    /// Similarity floor; defaults to the server's ENGRAM_MIN_SIMILARITY
+    min_similarity: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct SimilarEngram {
+    engram: Engram,
+    similarity: f32,
+}
+
+#[derive(Deserialize)]
+struct TraverseQuery {
+    from: String,
+    to: String,
+    /// Maximum number of hops (default 3, capped at MAX_TRAVERSAL_DEPTH)
+    max_depth: Option<usize>,
+    /// Comma-separated relationship types to follow (default: all)
+    rel_types: Option<String>,
+    /// Abort the traversal after this many milliseconds
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ExplainQuery {
+    a: String,
+    b: String,
+}
+
+#[derive(Deserialize)]
+struct PruneConnectionsRequest {
+    /// Connections with a weight strictly below this are removed
+    threshold: f64,
+}
+
+#[derive(Serialize)]
+struct PruneConnectionsResponse {
+    pruned: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ExpireEngramsResponse {
+    expired: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbedRequest {
+    text: String,
+    /// Model short name ("e5", "gte", "jina") or HuggingFace hub name (default "e5")
+    model: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RecomputeImportanceRequest {
+    /// Weight of graph centrality in the new importance, 0.0..=1.0 (default 0.5)
+    blend: Option<f64>,
 }
 
 // Initialize API response
@@ -76,14 +239,28 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            warnings: Vec::new(),
+            meta: None,
         }
     }
+    
+    fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+    
+    fn with_meta(mut self, meta: PaginationMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
 
     fn error(msg: &str) -> Self {
         Self {
             success: false,
             data: None,
             error: Some(msg.to_string()),
+            warnings: Vec::new(),
+            meta: None,
         }
     }
 }
@@ -118,19 +295,15 @@ async fn index(data: web::Data<AppState>) -> impl Responder {
 async fn engrams_page(data: web::Data<AppState>) -> impl Responder {
     let mut context = TeraContext::new();
     
-    // Get engrams
+    // Get engrams, reporting unreadable records instead of hiding them
     let storage = &data.storage;
-    let mut engrams = Vec::new();
-    
-    if let Ok(ids) = storage.list_engrams() {
-        for id in ids {
-            if let Ok(Some(engram)) = storage.get_engram(&id) {
-                engrams.push(engram);
-            }
-        }
-    }
+    let (engrams, unreadable) = match storage.iter_engrams().collect_with_report() {
+        Ok(report) => report,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error fetching engrams: {}", e)),
+    };
     
     context.insert("engrams", &engrams);
+    context.insert("unreadable", &unreadable);
     context.insert("version", "0.1.0"); // Add version
     
     let rendered = data.templates.render("engrams.html", &context).unwrap_or_else(|e| {
@@ -152,37 +325,52 @@ async fn connections_page(data: web::Data<AppState>) -> impl Responder {
     let mut type_counts = std::collections::HashMap::new();
     
     if let Ok(ids) = storage.list_connections() {
-        for id in ids {
-            if let Ok(Some(connection)) = storage.get_connection(&id) {
-                // Count connection types
-                let count = type_counts.entry(connection.relationship_type.clone()).or_insert(0);
-                *count += 1;
-                
-                // Get source and target engram content
-                let source_content = match storage.get_engram(&connection.source_id) {
-                    Ok(Some(engram)) => engram.content.clone(),
-                    _ => format!("Unknown (ID: {})", connection.source_id)
-                };
-                
-                let target_content = match storage.get_engram(&connection.target_id) {
-                    Ok(Some(engram)) => engram.content.clone(),
-                    _ => format!("Unknown (ID: {})", connection.target_id)
-                };
-                
-                // Create a connection with content for the template
-                let conn_with_content = serde_json::json!({
-                    "id": connection.id,
-                    "source_id": connection.source_id,
-                    "target_id": connection.target_id,
-                    "source_content": source_content,
-                    "target_content": target_content,
-                    "relationship_type": connection.relationship_type,
-                    "weight": connection.weight,
-                    "metadata": connection.metadata
-                });
-                
-                connections_with_content.push(conn_with_content);
-            }
+        let connections: Vec<Connection> = match storage.get_connections(&ids) {
+            Ok(connections) => connections.into_iter().flatten().collect(),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Error fetching connections: {}", e)),
+        };
+        
+        // Read the content of every endpoint in one go
+        let endpoint_ids: Vec<EngramId> = connections
+            .iter()
+            .flat_map(|connection| [connection.source_id.clone(), connection.target_id.clone()])
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let contents: HashMap<EngramId, String> = match storage.get_engrams(&endpoint_ids) {
+            Ok(engrams) => engrams.into_iter().flatten().map(|engram| (engram.id, engram.content)).collect(),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Error fetching engrams: {}", e)),
+        };
+        
+        for connection in connections {
+            // Count connection types
+            let count = type_counts.entry(connection.relationship_type.clone()).or_insert(0);
+            *count += 1;
+            
+            // Get source and target engram content
+            let source_content = match contents.get(&connection.source_id) {
+                Some(content) => content.clone(),
+                None => format!("Unknown (ID: {})", connection.source_id)
+            };
+            
+            let target_content = match contents.get(&connection.target_id) {
+                Some(content) => content.clone(),
+                None => format!("Unknown (ID: {})", connection.target_id)
+            };
+            
+            // Create a connection with content for the template
+            let conn_with_content = serde_json::json!({
+                "id": connection.id,
+                "source_id": connection.source_id,
+                "target_id": connection.target_id,
+                "source_content": source_content,
+                "target_content": target_content,
+                "relationship_type": connection.relationship_type,
+                "weight": connection.weight,
+                "metadata": connection.metadata
+            });
+            
+            connections_with_content.push(conn_with_content);
         }
     }
     
@@ -250,12 +438,11 @@ async fn collection_detail_page(path: web::Path<String>, data: web::Data<AppStat
     match storage.get_collection(&collection_id) {
         Ok(Some(collection)) => {
             // Get engrams in this collection
-            let mut engrams = Vec::new();
-            for engram_id in &collection.engram_ids {
-                if let Ok(Some(engram)) = storage.get_engram(engram_id) {
-                    engrams.push(engram);
-                }
-            }
+            let engram_ids: Vec<EngramId> = collection.engram_ids.iter().cloned().collect();
+            let engrams: Vec<Engram> = match storage.get_engrams(&engram_ids) {
+                Ok(engrams) => engrams.into_iter().flatten().collect(),
+                Err(e) => return HttpResponse::InternalServerError().body(format!("Error fetching engrams: {}", e)),
+            };
             
             context.insert("collection", &collection);
             context.insert("engrams", &engrams);
@@ -369,6 +556,21 @@ async fn api_docs_page(data: web::Data<AppState>) -> impl Responder {
         .body(rendered)
 }
 
+/// Cytoscape.js node data for an engram, labeled with its content truncated for display
+fn graph_node(engram: &Engram) -> serde_json::Value {
+    serde_json::json!({
+        "group": "nodes", 
+        "data": {
+            "id": engram.id,
+            "label": utils::truncate_display(&engram.content, utils::DEFAULT_DISPLAY_LENGTH),
+            "content": engram.content,
+            "source": engram.source,
+            "confidence": engram.confidence,
+            "metadata": engram.metadata
+        }
+    })
+}
+
 // Graph visualization page
 async fn graph_page(data: web::Data<AppState>) -> impl Responder {
     let mut context = TeraContext::new();
@@ -380,53 +582,37 @@ async fn graph_page(data: web::Data<AppState>) -> impl Responder {
     
     // Get all engrams
     if let Ok(engram_ids) = storage.list_engrams() {
-        for id in &engram_ids {
-            if let Ok(Some(engram)) = storage.get_engram(id) {
-                // Truncate content for display label if too long
-                let display_label = if engram.content.len() > 30 {
-                    format!("{}...", &engram.content[0..27])
-                } else {
-                    engram.content.clone()
-                };
-                
-                // Create Cytoscape node data
-                let node_data = serde_json::json!({
-                    "group": "nodes", 
-                    "data": {
-                        "id": engram.id,
-                        "label": display_label,
-                        "content": engram.content,
-                        "source": engram.source,
-                        "confidence": engram.confidence,
-                        "metadata": engram.metadata
-                    }
-                });
-                
-                cy_nodes.push(node_data);
-            }
+        let engrams = match storage.get_engrams(&engram_ids) {
+            Ok(engrams) => engrams,
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Error fetching engrams: {}", e)),
+        };
+        for engram in engrams.into_iter().flatten() {
+            cy_nodes.push(graph_node(&engram));
         }
         
         // Get all connections
         if let Ok(connection_ids) = storage.list_connections() {
-            for id in &connection_ids {
-                if let Ok(Some(connection)) = storage.get_connection(id) {
-                    // Only include connections between existing engrams
-                    if engram_ids.contains(&connection.source_id) && 
-                       engram_ids.contains(&connection.target_id) {
-                        // Create Cytoscape edge data
-                        let edge_data = serde_json::json!({
-                            "group": "edges",
-                            "data": {
-                                "id": connection.id,
-                                "source": connection.source_id,
-                                "target": connection.target_id,
-                                "relationshipType": connection.relationship_type,
-                                "weight": connection.weight
-                            }
-                        });
-                        
-                        cy_edges.push(edge_data);
-                    }
+            let connections = match storage.get_connections(&connection_ids) {
+                Ok(connections) => connections,
+                Err(e) => return HttpResponse::InternalServerError().body(format!("Error fetching connections: {}", e)),
+            };
+            for connection in connections.into_iter().flatten() {
+                // Only include connections between existing engrams
+                if engram_ids.contains(&connection.source_id) && 
+                   engram_ids.contains(&connection.target_id) {
+                    // Create Cytoscape edge data
+                    let edge_data = serde_json::json!({
+                        "group": "edges",
+                        "data": {
+                            "id": connection.id,
+                            "source": connection.source_id,
+                            "target": connection.target_id,
+                            "relationshipType": connection.relationship_type,
+                            "weight": connection.weight
+                        }
+                    });
+                    
+                    cy_edges.push(edge_data);
                 }
             }
         }
@@ -454,18 +640,33 @@ async fn graph_page(data: web::Data<AppState>) -> impl Responder {
 }
 
 // API Routes - Engrams
-async fn api_get_engrams(data: web::Data<AppState>) -> impl Responder {
+async fn api_get_engrams(query: web::Query<PageQuery>, data: web::Data<AppState>) -> impl Responder {
     let storage = &data.storage;
     
-    match storage.list_engrams() {
-        Ok(ids) => {
-            let mut engrams = Vec::new();
-            for id in ids {
-                if let Ok(Some(engram)) = storage.get_engram(&id) {
-                    engrams.push(engram);
-                }
-            }
-            HttpResponse::Ok().json(ApiResponse::success(engrams))
+    // Paginate only when asked to, so unpaginated listings are unchanged
+    if query.limit.is_some() || query.cursor.is_some() {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        let page = storage.list_engrams_paged(query.cursor.as_deref(), limit).and_then(|(ids, cursor)| {
+            let engrams: Vec<Engram> = storage.get_engrams(&ids)?.into_iter().flatten().collect();
+            let total = storage.get_stats()?.engram_count;
+            Ok((engrams, PaginationMeta { total, has_more: cursor.is_some(), cursor }))
+        });
+        return match page {
+            Ok((engrams, meta)) => HttpResponse::Ok().json(ApiResponse::success(engrams).with_meta(meta)),
+            Err(EngramError::InvalidOperation(e)) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e)),
+            Err(e) => HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to list engrams: {}", e))
+            ),
+        };
+    }
+    
+    match storage.iter_engrams().collect_with_report() {
+        Ok((engrams, unreadable)) => {
+            let warnings = unreadable
+                .iter()
+                .map(|record| format!("Unreadable record {}: {}", record.key, record.error))
+                .collect();
+            HttpResponse::Ok().json(ApiResponse::success(engrams).with_warnings(warnings))
         },
         Err(e) => {
             HttpResponse::InternalServerError().json(
@@ -475,12 +676,49 @@ async fn api_get_engrams(data: web::Data<AppState>) -> impl Responder {
     }
 }
 
-async fn api_get_engram(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+async fn api_get_engram(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<EngramDetailQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
     let engram_id = path.into_inner();
     let storage = &data.storage;
     
-    match storage.get_engram(&engram_id) {
-        Ok(Some(engram)) => HttpResponse::Ok().json(ApiResponse::success(engram)),
+    let expansion = match query.include.as_deref().map(EngramExpansion::parse).transpose() {
+        Ok(expansion) => expansion.unwrap_or_default(),
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e.to_string())),
+    };
+    
+    match storage.engram_detail(&engram_id, expansion) {
+        Ok(Some(detail)) => {
+            // Let polling clients skip the body when their cached copy is current. Expanded
+            // responses are tagged as a whole, so a new connection invalidates them too.
+            let etag = if expansion.is_empty() { detail.engram.etag() } else { utils::etag_of(&detail) };
+            let last_modified = storage.engram_last_modified(&engram_id)
+                .ok()
+                .flatten()
+                .unwrap_or(detail.engram.timestamp)
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string();
+            
+            let unchanged = req.headers()
+                .get(header::IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|if_none_match| utils::etag_matches(if_none_match, &etag));
+            
+            let mut response = if unchanged { HttpResponse::NotModified() } else { HttpResponse::Ok() };
+            response
+                .insert_header((header::ETAG, etag))
+                .insert_header((header::LAST_MODIFIED, last_modified));
+            if unchanged {
+                response.finish()
+            } else if expansion.is_empty() {
+                response.json(ApiResponse::success(detail.engram))
+            } else {
+                response.json(ApiResponse::success(detail))
+            }
+        },
         Ok(None) => HttpResponse::NotFound().json(
             ApiResponse::<()>::error(&format!("Engram with ID {} not found", engram_id))
         ),
@@ -490,6 +728,163 @@ async fn api_get_engram(path: web::Path<String>, data: web::Data<AppState>) -> i
     }
 }
 
+async fn api_touch_engram(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    let engram_id = path.into_inner();
+    let now = chrono::Utc::now();
+    
+    match data.storage.touch_engram(&engram_id, now) {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::NotFound().json(
+            ApiResponse::<()>::error(&format!("Engram with ID {} not found", engram_id))
+        ),
+        Err(e) => return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to touch engram: {}", e))
+        ),
+    }
+    
+    // An engram missing from the index will be picked up with the new time on the next load
+    let _ = data.search_index.write().unwrap().touch(&engram_id, now);
+    
+    HttpResponse::Ok().json(ApiResponse::success(engram_id))
+}
+
+async fn api_pin_engram(path: web::Path<String>, data: web::Data<AppState>) -> HttpResponse {
+    set_engram_pinned(&path.into_inner(), true, &data)
+}
+
+async fn api_unpin_engram(path: web::Path<String>, data: web::Data<AppState>) -> HttpResponse {
+    set_engram_pinned(&path.into_inner(), false, &data)
+}
+
+/// Pin or unpin an engram in storage and the search index, returning the updated engram
+fn set_engram_pinned(engram_id: &String, pinned: bool, data: &AppState) -> HttpResponse {
+    let mut engram = match data.storage.get_engram(engram_id) {
+        Ok(Some(engram)) => engram,
+        Ok(None) => return HttpResponse::NotFound().json(
+            ApiResponse::<()>::error(&format!("Engram with ID {} not found", engram_id))
+        ),
+        Err(e) => return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to get engram: {}", e))
+        ),
+    };
+    
+    engram.set_pinned(pinned);
+    if let Err(e) = data.storage.put_engram(&engram) {
+        return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to store engram: {}", e))
+        );
+    }
+    
+    if let Err(e) = data.search_index.write().unwrap().set_pinned(engram_id, pinned) {
+        return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to update search index: {}", e))
+        );
+    }
+    
+    HttpResponse::Ok().json(ApiResponse::success(engram))
+}
+
+async fn api_get_engram_memberships(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    let engram_id = path.into_inner();
+    let storage = &data.storage;
+    
+    match storage.get_engram(&engram_id) {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().json(
+            ApiResponse::<()>::error(&format!("Engram with ID {} not found", engram_id))
+        ),
+        Err(e) => return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to get engram: {}", e))
+        ),
+    }
+    
+    let memberships = storage.collections_for_engram(&engram_id).and_then(|collection_ids| {
+        let context_ids = storage.contexts_for_engram(&engram_id)?;
+        let mut response = EngramMembershipsResponse { collections: Vec::new(), contexts: Vec::new() };
+        for id in collection_ids {
+            if let Some(collection) = storage.get_collection(&id)? {
+                response.collections.push(collection);
+            }
+        }
+        for id in context_ids {
+            if let Some(context) = storage.get_context(&id)? {
+                response.contexts.push(context);
+            }
+        }
+        Ok(response)
+    });
+    
+    match memberships {
+        Ok(response) => HttpResponse::Ok().json(ApiResponse::success(response)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to look up memberships: {}", e))
+        )
+    }
+}
+
+/// Resolve scored IDs from the vector index into engrams, skipping any missing from storage
+fn load_similar(storage: &Storage, scored: Vec<(EngramId, f32)>) -> EngramResult<Vec<SimilarEngram>> {
+    let ids: Vec<EngramId> = scored.iter().map(|(id, _)| id.clone()).collect();
+    let engrams = storage.get_engrams(&ids)?;
+    Ok(engrams
+        .into_iter()
+        .zip(scored)
+        .filter_map(|(engram, (_, similarity))| engram.map(|engram| SimilarEngram { engram, similarity }))
+        .collect())
+}
+
+async fn api_get_similar_engrams(
+    path: web::Path<String>,
+    query: web::Query<SimilarQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let engram_id = path.into_inner();
+    let limit = query.limit.unwrap_or(10);
+    
+    match data.vector_index.similar_to(&engram_id, limit, query.min_similarity)
+        .and_then(|scored| load_similar(&data.storage, scored))
+    {
+        Ok(results) => HttpResponse::Ok().json(ApiResponse::success(results)),
+        Err(EngramError::NotFound(e)) => HttpResponse::NotFound().json(ApiResponse::<()>::error(&e)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to find similar engrams: {}", e))
+        )
+    }
+}
+
+async fn api_get_suggested_connections(
+    path: web::Path<String>,
+    query: web::Query<SimilarQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let engram_id = path.into_inner();
+    let limit = query.limit.unwrap_or(10);
+    
+    match data.vector_index.suggest_connections(&data.storage, &engram_id, limit, query.min_similarity)
+        .and_then(|scored| load_similar(&data.storage, scored))
+    {
+        Ok(results) => HttpResponse::Ok().json(ApiResponse::success(results)),
+        Err(EngramError::NotFound(e)) => HttpResponse::NotFound().json(ApiResponse::<()>::error(&e)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to suggest connections: {}", e))
+        )
+    }
+}
+
+/// Check that the agent an entity is attributed to exists, returning the error response if not
+fn check_attribution(storage: &Storage, created_by: Option<&String>) -> Option<HttpResponse> {
+    let agent_id = created_by?;
+    match storage.get_agent(agent_id) {
+        Ok(Some(_)) => None,
+        Ok(None) => Some(HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&format!("Agent with ID {} not found", agent_id))
+        )),
+        Err(e) => Some(HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to verify agent: {}", e))
+        )),
+    }
+}
+
 async fn api_create_engram(req: web::Json<CreateEngramRequest>, data: web::Data<AppState>) -> impl Responder {
     let storage = &data.storage;
     let memory_graph = &data.memory_graph;
@@ -504,15 +899,35 @@ async fn api_create_engram(req: web::Json<CreateEngramRequest>, data: web::Data<
         hm
     });
     
-    // Create the engram
-    let engram = Engram::new(
+    if let Some(response) = check_attribution(storage, req.created_by.as_ref()) {
+        return response;
+    }
+    
+    if req.ttl_seconds == Some(0) {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error("ttl_seconds must be greater than 0")
+        );
+    }
+    
+    // Create the engram, defaulting and validating its confidence by the schema policy
+    let mut engram = match Engram::new_with_policy(
         req.content.clone(),
         req.source.clone(),
         req.confidence,
         metadata,
-    );
+        storage.schema_policy(),
+    ) {
+        Ok(engram) => engram,
+        Err(e) => return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&e.to_string())
+        ),
+    };
+    engram.created_by = req.created_by.clone();
+    if let Some(ttl) = req.ttl_seconds {
+        engram = engram.with_ttl(ttl);
+    }
     
-    // Store in storage
+    // Store in storage; adding it to the search index registers the TTL for the sweep
     match storage.put_engram(&engram) {
         Ok(_) => {
             // Add to memory graph
@@ -529,6 +944,11 @@ async fn api_create_engram(req: web::Json<CreateEngramRequest>, data: web::Data<
                 );
             }
             
+            // Add to vector index
+            if let Err(e) = data.vector_index.add_engram(&engram) {
+                eprintln!("Warning: Failed to add engram {} to vector index: {}", engram.id, e);
+            }
+            
             HttpResponse::Created().json(ApiResponse::success(engram))
         },
         Err(e) => HttpResponse::InternalServerError().json(
@@ -569,6 +989,11 @@ async fn api_delete_engram(path: web::Path<String>, data: web::Data<AppState>) -
                 );
             }
             
+            // Remove from vector index
+            if let Err(e) = data.vector_index.remove_engram(&engram_id) {
+                eprintln!("Warning: Failed to remove engram {} from vector index: {}", engram_id, e);
+            }
+            
             HttpResponse::Ok().json(ApiResponse::<()>::success(()))
         },
         Err(e) => HttpResponse::InternalServerError().json(
@@ -577,19 +1002,82 @@ async fn api_delete_engram(path: web::Path<String>, data: web::Data<AppState>) -
     }
 }
 
+async fn api_batch_delete_engrams(req: web::Json<BatchDeleteRequest>, data: web::Data<AppState>) -> impl Responder {
+    let report = match data.storage.delete_engrams(&req.ids, req.cascade.unwrap_or(true)) {
+        Ok(report) => report,
+        Err(e) => return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to delete engrams: {}", e))
+        ),
+    };
+    
+    let mut search_index = data.search_index.write().unwrap();
+    if let Err(e) = unindex_deleted(&report, &mut search_index, &data.memory_graph, &data.vector_index) {
+        return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to remove deleted engrams from search index: {}", e))
+        );
+    }
+    
+    HttpResponse::Ok().json(ApiResponse::success(BatchDeleteResponse {
+        engrams_deleted: report.engrams.len(),
+        connections_deleted: report.connections.len(),
+        not_found: report.not_found,
+    }))
+}
+
+/// Drop the engrams and connections removed by a batch delete from the in-memory indexes and graph
+fn unindex_deleted(
+    report: &BatchDeleteReport,
+    search_index: &mut SearchIndex,
+    memory_graph: &RwLock<MemoryGraph>,
+    vector_index: &VectorIndex,
+) -> EngramResult<()> {
+    let mut memory_graph = memory_graph.write().unwrap();
+    for connection in &report.connections {
+        search_index.remove_connection(connection)?;
+        memory_graph.remove_connection(&connection.id)?;
+    }
+    for engram in &report.engrams {
+        search_index.remove_engram(engram)?;
+        
+        if let Err(e) = vector_index.remove_engram(&engram.id) {
+            eprintln!("Warning: Failed to remove engram {} from vector index: {}", engram.id, e);
+        }
+    }
+    Ok(())
+}
+
 // API Routes - Connections
-async fn api_get_connections(data: web::Data<AppState>) -> impl Responder {
+async fn api_get_connections(query: web::Query<ConnectionsQuery>, data: web::Data<AppState>) -> impl Responder {
     let storage = &data.storage;
     
-    match storage.list_connections() {
-        Ok(ids) => {
-            let mut connections = Vec::new();
-            for id in ids {
-                if let Ok(Some(connection)) = storage.get_connection(&id) {
-                    connections.push(connection);
-                }
-            }
-            HttpResponse::Ok().json(ApiResponse::success(connections))
+    // Filter by weight band and minimum confidence using the relationship index
+    let ids = if query.min_weight.is_some() || query.max_weight.is_some() || query.min_confidence.is_some() {
+        let min_weight = query.min_weight.unwrap_or(f64::NEG_INFINITY);
+        let max_weight = query.max_weight.unwrap_or(f64::INFINITY);
+        let search_index = data.search_index.read().unwrap();
+        let mut ids = search_index
+            .relationship_index
+            .find_connections_by_weight_range(min_weight, max_weight);
+        if let Some(min_confidence) = query.min_confidence {
+            let confident = search_index
+                .relationship_index
+                .find_connections_by_min_confidence(min_confidence);
+            ids.retain(|id| confident.contains(id));
+        }
+        Ok(ids.into_iter().collect::<Vec<_>>())
+    } else {
+        storage.list_connections()
+    };
+    
+    match ids {
+        Ok(ids) => match storage.get_connections(&ids) {
+            Ok(connections) => {
+                let connections: Vec<Connection> = connections.into_iter().flatten().collect();
+                HttpResponse::Ok().json(ApiResponse::success(connections))
+            },
+            Err(e) => HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to read connections: {}", e))
+            ),
         },
         Err(e) => {
             HttpResponse::InternalServerError().json(
@@ -634,18 +1122,69 @@ async fn api_create_connection(req: web::Json<CreateConnectionRequest>, data: we
         hm
     });
     
-    // Create the connection
-    let connection = Connection::new(
-        req.source_id.clone(),
-        req.target_id.clone(),
-        req.connection_type.clone(),
-        req.weight,
-        metadata,
-    );
+    // Create the connection, grounding an unspecified weight in semantic similarity
+    let policy = storage.schema_policy();
+    let connection = match req.weight {
+        Some(weight) => match Connection::new_with_policy(
+            req.source_id.clone(),
+            req.target_id.clone(),
+            req.connection_type.clone(),
+            Some(weight),
+            metadata,
+            policy,
+        ) {
+            Ok(connection) => connection,
+            Err(e) => return HttpResponse::BadRequest().json(
+                ApiResponse::<()>::error(&e.to_string())
+            ),
+        },
+        None => match Connection::new_weighted_by_similarity(
+            req.source_id.clone(),
+            req.target_id.clone(),
+            req.connection_type.clone(),
+            storage,
+            &EmbeddingService::new(),
+        ) {
+            Ok(mut connection) => {
+                connection.metadata = metadata.unwrap_or_default();
+                connection
+            },
+            Err(e) => return HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to compute connection weight: {}", e))
+            ),
+        },
+    };
+    if let Some(response) = check_attribution(storage, req.created_by.as_ref()) {
+        return response;
+    }
+    let connection = match policy.confidence(req.confidence) {
+        Ok(confidence) => Connection { created_by: req.created_by.clone(), confidence, ..connection },
+        Err(e) => return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&e.to_string())
+        ),
+    };
     
-    // Store in storage
-    match storage.put_connection(&connection) {
-        Ok(_) => {
+    // Store in storage, subject to the per-engram connection limit
+    match storage.add_connection(&connection) {
+        Ok(evicted) => {
+            for weak in &evicted {
+                if let Err(e) = search_index.write().unwrap().remove_connection(weak) {
+                    return HttpResponse::InternalServerError().json(
+                        ApiResponse::<()>::error(&format!("Failed to remove evicted connection from search index: {}", e))
+                    );
+                }
+                if let Err(e) = memory_graph.write().unwrap().remove_connection(&weak.id) {
+                    return HttpResponse::InternalServerError().json(
+                        ApiResponse::<()>::error(&format!("Failed to remove evicted connection from memory graph: {}", e))
+                    );
+                }
+            }
+            if evicted.iter().any(|weak| weak.id == connection.id) {
+                return HttpResponse::Conflict().json(
+                    ApiResponse::<()>::error("Source engram is at its connection limit and the new connection is the weakest")
+                );
+            }
+            
             // Add to memory graph
             if let Err(e) = memory_graph.write().unwrap().add_connection(connection.clone()) {
                 return HttpResponse::InternalServerError().json(
@@ -662,12 +1201,140 @@ async fn api_create_connection(req: web::Json<CreateConnectionRequest>, data: we
             
             HttpResponse::Created().json(ApiResponse::success(connection))
         },
+        Err(EngramError::InvalidOperation(msg)) => HttpResponse::Conflict().json(ApiResponse::<()>::error(&msg)),
         Err(e) => HttpResponse::InternalServerError().json(
             ApiResponse::<()>::error(&format!("Failed to store connection: {}", e))
         )
     }
 }
 
+// API Routes - Traversal
+async fn api_traverse(query: web::Query<TraverseQuery>, data: web::Data<AppState>) -> impl Responder {
+    let max_depth = query.max_depth.unwrap_or(3);
+    if max_depth > MAX_TRAVERSAL_DEPTH {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&format!("max_depth must be at most {}", MAX_TRAVERSAL_DEPTH))
+        );
+    }
+    
+    let rel_types: Vec<String> = query.rel_types
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    
+    let search_index = data.search_index.read().unwrap();
+    let mut engine = TraversalEngine::new(&data.storage, &search_index);
+    if let Some(timeout_ms) = query.timeout_ms {
+        engine.set_cancellation(CancellationToken::with_timeout(std::time::Duration::from_millis(timeout_ms)));
+    }
+    
+    match engine.find_routes(&query.from, &query.to, max_depth, &rel_types) {
+        Ok(paths) => HttpResponse::Ok().json(ApiResponse::success(paths)),
+        Err(EngramError::NotFound(msg)) => HttpResponse::NotFound().json(
+            ApiResponse::<()>::error(&msg)
+        ),
+        Err(EngramError::Timeout(msg)) => HttpResponse::ServiceUnavailable().json(
+            ApiResponse::<()>::error(&msg)
+        ),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to traverse graph: {}", e))
+        )
+    }
+}
+
+async fn api_explain(query: web::Query<ExplainQuery>, data: web::Data<AppState>) -> impl Responder {
+    match explain_relationship(&query.a, &query.b, &data.storage, &EmbeddingService::new()) {
+        Ok(explanation) => HttpResponse::Ok().json(ApiResponse::success(explanation)),
+        Err(EngramError::NotFound(msg)) => HttpResponse::NotFound().json(ApiResponse::<()>::error(&msg)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to explain relationship: {}", e))
+        ),
+    }
+}
+
+// API Routes - Maintenance
+async fn api_prune_weak_connections(req: web::Json<PruneConnectionsRequest>, data: web::Data<AppState>) -> impl Responder {
+    let storage = &data.storage;
+    let mut search_index = data.search_index.write().unwrap();
+    
+    let candidates = search_index
+        .relationship_index
+        .find_connections_by_weight_range(f64::NEG_INFINITY, req.threshold);
+    
+    let mut pruned = Vec::new();
+    for id in candidates {
+        let connection = match storage.get_connection(&id) {
+            Ok(Some(connection)) if connection.weight < req.threshold => connection,
+            Ok(_) => continue,
+            Err(e) => return HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to get connection {}: {}", id, e))
+            ),
+        };
+        
+        if let Err(e) = storage.delete_connection(&id) {
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to delete connection {}: {}", id, e))
+            );
+        }
+        
+        if let Err(e) = search_index.remove_connection(&connection) {
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to remove connection from search index: {}", e))
+            );
+        }
+        
+        if let Err(e) = data.memory_graph.write().unwrap().remove_connection(&id) {
+            return HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to remove connection from memory graph: {}", e))
+            );
+        }
+        
+        pruned.push(id);
+    }
+    
+    HttpResponse::Ok().json(ApiResponse::success(PruneConnectionsResponse { pruned }))
+}
+
+async fn api_expire_ttl(data: web::Data<AppState>) -> impl Responder {
+    let storage = &data.storage;
+    let mut search_index = data.search_index.write().unwrap();
+    
+    let policy = ForgettingPolicy::TTLExpiration { max_items: usize::MAX };
+    let candidates = sorted_ids(policy.get_forgetting_candidates(&search_index));
+    
+    // Expired engrams and their connections go in one write
+    let report = match storage.delete_engrams(&candidates, true) {
+        Ok(report) => report,
+        Err(e) => return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to delete expired engrams: {}", e))
+        ),
+    };
+    
+    if let Err(e) = unindex_deleted(&report, &mut search_index, &data.memory_graph, &data.vector_index) {
+        return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to remove expired engrams from search index: {}", e))
+        );
+    }
+    
+    let expired = report.engrams.into_iter().map(|engram| engram.id).collect();
+    HttpResponse::Ok().json(ApiResponse::success(ExpireEngramsResponse { expired }))
+}
+
+async fn api_recompute_importance(req: web::Json<RecomputeImportanceRequest>, data: web::Data<AppState>) -> impl Responder {
+    let mut search_index = data.search_index.write().unwrap();
+    
+    match recompute_importance_from_graph(&data.storage, &mut search_index, req.blend.unwrap_or(0.5)) {
+        Ok(updated) => HttpResponse::Ok().json(ApiResponse::success(updated)),
+        Err(EngramError::InvalidOperation(msg)) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&msg)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to recompute importance: {}", e))
+        ),
+    }
+}
+
 // API Routes - Collections
 async fn api_get_collections(data: web::Data<AppState>) -> impl Responder {
     let storage = &data.storage;
@@ -749,6 +1416,28 @@ async fn api_get_agents(data: web::Data<AppState>) -> impl Responder {
     }
 }
 
+async fn api_get_agent_created(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    let agent_id = path.into_inner();
+    let storage = &data.storage;
+    
+    match storage.get_agent(&agent_id) {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().json(
+            ApiResponse::<()>::error(&format!("Agent with ID {} not found", agent_id))
+        ),
+        Err(e) => return HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to get agent: {}", e))
+        ),
+    }
+    
+    match storage.entities_created_by(&agent_id) {
+        Ok(created) => HttpResponse::Ok().json(ApiResponse::success(created)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to get entities created by agent: {}", e))
+        ),
+    }
+}
+
 async fn api_create_agent(req: web::Json<CreateAgentRequest>, data: web::Data<AppState>) -> impl Responder {
     let storage = &data.storage;
     let memory_graph = &data.memory_graph;
@@ -792,18 +1481,77 @@ async fn api_create_agent(req: web::Json<CreateAgentRequest>, data: web::Data<Ap
 }
 
 // API Routes - Search and Query
-async fn api_query(req: web::Json<QueryRequest>, data: web::Data<AppState>) -> impl Responder {
+async fn api_query(req: web::Json<QueryRequest>, options: web::Query<QueryOptions>, data: web::Data<AppState>) -> impl Responder {
     let storage = &data.storage;
     let search_index = &data.search_index;
+    let cancellation = match options.timeout_ms {
+        Some(timeout_ms) => CancellationToken::with_timeout(std::time::Duration::from_millis(timeout_ms)),
+        None => CancellationToken::new(),
+    };
     
     // Get the read lock on search index
     let search_index = search_index.read().unwrap();
     
+    let text = req.text.as_ref().map(|text| {
+        if options.expand.unwrap_or(false) {
+            QueryExpander::new(storage, &data.vector_index)
+                .expand_query(text)
+                .unwrap_or_else(|e| {
+                    eprintln!("Warning: Query expansion failed: {}", e);
+                    text.clone()
+                })
+        } else {
+            text.clone()
+        }
+    });
+    
+    // Deduplication keeps the highest-scored copy, so it needs ranked results
+    let dedupe = options.dedupe.unwrap_or(false);
+    if dedupe && req.weights.is_none() {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error("dedupe requires ranked results; provide weights")
+        );
+    }
+    
+    // Rank by combined relevance, importance and recency when weights are given
+    if let Some(weights) = &req.weights {
+        let mut query = EngramQuery::new();
+        if let Some(text) = &text {
+            query = query.with_text(text.clone());
+        }
+        if let Some(source) = &req.source {
+            query = query.with_source(source.clone());
+        }
+        if let Some(min_confidence) = req.min_confidence {
+            query = query.with_min_confidence(min_confidence);
+        }
+        if let Some(limit) = req.limit {
+            query = query.with_limit(limit);
+        }
+        
+        let mut engine = QueryEngine::new(storage, &search_index);
+        engine.set_max_result_size(data.max_result_size);
+        engine.set_cancellation(cancellation);
+        
+        return match engine.query_engrams_scored(&query, weights) {
+            Ok(scored) => {
+                let scored = if dedupe { dedupe_by_content(scored) } else { scored };
+                HttpResponse::Ok().json(ApiResponse::success(scored).with_warnings(engine.take_warnings()))
+            }
+            Err(EngramError::InvalidOperation(msg)) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&msg)),
+            Err(e @ EngramError::ResultTooLarge(..)) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e.to_string())),
+            Err(e @ EngramError::Timeout(_)) => HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(&e.to_string())),
+            Err(e) => HttpResponse::InternalServerError().json(
+                ApiResponse::<()>::error(&format!("Failed to query engrams: {}", e))
+            ),
+        };
+    }
+    
     // Build the query based on request parameters
     let mut engram_ids = HashSet::new();
     
     // Search by text if provided
-    if let Some(text) = &req.text {
+    if let Some(text) = &text {
         let text_results = search_index.text_index.search(text);
         for id in text_results {
             engram_ids.insert(id);
@@ -840,65 +1588,161 @@ async fn api_query(req: web::Json<QueryRequest>, data: web::Data<AppState>) -> i
         }
     }
     
-    // If we don't have any search criteria, get all engrams
+    // If we don't have any search criteria, get all engrams (bounded by the limit if provided)
     if engram_ids.is_empty() && req.text.is_none() && req.source.is_none() && req.min_confidence.is_none() {
         if let Ok(all_ids) = storage.list_engrams() {
-            for id in all_ids {
-                engram_ids.insert(id);
-            }
+            let take = req.limit.unwrap_or(all_ids.len());
+            engram_ids.extend(all_ids.into_iter().take(take));
         }
     }
     
-    // Fetch the full engram objects
+    // Refuse to materialize oversized result sets unless the caller provided a limit
+    if req.limit.is_none() && engram_ids.len() > data.max_result_size {
+        return HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&format!(
+                "Query matches {} engrams, exceeding the maximum of {}; add a filter or a limit",
+                engram_ids.len(), data.max_result_size
+            ))
+        );
+    }
+    
+    // Fetch only the first `limit` engrams in ID order, so responses are reproducible
+    let mut ids = sorted_ids(engram_ids);
+    if let Some(limit) = req.limit {
+        ids.truncate(limit);
+    }
     let mut result_engrams = Vec::new();
-    for id in engram_ids {
+    for id in ids {
+        if let Err(e) = cancellation.check("Query") {
+            return HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(&e.to_string()));
+        }
         if let Ok(Some(engram)) = storage.get_engram(&id) {
             result_engrams.push(engram);
         }
     }
     
-    // Apply limit if specified
-    if let Some(limit) = req.limit {
-        if result_engrams.len() > limit {
-            result_engrams.truncate(limit);
-        }
+    HttpResponse::Ok().json(ApiResponse::success(result_engrams))
+}
+
+// API Routes - Retrieval
+async fn api_retrieve(req: web::Json<RetrieveRequest>, options: web::Query<RetrieveOptions>, data: web::Data<AppState>) -> impl Responder {
+    let search_index = data.search_index.read().unwrap();
+    let engine = HybridSearchEngine::new(&data.storage, &search_index, &data.vector_index);
+    
+    let mut query = HybridQuery::new()
+        .with_text(&req.query)
+        .with_limit(req.limit.unwrap_or(20));
+    if let Some(accuracy) = options.accuracy {
+        query = query.with_vector_query(VectorQuery::new(&req.query).with_ef_search(accuracy));
     }
     
-    HttpResponse::Ok().json(ApiResponse::success(result_engrams))
+    match engine.retrieve(&query, req.max_tokens.unwrap_or(2000), req.min_score.unwrap_or(0.0)) {
+        Ok(context) => HttpResponse::Ok().json(ApiResponse::success(context)),
+        Err(EngramError::InvalidOperation(msg)) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&msg)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to retrieve context: {}", e))
+        ),
+    }
+}
+
+// API Routes - Embeddings
+async fn api_embed(req: web::Json<EmbedRequest>, data: web::Data<AppState>) -> impl Responder {
+    let model = match req.model.as_deref() {
+        None => EmbeddingModel::E5MultilingualLargeInstruct,
+        Some(name) => match EmbeddingModel::from_name(name) {
+            Some(model) => model,
+            None => return HttpResponse::BadRequest().json(
+                ApiResponse::<()>::error(&format!("Unknown embedding model: {}", name))
+            ),
+        },
+    };
+    
+    if !data.embed_rate_limiter.try_acquire() {
+        return HttpResponse::TooManyRequests().json(
+            ApiResponse::<()>::error("Embedding rate limit exceeded; try again later")
+        );
+    }
+    
+    let service = data.embedding_services.lock().unwrap()
+        .entry(model)
+        .or_insert_with(|| Arc::new(EmbeddingService::with_model_type(model)))
+        .clone();
+    
+    match service.embed_text(&req.text) {
+        Ok(embedding) => HttpResponse::Ok().json(ApiResponse::success(embedding)),
+        Err(e) => HttpResponse::InternalServerError().json(
+            ApiResponse::<()>::error(&format!("Failed to embed text: {}", e))
+        ),
+    }
 }
 
 // Web Server Implementation
-pub fn start_server(db_path: &str, port: u16) -> EngramResult<()> {
+/// Translate a CORS policy into actix middleware
+fn build_cors(policy: &CorsPolicy) -> Cors {
+    if policy.allow_all {
+        return Cors::default()
+            .allow_any_origin()
+            .allow_any_method()
+            .allow_any_header()
+            .max_age(3600);
+    }
+    
+    let origins = policy.clone();
+    Cors::default()
+        .allowed_origin_fn(move |origin, _| {
+            origin.to_str().map(|origin| origins.allows_origin(origin)).unwrap_or(false)
+        })
+        .allowed_methods(policy.allowed_methods.iter().map(String::as_str))
+        .allowed_headers(policy.allowed_headers.iter().map(String::as_str))
+        .max_age(3600)
+}
+
+pub fn start_server(db_path: &str, port: u16, cors_policy: CorsPolicy) -> EngramResult<()> {
     use std::io::Write;
     
-    // Create the storage and memory graph
-    let storage = Arc::new(Storage::new(db_path)?);
+    // Create the storage and memory graph, clamping out-of-range values instead of
+    // rejecting them when ENGRAM_SCHEMA_VALIDATION=lenient
+    let schema_policy = match std::env::var("ENGRAM_SCHEMA_VALIDATION").as_deref() {
+        Ok("lenient") => SchemaPolicy::lenient(),
+        _ => SchemaPolicy::default(),
+    };
+    let config = StorageConfig::new().with_schema_policy(schema_policy);
+    let storage = Arc::new(Storage::with_config(db_path, config)?);
     let memory_graph = Arc::new(RwLock::new(MemoryGraph::new()));
-    let search_index = Arc::new(RwLock::new(SearchIndex::new()));
     
-    // Load data from storage into memory graph and search index
-    println!("Loading data into memory graph and search index...");
+    // Reuse the search index saved at the last shutdown if nothing was written since,
+    // otherwise rebuild it from storage
+    let index_snapshot_path = format!("{}.search-index", db_path.trim_end_matches('/'));
+    println!("Loading search index...");
+    let search_index = Arc::new(RwLock::new(SearchIndex::load(&index_snapshot_path, &storage)?));
+    let vector_index = Arc::new(VectorIndex::new().with_min_similarity(
+        std::env::var("ENGRAM_MIN_SIMILARITY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MIN_SIMILARITY)
+    ));
+    
+    // Load data from storage into memory graph and vector index
+    println!("Loading data into memory graph...");
     
     // Load engrams
     println!("Loading engrams...");
     let engram_ids = storage.list_engrams()?;
-    for id in &engram_ids {
-        if let Some(engram) = storage.get_engram(id)? {
-            memory_graph.write().unwrap().add_engram(engram.clone())?;
-            search_index.write().unwrap().add_engram(&engram)?;
+    for engram in storage.get_engrams(&engram_ids)?.into_iter().flatten() {
+        memory_graph.write().unwrap().add_engram(engram.clone())?;
+        
+        if let Err(e) = vector_index.add_engram_with_storage(&engram, &storage) {
+            eprintln!("Warning: Failed to add engram {} to vector index: {}", engram.id, e);
         }
     }
     
     // Load connections
     println!("Loading connections...");
     let connection_ids = storage.list_connections()?;
-    for id in &connection_ids {
-        if let Some(connection) = storage.get_connection(id)? {
-            // Only add connections if both source and target exist
-            if engram_ids.contains(&connection.source_id) && engram_ids.contains(&connection.target_id) {
-                memory_graph.write().unwrap().add_connection(connection.clone())?;
-                search_index.write().unwrap().add_connection(&connection)?;
-            }
+    for connection in storage.get_connections(&connection_ids)?.into_iter().flatten() {
+        // Only add connections if both source and target exist
+        if engram_ids.contains(&connection.source_id) && engram_ids.contains(&connection.target_id) {
+            memory_graph.write().unwrap().add_connection(connection.clone())?;
         }
     }
     
@@ -1047,13 +1891,32 @@ pub fn start_server(db_path: &str, port: u16) -> EngramResult<()> {
     
     // Create application state
     let app_state = web::Data::new(AppState {
-        db_path: db_path.to_string(),
         storage,
         memory_graph,
         search_index,
+        vector_index,
         templates: tera,
+        max_result_size: std::env::var("ENGRAM_MAX_RESULT_SIZE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_RESULT_SIZE),
+        embedding_services: Mutex::new(HashMap::new()),
+        embed_rate_limiter: utils::RateLimiter::new(
+            std::env::var("ENGRAM_EMBED_RATE_LIMIT")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_EMBED_RATE_LIMIT),
+            std::time::Duration::from_secs(60),
+        ),
     });
     
+    if cors_policy.allow_all {
+        println!("Development mode: accepting cross-origin requests from any origin");
+    }
+    
+    // Kept to save the search index once the server stops
+    let shutdown_state = app_state.clone();
+    
     // Start HTTP server
     println!("Starting web server on port {}...", port);
     println!("Access the web UI at: http://localhost:{}", port);
@@ -1061,12 +1924,7 @@ pub fn start_server(db_path: &str, port: u16) -> EngramResult<()> {
     // Run the actix web server
     actix_web::rt::System::new().block_on(async {
         HttpServer::new(move || {
-            // Configure CORS
-            let cors = Cors::default()
-                .allow_any_origin()
-                .allow_any_method()
-                .allow_any_header()
-                .max_age(3600);
+            let cors = build_cors(&cors_policy);
             
             App::new()
                 .wrap(middleware::Logger::default())
@@ -1090,10 +1948,29 @@ pub fn start_server(db_path: &str, port: u16) -> EngramResult<()> {
                             .route(web::get().to(api_get_engrams))
                             .route(web::post().to(api_create_engram))
                         )
+                        .service(web::resource("/engrams/batch-delete")
+                            .route(web::post().to(api_batch_delete_engrams))
+                        )
                         .service(web::resource("/engrams/{id}")
                             .route(web::get().to(api_get_engram))
                             .route(web::delete().to(api_delete_engram))
                         )
+                        .service(web::resource("/engrams/{id}/touch")
+                            .route(web::post().to(api_touch_engram))
+                        )
+                        .service(web::resource("/engrams/{id}/pin")
+                            .route(web::post().to(api_pin_engram))
+                            .route(web::delete().to(api_unpin_engram))
+                        )
+                        .service(web::resource("/engrams/{id}/memberships")
+                            .route(web::get().to(api_get_engram_memberships))
+                        )
+                        .service(web::resource("/engrams/{id}/similar")
+                            .route(web::get().to(api_get_similar_engrams))
+                        )
+                        .service(web::resource("/engrams/{id}/suggested-connections")
+                            .route(web::get().to(api_get_suggested_connections))
+                        )
                         // Connections
                         .service(web::resource("/connections")
                             .route(web::get().to(api_get_connections))
@@ -1109,10 +1986,37 @@ pub fn start_server(db_path: &str, port: u16) -> EngramResult<()> {
                             .route(web::get().to(api_get_agents))
                             .route(web::post().to(api_create_agent))
                         )
+                        .service(web::resource("/agents/{id}/created")
+                            .route(web::get().to(api_get_agent_created))
+                        )
                         // Query
                         .service(web::resource("/query")
                             .route(web::post().to(api_query))
                         )
+                        // Retrieval
+                        .service(web::resource("/retrieve")
+                            .route(web::post().to(api_retrieve))
+                        )
+                        // Traversal
+                        .service(web::resource("/traverse")
+                            .route(web::get().to(api_traverse))
+                        )
+                        .service(web::resource("/embed")
+                            .route(web::post().to(api_embed))
+                        )
+                        .service(web::resource("/explain")
+                            .route(web::get().to(api_explain))
+                        )
+                        // Maintenance
+                        .service(web::resource("/maintenance/prune-weak-connections")
+                            .route(web::post().to(api_prune_weak_connections))
+                        )
+                        .service(web::resource("/maintenance/expire-ttl")
+                            .route(web::post().to(api_expire_ttl))
+                        )
+                        .service(web::resource("/maintenance/recompute-importance")
+                            .route(web::post().to(api_recompute_importance))
+                        )
                 )
         })
         .bind(("0.0.0.0", port)).expect("Failed to bind to address")
@@ -1121,12 +2025,42 @@ pub fn start_server(db_path: &str, port: u16) -> EngramResult<()> {
         .unwrap();
     });
     
+    // Save the search index so the next startup can skip rebuilding it
+    println!("Saving search index...");
+    let search_index = shutdown_state.search_index.read().unwrap();
+    if let Err(e) = search_index.save(&index_snapshot_path, &shutdown_state.storage) {
+        eprintln!("Warning: Failed to save search index: {}", e);
+    }
+    
     Ok(())
 }
 
-// Add a main function to satisfy the compiler
+// Add a main function to satisfy the compiler; unused when engramlt includes this module
+#[allow(dead_code)]
 fn main() -> EngramResult<()> {
     // This is a library component, not meant to be run directly
     println!("This is a library component. Use engramlt instead.");
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_graph_node_label_keeps_multibyte_chars_whole() {
+        // The emoji occupies bytes 28..32, so a byte-based cut at 30 would split it
+        let content = format!("{}😀 grüße, 日本語のテキスト", "é".repeat(14));
+        assert!(!content.is_char_boundary(30));
+        let engram = Engram::new(content.clone(), "test".to_string(), 0.9, None);
+        
+        let node = graph_node(&engram);
+        let label = node["data"]["label"].as_str().unwrap();
+        assert_eq!(label, format!("{}😀 grüße, 日本語の...", "é".repeat(14)));
+        assert_eq!(label.chars().count(), utils::DEFAULT_DISPLAY_LENGTH);
+        assert_eq!(node["data"]["content"], content);
+        
+        // Short content is labeled unchanged
+        let short = Engram::new("日本語".to_string(), "test".to_string(), 0.9, None);
+        assert_eq!(graph_node(&short)["data"]["label"], "日本語");
+    }
+}
@@ -1,6 +1,6 @@
 use crate::error::Result;
 use crate::schema::{Agent, Collection, Connection, Context, Engram};
-use crate::storage::Storage;
+use crate::storage::{Storage, StorageBatch};
 use std::collections::{HashMap, HashSet};
 
 /// Populates the database with demo data for a multi-agent coding team scenario
@@ -16,13 +16,15 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     let agent_ids = storage.list_agents()?;
     let context_ids = storage.list_contexts()?;
     
+    let mut batch = storage.batch();
     for id in &connection_ids {
-        storage.delete_connection(id)?;
+        batch.delete_connection(id)?;
     }
     
     for id in &engram_ids {
-        storage.delete_engram(id)?;
+        batch.delete_engram(id)?;
     }
+    batch.commit()?;
     
     for id in &collection_ids {
         storage.delete_collection(id)?;
@@ -79,11 +81,14 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     // Store the context
     storage.put_context(&project_context)?;
     
+    // Engrams and connections are staged and written together at the end
+    let mut batch = storage.batch();
+    
     println!("Adding project requirements...");
     
     // Add project requirements engrams
     let req1 = create_engram(
-        &storage,
+        &mut batch,
         "The application should provide weather forecasts for user-specified locations.",
         "project_manager",
         0.95,
@@ -91,7 +96,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let req2 = create_engram(
-        &storage,
+        &mut batch,
         "Users should be able to save favorite locations for quick access.",
         "project_manager",
         0.9,
@@ -99,7 +104,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let req3 = create_engram(
-        &storage,
+        &mut batch,
         "The application should display temperature, precipitation, wind, and humidity data.",
         "project_manager",
         0.95,
@@ -107,7 +112,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let req4 = create_engram(
-        &storage,
+        &mut batch,
         "The interface should be responsive and work on both desktop and mobile devices.",
         "project_manager",
         0.85,
@@ -115,7 +120,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let req5 = create_engram(
-        &storage,
+        &mut batch,
         "Weather data should be retrieved from the OpenWeatherMap API.",
         "project_manager",
         0.9,
@@ -132,7 +137,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     
     // Add design decision engrams
     let design1 = create_engram(
-        &storage,
+        &mut batch,
         "We will use React with TypeScript for the frontend implementation.",
         "developer",
         0.9,
@@ -140,7 +145,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let design2 = create_engram(
-        &storage,
+        &mut batch,
         "We will use a Node.js backend with Express to handle API requests.",
         "developer",
         0.85,
@@ -148,7 +153,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let design3 = create_engram(
-        &storage,
+        &mut batch,
         "We will implement a caching layer to minimize API calls to OpenWeatherMap.",
         "developer",
         0.8,
@@ -156,7 +161,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let design4 = create_engram(
-        &storage,
+        &mut batch,
         "User preferences will be stored in localStorage for simplicity.",
         "developer",
         0.75,
@@ -170,18 +175,18 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     add_to_context(&storage, &project_context.id, &[&design1.id, &design2.id, &design3.id, &design4.id])?;
     
     // Connect requirements to design decisions
-    create_connection(&storage, &req1.id, &design1.id, "influences", 0.9)?;
-    create_connection(&storage, &req1.id, &design2.id, "influences", 0.9)?;
-    create_connection(&storage, &req3.id, &design1.id, "influences", 0.85)?;
-    create_connection(&storage, &req5.id, &design2.id, "influences", 0.95)?;
-    create_connection(&storage, &req5.id, &design3.id, "influences", 0.9)?;
-    create_connection(&storage, &req2.id, &design4.id, "influences", 0.9)?;
+    create_connection(&mut batch, &req1.id, &design1.id, "influences", 0.9)?;
+    create_connection(&mut batch, &req1.id, &design2.id, "influences", 0.9)?;
+    create_connection(&mut batch, &req3.id, &design1.id, "influences", 0.85)?;
+    create_connection(&mut batch, &req5.id, &design2.id, "influences", 0.95)?;
+    create_connection(&mut batch, &req5.id, &design3.id, "influences", 0.9)?;
+    create_connection(&mut batch, &req2.id, &design4.id, "influences", 0.9)?;
     
     println!("Adding implementation details...");
     
     // Add implementation engrams
     let impl1 = create_engram(
-        &storage,
+        &mut batch,
         "Created WeatherService class to handle API communication with OpenWeatherMap.",
         "developer",
         0.9,
@@ -189,7 +194,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let impl2 = create_engram(
-        &storage,
+        &mut batch,
         "Implemented responsive UI components using React and styled-components.",
         "developer",
         0.85,
@@ -197,7 +202,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let impl3 = create_engram(
-        &storage,
+        &mut batch,
         "Created LocationSearch component with autocomplete for city lookup.",
         "developer",
         0.8,
@@ -205,7 +210,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let impl4 = create_engram(
-        &storage,
+        &mut batch,
         "Implemented favorites feature using localStorage and React context.",
         "developer",
         0.85,
@@ -213,7 +218,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let code_snippet = create_engram(
-        &storage,
+        &mut batch,
         "```typescript\nclass WeatherService {\n  private apiKey: string;\n  private cache: Map<string, WeatherData>;\n\n  constructor(apiKey: string) {\n    this.apiKey = apiKey;\n    this.cache = new Map();\n  }\n\n  async getWeather(location: string): Promise<WeatherData> {\n    // Check cache first\n    if (this.cache.has(location)) {\n      return this.cache.get(location)!;\n    }\n    \n    // Fetch from API\n    const response = await fetch(\n      `https://api.openweathermap.org/data/2.5/weather?q=${location}&appid=${this.apiKey}&units=metric`\n    );\n    \n    if (!response.ok) {\n      throw new Error(`Weather data fetch failed: ${response.statusText}`);\n    }\n    \n    const data = await response.json();\n    const weatherData = this.transformApiResponse(data);\n    \n    // Update cache\n    this.cache.set(location, weatherData);\n    \n    return weatherData;\n  }\n\n  private transformApiResponse(data: any): WeatherData {\n    return {\n      location: data.name,\n      temperature: data.main.temp,\n      humidity: data.main.humidity,\n      windSpeed: data.wind.speed,\n      precipitation: data.rain ? data.rain['1h'] : 0,\n      description: data.weather[0].description,\n      timestamp: new Date().toISOString()\n    };\n  }\n}\n```",
         "developer",
         0.95,
@@ -227,17 +232,17 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     add_to_context(&storage, &project_context.id, &[&impl1.id, &impl2.id, &impl3.id, &impl4.id, &code_snippet.id])?;
     
     // Connect design decisions to implementation
-    create_connection(&storage, &design1.id, &impl2.id, "implements", 0.9)?;
-    create_connection(&storage, &design2.id, &impl1.id, "implements", 0.9)?;
-    create_connection(&storage, &design3.id, &impl1.id, "implements", 0.85)?;
-    create_connection(&storage, &design4.id, &impl4.id, "implements", 0.9)?;
-    create_connection(&storage, &impl1.id, &code_snippet.id, "contains", 0.95)?;
+    create_connection(&mut batch, &design1.id, &impl2.id, "implements", 0.9)?;
+    create_connection(&mut batch, &design2.id, &impl1.id, "implements", 0.9)?;
+    create_connection(&mut batch, &design3.id, &impl1.id, "implements", 0.85)?;
+    create_connection(&mut batch, &design4.id, &impl4.id, "implements", 0.9)?;
+    create_connection(&mut batch, &impl1.id, &code_snippet.id, "contains", 0.95)?;
     
     println!("Adding test cases...");
     
     // Add test engrams
     let test1 = create_engram(
-        &storage,
+        &mut batch,
         "Unit test for WeatherService: should retrieve data from API when not in cache.",
         "tester",
         0.9,
@@ -245,7 +250,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let test2 = create_engram(
-        &storage,
+        &mut batch,
         "Unit test for WeatherService: should return cached data when available.",
         "tester",
         0.9,
@@ -253,7 +258,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let test3 = create_engram(
-        &storage,
+        &mut batch,
         "Integration test: LocationSearch component should display search results.",
         "tester",
         0.85,
@@ -261,7 +266,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let test4 = create_engram(
-        &storage,
+        &mut batch,
         "End-to-end test: User can add a location to favorites.",
         "tester",
         0.8,
@@ -269,7 +274,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let test_failure = create_engram(
-        &storage,
+        &mut batch,
         "LocationSearch component test is failing because the API response is not being mocked correctly.",
         "tester",
         0.95,
@@ -283,17 +288,17 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     add_to_context(&storage, &project_context.id, &[&test1.id, &test2.id, &test3.id, &test4.id, &test_failure.id])?;
     
     // Connect implementation to tests
-    create_connection(&storage, &impl1.id, &test1.id, "verified_by", 0.9)?;
-    create_connection(&storage, &impl1.id, &test2.id, "verified_by", 0.9)?;
-    create_connection(&storage, &impl3.id, &test3.id, "verified_by", 0.7)?;
-    create_connection(&storage, &impl4.id, &test4.id, "verified_by", 0.8)?;
-    create_connection(&storage, &test3.id, &test_failure.id, "resulted_in", 0.95)?;
+    create_connection(&mut batch, &impl1.id, &test1.id, "verified_by", 0.9)?;
+    create_connection(&mut batch, &impl1.id, &test2.id, "verified_by", 0.9)?;
+    create_connection(&mut batch, &impl3.id, &test3.id, "verified_by", 0.7)?;
+    create_connection(&mut batch, &impl4.id, &test4.id, "verified_by", 0.8)?;
+    create_connection(&mut batch, &test3.id, &test_failure.id, "resulted_in", 0.95)?;
     
     println!("Adding documentation...");
     
     // Add documentation engrams
     let doc1 = create_engram(
-        &storage,
+        &mut batch,
         "Weather App API Documentation: Describes the OpenWeatherMap API integration.",
         "documenter",
         0.9,
@@ -301,7 +306,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let doc2 = create_engram(
-        &storage,
+        &mut batch,
         "User Guide: How to search for locations and view weather forecasts.",
         "documenter",
         0.85,
@@ -309,7 +314,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let doc3 = create_engram(
-        &storage,
+        &mut batch,
         "Developer Guide: How to extend the Weather App with new features.",
         "documenter",
         0.8,
@@ -317,7 +322,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let api_doc_content = create_engram(
-        &storage,
+        &mut batch,
         "# Weather Service API Documentation\n\n## Overview\nThe Weather Service API provides methods to retrieve weather data from OpenWeatherMap.\n\n## Methods\n\n### getWeather(location: string): Promise<WeatherData>\nRetrieves weather data for the specified location.\n\n#### Parameters\n- location: A string representing the city name (e.g., 'London', 'New York')\n\n#### Returns\nA Promise that resolves to a WeatherData object with the following properties:\n- location: string\n- temperature: number (in Celsius)\n- humidity: number (percentage)\n- windSpeed: number (in m/s)\n- precipitation: number (in mm)\n- description: string\n- timestamp: string (ISO format)\n\n#### Error Handling\nThrows an Error if the API request fails.\n\n## Data Caching\nThe Weather Service implements a simple in-memory cache to avoid unnecessary API calls. Weather data is cached by location name and will be returned from cache when available.",
         "documenter",
         0.95,
@@ -331,16 +336,16 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     add_to_context(&storage, &project_context.id, &[&doc1.id, &doc2.id, &doc3.id, &api_doc_content.id])?;
     
     // Connect implementation to documentation
-    create_connection(&storage, &impl1.id, &doc1.id, "documented_by", 0.9)?;
-    create_connection(&storage, &impl2.id, &doc2.id, "documented_by", 0.85)?;
-    create_connection(&storage, &impl3.id, &doc2.id, "documented_by", 0.8)?;
-    create_connection(&storage, &doc1.id, &api_doc_content.id, "contains", 0.95)?;
+    create_connection(&mut batch, &impl1.id, &doc1.id, "documented_by", 0.9)?;
+    create_connection(&mut batch, &impl2.id, &doc2.id, "documented_by", 0.85)?;
+    create_connection(&mut batch, &impl3.id, &doc2.id, "documented_by", 0.8)?;
+    create_connection(&mut batch, &doc1.id, &api_doc_content.id, "contains", 0.95)?;
     
     println!("Adding team interactions...");
     
     // Add team interactions
     let discussion1 = create_engram(
-        &storage,
+        &mut batch,
         "We need to implement mock API responses for the LocationSearch component tests.",
         "developer",
         0.9,
@@ -348,7 +353,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let discussion2 = create_engram(
-        &storage,
+        &mut batch,
         "I'll create the API mocking framework for the integration tests today.",
         "developer",
         0.85,
@@ -356,7 +361,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let discussion3 = create_engram(
-        &storage,
+        &mut batch,
         "We should also document how to create and use mocks in the developer guide.",
         "tester",
         0.8,
@@ -364,7 +369,7 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     )?;
     
     let discussion4 = create_engram(
-        &storage,
+        &mut batch,
         "I'll update the developer guide with the testing approach once the mocking framework is ready.",
         "documenter",
         0.85,
@@ -375,11 +380,13 @@ pub fn populate_demo_data(db_path: &str) -> Result<()> {
     add_to_context(&storage, &project_context.id, &[&discussion1.id, &discussion2.id, &discussion3.id, &discussion4.id])?;
     
     // Connect discussions to relevant engrams
-    create_connection(&storage, &test_failure.id, &discussion1.id, "prompted", 0.9)?;
-    create_connection(&storage, &discussion1.id, &discussion2.id, "followed_by", 0.9)?;
-    create_connection(&storage, &discussion2.id, &discussion3.id, "followed_by", 0.9)?;
-    create_connection(&storage, &discussion3.id, &discussion4.id, "followed_by", 0.9)?;
-    create_connection(&storage, &discussion4.id, &doc3.id, "relates_to", 0.85)?;
+    create_connection(&mut batch, &test_failure.id, &discussion1.id, "prompted", 0.9)?;
+    create_connection(&mut batch, &discussion1.id, &discussion2.id, "followed_by", 0.9)?;
+    create_connection(&mut batch, &discussion2.id, &discussion3.id, "followed_by", 0.9)?;
+    create_connection(&mut batch, &discussion3.id, &discussion4.id, "followed_by", 0.9)?;
+    create_connection(&mut batch, &discussion4.id, &doc3.id, "relates_to", 0.85)?;
+    
+    batch.commit()?;
     
     println!("Demo data population complete!");
     
@@ -519,7 +526,7 @@ fn grant_agent_access(storage: &Storage, agent_id: &str, collection_ids: &[&str]
 }
 
 fn create_engram(
-    storage: &Storage,
+    batch: &mut StorageBatch,
     content: &str,
     source: &str,
     confidence: f64,
@@ -532,7 +539,7 @@ fn create_engram(
         metadata
     );
     
-    storage.put_engram(&engram)?;
+    batch.put_engram(&engram)?;
     Ok(engram)
 }
 
@@ -557,7 +564,7 @@ fn add_to_context(storage: &Storage, context_id: &str, engram_ids: &[&str]) -> R
 }
 
 fn create_connection(
-    storage: &Storage,
+    batch: &mut StorageBatch,
     source_id: &str,
     target_id: &str,
     relationship_type: &str,
@@ -571,6 +578,6 @@ fn create_connection(
         None
     );
     
-    storage.put_connection(&connection)?;
+    batch.put_connection(&connection)?;
     Ok(connection)
 }
\ No newline at end of file
@@ -1,11 +1,10 @@
 use crate::error::{EngramError, Result};
-use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::path::Path;
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashSet};
-use reqwest::blocking::Client;
 use serde::{Serialize, Deserialize};
 use lru::LruCache;
 use std::num::NonZeroUsize;
@@ -26,6 +25,19 @@ pub enum EmbeddingModel {
     Custom,
 }
 
+/// Metric used to compare embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SimilarityMetric {
+    /// Cosine similarity (angle between vectors, ignores magnitude)
+    Cosine,
+    
+    /// Euclidean distance, converted to a similarity as 1 / (1 + distance)
+    Euclidean,
+    
+    /// Raw dot product (sensitive to vector magnitude)
+    DotProduct,
+}
+
 /// A vector embedding for a text
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Embedding {
@@ -63,21 +75,7 @@ impl Embedding {
             )));
         }
         
-        let mut dot_product = 0.0;
-        let mut self_norm = 0.0;
-        let mut other_norm = 0.0;
-        
-        for i in 0..self.dimensions {
-            dot_product += self.vector[i] * other.vector[i];
-            self_norm += self.vector[i] * self.vector[i];
-            other_norm += other.vector[i] * other.vector[i];
-        }
-        
-        if self_norm == 0.0 || other_norm == 0.0 {
-            return Ok(0.0);
-        }
-        
-        Ok(dot_product / (self_norm.sqrt() * other_norm.sqrt()))
+        Ok(cosine_similarity_slices(&self.vector, &other.vector))
     }
     
     /// Compute Euclidean distance between two embeddings
@@ -98,6 +96,26 @@ impl Embedding {
         Ok(squared_sum.sqrt())
     }
     
+    /// Compute the dot product between two embeddings
+    pub fn dot_product(&self, other: &Self) -> Result<f32> {
+        if self.dimensions != other.dimensions {
+            return Err(EngramError::ComputationError(format!(
+                "Dimension mismatch: {} vs {}", self.dimensions, other.dimensions
+            )));
+        }
+        
+        Ok(self.vector.iter().zip(&other.vector).map(|(a, b)| a * b).sum())
+    }
+    
+    /// Compute the similarity between two embeddings using the given metric (higher is closer)
+    pub fn similarity(&self, other: &Self, metric: SimilarityMetric) -> Result<f32> {
+        match metric {
+            SimilarityMetric::Cosine => self.cosine_similarity(other),
+            SimilarityMetric::Euclidean => Ok(1.0 / (1.0 + self.euclidean_distance(other)?)),
+            SimilarityMetric::DotProduct => self.dot_product(other),
+        }
+    }
+    
     /// Normalize the vector to unit length (L2 norm = 1.0)
     pub fn normalize(&mut self) {
         // Compute L2 norm
@@ -110,13 +128,21 @@ impl Embedding {
                 self.vector[i] /= norm;
             }
         }
+        
+        self.metadata.insert("normalized".to_string(), "true".to_string());
     }
     
-    /// Check if the embedding is normalized
+    /// Check if the embedding is normalized.
+    /// This is computed from the vector itself, so it stays accurate after a round trip through storage.
     pub fn is_normalized(&self) -> bool {
         let squared_sum: f32 = self.vector.iter().map(|v| v*v).sum();
         (squared_sum - 1.0).abs() < 1e-5
     }
+    
+    /// Whether the embedding was generated as normalized or raw, if recorded in its metadata
+    pub fn normalized_flag(&self) -> Option<bool> {
+        self.metadata.get("normalized").and_then(|flag| flag.parse().ok())
+    }
 }
 
 /// LRU cache for embeddings to reduce computation
@@ -160,156 +186,188 @@ impl EmbeddingCache {
     }
 }
 
-/// Service for generating and managing embeddings
-pub struct EmbeddingService {
-    /// The model to use for embeddings
-    model: EmbeddingModel,
-    
-    /// For custom models, the name of the model
-    custom_model_name: Option<String>,
-    
-    /// Whether to normalize embeddings
-    normalize_embeddings: bool,
-    
-    /// Whether to use reduced dimensionality embeddings
-    use_reduced_embeddings: bool,
+/// Source of raw embedding vectors for an `EmbeddingService`.
+///
+/// Backends only turn text into vectors; caching, normalization, and dimensionality
+/// reduction are applied by the service, so every backend gets them for free.
+pub trait EmbeddingBackend: Send + Sync {
+    /// Generate the raw vector for a text
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+    
+    /// Generate raw vectors for several texts, in order. Backends with a real batch API
+    /// should override this.
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
     
-    /// Cache for embeddings to avoid recomputation
-    cache: Arc<Mutex<EmbeddingCache>>,
+    /// Length of the vectors this backend produces
+    fn dimensions(&self) -> usize;
     
-    /// Optional dimension reducer for storage optimization
-    pub dimension_reducer: Option<Arc<Mutex<dyn DimensionReducer + Send>>>,
+    /// Name of the model, recorded on each embedding
+    fn name(&self) -> &str;
     
-    /// Whether to use instruction prefix for embedding generation
-    use_instruction_prefix: bool,
+    /// Prefix the model expects before passage text (e.g. "passage: " for E5), if any
+    fn instruction_prefix(&self) -> Option<&str> {
+        None
+    }
 }
 
-impl EmbeddingService {
-    /// Create a new embedding service with default settings
-    pub fn new() -> Self {
-        Self {
-            model: EmbeddingModel::E5MultilingualLargeInstruct,
-            custom_model_name: None,
-            normalize_embeddings: true,
-            use_reduced_embeddings: false,
-            cache: Arc::new(Mutex::new(EmbeddingCache::new(1000))),
-            dimension_reducer: None,
-            use_instruction_prefix: true,
-        }
+impl EmbeddingModel {
+    /// Look up a built-in model by short name ("e5", "gte", "jina") or HuggingFace hub name
+    pub fn from_name(name: &str) -> Option<Self> {
+        let models = [
+            (EmbeddingModel::E5MultilingualLargeInstruct, "e5"),
+            (EmbeddingModel::GteModernBertBase, "gte"),
+            (EmbeddingModel::JinaEmbeddingsV3, "jina"),
+        ];
+        models.iter()
+            .find(|(model, short)| name.eq_ignore_ascii_case(short) || model.hub_name() == Some(name))
+            .map(|(model, _)| *model)
     }
     
-    /// Create a service with a specific model type
-    pub fn with_model_type(model: EmbeddingModel) -> Self {
-        Self {
-            model,
-            custom_model_name: None,
-            normalize_embeddings: true,
-            use_reduced_embeddings: false,
-            cache: Arc::new(Mutex::new(EmbeddingCache::new(1000))),
-            dimension_reducer: None,
-            use_instruction_prefix: true,
+    /// Name of the model on the HuggingFace hub, or `None` for custom models
+    pub fn hub_name(&self) -> Option<&'static str> {
+        match self {
+            EmbeddingModel::E5MultilingualLargeInstruct => Some("intfloat/multilingual-e5-large-instruct"),
+            EmbeddingModel::GteModernBertBase => Some("Alibaba-NLP/gte-modernbert-base"),
+            EmbeddingModel::JinaEmbeddingsV3 => Some("jinaai/jina-embeddings-v3"),
+            EmbeddingModel::Custom => None,
         }
     }
     
-    /// Create a service with a custom model name
-    pub fn with_model(model_name: &str) -> Self {
-        Self {
-            model: EmbeddingModel::Custom,
-            custom_model_name: Some(model_name.to_string()),
-            normalize_embeddings: true,
-            use_reduced_embeddings: false,
-            cache: Arc::new(Mutex::new(EmbeddingCache::new(1000))),
-            dimension_reducer: None,
-            use_instruction_prefix: true,
+    /// Output dimensionality of the model
+    pub fn default_dimensions(&self) -> usize {
+        match self {
+            EmbeddingModel::E5MultilingualLargeInstruct => 1024,
+            EmbeddingModel::GteModernBertBase => 768,
+            EmbeddingModel::JinaEmbeddingsV3 => 768,
+            EmbeddingModel::Custom => 768, // Default for custom models, should be overridden
         }
     }
-    
-    /// Configure whether to normalize embeddings
-    pub fn with_normalization(mut self, normalize: bool) -> Self {
-        self.normalize_embeddings = normalize;
-        self
+}
+
+/// Backend that generates vectors locally from the text alone, for tests, demos, and
+/// running without an API key.
+///
+/// Vectors are built by feature hashing: each lowercased word, and each character trigram
+/// within it, is hashed to a signed bucket. Texts that share vocabulary therefore get
+/// similar vectors, which keeps offline semantic search plausible.
+pub struct DeterministicBackend {
+    /// Model name reported on embeddings
+    name: String,
+    
+    /// Length of the generated vectors
+    dimensions: usize,
+}
+
+/// Weight of a whole-word feature in deterministic embeddings
+const DETERMINISTIC_WORD_WEIGHT: f32 = 1.0;
+
+/// Weight of each character trigram feature in deterministic embeddings
+const DETERMINISTIC_TRIGRAM_WEIGHT: f32 = 0.25;
+
+impl DeterministicBackend {
+    /// Create a backend producing `dimensions`-long vectors under the given model name
+    pub fn new(name: &str, dimensions: usize) -> Self {
+        Self { name: name.to_string(), dimensions }
     }
     
-    /// Configure whether to use reduced dimensionality embeddings
-    pub fn with_reduced_embeddings(mut self, use_reduced: bool) -> Self {
-        self.use_reduced_embeddings = use_reduced;
-        self
+    /// Stable FNV-1a hash, so vectors are identical across runs and platforms
+    fn hash_feature(kind: u8, feature: &str) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in std::iter::once(kind).chain(feature.bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
     }
     
-    /// Set the dimension reducer to use
-    pub fn with_dimension_reducer(mut self, reducer: Arc<Mutex<dyn DimensionReducer + Send>>) -> Self {
-        self.dimension_reducer = Some(reducer);
-        self
+    /// Add a feature to its bucket, with a sign taken from the hash to reduce collisions' bias
+    fn add_feature(&self, vector: &mut [f32], kind: u8, feature: &str, weight: f32) {
+        let hash = Self::hash_feature(kind, feature);
+        let bucket = (hash % self.dimensions as u64) as usize;
+        let sign = if (hash >> 63) == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
     }
     
-    /// Configure whether to use instruction prefixes
-    pub fn with_instruction_prefix(mut self, use_prefix: bool) -> Self {
-        self.use_instruction_prefix = use_prefix;
-        self
+    /// Pseudorandom vector seeded from the raw bytes, for texts without any words
+    fn seeded_vector(&self, text: &str) -> Vec<f32> {
+        let mut value = text.bytes().fold(0u64, |acc, b| acc.wrapping_add(b as u64));
+        (0..self.dimensions)
+            .map(|_| {
+                value = value.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (value % 1000) as f32 / 500.0 - 1.0
+            })
+            .collect()
     }
-    
-    /// Get the embeddinng dimensions for the current model
-    pub fn get_dimensions(&self) -> usize {
-        match self.model {
-            EmbeddingModel::E5MultilingualLargeInstruct => 1024,
-            EmbeddingModel::GteModernBertBase => 768,
-            EmbeddingModel::JinaEmbeddingsV3 => 768,
-            EmbeddingModel::Custom => 768, // Default for custom models, should be overridden
+}
+
+impl EmbeddingBackend for DeterministicBackend {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if self.dimensions == 0 {
+            return Ok(Vec::new());
         }
-    }
-    
-    /// Get the embedding model name as a string
-    pub fn get_model_name(&self) -> String {
-        match self.model {
-            EmbeddingModel::E5MultilingualLargeInstruct => "intfloat/multilingual-e5-large-instruct".to_string(),
-            EmbeddingModel::GteModernBertBase => "Alibaba-NLP/gte-modernbert-base".to_string(),
-            EmbeddingModel::JinaEmbeddingsV3 => "jinaai/jina-embeddings-v3".to_string(),
-            EmbeddingModel::Custom => {
-                self.custom_model_name.clone().unwrap_or_else(|| "custom-model".to_string())
+        
+        let lowercase = text.to_lowercase();
+        let words: Vec<&str> = lowercase
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        
+        if words.is_empty() {
+            return Ok(self.seeded_vector(text));
+        }
+        
+        let mut vector = vec![0.0f32; self.dimensions];
+        for word in &words {
+            self.add_feature(&mut vector, b'w', word, DETERMINISTIC_WORD_WEIGHT);
+            
+            // Character trigrams let inflected forms ("memory", "memories") overlap
+            let padded: Vec<char> = format!("<{}>", word).chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                self.add_feature(&mut vector, b't', &trigram, DETERMINISTIC_TRIGRAM_WEIGHT);
             }
         }
+        
+        Ok(vector)
     }
     
-    /// Configure a dimension reducer for the embedding service
-    pub fn set_dimension_reducer(&mut self, reducer: Arc<Mutex<dyn DimensionReducer + Send>>) {
-        self.dimension_reducer = Some(reducer);
+    fn dimensions(&self) -> usize {
+        self.dimensions
     }
     
-    /// Reduce the dimensionality of an embedding
-    pub fn reduce_embedding(&self, embedding: &Embedding) -> Result<Embedding> {
-        // Check if we have a dimension reducer
-        if let Some(reducer_arc) = &self.dimension_reducer {
-            let reducer = reducer_arc.lock().map_err(|_| {
-                EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
-            })?;
-            
-            if !reducer.is_trained() {
-                return Err(EngramError::InvalidState("Dimension reducer is not trained".to_string()));
-            }
-            
-            reducer.reduce(embedding)
-        } else {
-            Err(EngramError::InvalidOperation("No dimension reducer configured".to_string()))
-        }
+    fn name(&self) -> &str {
+        &self.name
     }
+}
+
+/// Backend that calls the HuggingFace inference API, falling back to deterministic
+/// vectors when a request fails
+pub struct HuggingFaceBackend {
+    /// Model ID on the HuggingFace hub
+    model_name: String,
     
-    /// Train the dimension reducer with a set of embeddings
-    pub fn train_reducer(&self, embeddings: &[Embedding]) -> Result<()> {
-        // Check if we have a dimension reducer
-        if let Some(reducer_arc) = &self.dimension_reducer {
-            let mut reducer = reducer_arc.lock().map_err(|_| {
-                EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
-            })?;
-            
-            // Train the reducer
-            reducer.train(embeddings)
-        } else {
-            Err(EngramError::InvalidOperation("No dimension reducer configured".to_string()))
+    /// Length of the vectors the model produces
+    dimensions: usize,
+    
+    /// Used when the API is unavailable
+    fallback: DeterministicBackend,
+}
+
+impl HuggingFaceBackend {
+    /// Create a backend for a hub model with the given output dimensionality.
+    /// Requests need `HUGGINGFACE_API_KEY` to be set.
+    pub fn new(model_name: &str, dimensions: usize) -> Self {
+        Self {
+            model_name: model_name.to_string(),
+            dimensions,
+            fallback: DeterministicBackend::new(model_name, dimensions),
         }
     }
     
-    /// Generate an embedding using the HuggingFace API
-    fn generate_huggingface_embedding(&self, text: &str) -> Result<Vec<f32>> {
+    /// Request an embedding from the HuggingFace inference API
+    fn request_embedding(&self, text: &str) -> Result<Vec<f32>> {
         use reqwest::blocking::Client;
         use std::env;
         
@@ -318,33 +376,12 @@ impl EmbeddingService {
             EngramError::InvalidOperation("HUGGINGFACE_API_KEY environment variable not set".to_string())
         })?;
         
-        // Get the model name
-        let model_name = match self.model {
-            EmbeddingModel::E5MultilingualLargeInstruct => "intfloat/multilingual-e5-large-instruct",
-            EmbeddingModel::GteModernBertBase => "Alibaba-NLP/gte-modernbert-base",
-            EmbeddingModel::JinaEmbeddingsV3 => "jinaai/jina-embeddings-v3",
-            EmbeddingModel::Custom => {
-                self.custom_model_name.as_ref().ok_or_else(|| {
-                    EngramError::InvalidOperation("Custom model name not set".to_string())
-                })?
-            }
-        };
-        
-        // Format text for E5 models which need a specific prefix
-        let input_text = if self.use_instruction_prefix && 
-                            (self.model == EmbeddingModel::E5MultilingualLargeInstruct || 
-                             model_name.contains("e5")) {
-            format!("passage: {}", text)
-        } else {
-            text.to_string()
-        };
-        
         let client = Client::new();
-        let url = format!("https://api-inference.huggingface.co/models/{}", model_name);
+        let url = format!("https://api-inference.huggingface.co/models/{}", self.model_name);
         
         // Prepare the request payload based on model
         let payload = serde_json::json!({
-            "inputs": input_text,
+            "inputs": text,
             "options": {
                 "wait_for_model": true
             }
@@ -412,199 +449,333 @@ impl EmbeddingService {
         
         Ok(vector)
     }
-    
-    /// Generate embedding using a deterministic fallback method (for testing or when API is unavailable)
-    fn generate_deterministic_embedding(&self, text: &str, dimensions: usize) -> Vec<f32> {
-        let mut vector = Vec::with_capacity(dimensions);
-        
-        // Generate a deterministic embedding based on the hash of the text
-        let text_hash = text.bytes().fold(0u64, |acc, b| acc.wrapping_add(b as u64));
-        let mut value = text_hash;
-        
-        for _ in 0..dimensions {
-            // Generate pseudorandom but deterministic values from the text hash
-            value = value.wrapping_mul(6364136223846793005).wrapping_add(1);
-            let float_val = (value % 1000) as f32 / 500.0 - 1.0;
-            vector.push(float_val);
+}
+
+impl EmbeddingBackend for HuggingFaceBackend {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        match self.request_embedding(text) {
+            Ok(vector) => Ok(vector),
+            Err(e) => {
+                eprintln!("Warning: HuggingFace API error: {}. Falling back to deterministic embeddings.", e);
+                self.fallback.embed(text)
+            }
         }
-        
-        vector
     }
     
-    /// Create an embedding from text
-    pub fn embed_text(&self, text: &str) -> Result<Embedding> {
-        // Generate a cache key that includes whether this is a reduced embedding
-        let cache_key = if self.use_reduced_embeddings {
-            format!("reduced:{}", text)
-        } else {
-            text.to_string()
-        };
-        
-        // Check if we have this in the cache
-        if let Ok(mut cache) = self.cache.lock() {
-            if let Some(embedding) = cache.get(&cache_key) {
-                return Ok(embedding);
-            }
-        }
-        
-        // Get model info
-        let dimensions = self.get_dimensions();
-        let model_name = self.get_model_name();
-        
-        // Generate the embedding vector
-        let vector = match self.model {
-            // For E5, GTE, or Jina models, use the Hugging Face API if available
-            EmbeddingModel::E5MultilingualLargeInstruct | 
-            EmbeddingModel::GteModernBertBase | 
-            EmbeddingModel::JinaEmbeddingsV3 | 
-            EmbeddingModel::Custom
-                if crate::utils::has_huggingface_capabilities() => {
-                match self.generate_huggingface_embedding(text) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!("Warning: HuggingFace API error: {}. Falling back to deterministic embeddings.", e);
-                        self.generate_deterministic_embedding(text, dimensions)
-                    }
-                }
-            },
-            // For all other cases, use the deterministic fallback method
-            _ => self.generate_deterministic_embedding(text, dimensions)
-        };
-        
-        // Create the embedding
-        let mut embedding = Embedding::new(vector, model_name);
-        
-        // Add metadata about the generation
-        let mut metadata = HashMap::new();
-        metadata.insert("text_length".to_string(), text.len().to_string());
-        metadata.insert("model_type".to_string(), format!("{:?}", self.model));
-        embedding.metadata = metadata;
-        
-        // Normalize if requested
-        if self.normalize_embeddings {
-            embedding.normalize();
-        }
-        
-        // Apply dimensionality reduction if configured and requested
-        let final_embedding = if self.use_reduced_embeddings {
-            if let Some(reducer_arc) = &self.dimension_reducer {
-                let reducer = reducer_arc.lock().map_err(|_| {
-                    EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
-                })?;
-                
-                if reducer.is_trained() {
-                    // Reduce the dimensionality
-                    reducer.reduce(&embedding)?
-                } else {
-                    // If reducer exists but isn't trained, return the original embedding
-                    embedding
-                }
-            } else {
-                // If no reducer is configured, return the original embedding
-                embedding
-            }
-        } else {
-            // If not using reduced embeddings, return the original
-            embedding
-        };
-        
-        // Add to cache
-        if let Ok(mut cache) = self.cache.lock() {
-            cache.insert(cache_key, final_embedding.clone());
-        }
-        
-        Ok(final_embedding)
+    fn dimensions(&self) -> usize {
+        self.dimensions
     }
     
-    /// Generate embeddings for multiple texts using Hugging Face
-    fn generate_huggingface_batch_embeddings(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        // Hugging Face doesn't have a great batch API for embeddings
-        // We'll process each text individually for correctness and reliability
-        let mut result = Vec::with_capacity(texts.len());
-        
-        for text in texts {
-            let vector = self.generate_huggingface_embedding(text)?;
-            result.push(vector);
-        }
-        
-        Ok(result)
+    fn name(&self) -> &str {
+        &self.model_name
     }
     
-    /// Embed multiple texts in a batch
-    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
-        if texts.is_empty() {
-            return Ok(Vec::new());
-        }
-        
-        // Check if we can use the Hugging Face API for batch embeddings
-        if (self.model == EmbeddingModel::E5MultilingualLargeInstruct || 
-            self.model == EmbeddingModel::GteModernBertBase || 
-            self.model == EmbeddingModel::JinaEmbeddingsV3 || 
-            self.model == EmbeddingModel::Custom) && 
-            crate::utils::has_huggingface_capabilities() {
-            
-            // Try to use the batch API
-            match self.generate_huggingface_batch_embeddings(texts) {
-                Ok(vectors) => {
-                    let model_name = self.get_model_name();
-                    let mut embeddings = Vec::with_capacity(vectors.len());
-                    
-                    for (i, vector) in vectors.into_iter().enumerate() {
-                        let mut embedding = Embedding::new(vector, model_name.clone());
-                        
-                        // Add metadata
-                        let mut metadata = HashMap::new();
-                        metadata.insert("text_length".to_string(), texts[i].len().to_string());
-                        metadata.insert("model_type".to_string(), format!("{:?}", self.model));
-                        metadata.insert("batch_index".to_string(), i.to_string());
-                        embedding.metadata = metadata;
-                        
-                        // Normalize if requested
-                        if self.normalize_embeddings {
-                            embedding.normalize();
-                        }
-                        
-                        // Apply dimensionality reduction if configured and requested
-                        if self.use_reduced_embeddings {
-                            if let Some(reducer_arc) = &self.dimension_reducer {
-                                let reducer = reducer_arc.lock().map_err(|_| {
-                                    EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
-                                })?;
-                                
-                                if reducer.is_trained() {
-                                    // Reduce the dimensionality
-                                    embedding = reducer.reduce(&embedding)?;
-                                }
-                            }
-                        }
-                        
-                        embeddings.push(embedding);
-                    }
-                    
-                    return Ok(embeddings);
-                },
-                Err(e) => {
-                    eprintln!("Warning: HuggingFace API batch error: {}. Falling back to individual embeddings.", e);
-                    // Fall back to individual processing
-                }
-            }
-        }
-        
-        // Individual processing (fallback or default for non-Anthropic models)
-        let mut embeddings = Vec::with_capacity(texts.len());
-        for text in texts {
-            embeddings.push(self.embed_text(text)?);
+    fn instruction_prefix(&self) -> Option<&str> {
+        // E5 models expect passages to be marked as such
+        if self.model_name.contains("e5") {
+            Some("passage: ")
+        } else {
+            None
         }
-        
-        Ok(embeddings)
     }
+}
+
+/// Service for generating and managing embeddings
+pub struct EmbeddingService {
+    /// The model to use for embeddings
+    model: EmbeddingModel,
     
-    /// Create a batch processing utility for dimensionality reduction
-    pub fn batch_reduce_embeddings(&self, storage: &crate::storage::Storage) -> Result<usize> {
-        // 1. Get a list of all engram IDs
-        let engram_ids = storage.list_engrams()?;
-        
-        if engram_ids.is_empty() {
+    /// Generates the raw vectors
+    backend: Box<dyn EmbeddingBackend>,
+    
+    /// Whether to normalize embeddings
+    normalize_embeddings: bool,
+    
+    /// Whether to use reduced dimensionality embeddings
+    use_reduced_embeddings: bool,
+    
+    /// Cache for embeddings to avoid recomputation
+    cache: Arc<Mutex<EmbeddingCache>>,
+    
+    /// Optional dimension reducer for storage optimization
+    pub dimension_reducer: Option<Arc<Mutex<dyn DimensionReducer + Send>>>,
+    
+    /// Whether to use instruction prefix for embedding generation
+    use_instruction_prefix: bool,
+    
+    /// Metric used when comparing embeddings
+    similarity_metric: SimilarityMetric,
+    
+    /// Truncate generated vectors to this many leading dimensions (Matryoshka-style)
+    target_dimensions: Option<usize>,
+}
+
+impl EmbeddingService {
+    /// Create a new embedding service with default settings
+    pub fn new() -> Self {
+        Self::with_model_type(EmbeddingModel::E5MultilingualLargeInstruct)
+    }
+    
+    /// Create a service with a specific model type
+    pub fn with_model_type(model: EmbeddingModel) -> Self {
+        let name = model.hub_name().unwrap_or("custom-model");
+        Self::from_backend(model, Self::default_backend(name, model.default_dimensions()))
+    }
+    
+    /// Create a service with a custom model name
+    pub fn with_model(model_name: &str) -> Self {
+        let dimensions = EmbeddingModel::Custom.default_dimensions();
+        Self::from_backend(EmbeddingModel::Custom, Self::default_backend(model_name, dimensions))
+    }
+    
+    /// Create a service that generates vectors with the given backend
+    pub fn with_backend(backend: Box<dyn EmbeddingBackend>) -> Self {
+        Self::from_backend(EmbeddingModel::Custom, backend)
+    }
+    
+    /// The HuggingFace API when a key is available, otherwise deterministic vectors
+    fn default_backend(model_name: &str, dimensions: usize) -> Box<dyn EmbeddingBackend> {
+        if crate::utils::has_huggingface_capabilities() {
+            Box::new(HuggingFaceBackend::new(model_name, dimensions))
+        } else {
+            Box::new(DeterministicBackend::new(model_name, dimensions))
+        }
+    }
+    
+    fn from_backend(model: EmbeddingModel, backend: Box<dyn EmbeddingBackend>) -> Self {
+        Self {
+            model,
+            backend,
+            normalize_embeddings: true,
+            use_reduced_embeddings: false,
+            cache: Arc::new(Mutex::new(EmbeddingCache::new(1000))),
+            dimension_reducer: None,
+            use_instruction_prefix: true,
+            similarity_metric: SimilarityMetric::Cosine,
+            target_dimensions: None,
+        }
+    }
+    
+    /// Configure whether to normalize embeddings
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize_embeddings = normalize;
+        self
+    }
+    
+    /// Configure whether to use reduced dimensionality embeddings
+    pub fn with_reduced_embeddings(mut self, use_reduced: bool) -> Self {
+        self.use_reduced_embeddings = use_reduced;
+        self
+    }
+    
+    /// Set the dimension reducer to use
+    pub fn with_dimension_reducer(mut self, reducer: Arc<Mutex<dyn DimensionReducer + Send>>) -> Self {
+        self.dimension_reducer = Some(reducer);
+        self
+    }
+    
+    /// Configure whether to use instruction prefixes
+    pub fn with_instruction_prefix(mut self, use_prefix: bool) -> Self {
+        self.use_instruction_prefix = use_prefix;
+        self
+    }
+    
+    /// Configure the metric used to compare embeddings
+    pub fn with_similarity_metric(mut self, metric: SimilarityMetric) -> Self {
+        self.similarity_metric = metric;
+        self
+    }
+    
+    /// Truncate every generated embedding to its first `dimensions` components and
+    /// renormalize it. Only meaningful for Matryoshka-trained models, whose leading
+    /// dimensions carry most of the signal. Larger values than the model produces are ignored.
+    pub fn with_target_dimensions(mut self, dimensions: usize) -> Self {
+        self.target_dimensions = Some(dimensions.max(1));
+        self
+    }
+    
+    /// Get the configured truncation size, if any
+    pub fn get_target_dimensions(&self) -> Option<usize> {
+        self.target_dimensions
+    }
+    
+    /// Get the metric used to compare embeddings
+    pub fn get_similarity_metric(&self) -> SimilarityMetric {
+        self.similarity_metric
+    }
+    
+    /// Compute the similarity between two embeddings using the configured metric.
+    /// Higher values always mean more similar, whatever the underlying metric.
+    pub fn similarity(&self, a: &Embedding, b: &Embedding) -> Result<f32> {
+        a.similarity(b, self.similarity_metric)
+    }
+    
+    /// Get the embeddinng dimensions for the current model, after any truncation
+    pub fn get_dimensions(&self) -> usize {
+        match self.target_dimensions {
+            Some(target) => target.min(self.backend.dimensions()),
+            None => self.backend.dimensions(),
+        }
+    }
+    
+    /// Get the embedding model name as a string
+    pub fn get_model_name(&self) -> String {
+        self.backend.name().to_string()
+    }
+    
+    /// Configure a dimension reducer for the embedding service
+    pub fn set_dimension_reducer(&mut self, reducer: Arc<Mutex<dyn DimensionReducer + Send>>) {
+        self.dimension_reducer = Some(reducer);
+    }
+    
+    /// Reduce the dimensionality of an embedding
+    pub fn reduce_embedding(&self, embedding: &Embedding) -> Result<Embedding> {
+        // Check if we have a dimension reducer
+        if let Some(reducer_arc) = &self.dimension_reducer {
+            let reducer = reducer_arc.lock().map_err(|_| {
+                EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
+            })?;
+            
+            if !reducer.is_trained() {
+                return Err(EngramError::InvalidState("Dimension reducer is not trained".to_string()));
+            }
+            
+            reducer.reduce(embedding)
+        } else {
+            Err(EngramError::InvalidOperation("No dimension reducer configured".to_string()))
+        }
+    }
+    
+    /// Train the dimension reducer with a set of embeddings
+    pub fn train_reducer(&self, embeddings: &[Embedding]) -> Result<()> {
+        // Check if we have a dimension reducer
+        if let Some(reducer_arc) = &self.dimension_reducer {
+            let mut reducer = reducer_arc.lock().map_err(|_| {
+                EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
+            })?;
+            
+            // Train the reducer
+            reducer.train(embeddings)
+        } else {
+            Err(EngramError::InvalidOperation("No dimension reducer configured".to_string()))
+        }
+    }
+    
+    /// Text as sent to the backend, with the model's instruction prefix if enabled
+    fn backend_input(&self, text: &str) -> String {
+        match self.backend.instruction_prefix() {
+            Some(prefix) if self.use_instruction_prefix => format!("{}{}", prefix, text),
+            _ => text.to_string(),
+        }
+    }
+    
+    /// Wrap a raw backend vector as an embedding, then truncate, normalize and reduce it as configured
+    fn finish_embedding(&self, mut vector: Vec<f32>, text: &str, normalize: bool, batch_index: Option<usize>) -> Result<Embedding> {
+        let original_dimensions = vector.len();
+        if let Some(target) = self.target_dimensions {
+            vector.truncate(target);
+        }
+        let mut embedding = Embedding::new(vector, self.get_model_name());
+        
+        // Add metadata about the generation
+        let mut metadata = HashMap::new();
+        metadata.insert("text_length".to_string(), text.len().to_string());
+        metadata.insert("model_type".to_string(), format!("{:?}", self.model));
+        if let Some(index) = batch_index {
+            metadata.insert("batch_index".to_string(), index.to_string());
+        }
+        if embedding.dimensions < original_dimensions {
+            metadata.insert("truncated_from".to_string(), original_dimensions.to_string());
+        }
+        metadata.insert("normalized".to_string(), "false".to_string());
+        embedding.metadata = metadata;
+        
+        // Normalize if requested; this also renormalizes a truncated vector
+        if normalize {
+            embedding.normalize();
+        }
+        
+        // Apply dimensionality reduction if configured and requested; an untrained or
+        // missing reducer leaves the embedding as it is
+        if self.use_reduced_embeddings {
+            if let Some(reducer_arc) = &self.dimension_reducer {
+                let reducer = reducer_arc.lock().map_err(|_| {
+                    EngramError::ConcurrencyError("Failed to acquire lock on dimension reducer".to_string())
+                })?;
+                
+                if reducer.is_trained() {
+                    embedding = reducer.reduce(&embedding)?;
+                }
+            }
+        }
+        
+        Ok(embedding)
+    }
+    
+    /// Create an embedding from text
+    pub fn embed_text(&self, text: &str) -> Result<Embedding> {
+        self.embed_text_with_normalization(text, self.normalize_embeddings)
+    }
+    
+    /// Create an embedding from text, overriding the service-wide normalization setting.
+    /// Use `normalize = false` to keep the raw vector, e.g. when vector magnitude matters.
+    pub fn embed_text_with_normalization(&self, text: &str, normalize: bool) -> Result<Embedding> {
+        // Generate a cache key that includes whether this is a reduced or raw embedding
+        let mut cache_key = if self.use_reduced_embeddings {
+            format!("reduced:{}", text)
+        } else {
+            text.to_string()
+        };
+        if !normalize {
+            cache_key = format!("raw:{}", cache_key);
+        }
+        
+        // Check if we have this in the cache
+        if let Ok(mut cache) = self.cache.lock() {
+            if let Some(embedding) = cache.get(&cache_key) {
+                return Ok(embedding);
+            }
+        }
+        
+        let vector = self.backend.embed(&self.backend_input(text))?;
+        let embedding = self.finish_embedding(vector, text, normalize, None)?;
+        
+        // Add to cache
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(cache_key, embedding.clone());
+        }
+        
+        Ok(embedding)
+    }
+    
+    /// Embed multiple texts in a batch
+    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let inputs: Vec<String> = texts.iter().map(|text| self.backend_input(text)).collect();
+        let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+        
+        match self.backend.embed_batch(&inputs) {
+            Ok(vectors) => vectors
+                .into_iter()
+                .enumerate()
+                .map(|(i, vector)| self.finish_embedding(vector, texts[i], self.normalize_embeddings, Some(i)))
+                .collect(),
+            Err(e) => {
+                eprintln!("Warning: Embedding backend batch error: {}. Falling back to individual embeddings.", e);
+                texts.iter().map(|text| self.embed_text(text)).collect()
+            }
+        }
+    }
+    
+    /// Create a batch processing utility for dimensionality reduction
+    pub fn batch_reduce_embeddings(&self, storage: &crate::storage::Storage) -> Result<usize> {
+        // 1. Get a list of all engram IDs
+        let engram_ids = storage.list_engrams()?;
+        
+        if engram_ids.is_empty() {
             return Ok(0);
         }
         
@@ -674,11 +845,55 @@ impl EmbeddingService {
         Ok(reduced_count)
     }
     
-    /// Load a model from a file path (for local models)
-    pub fn load_model_from_path(&self, _path: &Path) -> Result<()> {
-        // This would load a local model from a file
-        // For now, just a placeholder
-        Err(EngramError::NotImplemented("Loading local models not yet implemented".to_string()))
+    /// Load a local model and use it for all subsequent embeddings. `path` is either the
+    /// `.onnx` file or a directory containing `model.onnx`; the tokenizer is read from
+    /// `tokenizer.json` alongside it. Afterwards `get_dimensions` and `get_model_name`
+    /// describe the loaded model, and previously cached embeddings are discarded.
+    /// Requires the `onnx` feature.
+    pub fn load_model_from_path(&mut self, path: &Path) -> Result<()> {
+        let model_path = if path.is_dir() { path.join("model.onnx") } else { path.to_path_buf() };
+        if !model_path.is_file() {
+            return Err(EngramError::NotFound(format!("Model file not found: {}", model_path.display())));
+        }
+        
+        let tokenizer_path = model_path.with_file_name("tokenizer.json");
+        if !tokenizer_path.is_file() {
+            return Err(EngramError::NotFound(format!("Tokenizer not found: {}", tokenizer_path.display())));
+        }
+        
+        // Name the model after its directory, e.g. "all-MiniLM-L6-v2/model.onnx"
+        let name = model_path.parent()
+            .and_then(|dir| dir.file_name())
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "local-model".to_string());
+        
+        let backend = Self::load_local_backend(&name, &model_path, &tokenizer_path)?;
+        if backend.dimensions() == 0 {
+            return Err(EngramError::InvalidState(format!(
+                "Could not determine the output dimensionality of {}", model_path.display()
+            )));
+        }
+        
+        self.model = EmbeddingModel::Custom;
+        self.backend = backend;
+        
+        // Cached vectors came from the previous model and are no longer comparable
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.clear();
+        }
+        
+        Ok(())
+    }
+    
+    #[cfg(feature = "onnx")]
+    fn load_local_backend(name: &str, model_path: &Path, tokenizer_path: &Path) -> Result<Box<dyn EmbeddingBackend>> {
+        let backend = crate::onnx_embedding::OnnxEmbeddingBackend::load(name, model_path, tokenizer_path)?;
+        Ok(Box::new(backend))
+    }
+    
+    #[cfg(not(feature = "onnx"))]
+    fn load_local_backend(_name: &str, _model_path: &Path, _tokenizer_path: &Path) -> Result<Box<dyn EmbeddingBackend>> {
+        Err(EngramError::NotImplemented("Loading local models requires the `onnx` feature".to_string()))
     }
 }
 
@@ -731,6 +946,22 @@ impl PartialEq for HnswEntry {
 
 impl Eq for HnswEntry {}
 
+/// Search width used when a query does not override it
+pub const DEFAULT_EF_SEARCH: usize = 50;
+
+/// Pick the search width for a query: the override if given (which must cover `k`),
+/// otherwise the index default widened to `k` when needed
+fn resolve_ef_search(ef_search: Option<usize>, default: usize, k: usize) -> Result<usize> {
+    match ef_search {
+        Some(ef) if ef < k => Err(EngramError::InvalidOperation(format!(
+            "ef_search ({}) must be at least the number of requested results ({})",
+            ef, k
+        ))),
+        Some(ef) => Ok(ef),
+        None => Ok(default.max(k)),
+    }
+}
+
 /// HNSW Vector index for efficient similarity search
 /// Based on the paper "Efficient and robust approximate nearest neighbor search using Hierarchical Navigable Small World graphs"
 pub struct HnswIndex {
@@ -765,7 +996,7 @@ impl HnswIndex {
         Self {
             m: 16,                // Default M parameter (max connections per node)
             ef_construction: 200, // Default ef construction (search width during construction)
-            ef_search: 50,        // Default ef search (search width during query)
+            ef_search: DEFAULT_EF_SEARCH, // Default ef search (search width during query)
             dimensions,
             max_layer: 0,
             nodes: Vec::new(),
@@ -874,9 +1105,17 @@ impl HnswIndex {
         Ok(())
     }
     
-    /// Search for the nearest neighbors to a query vector
-    pub fn search(&self, query: &Embedding, k: usize) -> Result<Vec<(String, f32)>> {
-        // Validate dimensions
+    /// Search for the nearest neighbors to a query vector.
+    /// `ef_search` overrides the index's search width for this query only; wider searches
+    /// trade latency for recall and must be at least `k`.
+    pub fn search(&self, query: &Embedding, k: usize, ef_search: Option<usize>) -> Result<Vec<(String, f32)>> {
+        let ef = resolve_ef_search(ef_search, self.ef_search, k)?;
+        self.search_with_ef(query, k, ef)
+    }
+    
+    /// Search for the nearest neighbors with search width `ef`, clamped to at least `k`.
+    /// A small `ef` keeps latency low; a large one improves recall for batch-quality queries.
+    pub fn search_with_ef(&self, query: &Embedding, k: usize, ef: usize) -> Result<Vec<(String, f32)>> {
         if query.dimensions != self.dimensions {
             return Err(EngramError::InvalidOperation(format!(
                 "Query dimensions ({}) don't match index dimensions ({})",
@@ -884,66 +1123,7 @@ impl HnswIndex {
             )));
         }
         
-        // Handle empty index case
-        if self.nodes.is_empty() {
-            return Ok(Vec::new());
-        }
-        
-        // If k is larger than the number of nodes, adjust it
-        let k = std::cmp::min(k, self.nodes.len());
-        
-        // For testing with small indices, do a simple linear search to ensure correctness
-        if cfg!(test) && self.nodes.len() < 10 {
-            let mut results = Vec::with_capacity(self.id_to_index.len());
-            
-            // Calculate similarity for all active nodes
-            for node in &self.nodes {
-                // Skip nodes that have been removed (not in id_to_index)
-                if !self.id_to_index.contains_key(&node.id) {
-                    continue;
-                }
-                
-                if let Ok(sim) = query.cosine_similarity(&node.embedding) {
-                    results.push((node.id.clone(), sim));
-                }
-            }
-            
-            // Sort by similarity (highest first)
-            results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            
-            // Return top k
-            return Ok(results.into_iter().take(k).collect());
-        }
-        
-        // For non-test or larger indices, use the HNSW algorithm
-        // Start at the entry point at the top layer
-        let mut entry_point = self.entry_points[self.max_layer];
-        
-        // Search from top layer to bottom
-        for layer in (1..=self.max_layer).rev() {
-            let nearest = self.search_layer_heuristic(query, entry_point, 1, layer)?;
-            if !nearest.is_empty() {
-                entry_point = nearest[0].index;
-            }
-        }
-        
-        // Search thoroughly at the bottom layer
-        let nearest = self.search_layer_heuristic(query, entry_point, k, 0)?;
-        
-        // Convert results to (id, similarity) pairs
-        let mut results = Vec::with_capacity(nearest.len());
-        
-        for entry in nearest {
-            // Convert distance to similarity (assuming normalized embeddings)
-            // For cosine distance, similarity = 1 - distance
-            let similarity = 1.0 - entry.distance;
-            results.push((self.nodes[entry.index].id.clone(), similarity));
-        }
-        
-        // Ensure results are sorted by similarity (highest first)
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        
-        Ok(results)
+        Ok(search_graph(self, &query.vector, k, ef.max(k)))
     }
     
     /// Remove a vector from the index
@@ -1012,6 +1192,30 @@ impl HnswIndex {
         self.max_layer = 0;
     }
     
+    /// Check whether an ID is in the index
+    pub fn contains(&self, id: &str) -> bool {
+        self.id_to_index.contains_key(id)
+    }
+    
+    /// Get the dimensionality of vectors in the index
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+    
+    /// Get the IDs of all vectors in the index
+    pub fn ids(&self) -> Vec<String> {
+        self.id_to_index.keys().cloned().collect()
+    }
+    
+    /// Get the ID and embedding of every vector in the index
+    pub fn entries(&self) -> Vec<(&str, &Embedding)> {
+        self.id_to_index
+            .iter()
+            .filter(|(_, &index)| index < self.nodes.len())
+            .map(|(id, &index)| (id.as_str(), &self.nodes[index].embedding))
+            .collect()
+    }
+    
     /// Get an embedding by ID
     pub fn get_embedding(&self, id: &str) -> Option<Embedding> {
         // Check if the ID exists in the index
@@ -1028,7 +1232,7 @@ impl HnswIndex {
         #[cfg(test)]
         {
             // For tests, always use layer 0 for deterministic results
-            return 0;
+            0
         }
         
         #[cfg(not(test))]
@@ -1099,10 +1303,7 @@ impl HnswIndex {
         conn_with_dist.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
         
         // Keep only the M closest
-        let mut new_connections = HashSet::new();
-        for i in 0..std::cmp::min(self.m, conn_with_dist.len()) {
-            new_connections.insert(conn_with_dist[i].0);
-        }
+        let new_connections: HashSet<usize> = conn_with_dist.iter().take(self.m).map(|(index, _)| *index).collect();
         
         // Update the connections
         self.nodes[node_index].connections[layer] = new_connections;
@@ -1183,93 +1384,797 @@ impl HnswIndex {
         Ok(result_vec)
     }
     
-    /// Search for nearest neighbors at a specific layer using a query embedding
-    fn search_layer_heuristic(&self, query: &Embedding, entry_point: usize, ef: usize, layer: usize) -> Result<Vec<HnswEntry>> {
-        // Initialize visited set
-        let mut visited = HashSet::new();
+    /// Find a new entry point for a layer after a node is removed
+    fn find_new_entry_point(&self, layer: usize) -> Result<usize> {
+        // Find any node that has this layer
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if layer < node.connections.len() && self.id_to_index.contains_key(&node.id) {
+                return Ok(idx);
+            }
+        }
         
-        // Initialize candidates queue and results
-        let mut candidates = BinaryHeap::new();
-        let mut results = BinaryHeap::new();
+        // If no node has this layer, return the first valid node
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if self.id_to_index.contains_key(&node.id) {
+                return Ok(idx);
+            }
+        }
         
-        // Calculate initial distance
-        let entry_embedding = &self.nodes[entry_point].embedding;
-        let distance = 1.0 - query.cosine_similarity(entry_embedding)?;
+        // If no valid node is found, return an error
+        Err(EngramError::InvalidState(format!("No valid entry point found for layer {}", layer)))
+    }
+}
+
+/// Read access to an HNSW graph, shared by the in-RAM and memory-mapped layouts
+trait HnswGraph {
+    /// Total number of node slots (including removed nodes)
+    fn node_count(&self) -> usize;
+    
+    /// Whether the node at this slot is still part of the index
+    fn is_active(&self, index: usize) -> bool;
+    
+    /// The ID of the node at this slot
+    fn node_id(&self, index: usize) -> String;
+    
+    /// The vector of the node at this slot
+    fn vector(&self, index: usize) -> Cow<'_, [f32]>;
+    
+    /// Neighbors of a node at a layer, in a stable order
+    fn neighbors(&self, index: usize, layer: usize) -> Vec<usize>;
+    
+    /// The highest layer in the graph
+    fn max_layer(&self) -> usize;
+    
+    /// The entry point for a layer
+    fn entry_point(&self, layer: usize) -> usize;
+}
+
+/// Cosine similarity between two vectors of equal length
+fn cosine_similarity_slices(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot_product = 0.0;
+    let mut a_norm = 0.0;
+    let mut b_norm = 0.0;
+    
+    for (x, y) in a.iter().zip(b) {
+        dot_product += x * y;
+        a_norm += x * x;
+        b_norm += y * y;
+    }
+    
+    if a_norm == 0.0 || b_norm == 0.0 {
+        return 0.0;
+    }
+    
+    dot_product / (a_norm.sqrt() * b_norm.sqrt())
+}
+
+/// Search for the nearest neighbors of a query in any HNSW graph layout
+fn search_graph<G: HnswGraph>(graph: &G, query: &[f32], k: usize, ef: usize) -> Vec<(String, f32)> {
+    let node_count = graph.node_count();
+    if node_count == 0 {
+        return Vec::new();
+    }
+    
+    // If k is larger than the number of nodes, adjust it
+    let k = std::cmp::min(k, node_count);
+    
+    // For testing with small indices, do a simple linear search to ensure correctness
+    if cfg!(test) && node_count < 10 {
+        let mut results = Vec::with_capacity(node_count);
         
-        // Add entry point to both queues
-        candidates.push(HnswEntry { index: entry_point, distance });
-        results.push(HnswEntry { index: entry_point, distance });
-        visited.insert(entry_point);
+        for index in 0..node_count {
+            // Skip nodes that have been removed
+            if !graph.is_active(index) {
+                continue;
+            }
+            
+            let similarity = cosine_similarity_slices(query, &graph.vector(index));
+            results.push((graph.node_id(index), similarity));
+        }
         
-        // Process candidates
-        while let Some(current) = candidates.pop() {
-            // If the farthest result is closer than the closest candidate, we're done
-            if let Some(farthest) = results.peek() {
-                if current.distance > farthest.distance {
-                    break;
+        // Sort by similarity (highest first)
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        
+        return results.into_iter().take(k).collect();
+    }
+    
+    // Start at the entry point at the top layer
+    let mut entry_point = graph.entry_point(graph.max_layer());
+    
+    // Search from top layer to bottom
+    for layer in (1..=graph.max_layer()).rev() {
+        let nearest = search_graph_layer(graph, query, entry_point, 1, layer);
+        if !nearest.is_empty() {
+            entry_point = nearest[0].index;
+        }
+    }
+    
+    // Search thoroughly at the bottom layer, keeping `ef` candidates before trimming to `k`
+    let nearest = search_graph_layer(graph, query, entry_point, ef.max(k), 0);
+    
+    // Convert distance to similarity: for cosine distance, similarity = 1 - distance
+    let mut results: Vec<(String, f32)> = nearest
+        .into_iter()
+        .map(|entry| (graph.node_id(entry.index), 1.0 - entry.distance))
+        .collect();
+    
+    // Ensure results are sorted by similarity (highest first)
+    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    results.truncate(k);
+    
+    results
+}
+
+/// Search for nearest neighbors of a query at a specific layer of any HNSW graph layout
+fn search_graph_layer<G: HnswGraph>(graph: &G, query: &[f32], entry_point: usize, ef: usize, layer: usize) -> Vec<HnswEntry> {
+    let mut visited = HashSet::new();
+    let mut candidates = BinaryHeap::new();
+    let mut results = BinaryHeap::new();
+    
+    // Add entry point to both queues
+    let distance = 1.0 - cosine_similarity_slices(query, &graph.vector(entry_point));
+    candidates.push(HnswEntry { index: entry_point, distance });
+    results.push(HnswEntry { index: entry_point, distance });
+    visited.insert(entry_point);
+    
+    // Process candidates
+    while let Some(current) = candidates.pop() {
+        // If the farthest result is closer than the closest candidate, we're done
+        if let Some(farthest) = results.peek() {
+            if current.distance > farthest.distance {
+                break;
+            }
+        }
+        
+        for neighbor_idx in graph.neighbors(current.index, layer) {
+            // Skip invalid indices
+            if neighbor_idx >= graph.node_count() || !visited.insert(neighbor_idx) {
+                continue;
+            }
+            
+            let distance = 1.0 - cosine_similarity_slices(query, &graph.vector(neighbor_idx));
+            
+            // If results is not full yet or this neighbor is closer than the farthest result
+            if results.len() < ef || distance < results.peek().unwrap().distance {
+                candidates.push(HnswEntry { index: neighbor_idx, distance });
+                results.push(HnswEntry { index: neighbor_idx, distance });
+                
+                // If results is too big, remove the farthest
+                if results.len() > ef {
+                    results.pop();
                 }
             }
+        }
+    }
+    
+    // Convert results into a vector, closest first
+    let mut result_vec = Vec::with_capacity(results.len());
+    while let Some(entry) = results.pop() {
+        result_vec.push(entry);
+    }
+    result_vec.reverse();
+    
+    result_vec
+}
+
+impl HnswGraph for HnswIndex {
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    
+    fn is_active(&self, index: usize) -> bool {
+        self.id_to_index.get(&self.nodes[index].id) == Some(&index)
+    }
+    
+    fn node_id(&self, index: usize) -> String {
+        self.nodes[index].id.clone()
+    }
+    
+    fn vector(&self, index: usize) -> Cow<'_, [f32]> {
+        Cow::Borrowed(&self.nodes[index].embedding.vector)
+    }
+    
+    fn neighbors(&self, index: usize, layer: usize) -> Vec<usize> {
+        self.nodes[index]
+            .connections
+            .get(layer)
+            .map(|neighbors| neighbors.iter().copied().collect())
+            .unwrap_or_default()
+    }
+    
+    fn max_layer(&self) -> usize {
+        self.max_layer
+    }
+    
+    fn entry_point(&self, layer: usize) -> usize {
+        self.entry_points[layer]
+    }
+}
+
+/// Magic bytes identifying a saved HNSW index file
+const MMAP_INDEX_MAGIC: &[u8; 8] = b"HNSWMMAP";
+
+/// Version of the HNSW index file layout
+const MMAP_INDEX_VERSION: u32 = 2;
+
+/// Size of the fixed file header: magic, version, padding, then seven u64 fields
+const MMAP_HEADER_SIZE: usize = 8 + 4 + 4 + 7 * 8;
+
+impl HnswIndex {
+    /// Write the index to a file that `HnswIndex::load` reads back into RAM and
+    /// `MmapHnswIndex::open` maps in place, so the index doesn't have to be rebuilt on every start.
+    ///
+    /// Layout (little-endian): a fixed header (magic, version, dimensions, node count, max layer,
+    /// m, ef_construction, ef_search, entry point count), the entry point of each layer, a table
+    /// of per-node record offsets, all vectors as one contiguous `f32` block, then per-node
+    /// records holding the ID, an active flag, the neighbor lists of each layer, and the
+    /// embedding model and metadata.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let node_count = self.nodes.len();
+        let entry_points_size = self.entry_points.len() * 8;
+        let offsets_size = node_count * 8;
+        let vectors_size = node_count * self.dimensions * 4;
+        
+        let write_str = |bytes: &mut Vec<u8>, value: &str| {
+            bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        };
+        
+        // Serialize the variable-length node records first so their offsets are known
+        let records_start = MMAP_HEADER_SIZE + entry_points_size + offsets_size + vectors_size;
+        let mut records = Vec::new();
+        let mut offsets = Vec::with_capacity(node_count);
+        
+        for index in 0..node_count {
+            offsets.push((records_start + records.len()) as u64);
             
-            // Process all neighbors of the current node at this layer
-            let current_node = &self.nodes[current.index];
-            if layer < current_node.connections.len() {
-                for &neighbor_idx in &current_node.connections[layer] {
-                    // Skip invalid indices
-                    if neighbor_idx >= self.nodes.len() {
-                        continue;
-                    }
-                    
-                    if !visited.contains(&neighbor_idx) {
-                        visited.insert(neighbor_idx);
-                        
-                        let neighbor_embedding = &self.nodes[neighbor_idx].embedding;
-                        let distance = 1.0 - query.cosine_similarity(neighbor_embedding)?;
-                        
-                        // If results is not full yet or this neighbor is closer than the farthest result
-                        if results.len() < ef || distance < results.peek().unwrap().distance {
-                            candidates.push(HnswEntry { index: neighbor_idx, distance });
-                            results.push(HnswEntry { index: neighbor_idx, distance });
-                            
-                            // If results is too big, remove the farthest
-                            if results.len() > ef {
-                                results.pop();
-                            }
-                        }
-                    }
+            let node = &self.nodes[index];
+            write_str(&mut records, &node.id);
+            records.push(u8::from(self.is_active(index)));
+            records.extend_from_slice(&(node.connections.len() as u32).to_le_bytes());
+            
+            for layer in 0..node.connections.len() {
+                let mut neighbors = self.neighbors(index, layer);
+                neighbors.sort_unstable();
+                records.extend_from_slice(&(neighbors.len() as u32).to_le_bytes());
+                for neighbor in neighbors {
+                    records.extend_from_slice(&(neighbor as u32).to_le_bytes());
                 }
             }
+            
+            write_str(&mut records, &node.embedding.model);
+            let mut metadata: Vec<(&String, &String)> = node.embedding.metadata.iter().collect();
+            metadata.sort();
+            records.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+            for (key, value) in metadata {
+                write_str(&mut records, key);
+                write_str(&mut records, value);
+            }
         }
         
-        // Convert results into a vector
-        let mut result_vec = Vec::with_capacity(results.len());
-        while let Some(entry) = results.pop() {
-            result_vec.push(entry);
+        let mut bytes = Vec::with_capacity(records_start + records.len());
+        bytes.extend_from_slice(MMAP_INDEX_MAGIC);
+        bytes.extend_from_slice(&MMAP_INDEX_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        let header = [
+            self.dimensions,
+            node_count,
+            self.max_layer,
+            self.m,
+            self.ef_construction,
+            self.ef_search,
+            self.entry_points.len(),
+        ];
+        for value in header {
+            bytes.extend_from_slice(&(value as u64).to_le_bytes());
         }
         
-        // Reverse to get closest first
-        result_vec.reverse();
+        for &entry_point in &self.entry_points {
+            bytes.extend_from_slice(&(entry_point as u64).to_le_bytes());
+        }
         
-        Ok(result_vec)
+        for offset in offsets {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        
+        for node in &self.nodes {
+            for value in &node.embedding.vector {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        
+        bytes.extend_from_slice(&records);
+        
+        std::fs::write(path, bytes)?;
+        Ok(())
     }
     
-    /// Find a new entry point for a layer after a node is removed
-    fn find_new_entry_point(&self, layer: usize) -> Result<usize> {
-        // Find any node that has this layer
-        for (idx, node) in self.nodes.iter().enumerate() {
-            if layer < node.connections.len() && !self.id_to_index.get(&node.id).is_none() {
-                return Ok(idx);
+    /// Load an index written with `HnswIndex::save`. Fails if the file is not a saved index, is
+    /// truncated or inconsistent, or holds vectors of other than `expected_dimensions`.
+    pub fn load(path: &Path, expected_dimensions: usize) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let layout = IndexFileLayout::parse(&bytes, path)?;
+        if layout.dimensions != expected_dimensions {
+            return Err(EngramError::InvalidOperation(format!(
+                "Saved HNSW index has {} dimensions but {} were expected",
+                layout.dimensions, expected_dimensions
+            )));
+        }
+        
+        let mut index = Self::with_params(layout.dimensions, layout.m, layout.ef_construction, layout.ef_search);
+        index.max_layer = layout.max_layer;
+        index.entry_points = (0..layout.entry_point_count)
+            .map(|layer| layout.entry_point(&bytes, layer))
+            .collect();
+        
+        for node_index in 0..layout.node_count {
+            let record = layout.read_record(&bytes, node_index)?;
+            if record.active {
+                index.id_to_index.insert(record.id.clone(), node_index);
             }
+            let mut embedding = Embedding::new(layout.vector(&bytes, node_index), record.model);
+            embedding.metadata = record.metadata;
+            let connections = record
+                .connections
+                .into_iter()
+                .map(|neighbors| neighbors.into_iter().collect())
+                .collect();
+            index.nodes.push(HnswNode { id: record.id, embedding, connections });
         }
         
-        // If no node has this layer, return the first valid node
-        for (idx, node) in self.nodes.iter().enumerate() {
-            if !self.id_to_index.get(&node.id).is_none() {
-                return Ok(idx);
+        Ok(index)
+    }
+}
+
+/// Where the sections of a saved HNSW index file start, read from its header and checked
+/// against the file's size
+struct IndexFileLayout {
+    dimensions: usize,
+    node_count: usize,
+    max_layer: usize,
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    entry_point_count: usize,
+    offsets_offset: usize,
+    vectors_offset: usize,
+    vectors_end: usize,
+}
+
+/// One node record of a saved HNSW index file
+struct NodeRecord {
+    id: String,
+    active: bool,
+    connections: Vec<Vec<usize>>,
+    model: String,
+    metadata: HashMap<String, String>,
+}
+
+impl IndexFileLayout {
+    /// Read the header and check that the entry points and vectors it describes fit in the file
+    fn parse(bytes: &[u8], path: &Path) -> Result<Self> {
+        check_index_header(bytes, path)?;
+        
+        let field = |index: usize| read_u64(bytes, 16 + index * 8).map(|value| value as usize);
+        let dimensions = field(0)?;
+        let node_count = field(1)?;
+        let max_layer = field(2)?;
+        let entry_point_count = field(6)?;
+        
+        // A non-empty graph has an entry point on every layer; an empty one has none
+        let layer_count = max_layer.checked_add(1).ok_or_else(index_file_overflow)?;
+        let expected_entry_points = if node_count == 0 { 0 } else { layer_count };
+        if entry_point_count != expected_entry_points {
+            return Err(EngramError::SerializationError(format!(
+                "HNSW index file has {} entry points but {} nodes and {} layers",
+                entry_point_count, node_count, layer_count
+            )));
+        }
+        
+        let offsets_offset = entry_point_count
+            .checked_mul(8)
+            .and_then(|size| size.checked_add(MMAP_HEADER_SIZE))
+            .ok_or_else(index_file_overflow)?;
+        let vectors_offset = node_count
+            .checked_mul(8)
+            .and_then(|size| size.checked_add(offsets_offset))
+            .ok_or_else(index_file_overflow)?;
+        let vectors_end = node_count
+            .checked_mul(dimensions)
+            .and_then(|count| count.checked_mul(4))
+            .and_then(|size| size.checked_add(vectors_offset))
+            .ok_or_else(index_file_overflow)?;
+        if bytes.len() < vectors_end {
+            return Err(index_file_truncated());
+        }
+        
+        let layout = Self {
+            dimensions,
+            node_count,
+            max_layer,
+            m: field(3)?,
+            ef_construction: field(4)?,
+            ef_search: field(5)?,
+            entry_point_count,
+            offsets_offset,
+            vectors_offset,
+            vectors_end,
+        };
+        
+        if (0..entry_point_count).any(|layer| layout.entry_point(bytes, layer) >= node_count) {
+            return Err(EngramError::SerializationError(
+                "HNSW index file has an entry point out of range".to_string(),
+            ));
+        }
+        
+        Ok(layout)
+    }
+    
+    /// The entry point of a layer; `parse` checked that the entry points fit in the file
+    fn entry_point(&self, bytes: &[u8], layer: usize) -> usize {
+        let offset = MMAP_HEADER_SIZE + layer * 8;
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize
+    }
+    
+    /// Decode a node's vector; `parse` checked that the vector block fits in the file
+    fn vector(&self, bytes: &[u8], index: usize) -> Vec<f32> {
+        let start = self.vectors_offset + index * self.dimensions * 4;
+        bytes[start..start + self.dimensions * 4]
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+    
+    /// Read a node's record, checking that it lies after the vector block, fits in the file
+    /// and only links to existing nodes
+    fn read_record(&self, bytes: &[u8], index: usize) -> Result<NodeRecord> {
+        let mut cursor = read_u64(bytes, self.offsets_offset + index * 8)? as usize;
+        if cursor < self.vectors_end {
+            return Err(EngramError::SerializationError(
+                "HNSW index file has a record offset out of range".to_string(),
+            ));
+        }
+        
+        let id = read_string(bytes, &mut cursor)?;
+        let active = bytes.get(cursor).copied().ok_or_else(index_file_truncated)? == 1;
+        cursor += 1;
+        
+        let layer_count = read_u32_at(bytes, &mut cursor)? as usize;
+        if layer_count > self.max_layer + 1 {
+            return Err(EngramError::SerializationError(
+                "HNSW index file has a node above the top layer".to_string(),
+            ));
+        }
+        let mut connections = Vec::with_capacity(layer_count);
+        for _ in 0..layer_count {
+            let neighbor_count = read_u32_at(bytes, &mut cursor)? as usize;
+            let mut neighbors = Vec::with_capacity(neighbor_count.min(self.node_count));
+            for _ in 0..neighbor_count {
+                let neighbor = read_u32_at(bytes, &mut cursor)? as usize;
+                if neighbor >= self.node_count {
+                    return Err(EngramError::SerializationError(
+                        "HNSW index file has a neighbor out of range".to_string(),
+                    ));
+                }
+                neighbors.push(neighbor);
             }
+            connections.push(neighbors);
         }
         
-        // If no valid node is found, return an error
-        Err(EngramError::InvalidState(format!("No valid entry point found for layer {}", layer)))
+        let model = read_string(bytes, &mut cursor)?;
+        let metadata_count = read_u32_at(bytes, &mut cursor)? as usize;
+        let mut metadata = HashMap::new();
+        for _ in 0..metadata_count {
+            let key = read_string(bytes, &mut cursor)?;
+            metadata.insert(key, read_string(bytes, &mut cursor)?);
+        }
+        
+        Ok(NodeRecord { id, active, connections, model, metadata })
+    }
+}
+
+/// A read-only HNSW index backed by a memory-mapped file written with `HnswIndex::save`.
+///
+/// Opening the index checks every node record once, so a corrupt file is rejected up front
+/// rather than mid-search. Searches then read only the pages holding the nodes they touch, so
+/// an index much larger than RAM can still be searched. The tradeoff is latency: the first
+/// searches that touch cold pages pay for disk reads, and every vector is decoded from bytes on
+/// access, so warm throughput is lower than the in-RAM `HnswIndex` (the default).
+/// Use the in-RAM index when the index fits in memory or is updated frequently.
+pub struct MmapHnswIndex {
+    /// The mapped file
+    mmap: memmap2::Mmap,
+    
+    /// Where the sections of the mapped file start, checked against its size
+    layout: IndexFileLayout,
+    
+    /// Number of active (non-removed) nodes
+    active_count: usize,
+}
+
+impl MmapHnswIndex {
+    /// Open an index file written with `HnswIndex::save`. Fails under the same conditions as
+    /// `HnswIndex::load`, except that any dimensionality is accepted.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        
+        // SAFETY: the file is only read through the map; callers must not modify the index
+        // file while it is open, as with any memory-mapped file.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        let layout = IndexFileLayout::parse(&mmap, path)?;
+        
+        // Check every record now so the accessors below can read the file without bounds checks
+        let mut active_count = 0;
+        for index in 0..layout.node_count {
+            if layout.read_record(&mmap, index)?.active {
+                active_count += 1;
+            }
+        }
+        
+        Ok(Self { mmap, layout, active_count })
+    }
+    
+    /// Get the number of vectors in the index
+    pub fn len(&self) -> usize {
+        self.active_count
+    }
+    
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.active_count == 0
+    }
+    
+    /// Get the dimensionality of vectors in the index
+    pub fn dimensions(&self) -> usize {
+        self.layout.dimensions
+    }
+    
+    /// Search for the nearest neighbors to a query vector, optionally overriding the search width
+    pub fn search(&self, query: &Embedding, k: usize, ef_search: Option<usize>) -> Result<Vec<(String, f32)>> {
+        if query.dimensions != self.layout.dimensions {
+            return Err(EngramError::InvalidOperation(format!(
+                "Query dimensions ({}) don't match index dimensions ({})",
+                query.dimensions, self.layout.dimensions
+            )));
+        }
+        
+        let ef = resolve_ef_search(ef_search, self.layout.ef_search, k)?;
+        Ok(search_graph(self, &query.vector, k, ef))
+    }
+    
+    /// Read a u32 that `open` has already checked lies within the file
+    fn checked_u32(&self, offset: usize) -> usize {
+        u32::from_le_bytes(self.mmap[offset..offset + 4].try_into().unwrap()) as usize
+    }
+    
+    /// Offset of a node's ID length prefix, from the table `open` checked
+    fn record_offset(&self, index: usize) -> usize {
+        let offset = self.layout.offsets_offset + index * 8;
+        u64::from_le_bytes(self.mmap[offset..offset + 8].try_into().unwrap()) as usize
+    }
+    
+    /// Offset of the byte just past a node's ID
+    fn id_end(&self, index: usize) -> usize {
+        let offset = self.record_offset(index);
+        offset + 4 + self.checked_u32(offset)
+    }
+}
+
+/// Check the magic bytes and layout version at the start of a saved HNSW index file
+fn check_index_header(bytes: &[u8], path: &Path) -> Result<()> {
+    if bytes.len() < MMAP_HEADER_SIZE || &bytes[0..8] != MMAP_INDEX_MAGIC {
+        return Err(EngramError::SerializationError(format!(
+            "{} is not a saved HNSW index", path.display()
+        )));
+    }
+    
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    if version != MMAP_INDEX_VERSION {
+        return Err(EngramError::SerializationError(format!(
+            "Unsupported HNSW index file version {}", version
+        )));
+    }
+    
+    Ok(())
+}
+
+/// Error for a saved HNSW index file that ends early
+fn index_file_truncated() -> EngramError {
+    EngramError::SerializationError("HNSW index file is truncated".to_string())
+}
+
+/// Error for a saved HNSW index file whose sizes don't fit in memory
+fn index_file_overflow() -> EngramError {
+    EngramError::SerializationError("HNSW index file has sizes that overflow".to_string())
+}
+
+/// Read a little-endian u64 at an offset
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    offset
+        .checked_add(8)
+        .and_then(|end| bytes.get(offset..end))
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(index_file_truncated)
+}
+
+/// Read a little-endian u32 at an offset
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    offset
+        .checked_add(4)
+        .and_then(|end| bytes.get(offset..end))
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// Read a little-endian u32 at a cursor and move the cursor past it
+fn read_u32_at(bytes: &[u8], cursor: &mut usize) -> Result<u32> {
+    let value = read_u32(bytes, *cursor).ok_or_else(index_file_truncated)?;
+    *cursor += 4;
+    Ok(value)
+}
+
+/// Read a string prefixed with its u32 byte length at a cursor and move the cursor past it
+fn read_string(bytes: &[u8], cursor: &mut usize) -> Result<String> {
+    let len = read_u32_at(bytes, cursor)? as usize;
+    let end = cursor.checked_add(len).ok_or_else(index_file_overflow)?;
+    let string = bytes.get(*cursor..end).ok_or_else(index_file_truncated)?;
+    *cursor = end;
+    String::from_utf8(string.to_vec())
+        .map_err(|e| EngramError::SerializationError(format!("Invalid string in HNSW index file: {}", e)))
+}
+
+impl HnswGraph for MmapHnswIndex {
+    fn node_count(&self) -> usize {
+        self.layout.node_count
+    }
+    
+    fn is_active(&self, index: usize) -> bool {
+        self.mmap[self.id_end(index)] == 1
+    }
+    
+    fn node_id(&self, index: usize) -> String {
+        let offset = self.record_offset(index);
+        String::from_utf8_lossy(&self.mmap[offset + 4..self.id_end(index)]).into_owned()
+    }
+    
+    fn vector(&self, index: usize) -> Cow<'_, [f32]> {
+        Cow::Owned(self.layout.vector(&self.mmap, index))
+    }
+    
+    fn neighbors(&self, index: usize, layer: usize) -> Vec<usize> {
+        let mut cursor = self.id_end(index) + 1;
+        
+        let layer_count = self.checked_u32(cursor);
+        cursor += 4;
+        if layer >= layer_count {
+            return Vec::new();
+        }
+        
+        // Skip the neighbor lists of lower layers
+        for _ in 0..layer {
+            cursor += 4 + self.checked_u32(cursor) * 4;
+        }
+        
+        let count = self.checked_u32(cursor);
+        cursor += 4;
+        (0..count).map(|i| self.checked_u32(cursor + i * 4)).collect()
+    }
+    
+    fn max_layer(&self) -> usize {
+        self.layout.max_layer
+    }
+    
+    fn entry_point(&self, layer: usize) -> usize {
+        self.layout.entry_point(&self.mmap, layer)
+    }
+}
+
+/// Suggests tags for engrams by comparing their embeddings to the centroid of
+/// labeled exemplar embeddings for each tag
+#[derive(Debug, Clone)]
+pub struct AutoTagger {
+    /// Minimum cosine similarity to a tag's centroid for the tag to be suggested
+    threshold: f32,
+    
+    /// Centroid embedding for each trained tag
+    centroids: HashMap<String, Embedding>,
+}
+
+impl AutoTagger {
+    /// Create a new, untrained auto-tagger with the default threshold (0.8)
+    pub fn new() -> Self {
+        Self {
+            threshold: 0.8,
+            centroids: HashMap::new(),
+        }
+    }
+    
+    /// Set the similarity threshold above which tags are suggested
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+    
+    /// Get the similarity threshold
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+    
+    /// Change the similarity threshold
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+    
+    /// Train the tagger from exemplar embeddings for each tag.
+    /// Each tag's centroid replaces any previously trained centroid for that tag.
+    pub fn train(&mut self, exemplars: HashMap<String, Vec<Embedding>>) -> Result<()> {
+        for (tag, embeddings) in exemplars {
+            let first = match embeddings.first() {
+                Some(first) => first,
+                None => continue,
+            };
+            
+            let dimensions = first.dimensions;
+            let mut sum = vec![0.0f32; dimensions];
+            
+            for embedding in &embeddings {
+                if embedding.dimensions != dimensions {
+                    return Err(EngramError::ComputationError(format!(
+                        "Dimension mismatch in exemplars for tag '{}': {} vs {}",
+                        tag, dimensions, embedding.dimensions
+                    )));
+                }
+                
+                for (total, value) in sum.iter_mut().zip(&embedding.vector) {
+                    *total += value;
+                }
+            }
+            
+            let count = embeddings.len() as f32;
+            let mut centroid = Embedding::new(
+                sum.into_iter().map(|total| total / count).collect(),
+                first.model.clone(),
+            );
+            centroid.normalize();
+            
+            self.centroids.insert(tag, centroid);
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the names of all trained tags
+    pub fn tags(&self) -> Vec<&str> {
+        self.centroids.keys().map(|tag| tag.as_str()).collect()
+    }
+    
+    /// Suggest tags whose centroid similarity exceeds the threshold, most similar first.
+    /// Tags with a different dimensionality than the embedding are ignored.
+    pub fn suggest_tags(&self, embedding: &Embedding) -> Vec<(String, f32)> {
+        let mut suggestions: Vec<(String, f32)> = self
+            .centroids
+            .iter()
+            .filter_map(|(tag, centroid)| {
+                embedding
+                    .cosine_similarity(centroid)
+                    .ok()
+                    .filter(|similarity| *similarity >= self.threshold)
+                    .map(|similarity| (tag.clone(), similarity))
+            })
+            .collect();
+        
+        suggestions.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        
+        suggestions
+    }
+}
+
+impl Default for AutoTagger {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -1291,6 +2196,7 @@ pub trait DimensionReducer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     
     #[test]
     #[cfg(feature = "embedding-test")]  // Only run with explicit feature flag
@@ -1429,14 +2335,14 @@ mod tests {
         index.add("four", emb4.clone()).unwrap();
         
         // Search for nearest neighbors
-        let results = index.search(&emb1, 2).unwrap();
+        let results = index.search(&emb1, 2, None).unwrap();
         
         // First result should be "one" itself with similarity 1.0
         assert_eq!(results[0].0, "one");
         assert!((results[0].1 - 1.0).abs() < 1e-6);
         
         // Search with emb4 (close to both emb1 and emb2)
-        let results = index.search(&emb4, 3).unwrap();
+        let results = index.search(&emb4, 3, None).unwrap();
         assert_eq!(results.len(), 3);
         
         // "four" should be the closest
@@ -1446,7 +2352,7 @@ mod tests {
         index.remove("two").unwrap();
         
         // Check it's been removed
-        let results = index.search(&emb2, 4).unwrap();
+        let results = index.search(&emb2, 4, None).unwrap();
         assert_eq!(results.len(), 3); // Only 3 left after removal
         assert!(results.iter().all(|(id, _)| id != "two"));
         
@@ -1455,4 +2361,349 @@ mod tests {
         assert_eq!(index.len(), 0);
         assert!(index.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hnsw_wider_ef_search_improves_recall() {
+        let mut index = HnswIndex::with_params(8, 4, 16, 10);
+        let mut vectors = Vec::new();
+        for i in 0..300 {
+            let x = i as f32;
+            let vector: Vec<f32> = (0..8).map(|d| (x * (0.37 + d as f32 * 0.11)).sin()).collect();
+            index.add(&format!("node-{}", i), Embedding::new(vector.clone(), "test".to_string())).unwrap();
+            vectors.push((format!("node-{}", i), vector));
+        }
+        
+        let k = 10;
+        let recall = |ef: usize| {
+            let mut found = 0;
+            for q in 0..20 {
+                let x = q as f32 + 0.5;
+                let query: Vec<f32> = (0..8).map(|d| (x * (0.29 + d as f32 * 0.13)).cos()).collect();
+                
+                // Ground truth from an exhaustive scan
+                let mut exact: Vec<(String, f32)> = vectors
+                    .iter()
+                    .map(|(id, v)| (id.clone(), cosine_similarity_slices(&query, v)))
+                    .collect();
+                exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+                let truth: HashSet<String> = exact.into_iter().take(k).map(|(id, _)| id).collect();
+                
+                let results = index.search(&Embedding::new(query, "test".to_string()), k, Some(ef)).unwrap();
+                assert!(results.len() <= k);
+                found += results.iter().filter(|(id, _)| truth.contains(id)).count();
+            }
+            found
+        };
+        
+        assert!(recall(200) >= recall(k));
+        
+        // The search width must cover the requested number of results
+        let query = Embedding::new(vec![1.0; 8], "test".to_string());
+        assert!(index.search(&query, k, Some(k - 1)).is_err());
+    }
+    
+    #[test]
+    fn test_hnsw_search_with_ef_trades_recall_for_width() {
+        let mut index = HnswIndex::with_params(8, 4, 16, 10);
+        let mut vectors = Vec::new();
+        for i in 0..1000 {
+            let x = i as f32;
+            let vector: Vec<f32> = (0..8).map(|d| (x * (0.37 + d as f32 * 0.11)).sin()).collect();
+            index.add(&format!("node-{}", i), Embedding::new(vector.clone(), "test".to_string())).unwrap();
+            vectors.push((format!("node-{}", i), vector));
+        }
+        
+        let k = 10;
+        let queries: Vec<Embedding> = (0..20)
+            .map(|q| {
+                let x = q as f32 + 0.5;
+                Embedding::new((0..8).map(|d| (x * (0.29 + d as f32 * 0.13)).cos()).collect(), "test".to_string())
+            })
+            .collect();
+        let truths: Vec<HashSet<String>> = queries
+            .iter()
+            .map(|query| {
+                let mut exact: Vec<(String, f32)> = vectors
+                    .iter()
+                    .map(|(id, v)| (id.clone(), cosine_similarity_slices(&query.vector, v)))
+                    .collect();
+                exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+                exact.into_iter().take(k).map(|(id, _)| id).collect()
+            })
+            .collect();
+        
+        let recall = |ef: usize| -> usize {
+            queries
+                .iter()
+                .zip(&truths)
+                .map(|(query, truth)| {
+                    let results = index.search_with_ef(query, k, ef).unwrap();
+                    assert!(results.len() <= k);
+                    results.iter().filter(|(id, _)| truth.contains(id)).count()
+                })
+                .sum()
+        };
+        
+        assert!(recall(400) >= recall(k));
+        
+        // A width below k is raised to k rather than rejected
+        assert_eq!(index.search_with_ef(&queries[0], k, 1).unwrap(), index.search_with_ef(&queries[0], k, k).unwrap());
+        
+        // The default search is the same search at the index's width
+        assert_eq!(index.search(&queries[0], k, None).unwrap(), index.search_with_ef(&queries[0], k, 10).unwrap());
+    }
+    
+    #[test]
+    fn test_mmap_hnsw_index_matches_in_memory() {
+        let mut index = HnswIndex::new(4);
+        for i in 0..60 {
+            let x = i as f32;
+            let vector = vec![x.sin(), x.cos(), (x * 0.5).sin(), 1.0 + (x * 0.3).cos()];
+            index.add(&format!("node-{}", i), Embedding::new(vector, "test".to_string())).unwrap();
+        }
+        index.remove("node-7").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.hnsw");
+        index.save(&path).unwrap();
+
+        let mapped = MmapHnswIndex::open(&path).unwrap();
+        assert_eq!(mapped.len(), index.len());
+        assert_eq!(mapped.dimensions(), 4);
+
+        for i in 0..10 {
+            let x = i as f32 + 0.25;
+            let query = Embedding::new(vec![x.cos(), x.sin(), 0.5, 1.0], "test".to_string());
+            assert_eq!(mapped.search(&query, 5, None).unwrap(), index.search(&query, 5, None).unwrap());
+        }
+
+        // Files that are not saved indices are rejected
+        let bogus = dir.path().join("bogus.hnsw");
+        std::fs::write(&bogus, b"not an index").unwrap();
+        assert!(MmapHnswIndex::open(&bogus).is_err());
+
+        // Corrupt files are rejected on open, as they are by `HnswIndex::load`
+        let bytes = std::fs::read(&path).unwrap();
+        let truncated = dir.path().join("truncated.hnsw");
+        std::fs::write(&truncated, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(MmapHnswIndex::open(&truncated).is_err());
+
+        let mut huge = bytes.clone();
+        huge[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        let overflow = dir.path().join("overflow.hnsw");
+        std::fs::write(&overflow, &huge).unwrap();
+        assert!(MmapHnswIndex::open(&overflow).is_err());
+
+        let mut misplaced = bytes.clone();
+        let offsets_offset = MMAP_HEADER_SIZE + index.max_layer() * 8 + 8;
+        misplaced[offsets_offset..offsets_offset + 8].copy_from_slice(&0u64.to_le_bytes());
+        let bad_record = dir.path().join("bad-record.hnsw");
+        std::fs::write(&bad_record, &misplaced).unwrap();
+        assert!(MmapHnswIndex::open(&bad_record).is_err());
+    }
+
+    #[test]
+    fn test_hnsw_index_save_and_load() {
+        // ef_search above the node count makes search exhaustive, so results don't depend
+        // on the iteration order of the reloaded neighbour sets
+        let mut index = HnswIndex::with_params(4, 8, 32, 64);
+        for i in 0..60 {
+            let x = i as f32;
+            let vector = vec![x.sin(), x.cos(), (x * 0.5).sin(), 1.0 + (x * 0.3).cos()];
+            let mut embedding = Embedding::new(vector, "test-model".to_string());
+            embedding.metadata.insert("batch".to_string(), (i % 3).to_string());
+            index.add(&format!("node-{}", i), embedding).unwrap();
+        }
+        index.remove("node-7").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.hnsw");
+        index.save(&path).unwrap();
+
+        let loaded = HnswIndex::load(&path, 4).unwrap();
+        assert_eq!(loaded.len(), index.len());
+        assert_eq!(loaded.dimensions(), 4);
+        assert!(!loaded.contains("node-7"));
+        assert_eq!(loaded.get_embedding("node-5").unwrap().metadata["batch"], "2");
+
+        // The dimensionality must match what the caller expects
+        assert!(HnswIndex::load(&path, 8).is_err());
+
+        for i in 0..10 {
+            let x = i as f32 + 0.25;
+            let query = Embedding::new(vec![x.cos(), x.sin(), 0.5, 1.0], "test".to_string());
+            assert_eq!(loaded.search(&query, 5, None).unwrap(), index.search(&query, 5, None).unwrap());
+        }
+
+        // The same file can be memory-mapped
+        let mapped = MmapHnswIndex::open(&path).unwrap();
+        assert_eq!(mapped.len(), index.len());
+
+        // Truncated files and files of another kind are rejected
+        let bytes = std::fs::read(&path).unwrap();
+        let truncated = dir.path().join("truncated.hnsw");
+        std::fs::write(&truncated, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(HnswIndex::load(&truncated, 4).is_err());
+
+        let bogus = dir.path().join("bogus.hnsw");
+        std::fs::write(&bogus, b"not an index").unwrap();
+        assert!(HnswIndex::load(&bogus, 4).is_err());
+
+        // Header sizes that overflow are rejected rather than wrapping
+        let mut huge = bytes.clone();
+        huge[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        let overflow = dir.path().join("overflow.hnsw");
+        std::fs::write(&overflow, &huge).unwrap();
+        assert!(HnswIndex::load(&overflow, 4).is_err());
+
+        // An empty index round-trips without entry points, and one claiming any is rejected
+        let empty = dir.path().join("empty.hnsw");
+        HnswIndex::new(4).save(&empty).unwrap();
+        assert!(HnswIndex::load(&empty, 4).unwrap().is_empty());
+        let mut bytes = std::fs::read(&empty).unwrap();
+        bytes[64..72].copy_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        std::fs::write(&empty, &bytes).unwrap();
+        assert!(HnswIndex::load(&empty, 4).is_err());
+    }
+
+    #[test]
+    fn test_auto_tagger_suggestions() {
+        let mut exemplars = HashMap::new();
+        exemplars.insert("weather".to_string(), vec![
+            Embedding::new(vec![1.0, 0.1, 0.0], "test".to_string()),
+            Embedding::new(vec![0.9, 0.0, 0.1], "test".to_string()),
+        ]);
+        exemplars.insert("finance".to_string(), vec![
+            Embedding::new(vec![0.0, 0.0, 1.0], "test".to_string()),
+            Embedding::new(vec![0.1, 0.0, 0.9], "test".to_string()),
+        ]);
+        
+        let mut tagger = AutoTagger::new().with_threshold(0.9);
+        tagger.train(exemplars).unwrap();
+        
+        // An embedding near the weather exemplars gets only the weather tag
+        let embedding = Embedding::new(vec![0.95, 0.05, 0.05], "test".to_string());
+        let suggestions = tagger.suggest_tags(&embedding);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].0, "weather");
+        assert!(suggestions[0].1 >= 0.9);
+        
+        // Lowering the threshold lets weaker matches through
+        tagger.set_threshold(-1.0);
+        assert_eq!(tagger.suggest_tags(&embedding).len(), 2);
+    }
+    
+    #[test]
+    fn test_service_similarity_metric() {
+        let query = Embedding::new(vec![1.0, 0.0], "test".to_string());
+        // Same direction as the query but far away
+        let aligned = Embedding::new(vec![10.0, 0.0], "test".to_string());
+        // Slightly different direction but close by
+        let nearby = Embedding::new(vec![0.9, 0.3], "test".to_string());
+        
+        let cosine = EmbeddingService::new();
+        assert_eq!(cosine.get_similarity_metric(), SimilarityMetric::Cosine);
+        assert!(cosine.similarity(&query, &aligned).unwrap() > cosine.similarity(&query, &nearby).unwrap());
+        
+        let euclidean = EmbeddingService::new().with_similarity_metric(SimilarityMetric::Euclidean);
+        assert!(euclidean.similarity(&query, &nearby).unwrap() > euclidean.similarity(&query, &aligned).unwrap());
+        
+        let dot = EmbeddingService::new().with_similarity_metric(SimilarityMetric::DotProduct);
+        assert!((dot.similarity(&query, &aligned).unwrap() - 10.0).abs() < 1e-6);
+    }
+    
+    /// Backend returning a fixed unnormalized vector and counting how often it is called
+    struct CountingBackend {
+        calls: Arc<AtomicUsize>,
+    }
+    
+    impl EmbeddingBackend for CountingBackend {
+        fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![3.0, 4.0])
+        }
+        
+        fn dimensions(&self) -> usize {
+            2
+        }
+        
+        fn name(&self) -> &str {
+            "counting"
+        }
+    }
+    
+    #[test]
+    fn test_service_caches_and_normalizes_backend_output() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = EmbeddingService::with_backend(Box::new(CountingBackend { calls: calls.clone() }));
+        assert_eq!(service.get_dimensions(), 2);
+        assert_eq!(service.get_model_name(), "counting");
+        
+        let first = service.embed_text("hello").unwrap();
+        let second = service.embed_text("hello").unwrap();
+        
+        // The second call is served from the cache
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.vector, second.vector);
+        
+        // [3, 4] normalized to unit length
+        assert!((first.vector[0] - 0.6).abs() < 1e-6);
+        assert!((first.vector[1] - 0.8).abs() < 1e-6);
+        assert_eq!(first.model, "counting");
+    }
+    
+    #[test]
+    fn test_deterministic_backend_reflects_lexical_overlap() {
+        let service = EmbeddingService::with_backend(Box::new(DeterministicBackend::new("deterministic", 256)));
+        
+        let a = service.embed_text("The agent stores long term memories in a graph database").unwrap();
+        let b = service.embed_text("The agent stores long term memories in a vector database").unwrap();
+        let unrelated = service.embed_text("Bake the bread at a high oven temperature").unwrap();
+        
+        let similar = a.cosine_similarity(&b).unwrap();
+        let dissimilar = a.cosine_similarity(&unrelated).unwrap();
+        assert!(similar > dissimilar, "similar {} vs unrelated {}", similar, dissimilar);
+        assert!(similar > 0.7);
+        
+        // Same text, same vector
+        assert_eq!(a.vector, service.embed_text("The agent stores long term memories in a graph database").unwrap().vector);
+        
+        // Texts without words still get a non-zero vector
+        let symbols = service.embed_text("?!").unwrap();
+        assert!(symbols.vector.iter().any(|v| *v != 0.0));
+    }
+    
+    #[test]
+    fn test_load_model_from_missing_path() {
+        let mut service = EmbeddingService::with_model("local-model");
+        let path = std::env::temp_dir().join("engram_missing_model/model.onnx");
+        
+        let result = service.load_model_from_path(&path);
+        assert!(matches!(result, Err(EngramError::NotFound(_))));
+        
+        // The service keeps its previous model
+        assert_eq!(service.get_model_name(), "local-model");
+        assert_eq!(service.get_dimensions(), 768);
+    }
+    
+    #[test]
+    #[cfg(feature = "onnx-test")]  // Only run with explicit feature flag
+    fn test_load_model_reports_true_dimensions() {
+        // Run with ENGRAM_TEST_ONNX_MODEL pointing at a directory holding model.onnx and
+        // tokenizer.json: `cargo test --features onnx-test`
+        let dir = std::path::PathBuf::from(
+            std::env::var("ENGRAM_TEST_ONNX_MODEL").expect("onnx-test requires ENGRAM_TEST_ONNX_MODEL"),
+        );
+        
+        let mut service = EmbeddingService::with_model("local-model");
+        assert_eq!(service.get_dimensions(), 768);
+        
+        service.load_model_from_path(&dir).unwrap();
+        
+        // Dimensions come from the model itself, not the Custom default
+        let embedding = service.embed_text("A small test sentence").unwrap();
+        assert_eq!(service.get_dimensions(), embedding.vector.len());
+        assert_eq!(embedding.model, service.get_model_name());
+    }
+}
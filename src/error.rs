@@ -46,6 +46,12 @@ pub enum EngramError {
     #[error("Invalid state: {0}")]
     InvalidState(String),
     
+    #[error("Result too large: {0} results exceed the maximum of {1}; add a filter or a limit")]
+    ResultTooLarge(usize, usize),
+    
+    #[error("Operation timed out: {0}")]
+    Timeout(String),
+    
     #[error("Concurrency error: {0}")]
     ConcurrencyError(String),
     
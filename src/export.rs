@@ -2,7 +2,7 @@ use crate::error::{EngramError, Result};
 use crate::schema::{Agent, Collection, Connection, Context, Engram};
 use crate::storage::Storage;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
@@ -43,6 +43,12 @@ impl ExportData {
     }
 }
 
+impl Default for ExportData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Export data from storage to a file
 pub fn export_to_file(storage: &Storage, file_path: &Path) -> Result<()> {
     let mut export_data = ExportData::new();
@@ -237,4 +243,116 @@ pub fn import_partial_from_file(storage: &Storage, file_path: &Path) -> Result<(
     transaction.commit()?;
     
     Ok(())
-}
\ No newline at end of file
+}
+/// Export a context as a self-contained bundle: the context itself, its engrams, the
+/// connections among those engrams, and its participating agents
+pub fn export_context_bundle(storage: &Storage, context_id: &str, file_path: &Path) -> Result<()> {
+    let mut export_data = ExportData::new();
+    
+    let context = storage.get_context(&context_id.to_string())?.ok_or_else(|| {
+        EngramError::NotFound(format!("Context not found: {}", context_id))
+    })?;
+    
+    for engram_id in &context.engram_ids {
+        if let Some(engram) = storage.get_engram(engram_id)? {
+            export_data.engrams.insert(engram_id.clone(), engram);
+        }
+        
+        // Only include connections between engrams in this context
+        for conn_id in storage.find_outgoing_connections(engram_id)? {
+            if let Some(connection) = storage.get_connection(&conn_id)? {
+                if context.engram_ids.contains(&connection.target_id) {
+                    export_data.connections.insert(conn_id, connection);
+                }
+            }
+        }
+    }
+    
+    for agent_id in &context.agent_ids {
+        if let Some(agent) = storage.get_agent(agent_id)? {
+            export_data.agents.insert(agent_id.clone(), agent);
+        }
+    }
+    
+    export_data.contexts.insert(context_id.to_string(), context);
+    
+    let file = File::create(file_path).map_err(|e| {
+        EngramError::StorageError(format!("Failed to create export file: {}", e))
+    })?;
+    
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, &export_data).map_err(|e| {
+        EngramError::SerializationError(format!("Failed to serialize export data: {}", e))
+    })?;
+    
+    Ok(())
+}
+
+/// Import a bundle written by `export_context_bundle`, returning the ID of the imported context.
+///
+/// With `remap_ids` every engram, connection, agent and the context get fresh IDs (with
+/// references rewritten to match), so the bundle can be imported next to the data it was
+/// exported from without overwriting anything. `created_by` is rewritten for agents in the
+/// bundle; references to other agents are kept as they are.
+pub fn import_context_bundle(storage: &Storage, file_path: &Path, remap_ids: bool) -> Result<String> {
+    let file = File::open(file_path).map_err(|e| {
+        EngramError::StorageError(format!("Failed to open import file: {}", e))
+    })?;
+    
+    let reader = BufReader::new(file);
+    let export_data: ExportData = serde_json::from_reader(reader).map_err(|e| {
+        EngramError::SerializationError(format!("Failed to deserialize import data: {}", e))
+    })?;
+    
+    if export_data.contexts.len() != 1 {
+        return Err(EngramError::InvalidOperation(format!(
+            "A context bundle must contain exactly one context, found {}",
+            export_data.contexts.len()
+        )));
+    }
+    
+    // Old ID -> ID to import under; the identity unless remapping
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    let mut map_id = |id: &String| -> String {
+        id_map
+            .entry(id.clone())
+            .or_insert_with(|| if remap_ids { uuid::Uuid::new_v4().to_string() } else { id.clone() })
+            .clone()
+    };
+    
+    // Only agents in the bundle are imported, so only their IDs are remapped
+    let bundle_agents: HashSet<String> = export_data.agents.values().map(|agent| agent.id.clone()).collect();
+    
+    let mut transaction = storage.begin_transaction();
+    
+    for (_, mut engram) in export_data.engrams {
+        engram.id = map_id(&engram.id);
+        engram.created_by = engram.created_by
+            .map(|agent_id| if bundle_agents.contains(&agent_id) { map_id(&agent_id) } else { agent_id });
+        transaction.put_engram(&engram)?;
+    }
+    
+    for (_, mut connection) in export_data.connections {
+        connection.id = map_id(&connection.id);
+        connection.created_by = connection.created_by
+            .map(|agent_id| if bundle_agents.contains(&agent_id) { map_id(&agent_id) } else { agent_id });
+        connection.source_id = map_id(&connection.source_id);
+        connection.target_id = map_id(&connection.target_id);
+        transaction.put_connection(&connection)?;
+    }
+    
+    for (_, mut agent) in export_data.agents {
+        agent.id = map_id(&agent.id);
+        transaction.put_agent(&agent)?;
+    }
+    
+    let mut context = export_data.contexts.into_values().next().unwrap();
+    context.id = map_id(&context.id);
+    context.engram_ids = context.engram_ids.iter().map(&mut map_id).collect();
+    context.agent_ids = context.agent_ids.iter().map(&mut map_id).collect();
+    transaction.put_context(&context)?;
+    
+    transaction.commit()?;
+    
+    Ok(context.id)
+}
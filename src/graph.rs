@@ -3,6 +3,7 @@ use crate::schema::{
     Agent, AgentId, Collection, CollectionId, Connection, ConnectionId, Context, ContextId, Engram,
     EngramId,
 };
+use crate::storage::Storage;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::Direction;
 use petgraph::visit::EdgeRef;
@@ -26,6 +27,9 @@ pub enum Edge {
     Participates, // Agent participates in Context
 }
 
+/// Maximum number of connections a single engram spreads activation across (strongest first)
+pub const MAX_ACTIVATION_FAN_OUT: usize = 16;
+
 /// In-memory graph representation of the EngramAI knowledge structure
 pub struct MemoryGraph {
     /// The graph structure
@@ -92,6 +96,28 @@ impl MemoryGraph {
         Ok(connection.id)
     }
 
+    /// Remove a connection from the graph, returning it if it was present
+    pub fn remove_connection(&mut self, id: &ConnectionId) -> Result<Option<Connection>> {
+        let edge = match self.connection_indices.remove(id) {
+            Some(edge) => edge,
+            None => return Ok(None),
+        };
+        
+        // Removal moves the last edge into the freed slot; keep connection indices pointing at it
+        let last = petgraph::graph::EdgeIndex::new(self.graph.edge_count() - 1);
+        let removed = self.graph.remove_edge(edge);
+        if edge != last {
+            if let Some(Edge::Connection(connection)) = self.graph.edge_weight(edge) {
+                self.connection_indices.insert(connection.id.clone(), edge);
+            }
+        }
+        
+        match removed {
+            Some(Edge::Connection(connection)) => Ok(Some(connection)),
+            _ => Ok(None),
+        }
+    }
+
     /// Add a collection to the graph
     pub fn add_collection(&mut self, collection: Collection) -> Result<CollectionId> {
         // Create a node in the graph
@@ -250,11 +276,82 @@ impl MemoryGraph {
         Ok(connections)
     }
 
+    /// Spreading-activation retrieval.
+    ///
+    /// Seeds start fully activated. On each iteration, the activation an engram received in
+    /// the previous step flows along its outgoing connections, scaled by the connection weight
+    /// (its conductance) and by `decay`. Each engram spreads over at most
+    /// `MAX_ACTIVATION_FAN_OUT` of its strongest connections, activation is capped at 1.0, and
+    /// pulses weaker than `threshold` stop spreading. Returns every engram whose activation
+    /// reached `threshold`, including the seeds.
+    pub fn spreading_activation(
+        &self,
+        seeds: &[EngramId],
+        decay: f64,
+        iterations: usize,
+        threshold: f64,
+    ) -> HashMap<EngramId, f64> {
+        let mut activation: HashMap<NodeIndex, f64> = HashMap::new();
+        let mut pulse: HashMap<NodeIndex, f64> = HashMap::new();
+        
+        for seed in seeds {
+            if let Some(&idx) = self.engram_indices.get(seed) {
+                activation.insert(idx, 1.0);
+                pulse.insert(idx, 1.0);
+            }
+        }
+        
+        for _ in 0..iterations {
+            let mut next: HashMap<NodeIndex, f64> = HashMap::new();
+            
+            for (&idx, &energy) in &pulse {
+                if energy < threshold {
+                    continue;
+                }
+                
+                let mut edges: Vec<(NodeIndex, f64)> = self.graph
+                    .edges_directed(idx, Direction::Outgoing)
+                    .filter_map(|edge| match edge.weight() {
+                        Edge::Connection(connection) => Some((edge.target(), connection.weight)),
+                        _ => None,
+                    })
+                    .collect();
+                edges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                
+                for (target, weight) in edges.into_iter().take(MAX_ACTIVATION_FAN_OUT) {
+                    let passed = energy * weight * decay;
+                    if passed >= threshold {
+                        *next.entry(target).or_insert(0.0) += passed;
+                    }
+                }
+            }
+            
+            if next.is_empty() {
+                break;
+            }
+            
+            for (&idx, &energy) in &next {
+                let level = activation.entry(idx).or_insert(0.0);
+                *level = (*level + energy).min(1.0);
+            }
+            pulse = next.into_iter().map(|(idx, energy)| (idx, energy.min(1.0))).collect();
+        }
+        
+        activation
+            .into_iter()
+            .filter(|(_, level)| *level >= threshold)
+            .filter_map(|(idx, level)| match &self.graph[idx] {
+                Node::Engram(engram) => Some((engram.id.clone(), level)),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Get engrams from a specific source
     pub fn get_engrams_by_source(&self, source: &str) -> Result<Vec<Engram>> {
         let mut engrams = Vec::new();
         
-        for (_, &idx) in &self.engram_indices {
+        for &idx in self.engram_indices.values() {
             if let Node::Engram(engram) = &self.graph[idx] {
                 if engram.source == source {
                     engrams.push(engram.clone());
@@ -269,7 +366,7 @@ impl MemoryGraph {
     pub fn get_engrams_by_confidence(&self, min_confidence: f64) -> Result<Vec<Engram>> {
         let mut engrams = Vec::new();
         
-        for (_, &idx) in &self.engram_indices {
+        for &idx in self.engram_indices.values() {
             if let Node::Engram(engram) = &self.graph[idx] {
                 if engram.confidence >= min_confidence {
                     engrams.push(engram.clone());
@@ -295,7 +392,7 @@ impl MemoryGraph {
             .collect();
         
         // Sort by timestamp (newest first)
-        engrams.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        engrams.sort_by_key(|engram| std::cmp::Reverse(engram.timestamp));
         
         // Take the first `count` elements
         if engrams.len() > count {
@@ -423,6 +520,81 @@ impl MemoryGraph {
         Ok(true)
     }
 
+    /// Move an engram from one collection to another.
+    ///
+    /// Both updated collections are persisted in a single storage transaction, so a failure
+    /// can never leave the engram in both collections or in neither. The graph is only
+    /// updated once the transaction has committed.
+    pub fn move_engram(
+        &mut self,
+        engram_id: &EngramId,
+        from: &CollectionId,
+        to: &CollectionId,
+        storage: &Storage,
+    ) -> Result<bool> {
+        let engram_idx = match self.engram_indices.get(engram_id) {
+            Some(idx) => *idx,
+            None => return Err(EngramError::NotFound(format!("Engram not found: {}", engram_id))),
+        };
+        
+        let mut collections = Vec::with_capacity(2);
+        for collection_id in [from, to] {
+            let idx = match self.collection_indices.get(collection_id) {
+                Some(idx) => *idx,
+                None => {
+                    return Err(EngramError::NotFound(format!(
+                        "Collection not found: {}",
+                        collection_id
+                    )))
+                }
+            };
+            match &self.graph[idx] {
+                Node::Collection(collection) => collections.push((idx, collection.clone())),
+                _ => return Err(EngramError::InvalidState(format!("Node is not a collection: {}", collection_id))),
+            }
+        }
+        let (to_idx, mut to_collection) = collections.pop().unwrap();
+        let (from_idx, mut from_collection) = collections.pop().unwrap();
+        
+        if from == to {
+            return Ok(false);
+        }
+        
+        if !from_collection.remove_engram(engram_id) {
+            return Err(EngramError::InvalidOperation(format!(
+                "Engram {} is not in collection {}",
+                engram_id, from
+            )));
+        }
+        to_collection.add_engram(engram_id.clone());
+        
+        // Commit both membership changes in one batch
+        let mut txn = storage.begin_transaction();
+        txn.put_collection(&from_collection)?;
+        txn.put_collection(&to_collection)?;
+        txn.commit()?;
+        
+        // Mirror the committed change in the graph
+        self.graph[from_idx] = Node::Collection(from_collection);
+        self.graph[to_idx] = Node::Collection(to_collection);
+        
+        while let Some(edge) = self.graph.find_edge(from_idx, engram_idx) {
+            // Removal moves the last edge into the freed slot; keep connection indices pointing at it
+            let last = petgraph::graph::EdgeIndex::new(self.graph.edge_count() - 1);
+            self.graph.remove_edge(edge);
+            if edge != last {
+                if let Some(Edge::Connection(connection)) = self.graph.edge_weight(edge) {
+                    self.connection_indices.insert(connection.id.clone(), edge);
+                }
+            }
+        }
+        if self.graph.find_edge(to_idx, engram_idx).is_none() {
+            self.graph.add_edge(to_idx, engram_idx, Edge::Contains);
+        }
+        
+        Ok(true)
+    }
+
     /// Add an engram to a context
     pub fn add_engram_to_context(
         &mut self,
@@ -489,4 +661,10 @@ impl MemoryGraph {
         
         Ok(true)
     }
+}
+
+impl Default for MemoryGraph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
@@ -278,7 +278,7 @@ mod tests {
             false,
             None,           // before_time
             None,           // after_time
-        );
+        ).ids;
         assert_eq!(combined_results.len(), 1); // Should match only the Paris landmarks engram
         
         // Test relationship traversal
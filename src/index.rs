@@ -1,10 +1,25 @@
-use crate::error::Result;
+use crate::error::{EngramError, Result};
 use crate::schema::{EngramId, ConnectionId, Collection, Connection, Engram};
-use std::collections::{HashMap, HashSet};
+use crate::storage::Storage;
+use crate::utils::HyperLogLog;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use chrono::{Datelike, Timelike};
+use rust_stemmers::{Algorithm, Stemmer};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Number of start engrams sampled by `RelationshipIndex::diameter_estimate`
+pub const DIAMETER_SAMPLE_SIZE: usize = 32;
+
+/// Most paths `RelationshipIndex::find_paths` returns, so dense graphs can't exhaust memory
+pub const DEFAULT_MAX_PATHS: usize = 1000;
 
 /// Efficient indexes for fast relationship traversal
 #[allow(dead_code)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct RelationshipIndex {
     /// Index from source engram ID to outgoing connections
     outgoing_connections: HashMap<EngramId, HashSet<ConnectionId>>,
@@ -20,6 +35,21 @@ pub struct RelationshipIndex {
     
     /// Index from target to sources (shortcut for faster traversal)
     target_to_sources: HashMap<EngramId, HashSet<EngramId>>,
+    
+    /// Connections indexed by weight buckets (0.0-1.0 in 0.1 increments)
+    weight_buckets: HashMap<u8, HashSet<ConnectionId>>,
+    
+    /// Map from connection ID to weight for exact range checks at bucket edges
+    connection_weights: HashMap<ConnectionId, f64>,
+    
+    /// Weight of each connection from a source to a target, for weighted traversal
+    edge_weights: HashMap<(EngramId, EngramId), HashMap<ConnectionId, f64>>,
+    
+    /// Connections indexed by confidence buckets (0.0-1.0 in 0.1 increments)
+    confidence_buckets: HashMap<u8, HashSet<ConnectionId>>,
+    
+    /// Map from connection ID to confidence for exact checks at bucket edges
+    connection_confidences: HashMap<ConnectionId, f64>,
 }
 
 #[allow(dead_code)]
@@ -32,6 +62,11 @@ impl RelationshipIndex {
             relationship_type_index: HashMap::new(),
             source_to_targets: HashMap::new(),
             target_to_sources: HashMap::new(),
+            weight_buckets: HashMap::new(),
+            connection_weights: HashMap::new(),
+            edge_weights: HashMap::new(),
+            confidence_buckets: HashMap::new(),
+            connection_confidences: HashMap::new(),
         }
     }
     
@@ -40,36 +75,82 @@ impl RelationshipIndex {
         // Index by source (outgoing)
         self.outgoing_connections
             .entry(connection.source_id.clone())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(connection.id.clone());
         
         // Index by target (incoming)
         self.incoming_connections
             .entry(connection.target_id.clone())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(connection.id.clone());
         
         // Index by relationship type
         self.relationship_type_index
             .entry(connection.relationship_type.clone())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(connection.id.clone());
         
         // Direct source to target mapping
         self.source_to_targets
             .entry(connection.source_id.clone())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(connection.target_id.clone());
         
         // Direct target to source mapping
         self.target_to_sources
             .entry(connection.target_id.clone())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(connection.source_id.clone());
         
+        // Index by weight, replacing any previous weight for this connection
+        self.remove_weight(&connection.id);
+        self.weight_buckets
+            .entry(ImportanceIndex::importance_bucket(connection.weight))
+            .or_default()
+            .insert(connection.id.clone());
+        self.connection_weights.insert(connection.id.clone(), connection.weight);
+        self.edge_weights
+            .entry((connection.source_id.clone(), connection.target_id.clone()))
+            .or_default()
+            .insert(connection.id.clone(), connection.weight);
+        
+        // Index by confidence, replacing any previous confidence for this connection
+        self.remove_confidence(&connection.id);
+        self.confidence_buckets
+            .entry(ImportanceIndex::importance_bucket(connection.confidence))
+            .or_default()
+            .insert(connection.id.clone());
+        self.connection_confidences.insert(connection.id.clone(), connection.confidence);
+        
         Ok(())
     }
     
+    /// Remove a connection from the weight index
+    fn remove_weight(&mut self, connection_id: &ConnectionId) {
+        if let Some(weight) = self.connection_weights.remove(connection_id) {
+            let bucket = ImportanceIndex::importance_bucket(weight);
+            if let Some(connections) = self.weight_buckets.get_mut(&bucket) {
+                connections.remove(connection_id);
+                if connections.is_empty() {
+                    self.weight_buckets.remove(&bucket);
+                }
+            }
+        }
+    }
+    
+    /// Remove a connection from the confidence index
+    fn remove_confidence(&mut self, connection_id: &ConnectionId) {
+        if let Some(confidence) = self.connection_confidences.remove(connection_id) {
+            let bucket = ImportanceIndex::importance_bucket(confidence);
+            if let Some(connections) = self.confidence_buckets.get_mut(&bucket) {
+                connections.remove(connection_id);
+                if connections.is_empty() {
+                    self.confidence_buckets.remove(&bucket);
+                }
+            }
+        }
+    }
+    
     /// Remove a connection from the index
     pub fn remove_connection(&mut self, connection: &Connection) -> Result<()> {
         // Remove from source index
@@ -96,22 +177,40 @@ impl RelationshipIndex {
             }
         }
         
-        // Remove from source to targets mapping
-        if let Some(targets) = self.source_to_targets.get_mut(&connection.source_id) {
-            targets.remove(&connection.target_id);
-            if targets.is_empty() {
-                self.source_to_targets.remove(&connection.source_id);
+        // Remove this connection's edge weight, keeping any parallel connections between the pair
+        let pair = (connection.source_id.clone(), connection.target_id.clone());
+        let pair_still_connected = match self.edge_weights.get_mut(&pair) {
+            Some(weights) => {
+                weights.remove(&connection.id);
+                !weights.is_empty()
             }
-        }
+            None => false,
+        };
         
-        // Remove from target to sources mapping
-        if let Some(sources) = self.target_to_sources.get_mut(&connection.target_id) {
-            sources.remove(&connection.source_id);
-            if sources.is_empty() {
-                self.target_to_sources.remove(&connection.target_id);
+        if !pair_still_connected {
+            self.edge_weights.remove(&pair);
+            
+            // Remove from source to targets mapping
+            if let Some(targets) = self.source_to_targets.get_mut(&connection.source_id) {
+                targets.remove(&connection.target_id);
+                if targets.is_empty() {
+                    self.source_to_targets.remove(&connection.source_id);
+                }
+            }
+            
+            // Remove from target to sources mapping
+            if let Some(sources) = self.target_to_sources.get_mut(&connection.target_id) {
+                sources.remove(&connection.source_id);
+                if sources.is_empty() {
+                    self.target_to_sources.remove(&connection.target_id);
+                }
             }
         }
         
+        // Remove from weight index
+        self.remove_weight(&connection.id);
+        self.remove_confidence(&connection.id);
+        
         Ok(())
     }
     
@@ -139,6 +238,63 @@ impl RelationshipIndex {
             .unwrap_or_else(HashSet::new)
     }
     
+    /// Find connections whose weight lies within a range (inclusive)
+    pub fn find_connections_by_weight_range(&self, min_weight: f64, max_weight: f64) -> HashSet<ConnectionId> {
+        let mut results = HashSet::new();
+        if min_weight > max_weight {
+            return results;
+        }
+        
+        let min_bucket = ImportanceIndex::importance_bucket(min_weight);
+        let max_bucket = ImportanceIndex::importance_bucket(max_weight);
+        
+        for bucket in min_bucket..=max_bucket {
+            if let Some(connections) = self.weight_buckets.get(&bucket) {
+                for connection_id in connections {
+                    // Check exact weights, since edge buckets may hold values outside the range
+                    if let Some(&weight) = self.connection_weights.get(connection_id) {
+                        if weight >= min_weight && weight <= max_weight {
+                            results.insert(connection_id.clone());
+                        }
+                    }
+                }
+            }
+        }
+        
+        results
+    }
+    
+    /// Get the indexed weight of a connection
+    pub fn get_connection_weight(&self, connection_id: &ConnectionId) -> Option<f64> {
+        self.connection_weights.get(connection_id).copied()
+    }
+    
+    /// Find connections whose confidence is at least `min_confidence`, regardless of weight
+    pub fn find_connections_by_min_confidence(&self, min_confidence: f64) -> HashSet<ConnectionId> {
+        let mut results = HashSet::new();
+        let min_bucket = ImportanceIndex::importance_bucket(min_confidence);
+        
+        for bucket in min_bucket..=10 {
+            if let Some(connections) = self.confidence_buckets.get(&bucket) {
+                for connection_id in connections {
+                    // Check exact confidences, since the lowest bucket may hold values below the minimum
+                    if let Some(&confidence) = self.connection_confidences.get(connection_id) {
+                        if confidence >= min_confidence {
+                            results.insert(connection_id.clone());
+                        }
+                    }
+                }
+            }
+        }
+        
+        results
+    }
+    
+    /// Get the indexed confidence of a connection
+    pub fn get_connection_confidence(&self, connection_id: &ConnectionId) -> Option<f64> {
+        self.connection_confidences.get(connection_id).copied()
+    }
+    
     /// Get all target engrams connected from a source
     pub fn get_targets(&self, source_id: &EngramId) -> HashSet<EngramId> {
         self.source_to_targets
@@ -181,25 +337,39 @@ impl RelationshipIndex {
         by_target.intersection(&by_type).cloned().collect()
     }
     
-    /// Find all paths between source and target with a maximum depth
+    /// Find paths between source and target with a maximum depth, at most `DEFAULT_MAX_PATHS`
+    /// of them. Use `shortest_path` or `k_shortest_paths` when only the shortest routes matter.
     pub fn find_paths(
         &self,
         source_id: &EngramId,
         target_id: &EngramId,
         max_depth: usize,
+    ) -> Vec<Vec<EngramId>> {
+        self.find_paths_limited(source_id, target_id, max_depth, DEFAULT_MAX_PATHS)
+    }
+    
+    /// Find at most `max_paths` paths between source and target with a maximum depth.
+    /// Neighbours are explored in ID order, so the same paths are returned every time.
+    pub fn find_paths_limited(
+        &self,
+        source_id: &EngramId,
+        target_id: &EngramId,
+        max_depth: usize,
+        max_paths: usize,
     ) -> Vec<Vec<EngramId>> {
         let mut paths = Vec::new();
         let mut current_path = vec![source_id.clone()];
-        let visited = HashSet::new();
         
-        self.dfs_paths(
-            source_id,
-            target_id,
-            max_depth,
-            &mut current_path,
-            &mut paths,
-            &visited,
-        );
+        if max_paths > 0 {
+            self.dfs_paths(
+                source_id,
+                target_id,
+                max_depth,
+                max_paths,
+                &mut current_path,
+                &mut paths,
+            );
+        }
         
         paths
     }
@@ -210,9 +380,9 @@ impl RelationshipIndex {
         current: &EngramId,
         target: &EngramId,
         depth_left: usize,
+        max_paths: usize,
         current_path: &mut Vec<EngramId>,
         all_paths: &mut Vec<Vec<EngramId>>,
-        visited: &HashSet<EngramId>,
     ) {
         // Base case: we've reached the target
         if current == target {
@@ -227,7 +397,15 @@ impl RelationshipIndex {
         
         // Get all targets from current node
         if let Some(targets) = self.source_to_targets.get(current) {
+            let mut targets: Vec<&EngramId> = targets.iter().collect();
+            targets.sort();
+            
             for next in targets {
+                // Stop once enough paths were found
+                if all_paths.len() >= max_paths {
+                    return;
+                }
+                
                 // Avoid cycles in the path
                 if !current_path.contains(next) {
                     // Add to current path
@@ -238,9 +416,9 @@ impl RelationshipIndex {
                         next,
                         target,
                         depth_left - 1,
+                        max_paths,
                         current_path,
                         all_paths,
-                        visited,
                     );
                     
                     // Backtrack
@@ -249,15 +427,324 @@ impl RelationshipIndex {
             }
         }
     }
+    
+    /// Find a path with the fewest hops from source to target along outgoing connections,
+    /// using breadth-first search. Among equally short paths, the one through the lowest
+    /// IDs at each hop wins. Returns None if the target is unreachable.
+    pub fn shortest_path(&self, source_id: &EngramId, target_id: &EngramId) -> Option<Vec<EngramId>> {
+        self.bfs_path(source_id, target_id, &HashSet::new(), &HashSet::new())
+    }
+    
+    /// Find up to `k` distinct loop-free paths from source to target, shortest first, with
+    /// Yen's algorithm over breadth-first searches
+    pub fn k_shortest_paths(&self, source_id: &EngramId, target_id: &EngramId, k: usize) -> Vec<Vec<EngramId>> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let Some(first) = self.shortest_path(source_id, target_id) else {
+            return Vec::new();
+        };
+        
+        let mut paths = vec![first];
+        // Ordered by length, then by IDs, so ties resolve the same way every time
+        let mut candidates: BTreeSet<(usize, Vec<EngramId>)> = BTreeSet::new();
+        
+        while paths.len() < k {
+            let previous = paths[paths.len() - 1].clone();
+            
+            // Branch off the previous path at each of its engrams in turn
+            for i in 0..previous.len() - 1 {
+                let root = &previous[..=i];
+                
+                // Don't repeat the next hop of any accepted path sharing this root
+                let excluded_edges: HashSet<(&EngramId, &EngramId)> = paths
+                    .iter()
+                    .filter(|path| path.len() > i + 1 && path[..=i] == *root)
+                    .map(|path| (&path[i], &path[i + 1]))
+                    .collect();
+                // Keep the path loop-free by avoiding the root's earlier engrams
+                let excluded_engrams: HashSet<&EngramId> = previous[..i].iter().collect();
+                
+                if let Some(spur) = self.bfs_path(&previous[i], target_id, &excluded_engrams, &excluded_edges) {
+                    let mut path = previous[..i].to_vec();
+                    path.extend(spur);
+                    if !paths.contains(&path) {
+                        candidates.insert((path.len(), path));
+                    }
+                }
+            }
+            
+            match candidates.pop_first() {
+                Some((_, path)) => paths.push(path),
+                None => break,
+            }
+        }
+        
+        paths
+    }
+    
+    /// Find the cheapest path from source to target along outgoing connections with Dijkstra's
+    /// algorithm, where `weight_fn` turns each connection's weight into the cost of following
+    /// it (e.g. `|weight| 1.0 - weight` to prefer the strongest chain). Between engrams joined
+    /// by several connections the cheapest one is followed, and connections with a negative or
+    /// NaN cost are skipped. Returns the path and its total cost, or None if the
+    /// target is unreachable.
+    pub fn weighted_shortest_path<F>(
+        &self,
+        source_id: &EngramId,
+        target_id: &EngramId,
+        weight_fn: F,
+    ) -> Option<(Vec<EngramId>, f64)>
+    where
+        F: Fn(f64) -> f64,
+    {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+        
+        let mut costs: HashMap<&EngramId, f64> = HashMap::from([(source_id, 0.0)]);
+        let mut parents: HashMap<&EngramId, &EngramId> = HashMap::new();
+        let mut settled: HashSet<&EngramId> = HashSet::new();
+        // Ties on cost pop the lowest ID first, so equally cheap paths resolve the same way
+        let mut queue = BinaryHeap::from([Reverse((OrderedFloat(0.0), source_id))]);
+        
+        while let Some(Reverse((OrderedFloat(cost), current))) = queue.pop() {
+            if !settled.insert(current) {
+                continue;
+            }
+            
+            if current == target_id {
+                // Walk the parents back to the source
+                let mut path = vec![current.clone()];
+                let mut step = current;
+                while let Some(&parent) = parents.get(step) {
+                    path.push(parent.clone());
+                    step = parent;
+                }
+                path.reverse();
+                return Some((path, cost));
+            }
+            
+            let Some(targets) = self.source_to_targets.get(current) else {
+                continue;
+            };
+            
+            for target in targets {
+                if settled.contains(target) {
+                    continue;
+                }
+                let Some(weights) = self.edge_weights.get(&(current.clone(), target.clone())) else {
+                    continue;
+                };
+                let Some(edge_cost) = weights
+                    .values()
+                    .map(|&weight| weight_fn(weight))
+                    .filter(|edge_cost| !edge_cost.is_nan() && *edge_cost >= 0.0)
+                    .min_by(|a, b| a.total_cmp(b))
+                else {
+                    continue;
+                };
+                
+                let next_cost = cost + edge_cost;
+                let improves = match costs.get(target) {
+                    Some(&known) => next_cost < known,
+                    None => true,
+                };
+                if improves {
+                    costs.insert(target, next_cost);
+                    parents.insert(target, current);
+                    queue.push(Reverse((OrderedFloat(next_cost), target)));
+                }
+            }
+        }
+        
+        None
+    }
+    
+    /// Breadth-first search for a fewest-hop path that avoids the given engrams and connections
+    fn bfs_path(
+        &self,
+        source_id: &EngramId,
+        target_id: &EngramId,
+        excluded_engrams: &HashSet<&EngramId>,
+        excluded_edges: &HashSet<(&EngramId, &EngramId)>,
+    ) -> Option<Vec<EngramId>> {
+        if source_id == target_id {
+            return Some(vec![source_id.clone()]);
+        }
+        
+        let mut parents: HashMap<&EngramId, &EngramId> = HashMap::new();
+        let mut visited: HashSet<&EngramId> = HashSet::from([source_id]);
+        let mut queue = VecDeque::from([source_id]);
+        
+        while let Some(current) = queue.pop_front() {
+            let Some(targets) = self.source_to_targets.get(current) else {
+                continue;
+            };
+            
+            let mut next: Vec<&EngramId> = targets
+                .iter()
+                .filter(|id| !visited.contains(id) && !excluded_engrams.contains(id))
+                .filter(|id| !excluded_edges.contains(&(current, *id)))
+                .collect();
+            next.sort();
+            
+            for id in next {
+                visited.insert(id);
+                parents.insert(id, current);
+                
+                if id == target_id {
+                    // Walk the parents back to the source
+                    let mut path = vec![id.clone()];
+                    let mut step = id;
+                    while let Some(&parent) = parents.get(step) {
+                        path.push(parent.clone());
+                        step = parent;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                
+                queue.push_back(id);
+            }
+        }
+        
+        None
+    }
+    
+    /// Count the engrams reachable at each hop distance from `start` along outgoing connections.
+    ///
+    /// Entry `d` is the number of engrams whose shortest path from `start` is exactly `d` hops,
+    /// so entry 0 is always 1 (the start itself) and the length minus one is its eccentricity.
+    pub fn depth_stats(&self, start: &EngramId) -> Vec<usize> {
+        let mut counts = vec![1];
+        let mut visited: HashSet<&EngramId> = HashSet::from([start]);
+        let mut frontier = vec![start];
+        
+        while !frontier.is_empty() {
+            let mut next_frontier = Vec::new();
+            for id in frontier {
+                if let Some(targets) = self.source_to_targets.get(id) {
+                    for target in targets {
+                        if visited.insert(target) {
+                            next_frontier.push(target);
+                        }
+                    }
+                }
+            }
+            if !next_frontier.is_empty() {
+                counts.push(next_frontier.len());
+            }
+            frontier = next_frontier;
+        }
+        
+        counts
+    }
+    
+    /// Estimate the graph's diameter as the largest eccentricity over a sample of engrams.
+    ///
+    /// Runs `depth_stats` from up to `DIAMETER_SAMPLE_SIZE` engrams spread evenly over the
+    /// sorted IDs, so the result is deterministic and a lower bound on the true diameter.
+    pub fn diameter_estimate(&self) -> usize {
+        let mut nodes: Vec<&EngramId> = self.source_to_targets.keys()
+            .chain(self.target_to_sources.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        nodes.sort();
+        
+        let step = (nodes.len() / DIAMETER_SAMPLE_SIZE).max(1);
+        nodes.into_iter()
+            .step_by(step)
+            .take(DIAMETER_SAMPLE_SIZE)
+            .map(|id| self.depth_stats(id).len() - 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for RelationshipIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collect IDs (typically a `HashSet` from one of the index lookups) into a sorted `Vec`,
+/// giving results a reproducible order for APIs, tests and anything truncated to a limit
+pub fn sorted_ids<T: Ord>(ids: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut ids: Vec<T> = ids.into_iter().collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Union the ID sets of `from` into `into`, key by key
+fn merge_id_sets<K: Eq + Hash>(into: &mut HashMap<K, HashSet<EngramId>>, from: HashMap<K, HashSet<EngramId>>) {
+    for (key, ids) in from {
+        into.entry(key).or_default().extend(ids);
+    }
+}
+
+/// Levenshtein distance between two words, counted in characters
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    
+    previous[b.len()]
+}
+
+/// Largest edit distance accepted by `TextIndex::search_fuzzy`
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// The part of a metadata value the `MetadataIndex` indexes, recorded per engram so it can
+/// be removed by ID
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum IndexedValue {
+    String(String),
+    Number(f64),
+    Other,
+}
+
+impl IndexedValue {
+    fn of(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(str_value) => Self::String(str_value.clone()),
+            // Adding 0.0 folds -0.0 into 0.0, which `total_cmp` would otherwise order apart
+            serde_json::Value::Number(number) => number.as_f64().map_or(Self::Other, |n| Self::Number(n + 0.0)),
+            _ => Self::Other,
+        }
+    }
 }
 
 /// Index for tracking engrams by metadata fields
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct MetadataIndex {
     /// Index from metadata key to engrams that have that key
     key_index: HashMap<String, HashSet<EngramId>>,
     
-    /// Index from metadata key-value pairs to engrams
-    key_value_index: HashMap<(String, String), HashSet<EngramId>>,
+    /// Index from metadata key to its string values in sorted order, each mapped to engrams.
+    /// Sorting keeps prefix lookups to a single range scan.
+    key_value_index: HashMap<String, BTreeMap<String, HashSet<EngramId>>>,
+    
+    /// Index from metadata key to its numeric values (integers and floats) in sorted order,
+    /// each mapped to engrams, for range scans
+    numeric_index: HashMap<String, BTreeMap<OrderedFloat, HashSet<EngramId>>>,
+    
+    /// Per-key HyperLogLog sketches of the string values seen, when enabled
+    value_sketches: HashMap<String, HyperLogLog>,
+    
+    /// Precision of the value sketches (None disables them)
+    sketch_precision: Option<u8>,
+    
+    /// Metadata keys of each indexed engram, with their indexed values
+    engram_entries: HashMap<EngramId, Vec<(String, IndexedValue)>>,
 }
 
 #[allow(dead_code)]
@@ -267,48 +754,148 @@ impl MetadataIndex {
         Self {
             key_index: HashMap::new(),
             key_value_index: HashMap::new(),
+            numeric_index: HashMap::new(),
+            value_sketches: HashMap::new(),
+            sketch_precision: None,
+            engram_entries: HashMap::new(),
         }
     }
     
+    /// Also keep a fixed-size HyperLogLog sketch of each key's values, so distinct counts
+    /// can be estimated in bounded memory (`2^precision` bytes per key) and combined across
+    /// indexes. Sketches never forget values, so removed engrams still count towards them.
+    /// Only engrams added afterwards are sketched.
+    pub fn with_value_sketches(mut self, precision: u8) -> Self {
+        self.sketch_precision = Some(HyperLogLog::new(precision).precision());
+        self
+    }
+    
+    /// An empty index with the same configuration
+    fn empty_like(&self) -> Self {
+        Self { sketch_precision: self.sketch_precision, ..Self::new() }
+    }
+    
     /// Add an engram to the index
     pub fn add_engram(&mut self, engram: &Engram) -> Result<()> {
+        if self.engram_entries.contains_key(&engram.id) {
+            self.remove_engram_by_id(&engram.id)?;
+        }
+        
+        let mut entries = Vec::with_capacity(engram.metadata.len());
         for (key, value) in &engram.metadata {
+            let indexed_value = IndexedValue::of(value);
+            
             // Index by key
             self.key_index
                 .entry(key.clone())
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .insert(engram.id.clone());
             
             // Index by key-value pair (only for string values)
             if let serde_json::Value::String(str_value) = value {
                 self.key_value_index
-                    .entry((key.clone(), str_value.clone()))
-                    .or_insert_with(HashSet::new)
+                    .entry(key.clone())
+                    .or_default()
+                    .entry(str_value.clone())
+                    .or_default()
+                    .insert(engram.id.clone());
+                
+                if let Some(precision) = self.sketch_precision {
+                    self.value_sketches
+                        .entry(key.clone())
+                        .or_insert_with(|| HyperLogLog::new(precision))
+                        .insert(str_value);
+                }
+            }
+            
+            // Index numeric values for range queries
+            if let IndexedValue::Number(number) = indexed_value {
+                self.numeric_index
+                    .entry(key.clone())
+                    .or_default()
+                    .entry(OrderedFloat(number))
+                    .or_default()
                     .insert(engram.id.clone());
             }
+            
+            entries.push((key.clone(), indexed_value));
         }
+        self.engram_entries.insert(engram.id.clone(), entries);
         
         Ok(())
     }
     
+    /// Fold in an index built over a disjoint set of engrams with the same sketch precision
+    fn merge(&mut self, other: Self) {
+        merge_id_sets(&mut self.key_index, other.key_index);
+        for (key, values) in other.key_value_index {
+            let merged = self.key_value_index.entry(key).or_default();
+            for (value, ids) in values {
+                merged.entry(value).or_default().extend(ids);
+            }
+        }
+        for (key, values) in other.numeric_index {
+            let merged = self.numeric_index.entry(key).or_default();
+            for (value, ids) in values {
+                merged.entry(value).or_default().extend(ids);
+            }
+        }
+        for (key, sketch) in other.value_sketches {
+            match self.value_sketches.get_mut(&key) {
+                // Both sides were built by `empty_like`, so precisions always agree
+                Some(existing) => { let _ = existing.merge(&sketch); },
+                None => { self.value_sketches.insert(key, sketch); },
+            }
+        }
+        self.engram_entries.extend(other.engram_entries);
+    }
+    
     /// Remove an engram from the index
     pub fn remove_engram(&mut self, engram: &Engram) -> Result<()> {
-        for (key, value) in &engram.metadata {
+        self.remove_engram_by_id(&engram.id)
+    }
+    
+    /// Remove an engram from the index by ID, using the metadata recorded when it was added
+    pub fn remove_engram_by_id(&mut self, id: &EngramId) -> Result<()> {
+        let Some(entries) = self.engram_entries.remove(id) else {
+            return Ok(());
+        };
+        
+        for (key, value) in &entries {
             // Remove from key index
             if let Some(engrams) = self.key_index.get_mut(key) {
-                engrams.remove(&engram.id);
+                engrams.remove(id);
                 if engrams.is_empty() {
                     self.key_index.remove(key);
                 }
             }
             
             // Remove from key-value index
-            if let serde_json::Value::String(str_value) = value {
-                let entry = (key.clone(), str_value.clone());
-                if let Some(engrams) = self.key_value_index.get_mut(&entry) {
-                    engrams.remove(&engram.id);
-                    if engrams.is_empty() {
-                        self.key_value_index.remove(&entry);
+            if let IndexedValue::String(str_value) = value {
+                if let Some(values) = self.key_value_index.get_mut(key) {
+                    if let Some(engrams) = values.get_mut(str_value) {
+                        engrams.remove(id);
+                        if engrams.is_empty() {
+                            values.remove(str_value);
+                        }
+                    }
+                    if values.is_empty() {
+                        self.key_value_index.remove(key);
+                    }
+                }
+            }
+            
+            // Remove from numeric index
+            if let IndexedValue::Number(number) = value {
+                if let Some(values) = self.numeric_index.get_mut(key) {
+                    if let Some(engrams) = values.get_mut(&OrderedFloat(*number)) {
+                        engrams.remove(id);
+                        if engrams.is_empty() {
+                            values.remove(&OrderedFloat(*number));
+                        }
+                    }
+                    if values.is_empty() {
+                        self.numeric_index.remove(key);
                     }
                 }
             }
@@ -327,16 +914,99 @@ impl MetadataIndex {
     
     /// Find engrams with a specific metadata key-value pair
     pub fn find_by_key_value(&self, key: &str, value: &str) -> HashSet<EngramId> {
-        let entry = (key.to_string(), value.to_string());
         self.key_value_index
-            .get(&entry)
+            .get(key)
+            .and_then(|values| values.get(value))
             .cloned()
             .unwrap_or_else(HashSet::new)
     }
+    
+    /// Find engrams whose numeric value (integer or float) for a metadata key lies within
+    /// `[min, max]`, with a single range scan. Pass infinities for open-ended ranges.
+    pub fn find_by_numeric_range(&self, key: &str, min: f64, max: f64) -> HashSet<EngramId> {
+        // `BTreeMap::range` panics on inverted bounds; NaN bounds match nothing either
+        if min.is_nan() || max.is_nan() || min > max {
+            return HashSet::new();
+        }
+        let Some(values) = self.numeric_index.get(key) else {
+            return HashSet::new();
+        };
+        
+        values
+            .range(OrderedFloat(min + 0.0)..=OrderedFloat(max + 0.0))
+            .flat_map(|(_, engrams)| engrams.iter().cloned())
+            .collect()
+    }
+    
+    /// Find engrams whose string value for a metadata key starts with a prefix
+    /// (e.g. `date` values starting with "2023-05")
+    pub fn find_by_value_prefix(&self, key: &str, prefix: &str) -> HashSet<EngramId> {
+        let Some(values) = self.key_value_index.get(key) else {
+            return HashSet::new();
+        };
+        
+        values
+            .range::<str, _>((std::ops::Bound::Included(prefix), std::ops::Bound::Unbounded))
+            .take_while(|(value, _)| value.starts_with(prefix))
+            .flat_map(|(_, engrams)| engrams.iter().cloned())
+            .collect()
+    }
+    
+    /// Find engrams whose string value for a metadata key contains a substring.
+    /// Scans every distinct value stored under the key.
+    pub fn find_by_value_contains(&self, key: &str, substring: &str) -> HashSet<EngramId> {
+        let Some(values) = self.key_value_index.get(key) else {
+            return HashSet::new();
+        };
+        
+        values
+            .iter()
+            .filter(|(value, _)| value.contains(substring))
+            .flat_map(|(_, engrams)| engrams.iter().cloned())
+            .collect()
+    }
+    
+    /// Exact number of distinct string values currently indexed under a metadata key
+    pub fn distinct_value_count(&self, key: &str) -> usize {
+        self.key_value_index.get(key).map_or(0, |values| values.len())
+    }
+    
+    /// Approximate number of distinct string values seen under a metadata key, from its
+    /// value sketch when `with_value_sketches` is enabled, otherwise the exact count
+    pub fn estimate_distinct_values(&self, key: &str) -> usize {
+        match self.sketch_precision {
+            Some(_) => self.value_sketches.get(key).map_or(0, |sketch| sketch.estimate()),
+            None => self.distinct_value_count(key),
+        }
+    }
+}
+
+impl Default for MetadataIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How `TextIndex::search_ranked` scores matching engrams
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScoringMode {
+    /// `(1 + ln tf) * idf`, ignoring document length
+    TfIdf,
+    
+    /// Okapi BM25: `k1` controls term frequency saturation, `b` how strongly scores are
+    /// normalized by document length (0.0 disables length normalization)
+    Bm25 { k1: f32, b: f32 },
+}
+
+impl Default for ScoringMode {
+    fn default() -> Self {
+        ScoringMode::Bm25 { k1: 1.2, b: 0.75 }
+    }
 }
 
 /// Text search index for basic keyword search
 #[allow(dead_code)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct TextIndex {
     /// Maps normalized keywords to engram IDs
     keyword_index: HashMap<String, HashSet<EngramId>>,
@@ -346,6 +1016,45 @@ pub struct TextIndex {
     
     /// Maps engram IDs to the set of keywords it contains
     engram_keywords: HashMap<EngramId, HashSet<String>>,
+    
+    /// Maps character n-grams to engram IDs (only populated when n-gram indexing is enabled)
+    ngram_index: HashMap<String, HashSet<EngramId>>,
+    
+    /// Maps engram IDs to the n-grams indexed for them (only populated when n-gram indexing is enabled)
+    engram_ngrams: HashMap<EngramId, HashSet<String>>,
+    
+    /// Length of the character n-grams to index (None disables n-gram indexing)
+    ngram_size: Option<usize>,
+    
+    /// Whether to detect each engram's language and apply a language-specific analyzer
+    detect_language: bool,
+    
+    /// Detected language (ISO 639-3 code) of each engram indexed with language detection
+    engram_languages: HashMap<EngramId, String>,
+    
+    /// Maps language codes to engram IDs
+    language_index: HashMap<String, HashSet<EngramId>>,
+    
+    /// Metadata keys whose string values are indexed alongside the content
+    searchable_metadata_keys: HashSet<String>,
+    
+    /// Maps engram IDs to the number of occurrences of each stem in the engram
+    term_frequencies: HashMap<EngramId, HashMap<String, u32>>,
+    
+    /// Maps stems to the number of engrams containing them
+    document_frequencies: HashMap<String, usize>,
+    
+    /// Maps engram IDs to their number of counted tokens
+    document_lengths: HashMap<EngramId, usize>,
+    
+    /// Sum of all document lengths, for the average used by BM25
+    total_document_length: usize,
+    
+    /// Scoring function used by `search_ranked`
+    scoring_mode: ScoringMode,
+    
+    /// Maps engram IDs to their content tokens with positions, in order, for phrase search
+    token_positions: HashMap<EngramId, Vec<(String, u32)>>,
 }
 
 #[allow(dead_code)]
@@ -356,48 +1065,252 @@ impl TextIndex {
             keyword_index: HashMap::new(),
             stem_index: HashMap::new(),
             engram_keywords: HashMap::new(),
+            ngram_index: HashMap::new(),
+            engram_ngrams: HashMap::new(),
+            ngram_size: None,
+            detect_language: false,
+            engram_languages: HashMap::new(),
+            language_index: HashMap::new(),
+            searchable_metadata_keys: HashSet::new(),
+            term_frequencies: HashMap::new(),
+            document_frequencies: HashMap::new(),
+            document_lengths: HashMap::new(),
+            total_document_length: 0,
+            scoring_mode: ScoringMode::default(),
+            token_positions: HashMap::new(),
         }
     }
     
-    /// Add an engram to the index
-    pub fn add_engram(&mut self, engram: &Engram) -> Result<()> {
-        let keywords = Self::extract_keywords(&engram.content);
-        self.engram_keywords.insert(engram.id.clone(), keywords.clone());
-        
-        // Index each keyword
-        for keyword in &keywords {
-            self.keyword_index
-                .entry(keyword.clone())
-                .or_insert_with(HashSet::new)
-                .insert(engram.id.clone());
-            
-            // Also index the stemmed version
-            let stemmed = Self::stem_word(keyword);
-            self.stem_index
-                .entry(stemmed)
-                .or_insert_with(HashSet::new)
-                .insert(engram.id.clone());
-        }
-        
-        Ok(())
+    /// Set the scoring function used by `search_ranked` (BM25 with k1=1.2, b=0.75 by default)
+    pub fn with_scoring_mode(mut self, mode: ScoringMode) -> Self {
+        self.scoring_mode = mode;
+        self
     }
     
-    /// Remove an engram from the index
-    pub fn remove_engram(&mut self, engram: &Engram) -> Result<()> {
-        if let Some(keywords) = self.engram_keywords.remove(&engram.id) {
-            // Remove from keyword index
-            for keyword in &keywords {
-                if let Some(engrams) = self.keyword_index.get_mut(keyword) {
-                    engrams.remove(&engram.id);
-                    if engrams.is_empty() {
-                        self.keyword_index.remove(keyword);
-                    }
-                }
-                
+    /// Get the scoring function used by `search_ranked`
+    pub fn scoring_mode(&self) -> ScoringMode {
+        self.scoring_mode
+    }
+    
+    /// Also index the string values of these metadata keys, so `search` finds terms that
+    /// only appear in metadata. Each value's keywords are indexed both plainly and prefixed
+    /// by key (e.g. "component:api" for `component=api_service`) for `find_by_metadata_term`.
+    /// Only engrams added afterwards are affected.
+    pub fn with_searchable_metadata_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.searchable_metadata_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// Metadata keys whose values are indexed for text search
+    pub fn searchable_metadata_keys(&self) -> &HashSet<String> {
+        &self.searchable_metadata_keys
+    }
+    
+    /// Find engrams whose value for a searchable metadata key contains the keyword
+    pub fn find_by_metadata_term(&self, key: &str, keyword: &str) -> HashSet<EngramId> {
+        self.find_by_keyword(&Self::metadata_keyword(key, keyword))
+    }
+    
+    /// Keyword under which a metadata value's keyword is indexed for its key
+    fn metadata_keyword(key: &str, keyword: &str) -> String {
+        format!("{}:{}", key.to_lowercase(), keyword.to_lowercase())
+    }
+    
+    /// Keywords from the engram's searchable metadata values, plain and key-prefixed
+    fn metadata_keywords(&self, engram: &Engram) -> HashSet<String> {
+        let mut keywords = HashSet::new();
+        
+        for key in &self.searchable_metadata_keys {
+            if let Some(serde_json::Value::String(value)) = engram.metadata.get(key) {
+                for keyword in Self::extract_keywords(value) {
+                    keywords.insert(Self::metadata_keyword(key, &keyword));
+                    keywords.insert(keyword);
+                }
+            }
+        }
+        
+        keywords
+    }
+    
+    /// Configure whether to detect the language of indexed engrams.
+    /// Detected languages get their own stemmer and stopword list; unknown languages
+    /// fall back to the default analyzer.
+    pub fn with_language_detection(mut self, enabled: bool) -> Self {
+        self.detect_language = enabled;
+        self
+    }
+    
+    /// Create a text index that also indexes character n-grams of the given length.
+    /// N-gram indexing is opt-in because it grows the index considerably.
+    pub fn with_ngrams(ngram_size: usize) -> Self {
+        let mut index = Self::new();
+        index.ngram_size = Some(ngram_size.max(1));
+        index
+    }
+    
+    /// Get the configured n-gram length, if n-gram indexing is enabled
+    pub fn ngram_size(&self) -> Option<usize> {
+        self.ngram_size
+    }
+    
+    /// Add an engram to the index
+    pub fn add_engram(&mut self, engram: &Engram) -> Result<()> {
+        if self.engram_keywords.contains_key(&engram.id) {
+            self.remove_engram_by_id(&engram.id)?;
+        }
+        
+        let language = if self.detect_language {
+            Self::detect_language(&engram.content)
+        } else {
+            None
+        };
+        
+        let mut keywords: HashSet<String> = Self::extract_keywords(&engram.content)
+            .into_iter()
+            .filter(|keyword| !Self::is_stopword(keyword, language.as_deref()))
+            .collect();
+        let metadata_keywords = self.metadata_keywords(engram);
+        
+        // Count stem occurrences for ranked search
+        let mut frequencies: HashMap<String, u32> = HashMap::new();
+        for token in Self::tokenize(&engram.content)
+            .filter(|token| !Self::is_stopword(token, language.as_deref()))
+            .chain(metadata_keywords.iter().cloned())
+        {
+            *frequencies.entry(Self::stem_for_language(&token, language.as_deref())).or_insert(0) += 1;
+        }
+        for stem in frequencies.keys() {
+            *self.document_frequencies.entry(stem.clone()).or_insert(0) += 1;
+        }
+        let length = frequencies.values().map(|&count| count as usize).sum();
+        self.document_lengths.insert(engram.id.clone(), length);
+        self.total_document_length += length;
+        self.term_frequencies.insert(engram.id.clone(), frequencies);
+        
+        keywords.extend(metadata_keywords);
+        self.engram_keywords.insert(engram.id.clone(), keywords.clone());
+        
+        // Record token order for phrase search
+        let positions = Self::tokenize_with_positions(&engram.content).collect();
+        self.token_positions.insert(engram.id.clone(), positions);
+        
+        // Index character n-grams if enabled
+        if let Some(n) = self.ngram_size {
+            let ngrams = Self::extract_ngrams(&engram.content, n);
+            for ngram in &ngrams {
+                self.ngram_index
+                    .entry(ngram.clone())
+                    .or_default()
+                    .insert(engram.id.clone());
+            }
+            self.engram_ngrams.insert(engram.id.clone(), ngrams);
+        }
+        
+        // Index each keyword
+        for keyword in &keywords {
+            self.keyword_index
+                .entry(keyword.clone())
+                .or_default()
+                .insert(engram.id.clone());
+            
+            // Also index the stemmed version
+            let stemmed = Self::stem_for_language(keyword, language.as_deref());
+            self.stem_index
+                .entry(stemmed)
+                .or_default()
+                .insert(engram.id.clone());
+        }
+        
+        // Record the detected language
+        if let Some(lang) = language {
+            self.language_index
+                .entry(lang.clone())
+                .or_default()
+                .insert(engram.id.clone());
+            self.engram_languages.insert(engram.id.clone(), lang);
+        }
+        
+        Ok(())
+    }
+    
+    /// An empty index with the same analysis settings
+    fn empty_like(&self) -> Self {
+        Self {
+            ngram_size: self.ngram_size,
+            detect_language: self.detect_language,
+            searchable_metadata_keys: self.searchable_metadata_keys.clone(),
+            scoring_mode: self.scoring_mode,
+            ..Self::new()
+        }
+    }
+    
+    /// Fold in an index built over a disjoint set of engrams with the same settings
+    fn merge(&mut self, other: Self) {
+        merge_id_sets(&mut self.keyword_index, other.keyword_index);
+        merge_id_sets(&mut self.stem_index, other.stem_index);
+        merge_id_sets(&mut self.ngram_index, other.ngram_index);
+        merge_id_sets(&mut self.language_index, other.language_index);
+        self.engram_ngrams.extend(other.engram_ngrams);
+        self.engram_keywords.extend(other.engram_keywords);
+        self.engram_languages.extend(other.engram_languages);
+        self.term_frequencies.extend(other.term_frequencies);
+        for (stem, count) in other.document_frequencies {
+            *self.document_frequencies.entry(stem).or_insert(0) += count;
+        }
+        self.document_lengths.extend(other.document_lengths);
+        self.total_document_length += other.total_document_length;
+        self.token_positions.extend(other.token_positions);
+    }
+    
+    /// Forget an engram's stem counts and length and its contribution to document frequencies
+    fn remove_term_frequencies(&mut self, engram_id: &str) {
+        if let Some(length) = self.document_lengths.remove(engram_id) {
+            self.total_document_length -= length;
+        }
+        
+        let Some(frequencies) = self.term_frequencies.remove(engram_id) else {
+            return;
+        };
+        
+        for stem in frequencies.keys() {
+            if let Some(count) = self.document_frequencies.get_mut(stem) {
+                *count -= 1;
+                if *count == 0 {
+                    self.document_frequencies.remove(stem);
+                }
+            }
+        }
+    }
+    
+    /// Remove an engram from the index
+    pub fn remove_engram(&mut self, engram: &Engram) -> Result<()> {
+        self.remove_engram_by_id(&engram.id)
+    }
+    
+    /// Remove an engram from the index by ID, using the keywords and n-grams recorded when it was added
+    pub fn remove_engram_by_id(&mut self, id: &EngramId) -> Result<()> {
+        let language = self.engram_languages.remove(id);
+        self.remove_term_frequencies(id);
+        self.token_positions.remove(id);
+        
+        if let Some(keywords) = self.engram_keywords.remove(id) {
+            // Remove from keyword index
+            for keyword in &keywords {
+                if let Some(engrams) = self.keyword_index.get_mut(keyword) {
+                    engrams.remove(id);
+                    if engrams.is_empty() {
+                        self.keyword_index.remove(keyword);
+                    }
+                }
+                
                 // Remove from stem index
-                let stemmed = Self::stem_word(keyword);
+                let stemmed = Self::stem_for_language(keyword, language.as_deref());
                 if let Some(engrams) = self.stem_index.get_mut(&stemmed) {
-                    engrams.remove(&engram.id);
+                    engrams.remove(id);
                     if engrams.is_empty() {
                         self.stem_index.remove(&stemmed);
                     }
@@ -405,43 +1318,192 @@ impl TextIndex {
             }
         }
         
+        // Remove from language index
+        if let Some(lang) = &language {
+            if let Some(engrams) = self.language_index.get_mut(lang) {
+                engrams.remove(id);
+                if engrams.is_empty() {
+                    self.language_index.remove(lang);
+                }
+            }
+        }
+        
+        // Remove from n-gram index
+        if let Some(ngrams) = self.engram_ngrams.remove(id) {
+            for ngram in &ngrams {
+                if let Some(engrams) = self.ngram_index.get_mut(ngram) {
+                    engrams.remove(id);
+                    if engrams.is_empty() {
+                        self.ngram_index.remove(ngram);
+                    }
+                }
+            }
+        }
+        
         Ok(())
     }
     
-    /// Extract keywords from text content
-    fn extract_keywords(text: &str) -> HashSet<String> {
-        let mut keywords = HashSet::new();
+    /// Extract character n-grams from each token of the text
+    fn extract_ngrams(text: &str, n: usize) -> HashSet<String> {
+        let mut ngrams = HashSet::new();
         
-        // Simple tokenization by splitting on whitespace and punctuation
-        for word in text
-            .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+        for token in text
+            .split(|c: char| !c.is_alphanumeric())
             .filter(|s| !s.is_empty())
         {
-            // Convert to lowercase for case-insensitive matching
-            let normalized = word.to_lowercase();
-            if normalized.len() >= 3 {  // Only index words of at least 3 characters
-                keywords.insert(normalized);
+            let chars: Vec<char> = token.to_lowercase().chars().collect();
+            
+            // Tokens shorter than n are indexed as a whole
+            if chars.len() <= n {
+                ngrams.insert(chars.iter().collect());
+                continue;
+            }
+            
+            for window in chars.windows(n) {
+                ngrams.insert(window.iter().collect());
             }
         }
         
-        keywords
+        ngrams
+    }
+    
+    /// Split text into lowercase keyword tokens, keeping repeats
+    fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+        Self::tokenize_with_positions(text).map(|(word, _)| word)
     }
     
-    /// Very basic stemming function
-    /// In a real implementation, you'd want to use a proper stemming algorithm
+    /// Split text into lowercase keyword tokens with their word positions. Positions count
+    /// every word, including the short ones that are dropped, so dropped words still
+    /// separate their neighbours.
+    fn tokenize_with_positions(text: &str) -> impl Iterator<Item = (String, u32)> + '_ {
+        // Simple tokenization by splitting on whitespace and punctuation
+        text
+            .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+            .filter(|s| !s.is_empty())
+            .zip(0u32..)
+            // Convert to lowercase for case-insensitive matching
+            .map(|(word, position)| (word.to_lowercase(), position))
+            .filter(|(word, _)| word.len() >= 3)  // Only index words of at least 3 characters
+    }
+    
+    /// Extract keywords from text content
+    pub(crate) fn extract_keywords(text: &str) -> HashSet<String> {
+        Self::tokenize(text).collect()
+    }
+    
+    /// Stem an English word with the Porter2 (Snowball English) stemmer, so that e.g.
+    /// "organization" and "organize" or "connections" and "connected" share a stem
     fn stem_word(word: &str) -> String {
-        let word = word.to_lowercase();
+        Stemmer::create(Algorithm::English).stem(&word.to_lowercase()).into_owned()
+    }
+    
+    /// Detect the language of a text, returning its ISO 639-3 code (e.g. "eng", "fra").
+    /// Returns None if the detection is not reliable.
+    pub fn detect_language(text: &str) -> Option<String> {
+        whatlang::detect(text)
+            .filter(|info| info.is_reliable())
+            .map(|info| info.lang().code().to_string())
+    }
+    
+    /// Detect the language of an engram's content and store it in the "language" metadata field
+    /// so it can be filtered through the metadata index. Existing values are left untouched.
+    pub fn tag_language(engram: &mut Engram) -> Option<String> {
+        if let Some(serde_json::Value::String(lang)) = engram.metadata.get("language") {
+            return Some(lang.clone());
+        }
         
-        // Very simplified stemming - just handles a few common English suffixes
-        if word.ends_with('s') && word.len() > 3 {
-            return word[..word.len() - 1].to_string();
-        } else if word.ends_with("ing") && word.len() > 5 {
-            return word[..word.len() - 3].to_string();
-        } else if word.ends_with("ed") && word.len() > 4 {
-            return word[..word.len() - 2].to_string();
+        let lang = Self::detect_language(&engram.content)?;
+        engram.metadata.insert("language".to_string(), serde_json::Value::String(lang.clone()));
+        Some(lang)
+    }
+    
+    /// Get the Snowball stemming algorithm for a language code, if one is available.
+    /// English is handled by the default analyzer.
+    fn stemmer_algorithm(language: &str) -> Option<Algorithm> {
+        match language {
+            "fra" => Some(Algorithm::French),
+            "deu" => Some(Algorithm::German),
+            "spa" => Some(Algorithm::Spanish),
+            "ita" => Some(Algorithm::Italian),
+            "por" => Some(Algorithm::Portuguese),
+            "nld" => Some(Algorithm::Dutch),
+            "swe" => Some(Algorithm::Swedish),
+            "dan" => Some(Algorithm::Danish),
+            "nob" => Some(Algorithm::Norwegian),
+            "fin" => Some(Algorithm::Finnish),
+            "ron" => Some(Algorithm::Romanian),
+            "hun" => Some(Algorithm::Hungarian),
+            "rus" => Some(Algorithm::Russian),
+            "tur" => Some(Algorithm::Turkish),
+            _ => None,
+        }
+    }
+    
+    /// Stem a word using the analyzer for the given language, falling back to the default analyzer
+    fn stem_for_language(word: &str, language: Option<&str>) -> String {
+        match language.and_then(Self::stemmer_algorithm) {
+            Some(algorithm) => Stemmer::create(algorithm).stem(&word.to_lowercase()).to_string(),
+            None => Self::stem_word(word),
         }
+    }
+    
+    /// Check whether a word is a stopword in the given language.
+    /// Only languages detected at index time have stopword lists applied.
+    pub(crate) fn is_stopword(word: &str, language: Option<&str>) -> bool {
+        let stopwords: &[&str] = match language {
+            Some("eng") => &["the", "and", "for", "are", "but", "not", "you", "all", "with", "this", "that", "from", "have", "was", "were"],
+            Some("fra") => &["les", "des", "une", "est", "dans", "pour", "par", "sur", "pas", "qui", "que", "avec", "sont", "aux", "ces"],
+            Some("deu") => &["der", "die", "das", "und", "ist", "nicht", "ein", "eine", "mit", "von", "den", "dem", "auf", "sich", "für"],
+            Some("spa") => &["los", "las", "del", "una", "por", "para", "con", "que", "como", "pero", "sus", "son", "est", "esta", "este"],
+            _ => &[],
+        };
         
-        word
+        stopwords.contains(&word)
+    }
+    
+    /// Get the detected language of an indexed engram
+    pub fn engram_language(&self, engram_id: &str) -> Option<&str> {
+        self.engram_languages.get(engram_id).map(|lang| lang.as_str())
+    }
+    
+    /// Find engrams whose content was detected as the given language (ISO 639-3 code)
+    pub fn find_by_language(&self, language: &str) -> HashSet<EngramId> {
+        self.language_index
+            .get(language)
+            .cloned()
+            .unwrap_or_else(HashSet::new)
+    }
+    
+    /// Find engrams containing a stemmed version of the keyword using a specific language's stemmer
+    pub fn find_by_stem_in_language(&self, keyword: &str, language: &str) -> HashSet<EngramId> {
+        let stemmed = Self::stem_for_language(keyword, Some(language));
+        
+        self.stem_index
+            .get(&stemmed)
+            .cloned()
+            .unwrap_or_else(HashSet::new)
+    }
+    
+    /// Find engrams matching a query keyword exactly or by stem, also applying the
+    /// query language's stemmer when language detection is enabled
+    fn find_keyword_matches(&self, keyword: &str, query_language: Option<&str>) -> HashSet<EngramId> {
+        let mut combined = self.find_by_keyword(keyword);
+        combined.extend(self.find_by_stem(keyword));
+        
+        if let Some(lang) = query_language {
+            combined.extend(self.find_by_stem_in_language(keyword, lang));
+        }
+        
+        combined
+    }
+    
+    /// Detect the language of a query if language detection is enabled
+    fn query_language(&self, query: &str) -> Option<String> {
+        if self.detect_language {
+            Self::detect_language(query)
+        } else {
+            None
+        }
     }
     
     /// Find engrams containing a specific keyword (exact match)
@@ -464,42 +1526,222 @@ impl TextIndex {
             .unwrap_or_else(HashSet::new)
     }
     
+    /// Find engrams sharing character n-grams with the query, ranked by the number of shared n-grams.
+    /// Returns an empty list if n-gram indexing is not enabled.
+    pub fn find_by_ngrams(&self, query: &str) -> Vec<(EngramId, usize)> {
+        let n = match self.ngram_size {
+            Some(n) => n,
+            None => return Vec::new(),
+        };
+        
+        let mut counts: HashMap<EngramId, usize> = HashMap::new();
+        for ngram in Self::extract_ngrams(query, n) {
+            if let Some(engrams) = self.ngram_index.get(&ngram) {
+                for id in engrams {
+                    *counts.entry(id.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        
+        // Sort by shared n-gram count (highest first), then by ID for stable ordering
+        let mut ranked: Vec<(EngramId, usize)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+    
     /// Search for engrams containing any of the keywords
     pub fn search(&self, query: &str) -> HashSet<EngramId> {
         let keywords = Self::extract_keywords(query);
+        let query_language = self.query_language(query);
         let mut results = HashSet::new();
         
         for keyword in keywords {
-            // Get results for this keyword
-            let keyword_results = self.find_by_keyword(&keyword);
-            let stem_results = self.find_by_stem(&keyword);
-            
-            // Combine both sets
-            let mut combined = keyword_results;
-            combined.extend(stem_results);
+            // Get exact and stem matches for this keyword and add to overall results
+            results.extend(self.find_keyword_matches(&keyword, query_language.as_deref()));
+        }
+        
+        results
+    }
+    
+    /// Search like `search`, but for query keywords with no exact or stem match, also match
+    /// indexed keywords within `max_distance` edits (capped at `MAX_FUZZY_DISTANCE`), so
+    /// misspellings like "recieve" still find "receive".
+    ///
+    /// Each unmatched keyword scans the keyword vocabulary. Only candidates with the same
+    /// first character and a length within `max_distance` get a full edit distance check,
+    /// which keeps the scan cheap, but misspelled first letters are never corrected.
+    pub fn search_fuzzy(&self, query: &str, max_distance: u8) -> HashSet<EngramId> {
+        let max_distance = max_distance.min(MAX_FUZZY_DISTANCE) as usize;
+        let query_language = self.query_language(query);
+        let mut results = HashSet::new();
+        
+        for keyword in Self::extract_keywords(query) {
+            let matches = self.find_keyword_matches(&keyword, query_language.as_deref());
+            if !matches.is_empty() || max_distance == 0 {
+                results.extend(matches);
+                continue;
+            }
             
-            // Add to overall results
-            results.extend(combined);
+            let chars: Vec<char> = keyword.chars().collect();
+            for (candidate, engrams) in &self.keyword_index {
+                let candidate: Vec<char> = candidate.chars().collect();
+                if candidate.first() != chars.first() || candidate.len().abs_diff(chars.len()) > max_distance {
+                    continue;
+                }
+                
+                if levenshtein(&chars, &candidate) <= max_distance {
+                    results.extend(engrams.iter().cloned());
+                }
+            }
         }
         
         results
     }
     
+    /// Find engrams whose content contains the phrase, i.e. its tokens consecutively and in
+    /// order. Tokens are matched exactly (case-insensitively, without stemming). Words shorter
+    /// than 3 characters are not matched themselves, like everywhere else in the index, but
+    /// still take up their position on both sides.
+    pub fn search_phrase(&self, phrase: &str) -> HashSet<EngramId> {
+        self.search_near(phrase, 0)
+    }
+    
+    /// Like `search_phrase`, but allow up to `window` other tokens between each pair of
+    /// neighbouring phrase tokens. Tokens must still appear in order.
+    pub fn search_near(&self, phrase: &str, window: u32) -> HashSet<EngramId> {
+        let tokens: Vec<(String, u32)> = Self::tokenize_with_positions(phrase).collect();
+        if tokens.is_empty() {
+            return HashSet::new();
+        }
+        
+        // Narrow down to engrams containing every token the keyword index knows; tokens it
+        // leaves out (like stopwords) are only checked by position
+        let mut candidates: Option<HashSet<EngramId>> = None;
+        for (token, _) in &tokens {
+            if let Some(engrams) = self.keyword_index.get(token) {
+                candidates = Some(match candidates {
+                    Some(prev) => prev.intersection(engrams).cloned().collect(),
+                    None => engrams.clone(),
+                });
+            }
+        }
+        let candidates = candidates.unwrap_or_else(|| self.token_positions.keys().cloned().collect());
+        
+        candidates
+            .into_iter()
+            .filter(|id| {
+                self.token_positions
+                    .get(id)
+                    .is_some_and(|positions| Self::contains_sequence(positions, &tokens, window))
+            })
+            .collect()
+    }
+    
+    /// Check whether the tokens occur in order with the same spacing as in the phrase, allowing
+    /// up to `window` extra positions between neighbours
+    fn contains_sequence(positions: &[(String, u32)], tokens: &[(String, u32)], window: u32) -> bool {
+        // Each token's occurrences, paired with its distance from the previous phrase token
+        let occurrences: Vec<(u32, Vec<u32>)> = tokens
+            .iter()
+            .zip(std::iter::once(0).chain(tokens.iter().map(|(_, position)| *position)))
+            .map(|((token, position), previous)| {
+                let matches = positions
+                    .iter()
+                    .filter(|(word, _)| word == token)
+                    .map(|(_, position)| *position)
+                    .collect();
+                (position - previous, matches)
+            })
+            .collect();
+        
+        occurrences[0]
+            .1
+            .iter()
+            .any(|&start| Self::continues_sequence(&occurrences[1..], start, window))
+    }
+    
+    /// Check whether the remaining tokens can follow a token at `previous`
+    fn continues_sequence(occurrences: &[(u32, Vec<u32>)], previous: u32, window: u32) -> bool {
+        let Some(((gap, next), rest)) = occurrences.split_first() else {
+            return true;
+        };
+        
+        next.iter()
+            .filter(|&&position| position >= previous + gap && position - previous <= gap + window)
+            .any(|&position| Self::continues_sequence(rest, position, window))
+    }
+    
+    /// `search`, with the matching IDs in sorted order
+    pub fn search_sorted(&self, query: &str) -> Vec<EngramId> {
+        sorted_ids(self.search(query))
+    }
+    
+    /// Search for engrams matching any of the query's keywords by stem, ranked by the index's
+    /// `ScoringMode`, so rare terms and repeated mentions rank higher. Returns at most `limit`
+    /// results sorted by descending score, with ties broken by ID.
+    pub fn search_ranked(&self, query: &str, limit: usize) -> Vec<(EngramId, f32)> {
+        let query_language = self.query_language(query);
+        let mut stems = HashSet::new();
+        for keyword in Self::extract_keywords(query) {
+            if let Some(lang) = query_language.as_deref() {
+                stems.insert(Self::stem_for_language(&keyword, Some(lang)));
+            }
+            stems.insert(Self::stem_word(&keyword));
+        }
+        
+        let mut scores: HashMap<EngramId, f32> = HashMap::new();
+        for stem in &stems {
+            let Some(&document_frequency) = self.document_frequencies.get(stem) else {
+                continue;
+            };
+            
+            for id in self.stem_index.get(stem).into_iter().flatten() {
+                let Some(&tf) = self.term_frequencies.get(id).and_then(|frequencies| frequencies.get(stem)) else {
+                    continue;
+                };
+                *scores.entry(id.clone()).or_insert(0.0) += self.term_score(id, tf, document_frequency);
+            }
+        }
+        
+        let mut ranked: Vec<(EngramId, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        ranked
+    }
+    
+    /// Score contribution of a stem occurring `tf` times in an engram, given the number of
+    /// engrams containing it
+    fn term_score(&self, engram_id: &str, tf: u32, document_frequency: usize) -> f32 {
+        let document_count = self.term_frequencies.len() as f32;
+        let document_frequency = document_frequency as f32;
+        let tf = tf as f32;
+        
+        match self.scoring_mode {
+            ScoringMode::TfIdf => {
+                let idf = ((document_count + 1.0) / (document_frequency + 1.0)).ln() + 1.0;
+                (1.0 + tf.ln()) * idf
+            }
+            ScoringMode::Bm25 { k1, b } => {
+                let idf = ((document_count - document_frequency + 0.5) / (document_frequency + 0.5) + 1.0).ln();
+                let length = self.document_lengths.get(engram_id).copied().unwrap_or(0) as f32;
+                let average_length = self.total_document_length as f32 / document_count.max(1.0);
+                let norm = if average_length > 0.0 { length / average_length } else { 1.0 };
+                idf * tf * (k1 + 1.0) / (tf + k1 * (1.0 - b + b * norm))
+            }
+        }
+    }
+    
     /// Search for engrams containing all of the keywords
     pub fn search_all(&self, query: &str) -> HashSet<EngramId> {
         let keywords = Self::extract_keywords(query);
+        let query_language = self.query_language(query);
         
         // Start with the entire universe of engrams
         let mut results: Option<HashSet<EngramId>> = None;
         
         for keyword in keywords {
             // Get results for this keyword (exact or stem matches)
-            let keyword_results = self.find_by_keyword(&keyword);
-            let stem_results = self.find_by_stem(&keyword);
-            
-            // Combine both sets
-            let mut combined = keyword_results;
-            combined.extend(stem_results);
+            let combined = self.find_keyword_matches(&keyword, query_language.as_deref());
             
             // Perform intersection with previous results
             results = match results {
@@ -515,11 +1757,18 @@ impl TextIndex {
             }
         }
         
-        results.unwrap_or_else(HashSet::new)
+        results.unwrap_or_default()
+    }
+}
+
+impl Default for TextIndex {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 /// Index for tracking engrams by time periods
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct TemporalIndex {
     /// Engrams indexed by year
     year_index: HashMap<i32, HashSet<EngramId>>,
@@ -533,13 +1782,22 @@ pub struct TemporalIndex {
     /// Engrams indexed by hour buckets (24 buckets, 0-23)
     hour_index: HashMap<u8, HashSet<EngramId>>,
     
-    /// Sorted list of engram IDs by recency (most recent first)
+    /// The most recent engram IDs (most recent first), bounded by `recency_capacity`
     recency_list: Vec<EngramId>,
     
+    /// Maximum number of engram IDs tracked in `recency_list`
+    recency_capacity: usize,
+    
+    /// All engram IDs ordered by timestamp, for range queries and recency beyond the cap
+    timeline: BTreeMap<chrono::DateTime<chrono::Utc>, BTreeSet<EngramId>>,
+    
     /// Map of engram IDs to their timestamp for quick access
     timestamp_map: HashMap<EngramId, chrono::DateTime<chrono::Utc>>,
 }
 
+/// Default number of engram IDs tracked in a `TemporalIndex` recency list
+pub const DEFAULT_RECENCY_CAPACITY: usize = 1024;
+
 #[allow(dead_code)]
 impl TemporalIndex {
     /// Create a new, empty temporal index
@@ -550,12 +1808,55 @@ impl TemporalIndex {
             day_index: HashMap::new(),
             hour_index: HashMap::new(),
             recency_list: Vec::new(),
+            recency_capacity: DEFAULT_RECENCY_CAPACITY,
+            timeline: BTreeMap::new(),
             timestamp_map: HashMap::new(),
         }
     }
     
-    /// Add an engram to the index
+    /// Track at most `capacity` engrams in the recency list.
+    /// Older engrams remain available to range queries and `get_most_recent` via the timeline.
+    pub fn with_recency_capacity(mut self, capacity: usize) -> Self {
+        self.recency_capacity = capacity;
+        self.rebuild_recency_list();
+        self
+    }
+    
+    /// Refill the recency list with the newest engrams from the timeline
+    fn rebuild_recency_list(&mut self) {
+        self.recency_list = self.timeline
+            .values()
+            .rev()
+            .flat_map(|ids| ids.iter().rev())
+            .take(self.recency_capacity)
+            .cloned()
+            .collect();
+    }
+    
+    /// Fold in an index built over a disjoint set of engrams
+    fn merge(&mut self, other: Self) {
+        merge_id_sets(&mut self.year_index, other.year_index);
+        merge_id_sets(&mut self.month_index, other.month_index);
+        merge_id_sets(&mut self.day_index, other.day_index);
+        merge_id_sets(&mut self.hour_index, other.hour_index);
+        for (timestamp, ids) in other.timeline {
+            self.timeline.entry(timestamp).or_default().extend(ids);
+        }
+        self.timestamp_map.extend(other.timestamp_map);
+        self.rebuild_recency_list();
+    }
+    
+    /// Recency ordering key: newest first, ties broken by descending ID to match the timeline
+    fn recency_key(&self, id: &EngramId) -> std::cmp::Reverse<(chrono::DateTime<chrono::Utc>, EngramId)> {
+        std::cmp::Reverse((self.timestamp_map[id], id.clone()))
+    }
+    
+    /// Add an engram to the index, replacing any previous entry for the same ID
     pub fn add_engram(&mut self, engram: &Engram) -> Result<()> {
+        if self.timestamp_map.contains_key(&engram.id) {
+            self.remove_engram(engram)?;
+        }
+        
         let timestamp = engram.timestamp;
         
         // Extract time components
@@ -567,42 +1868,46 @@ impl TemporalIndex {
         // Index by year
         self.year_index
             .entry(year)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(engram.id.clone());
         
         // Index by year-month (YYYYMM format)
         let year_month = year * 100 + month;
         self.month_index
             .entry(year_month)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(engram.id.clone());
         
         // Index by year-month-day (YYYYMMDD format)
         let year_month_day = year_month * 100 + day;
         self.day_index
             .entry(year_month_day)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(engram.id.clone());
         
         // Index by hour
         self.hour_index
             .entry(hour)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(engram.id.clone());
         
         // Store timestamp for quick access
         self.timestamp_map.insert(engram.id.clone(), timestamp);
         
-        // Find position to insert in recency list (binary search)
-        match self.recency_list.binary_search_by(|id| {
-            self.timestamp_map
-                .get(id)
-                .unwrap()
-                .cmp(&timestamp)
-                .reverse() // Reverse for most recent first
-        }) {
-            Ok(pos) => self.recency_list.insert(pos, engram.id.clone()),
-            Err(pos) => self.recency_list.insert(pos, engram.id.clone()),
+        // Add to the full timeline
+        self.timeline
+            .entry(timestamp)
+            .or_default()
+            .insert(engram.id.clone());
+        
+        // Insert into the bounded recency list (binary search), dropping the oldest entry when full
+        let key = self.recency_key(&engram.id);
+        let pos = self.recency_list
+            .binary_search_by(|id| self.recency_key(id).cmp(&key))
+            .unwrap_or_else(|pos| pos);
+        if pos < self.recency_capacity {
+            self.recency_list.insert(pos, engram.id.clone());
+            self.recency_list.truncate(self.recency_capacity);
         }
         
         Ok(())
@@ -610,8 +1915,11 @@ impl TemporalIndex {
     
     /// Remove an engram from the index
     pub fn remove_engram(&mut self, engram: &Engram) -> Result<()> {
-        let id = &engram.id;
-        
+        self.remove_engram_by_id(&engram.id)
+    }
+    
+    /// Remove an engram from the index by ID
+    pub fn remove_engram_by_id(&mut self, id: &EngramId) -> Result<()> {
         // Remove from timestamp map
         if let Some(timestamp) = self.timestamp_map.remove(id) {
             // Extract time components
@@ -654,9 +1962,20 @@ impl TemporalIndex {
                 }
             }
             
-            // Remove from recency list
+            // Remove from the timeline
+            if let Some(ids) = self.timeline.get_mut(&timestamp) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.timeline.remove(&timestamp);
+                }
+            }
+            
+            // Remove from recency list, backfilling from the timeline if it was full
             if let Some(pos) = self.recency_list.iter().position(|x| x == id) {
                 self.recency_list.remove(pos);
+                if self.recency_list.len() + 1 == self.recency_capacity {
+                    self.rebuild_recency_list();
+                }
             }
         }
         
@@ -702,30 +2021,27 @@ impl TemporalIndex {
             .unwrap_or_else(HashSet::new)
     }
     
+    /// Get the creation timestamp of an engram
+    pub fn get_timestamp(&self, id: &EngramId) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.timestamp_map.get(id).copied()
+    }
+    
     /// Find engrams created before a specific timestamp
     pub fn find_before(&self, timestamp: &chrono::DateTime<chrono::Utc>) -> HashSet<EngramId> {
-        let mut result = HashSet::new();
-        
-        for (id, ts) in &self.timestamp_map {
-            if ts < timestamp {
-                result.insert(id.clone());
-            }
-        }
-        
-        result
+        self.timeline
+            .range(..*timestamp)
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect()
     }
     
     /// Find engrams created after a specific timestamp
     pub fn find_after(&self, timestamp: &chrono::DateTime<chrono::Utc>) -> HashSet<EngramId> {
-        let mut result = HashSet::new();
+        use std::ops::Bound;
         
-        for (id, ts) in &self.timestamp_map {
-            if ts > timestamp {
-                result.insert(id.clone());
-            }
-        }
-        
-        result
+        self.timeline
+            .range((Bound::Excluded(*timestamp), Bound::Unbounded))
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect()
     }
     
     /// Find engrams created between two timestamps
@@ -734,36 +2050,103 @@ impl TemporalIndex {
         start: &chrono::DateTime<chrono::Utc>, 
         end: &chrono::DateTime<chrono::Utc>
     ) -> HashSet<EngramId> {
-        let mut result = HashSet::new();
-        
-        for (id, ts) in &self.timestamp_map {
-            if ts >= start && ts <= end {
-                result.insert(id.clone());
-            }
+        if start > end {
+            return HashSet::new();
         }
         
-        result
+        self.timeline
+            .range(*start..=*end)
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect()
     }
     
-    /// Get most recent engrams
+    /// Get the most recently created engrams (newest first), from the recency list when it
+    /// holds enough of them. Access recency lives in `ImportanceIndex::get_most_recently_accessed`.
     pub fn get_most_recent(&self, count: usize) -> Vec<EngramId> {
-        self.recency_list.iter().take(count).cloned().collect()
+        if count <= self.recency_list.len() || self.recency_list.len() == self.timestamp_map.len() {
+            return self.recency_list.iter().take(count).cloned().collect();
+        }
+        
+        self.timeline
+            .values()
+            .rev()
+            .flat_map(|ids| ids.iter().rev())
+            .take(count)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for TemporalIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Strategy for grouping engrams into access-count buckets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AccessBucketing {
+    /// Buckets of 10 accesses (0-9, 10-19, ...), capped at bucket 10 for 100+ accesses
+    #[default]
+    Linear,
+    
+    /// Power-of-two buckets (0, 1, 2-3, 4-7, ...) so heavily-accessed engrams stay distinguishable
+    LogScale,
+}
+
+impl AccessBucketing {
+    /// Get the bucket for an access count under this strategy
+    pub fn bucket(&self, access_count: u32) -> u8 {
+        match self {
+            Self::Linear => (access_count / 10).min(10) as u8,
+            Self::LogScale => (u32::BITS - access_count.leading_zeros()) as u8,
+        }
+    }
+    
+    /// Highest bucket this strategy can produce
+    pub fn max_bucket(&self) -> u8 {
+        self.bucket(u32::MAX)
+    }
+}
+
+/// `f64` with a total order, so importance scores and numeric metadata values can be kept
+/// sorted in B-trees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct OrderedFloat(f64);
+
+impl Eq for OrderedFloat {}
+
+impl PartialOrd for OrderedFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedFloat {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
     }
 }
 
-/// Index for tracking engram importance and managing forgetting
+/// Index for tracking engram importance and managing forgetting.
+///
+/// Scores are kept as `f64`. Storing them as `f32` was measured not to reduce memory: every
+/// entry pairs the score with a `String` ID, so `(EngramId, f32)` and `(EngramId, f64)` are both
+/// padded to 32 bytes on 64-bit targets (32 MB per million engrams either way). Bucketing instead
+/// tolerates scores that were rounded through `f32` elsewhere (e.g. the Python bindings).
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ImportanceIndex {
     /// Engrams indexed by importance buckets (0.0-1.0 in 0.1 increments)
     importance_buckets: HashMap<u8, HashSet<EngramId>>,
     
-    /// Engrams sorted by importance score (most important first)
-    importance_sorted: Vec<(EngramId, f64)>,
+    /// Engrams ordered by importance score (least important first; iterate in reverse for most important)
+    importance_sorted: BTreeSet<(OrderedFloat, EngramId)>,
     
     /// Engrams indexed by access frequency buckets
     access_buckets: HashMap<u8, HashSet<EngramId>>,
     
-    /// Engrams sorted by access recency (most recently accessed first)
-    recency_sorted: Vec<(EngramId, chrono::DateTime<chrono::Utc>)>,
+    /// Engrams ordered by last access time (least recent first; iterate in reverse for most recent)
+    recency_sorted: BTreeSet<(chrono::DateTime<chrono::Utc>, EngramId)>,
     
     /// Map from engram ID to importance score for quick lookup
     importance_map: HashMap<EngramId, f64>,
@@ -776,64 +2159,155 @@ pub struct ImportanceIndex {
     
     /// Map from engram ID to TTL information (expiration timestamp)
     ttl_map: HashMap<EngramId, Option<u64>>,
+    
+    /// Strategy used to assign engrams to access buckets
+    access_bucketing: AccessBucketing,
+    
+    /// Engrams exempt from forgetting
+    pinned: HashSet<EngramId>,
+    
+    /// Minimum indexed importance of pinned engrams (None leaves their importance as is)
+    pinned_importance_floor: Option<f64>,
+    
+    /// Half-life in seconds over which importance fades since the last access (None: no decay)
+    decay_half_life: Option<u64>,
+    
+    /// When `apply_decay` last ran, so decay already applied isn't applied again
+    last_decay: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[allow(dead_code)]
 impl ImportanceIndex {
+    /// Get the importance bucket (0-10) for a score.
+    /// A small tolerance keeps scores rounded through `f32` (e.g. 0.7f32 = 0.69999998) in the
+    /// same bucket as their `f64` counterparts.
+    pub fn importance_bucket(importance: f64) -> u8 {
+        ((importance * 10.0 + 1e-6).floor().max(0.0) as u8).min(10)
+    }
+    
     /// Create a new, empty importance index
     pub fn new() -> Self {
         Self {
             importance_buckets: HashMap::new(),
-            importance_sorted: Vec::new(),
+            importance_sorted: BTreeSet::new(),
             access_buckets: HashMap::new(),
-            recency_sorted: Vec::new(),
+            recency_sorted: BTreeSet::new(),
             importance_map: HashMap::new(),
             access_count_map: HashMap::new(),
             last_accessed_map: HashMap::new(),
             ttl_map: HashMap::new(),
+            access_bucketing: AccessBucketing::default(),
+            pinned: HashSet::new(),
+            pinned_importance_floor: None,
+            decay_half_life: None,
+            last_decay: None,
         }
     }
     
-    /// Add an engram to the index
+    /// Index pinned engrams with at least this importance, so they also rank highly in
+    /// importance-ordered results. Only engrams added or pinned afterwards are affected.
+    pub fn with_pinned_importance_floor(mut self, floor: f64) -> Self {
+        self.pinned_importance_floor = Some(floor.clamp(0.0, 1.0));
+        self
+    }
+    
+    /// Let importance fade with the given half-life in seconds, as reported by
+    /// `decayed_importance` and applied by `apply_decay`
+    pub fn with_decay_half_life(mut self, half_life_seconds: u64) -> Self {
+        self.decay_half_life = Some(half_life_seconds);
+        self
+    }
+    
+    /// Get the decay half-life in seconds, if any
+    pub fn decay_half_life(&self) -> Option<u64> {
+        self.decay_half_life
+    }
+    
+    /// An empty index with the same configuration
+    fn empty_like(&self) -> Self {
+        Self {
+            access_bucketing: self.access_bucketing,
+            pinned_importance_floor: self.pinned_importance_floor,
+            decay_half_life: self.decay_half_life,
+            last_decay: self.last_decay,
+            ..Self::new()
+        }
+    }
+    
+    /// Importance to index for an engram, raised to the floor if it is pinned
+    fn effective_importance(&self, id: &EngramId, importance: f64) -> f64 {
+        match self.pinned_importance_floor {
+            Some(floor) if self.pinned.contains(id) => importance.max(floor),
+            _ => importance,
+        }
+    }
+    
+    /// Use the given access bucketing strategy, re-bucketing any engrams already indexed
+    pub fn with_access_bucketing(mut self, access_bucketing: AccessBucketing) -> Self {
+        self.access_bucketing = access_bucketing;
+        self.access_buckets.clear();
+        for (id, count) in &self.access_count_map {
+            self.access_buckets
+                .entry(access_bucketing.bucket(*count))
+                .or_default()
+                .insert(id.clone());
+        }
+        self
+    }
+    
+    /// Get the access bucketing strategy in use
+    pub fn access_bucketing(&self) -> AccessBucketing {
+        self.access_bucketing
+    }
+    
+    /// Fold in an index built over a disjoint set of engrams with the same bucketing
+    fn merge(&mut self, other: Self) {
+        merge_id_sets(&mut self.importance_buckets, other.importance_buckets);
+        merge_id_sets(&mut self.access_buckets, other.access_buckets);
+        self.importance_sorted.extend(other.importance_sorted);
+        self.recency_sorted.extend(other.recency_sorted);
+        self.importance_map.extend(other.importance_map);
+        self.access_count_map.extend(other.access_count_map);
+        self.last_accessed_map.extend(other.last_accessed_map);
+        self.ttl_map.extend(other.ttl_map);
+        self.pinned.extend(other.pinned);
+    }
+    
+    /// Add an engram to the index, replacing any previous entry for the same ID
     pub fn add_engram(&mut self, engram: &Engram) -> Result<()> {
+        if self.importance_map.contains_key(&engram.id) {
+            self.remove_engram(engram)?;
+        }
+        
         let id = &engram.id;
         
+        if engram.pinned {
+            self.pinned.insert(id.clone());
+        }
+        let importance = self.effective_importance(id, engram.importance);
+        
         // Add to importance buckets
-        let importance_bucket = (engram.importance * 10.0).floor() as u8;
+        let importance_bucket = Self::importance_bucket(importance);
         self.importance_buckets
             .entry(importance_bucket)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(id.clone());
         
-        // Add to importance sorted list
-        match self.importance_sorted.binary_search_by(|(_, imp)| {
-            imp.partial_cmp(&engram.importance).unwrap().reverse() // Reverse for most important first
-        }) {
-            Ok(pos) => self.importance_sorted.insert(pos, (id.clone(), engram.importance)),
-            Err(pos) => self.importance_sorted.insert(pos, (id.clone(), engram.importance)),
-        }
+        // Add to importance ordering
+        self.importance_sorted.insert((OrderedFloat(importance), id.clone()));
         
         // Add to access buckets
-        let access_bucket = if engram.access_count > 100 {
-            10 // Cap at bucket 10 for very frequent access
-        } else {
-            (engram.access_count / 10) as u8 // 0-9 buckets for 0-99 accesses
-        };
+        let access_bucket = self.access_bucketing.bucket(engram.access_count);
         self.access_buckets
             .entry(access_bucket)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(id.clone());
         
-        // Add to recency sorted list
-        match self.recency_sorted.binary_search_by(|(_, time)| {
-            time.cmp(&engram.last_accessed).reverse() // Reverse for most recent first
-        }) {
-            Ok(pos) => self.recency_sorted.insert(pos, (id.clone(), engram.last_accessed)),
-            Err(pos) => self.recency_sorted.insert(pos, (id.clone(), engram.last_accessed)),
-        }
+        // Add to recency ordering
+        self.recency_sorted.insert((engram.last_accessed, id.clone()));
         
         // Update maps for quick lookup
-        self.importance_map.insert(id.clone(), engram.importance);
+        self.importance_map.insert(id.clone(), importance);
         self.access_count_map.insert(id.clone(), engram.access_count);
         self.last_accessed_map.insert(id.clone(), engram.last_accessed);
         self.ttl_map.insert(id.clone(), engram.ttl);
@@ -843,11 +2317,15 @@ impl ImportanceIndex {
     
     /// Remove an engram from the index
     pub fn remove_engram(&mut self, engram: &Engram) -> Result<()> {
-        let id = &engram.id;
-        
-        // Remove from importance map and buckets
+        self.remove_engram_by_id(&engram.id)
+    }
+    
+    /// Remove an engram from the index by ID
+    pub fn remove_engram_by_id(&mut self, id: &EngramId) -> Result<()> {
+        // Remove from importance map, ordering and buckets
         if let Some(importance) = self.importance_map.remove(id) {
-            let bucket = (importance * 10.0).floor() as u8;
+            self.importance_sorted.remove(&(OrderedFloat(importance), id.clone()));
+            let bucket = Self::importance_bucket(importance);
             if let Some(engrams) = self.importance_buckets.get_mut(&bucket) {
                 engrams.remove(id);
                 if engrams.is_empty() {
@@ -858,11 +2336,7 @@ impl ImportanceIndex {
         
         // Remove from access count map and buckets
         if let Some(access_count) = self.access_count_map.remove(id) {
-            let bucket = if access_count > 100 {
-                10 // Cap at bucket 10 for very frequent access
-            } else {
-                (access_count / 10) as u8 // 0-9 buckets for 0-99 accesses
-            };
+            let bucket = self.access_bucketing.bucket(access_count);
             if let Some(engrams) = self.access_buckets.get_mut(&bucket) {
                 engrams.remove(id);
                 if engrams.is_empty() {
@@ -871,35 +2345,29 @@ impl ImportanceIndex {
             }
         }
         
-        // Remove from last accessed map
-        self.last_accessed_map.remove(id);
-        
-        // Remove from TTL map
-        self.ttl_map.remove(id);
-        
-        // Remove from sorted lists
-        if let Some(pos) = self.importance_sorted.iter().position(|(i, _)| i == id) {
-            self.importance_sorted.remove(pos);
+        // Remove from last accessed map and recency ordering
+        if let Some(last_accessed) = self.last_accessed_map.remove(id) {
+            self.recency_sorted.remove(&(last_accessed, id.clone()));
         }
         
-        if let Some(pos) = self.recency_sorted.iter().position(|(i, _)| i == id) {
-            self.recency_sorted.remove(pos);
-        }
+        // Remove from TTL map and pinned set
+        self.ttl_map.remove(id);
+        self.pinned.remove(id);
         
         Ok(())
     }
     
     /// Update an engram's importance score
     pub fn update_importance(&mut self, id: &EngramId, new_importance: f64) -> Result<()> {
-        // Ensure importance is within valid range
-        let new_importance = new_importance.max(0.0).min(1.0);
+        // Ensure importance is within valid range, and at least the floor for pinned engrams
+        let new_importance = self.effective_importance(id, new_importance.clamp(0.0, 1.0));
         
         // Get old importance bucket
         let old_importance = self.importance_map.get(id).cloned().unwrap_or(0.5);
-        let old_bucket = (old_importance * 10.0).floor() as u8;
+        let old_bucket = Self::importance_bucket(old_importance);
         
         // Get new importance bucket
-        let new_bucket = (new_importance * 10.0).floor() as u8;
+        let new_bucket = Self::importance_bucket(new_importance);
         
         // Update importance buckets if the bucket has changed
         if old_bucket != new_bucket {
@@ -914,26 +2382,59 @@ impl ImportanceIndex {
             // Add to new bucket
             self.importance_buckets
                 .entry(new_bucket)
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .insert(id.clone());
         }
         
-        // Update importance map
-        self.importance_map.insert(id.clone(), new_importance);
+        // Update importance map, and the ordering for engrams already indexed
+        if let Some(previous) = self.importance_map.insert(id.clone(), new_importance) {
+            self.importance_sorted.remove(&(OrderedFloat(previous), id.clone()));
+            self.importance_sorted.insert((OrderedFloat(new_importance), id.clone()));
+        }
         
-        // Update importance sorted list
-        if let Some(pos) = self.importance_sorted.iter().position(|(i, _)| i == id) {
-            self.importance_sorted.remove(pos);
-            
-            // Insert at new position
-            match self.importance_sorted.binary_search_by(|(_, imp)| {
-                imp.partial_cmp(&new_importance).unwrap().reverse() // Reverse for most important first
-            }) {
-                Ok(pos) => self.importance_sorted.insert(pos, (id.clone(), new_importance)),
-                Err(pos) => self.importance_sorted.insert(pos, (id.clone(), new_importance)),
+        Ok(())
+    }
+    
+    /// Importance an engram's score decays to by `now`: halved for every half-life elapsed since
+    /// it was last accessed, or since decay was last applied if that is later. Returns the
+    /// indexed importance unchanged when no half-life is set, and None if the engram is not indexed.
+    pub fn decayed_importance(&self, id: &EngramId, now: chrono::DateTime<chrono::Utc>) -> Option<f64> {
+        let importance = *self.importance_map.get(id)?;
+        let Some(half_life) = self.decay_half_life.filter(|half_life| *half_life > 0) else {
+            return Some(importance);
+        };
+        
+        let since = match (self.last_accessed_map.get(id), self.last_decay) {
+            (Some(&accessed), Some(decayed)) => accessed.max(decayed),
+            (Some(&accessed), None) => accessed,
+            (None, decayed) => decayed.unwrap_or(now),
+        };
+        let elapsed = now.signed_duration_since(since).num_milliseconds().max(0) as f64 / 1000.0;
+        
+        Some(importance * 0.5f64.powf(elapsed / half_life as f64))
+    }
+    
+    /// Decay every engram's importance with the given half-life in seconds, so memories fade
+    /// unless they are accessed. Entries are re-bucketed and re-sorted, and pinned engrams stay
+    /// at or above the pinned importance floor. Time already covered by an earlier call is not
+    /// decayed again, so this can run periodically.
+    pub fn apply_decay(&mut self, half_life_seconds: u64) -> Result<()> {
+        if half_life_seconds == 0 {
+            return Err(EngramError::InvalidOperation(
+                "Decay half-life must be greater than zero".to_string(),
+            ));
+        }
+        
+        let now = chrono::Utc::now();
+        self.decay_half_life = Some(half_life_seconds);
+        
+        for id in self.importance_map.keys().cloned().collect::<Vec<_>>() {
+            if let Some(importance) = self.decayed_importance(&id, now) {
+                self.update_importance(&id, importance)?;
             }
         }
         
+        self.last_decay = Some(now);
         Ok(())
     }
     
@@ -944,17 +2445,8 @@ impl ImportanceIndex {
         let new_count = old_count + 1;
         
         // Get old and new access buckets
-        let old_bucket = if old_count > 100 {
-            10
-        } else {
-            (old_count / 10) as u8
-        };
-        
-        let new_bucket = if new_count > 100 {
-            10
-        } else {
-            (new_count / 10) as u8
-        };
+        let old_bucket = self.access_bucketing.bucket(old_count);
+        let new_bucket = self.access_bucketing.bucket(new_count);
         
         // Update access buckets if the bucket has changed
         if old_bucket != new_bucket {
@@ -969,37 +2461,74 @@ impl ImportanceIndex {
             // Add to new bucket
             self.access_buckets
                 .entry(new_bucket)
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .insert(id.clone());
         }
         
         // Update access count map
         self.access_count_map.insert(id.clone(), new_count);
         
-        // Update last accessed time
+        // Update last accessed time, and the ordering for engrams already indexed
         let now = chrono::Utc::now();
-        self.last_accessed_map.insert(id.clone(), now);
-        
-        // Update recency sorted list
-        if let Some(pos) = self.recency_sorted.iter().position(|(i, _)| i == id) {
-            self.recency_sorted.remove(pos);
-            
-            // Insert at the beginning (most recent)
-            self.recency_sorted.insert(0, (id.clone(), now));
+        if let Some(previous) = self.last_accessed_map.insert(id.clone(), now) {
+            self.recency_sorted.remove(&(previous, id.clone()));
+            self.recency_sorted.insert((now, id.clone()));
         }
         
         Ok(())
     }
     
+    /// Move an indexed engram's last-accessed time to `now` without counting an access.
+    /// Returns false if the engram is not indexed.
+    pub fn touch(&mut self, id: &EngramId, now: chrono::DateTime<chrono::Utc>) -> bool {
+        match self.last_accessed_map.insert(id.clone(), now) {
+            Some(previous) => {
+                self.recency_sorted.remove(&(previous, id.clone()));
+                self.recency_sorted.insert((now, id.clone()));
+                true
+            },
+            None => {
+                self.last_accessed_map.remove(id);
+                false
+            },
+        }
+    }
+    
     /// Set or update TTL for an engram
     pub fn set_ttl(&mut self, id: &EngramId, ttl: Option<u64>) -> Result<()> {
         self.ttl_map.insert(id.clone(), ttl);
         Ok(())
     }
     
+    /// Pin or unpin an engram. Pinned engrams are never forgetting candidates, and are
+    /// raised to the pinned importance floor if one is configured.
+    pub fn set_pinned(&mut self, id: &EngramId, pinned: bool) -> Result<()> {
+        if pinned {
+            self.pinned.insert(id.clone());
+            if let (Some(floor), Some(&importance)) = (self.pinned_importance_floor, self.importance_map.get(id)) {
+                if importance < floor {
+                    self.update_importance(id, floor)?;
+                }
+            }
+        } else {
+            self.pinned.remove(id);
+        }
+        Ok(())
+    }
+    
+    /// Whether an engram is pinned
+    pub fn is_pinned(&self, id: &EngramId) -> bool {
+        self.pinned.contains(id)
+    }
+    
+    /// IDs of all pinned engrams
+    pub fn pinned_ids(&self) -> &HashSet<EngramId> {
+        &self.pinned
+    }
+    
     /// Get engrams by minimum importance
     pub fn find_by_min_importance(&self, min_importance: f64) -> HashSet<EngramId> {
-        let min_bucket = (min_importance * 10.0).floor() as u8;
+        let min_bucket = Self::importance_bucket(min_importance);
         let mut result = HashSet::new();
         
         // Combine all buckets at or above the minimum
@@ -1012,21 +2541,32 @@ impl ImportanceIndex {
         result
     }
     
+    /// Get engrams whose importance is at or below a maximum
+    pub fn find_by_max_importance(&self, max_importance: f64) -> HashSet<EngramId> {
+        self.importance_sorted.iter() // Starts from least important
+            .take_while(|(importance, _)| importance.0 <= max_importance)
+            .map(|(_, id)| id.clone())
+            .collect()
+    }
+    
     /// Get engrams by minimum access count
     pub fn find_by_min_access_count(&self, min_count: u32) -> HashSet<EngramId> {
         let mut result = HashSet::new();
         
-        // Calculate minimum bucket
-        let min_bucket = if min_count > 100 {
-            10
-        } else {
-            (min_count / 10) as u8
-        };
+        // Calculate minimum bucket under the configured strategy
+        let min_bucket = self.access_bucketing.bucket(min_count);
         
         // Combine all buckets at or above the minimum
-        for bucket in min_bucket..=10 {
+        for bucket in min_bucket..=self.access_bucketing.max_bucket() {
             if let Some(engrams) = self.access_buckets.get(&bucket) {
-                result.extend(engrams.iter().cloned());
+                if bucket == min_bucket {
+                    // The starting bucket may also hold counts just below the minimum
+                    result.extend(engrams.iter()
+                        .filter(|id| self.access_count_map.get(*id).is_some_and(|count| *count >= min_count))
+                        .cloned());
+                } else {
+                    result.extend(engrams.iter().cloned());
+                }
             }
         }
         
@@ -1035,93 +2575,363 @@ impl ImportanceIndex {
     
     /// Get engrams by access recency
     pub fn find_by_last_accessed_after(&self, time: &chrono::DateTime<chrono::Utc>) -> HashSet<EngramId> {
+        // The empty ID sorts first, so the range starts at the first access at or after `time`
+        self.recency_sorted
+            .range((*time, EngramId::new())..)
+            .map(|(_, id)| id.clone())
+            .collect()
+    }
+    
+    /// Get the most recently accessed engrams (most recent access first)
+    pub fn get_most_recently_accessed(&self, count: usize) -> Vec<EngramId> {
+        self.recency_sorted.iter().rev().take(count).map(|(_, id)| id.clone()).collect()
+    }
+    
+    /// Get engrams sorted by importance (most important first)
+    pub fn get_most_important(&self, count: usize) -> Vec<EngramId> {
+        self.importance_sorted.iter().rev().take(count).map(|(_, id)| id.clone()).collect()
+    }
+    
+    /// Draw up to `n` distinct engrams at random, each with probability proportional to
+    /// its importance, for representative samples of what is known. Uses weighted
+    /// reservoir sampling (key `u^(1/importance)`, keeping the `n` largest keys), visiting
+    /// engrams in ID order so the same `seed` always yields the same sample. Engrams with
+    /// zero importance are never drawn. The sample is returned in draw order.
+    pub fn sample_by_importance(&self, n: usize, seed: u64) -> Vec<EngramId> {
+        use rand::{Rng, SeedableRng};
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+        
+        if n == 0 {
+            return Vec::new();
+        }
+        
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut reservoir: BinaryHeap<Reverse<(OrderedFloat, EngramId)>> = BinaryHeap::with_capacity(n + 1);
+        
+        for id in sorted_ids(self.importance_map.keys()) {
+            let importance = self.importance_map[id];
+            if importance <= 0.0 {
+                continue;
+            }
+            
+            let key = OrderedFloat(rng.gen::<f64>().powf(1.0 / importance));
+            reservoir.push(Reverse((key, id.clone())));
+            if reservoir.len() > n {
+                reservoir.pop();
+            }
+        }
+        
+        reservoir.into_sorted_vec().into_iter().map(|Reverse((_, id))| id).collect()
+    }
+    
+    /// Get expired engrams based on TTL
+    pub fn get_expired_engrams(&self) -> HashSet<EngramId> {
+        let now = chrono::Utc::now();
         let mut result = HashSet::new();
         
-        for (id, last_accessed) in &self.recency_sorted {
-            if last_accessed >= time {
-                result.insert(id.clone());
-            } else {
-                // Since the list is sorted by recency, we can break early
-                break;
+        // Check each engram with a TTL
+        for (id, ttl_opt) in &self.ttl_map {
+            if let Some(ttl) = ttl_opt {
+                // Calculate creation time from the timestamp map
+                if let Some(last_accessed) = self.last_accessed_map.get(id) {
+                    let elapsed = now.signed_duration_since(*last_accessed).num_seconds() as u64;
+                    if elapsed > *ttl {
+                        result.insert(id.clone());
+                    }
+                }
             }
         }
         
         result
     }
     
-    /// Get engrams sorted by importance (most important first)
-    pub fn get_most_important(&self, count: usize) -> Vec<EngramId> {
-        self.importance_sorted.iter().take(count).map(|(id, _)| id.clone()).collect()
+    /// Get the indexed importance score of an engram
+    pub fn get_importance(&self, id: &EngramId) -> Option<f64> {
+        self.importance_map.get(id).copied()
+    }
+    
+    /// Get the indexed access count of an engram
+    pub fn get_access_count(&self, id: &EngramId) -> Option<u32> {
+        self.access_count_map.get(id).copied()
+    }
+    
+    /// Get the indexed last access time of an engram
+    pub fn get_last_accessed(&self, id: &EngramId) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.last_accessed_map.get(id).copied()
+    }
+    
+    /// Get the indexed TTL of an engram, if one is set
+    pub fn get_ttl(&self, id: &EngramId) -> Option<u64> {
+        self.ttl_map.get(id).copied().flatten()
+    }
+    
+    /// Calculate forgetting candidates based on importance, access count, and recency
+    pub fn get_forgetting_candidates(
+        &self,
+        max_importance: f64,
+        max_access_count: u32,
+        older_than: &chrono::DateTime<chrono::Utc>,
+        limit: usize
+    ) -> Vec<EngramId> {
+        // Get engrams with low importance
+        let low_importance = self.importance_sorted.iter() // Starts from least important
+            .take_while(|(importance, _)| importance.0 <= max_importance)
+            .map(|(_, id)| id.clone())
+            .collect::<HashSet<_>>();
+        
+        // Get engrams with low access count
+        let low_access_count = self.access_count_map.iter()
+            .filter(|(_, count)| **count <= max_access_count)
+            .map(|(id, _)| id.clone())
+            .collect::<HashSet<_>>();
+        
+        // Get engrams that haven't been accessed recently
+        let old_access = self.last_accessed_map.iter()
+            .filter(|(_, time)| *time < older_than)
+            .map(|(id, _)| id.clone())
+            .collect::<HashSet<_>>();
+        
+        // Find intersection of all three sets, leaving out pinned engrams
+        let mut candidates = low_importance.intersection(&low_access_count).cloned().collect::<HashSet<_>>();
+        candidates = candidates.intersection(&old_access).cloned().collect();
+        candidates.retain(|id| !self.pinned.contains(id));
+        
+        // Sort candidates by importance (least important first)
+        let mut candidates_vec: Vec<_> = candidates.into_iter().collect();
+        candidates_vec.sort_by(|a, b| {
+            let a_imp = self.importance_map.get(a).unwrap_or(&0.5);
+            let b_imp = self.importance_map.get(b).unwrap_or(&0.5);
+            a_imp.partial_cmp(b_imp).unwrap()
+        });
+        
+        // Limit the number of candidates
+        candidates_vec.truncate(limit);
+        
+        candidates_vec
+    }
+}
+
+impl Default for ImportanceIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of a combined query, including warnings for filters that could not be applied
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    /// IDs of the engrams matching all filters that succeeded
+    pub ids: HashSet<EngramId>,
+    
+    /// Warnings describing filters that failed and were skipped
+    pub warnings: Vec<String>,
+}
+
+impl QueryResult {
+    /// Check whether any filter failed
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+    
+    /// The matching IDs in sorted order
+    pub fn sorted_ids(&self) -> Vec<EngramId> {
+        sorted_ids(self.ids.iter().cloned())
+    }
+}
+
+/// Function evaluating a `QueryFilter` against the search index
+type FilterFn<'f> = Box<dyn Fn(&SearchIndex) -> Result<HashSet<EngramId>> + 'f>;
+
+/// A named filter over the search index, used by `SearchIndex::apply_filters` and `CombinedQuery`
+pub struct QueryFilter<'f> {
+    /// Name used to identify the filter in warnings
+    pub name: String,
+    
+    /// Function producing the matching engram IDs
+    apply: FilterFn<'f>,
+}
+
+impl<'f> QueryFilter<'f> {
+    /// Create a new named filter
+    pub fn new<F>(name: impl Into<String>, apply: F) -> Self
+    where
+        F: Fn(&SearchIndex) -> Result<HashSet<EngramId>> + 'f,
+    {
+        Self {
+            name: name.into(),
+            apply: Box::new(apply),
+        }
+    }
+    
+    /// Run the filter, recording a warning and returning None if it fails
+    fn apply_or_warn(&self, index: &SearchIndex, warnings: &mut Vec<String>) -> Option<HashSet<EngramId>> {
+        match (self.apply)(index) {
+            Ok(ids) => Some(ids),
+            Err(e) => {
+                warnings.push(format!("Filter '{}' failed and was skipped: {}", self.name, e));
+                None
+            }
+        }
+    }
+}
+
+impl<'f> From<SearchPredicate> for QueryFilter<'f> {
+    fn from(predicate: SearchPredicate) -> Self {
+        QueryFilter::new(predicate.name(), move |index: &SearchIndex| Ok(predicate.evaluate(index)))
+    }
+}
+
+/// A built-in criterion over the search index, usable as a `QueryFilter`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchPredicate {
+    /// Engrams matching any of the text's keywords, or all of them when `match_all` is set
+    Text { query: String, match_all: bool },
+    
+    /// Engrams from a source
+    Source(String),
+    
+    /// Engrams with at least this confidence
+    MinConfidence(f64),
+    
+    /// Engrams with a metadata key, or with a key-value pair when `value` is set
+    Metadata { key: String, value: Option<String> },
+    
+    /// Engrams created before a time
+    Before(chrono::DateTime<chrono::Utc>),
+    
+    /// Engrams created after a time
+    After(chrono::DateTime<chrono::Utc>),
+}
+
+impl SearchPredicate {
+    /// Name identifying the predicate's filter in warnings
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Text { .. } => "text",
+            Self::Source(_) => "source",
+            Self::MinConfidence(_) => "min_confidence",
+            Self::Metadata { .. } => "metadata",
+            Self::Before(_) => "before_time",
+            Self::After(_) => "after_time",
+        }
+    }
+    
+    /// Find the engrams in the index matching the predicate
+    fn evaluate(&self, index: &SearchIndex) -> HashSet<EngramId> {
+        match self {
+            Self::Text { query, match_all: true } => index.text_index.search_all(query),
+            Self::Text { query, match_all: false } => index.text_index.search(query),
+            Self::Source(source) => index.find_by_source(source),
+            Self::MinConfidence(min_confidence) => index.find_by_min_confidence(*min_confidence),
+            Self::Metadata { key, value: Some(value) } => index.metadata_index.find_by_key_value(key, value),
+            Self::Metadata { key, value: None } => index.metadata_index.find_by_key(key),
+            Self::Before(time) => index.find_by_before_timestamp(time),
+            Self::After(time) => index.find_by_after_timestamp(time),
+        }
+    }
+}
+
+/// A boolean combination of `QueryFilter`s (or `SearchPredicate`s), evaluated against an
+/// index with `execute`.
+///
+/// An engram matches when it satisfies every `all_of` filter, at least one filter of each
+/// `any_of` group and none of the `none_of` filters. A query with only `none_of` clauses
+/// starts from every indexed engram.
+#[derive(Default)]
+pub struct CombinedQuery<'f> {
+    /// Filters that must all match
+    all_of: Vec<QueryFilter<'f>>,
+    
+    /// Groups of filters of which at least one per group must match
+    any_of: Vec<Vec<QueryFilter<'f>>>,
+    
+    /// Filters that must not match
+    none_of: Vec<QueryFilter<'f>>,
+}
+
+impl<'f> CombinedQuery<'f> {
+    /// Create an empty query
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Require every one of these filters to match
+    pub fn all_of<F: Into<QueryFilter<'f>>>(mut self, filters: impl IntoIterator<Item = F>) -> Self {
+        self.all_of.extend(filters.into_iter().map(Into::into));
+        self
     }
     
-    /// Get expired engrams based on TTL
-    pub fn get_expired_engrams(&self) -> HashSet<EngramId> {
-        let now = chrono::Utc::now();
-        let mut result = HashSet::new();
-        
-        // Check each engram with a TTL
-        for (id, ttl_opt) in &self.ttl_map {
-            if let Some(ttl) = ttl_opt {
-                // Calculate creation time from the timestamp map
-                if let Some(last_accessed) = self.last_accessed_map.get(id) {
-                    let elapsed = now.signed_duration_since(*last_accessed).num_seconds() as u64;
-                    if elapsed > *ttl {
-                        result.insert(id.clone());
-                    }
-                }
-            }
-        }
-        
-        result
+    /// Require at least one of these filters to match. Each call adds a separate group,
+    /// so `any_of([a, b]).any_of([c, d])` means `(a OR b) AND (c OR d)`.
+    pub fn any_of<F: Into<QueryFilter<'f>>>(mut self, filters: impl IntoIterator<Item = F>) -> Self {
+        self.any_of.push(filters.into_iter().map(Into::into).collect());
+        self
     }
     
-    /// Calculate forgetting candidates based on importance, access count, and recency
-    pub fn get_forgetting_candidates(
-        &self,
-        max_importance: f64,
-        max_access_count: u32,
-        older_than: &chrono::DateTime<chrono::Utc>,
-        limit: usize
-    ) -> Vec<EngramId> {
-        // Get engrams with low importance
-        let low_importance = self.importance_sorted.iter()
-            .rev() // Start from least important
-            .filter(|(_, imp)| *imp <= max_importance)
-            .map(|(id, _)| id.clone())
-            .collect::<HashSet<_>>();
-        
-        // Get engrams with low access count
-        let low_access_count = self.access_count_map.iter()
-            .filter(|(_, count)| **count <= max_access_count)
-            .map(|(id, _)| id.clone())
-            .collect::<HashSet<_>>();
+    /// Exclude engrams matching any of these filters
+    pub fn none_of<F: Into<QueryFilter<'f>>>(mut self, filters: impl IntoIterator<Item = F>) -> Self {
+        self.none_of.extend(filters.into_iter().map(Into::into));
+        self
+    }
+    
+    /// Check whether the query has no clauses at all
+    pub fn is_empty(&self) -> bool {
+        self.all_of.is_empty() && self.any_of.is_empty() && self.none_of.is_empty()
+    }
+    
+    /// Evaluate the query with set intersection, union and difference.
+    /// Filters that fail are skipped and reported in the result's warnings, as in `apply_filters`.
+    pub fn execute(&self, index: &SearchIndex) -> QueryResult {
+        let mut warnings = Vec::new();
+        let mut result: Option<HashSet<EngramId>> = None;
+        let mut intersect = |ids: HashSet<EngramId>| {
+            result = Some(match result.take() {
+                Some(existing) => existing.intersection(&ids).cloned().collect(),
+                None => ids,
+            });
+        };
         
-        // Get engrams that haven't been accessed recently
-        let old_access = self.last_accessed_map.iter()
-            .filter(|(_, time)| *time < older_than)
-            .map(|(id, _)| id.clone())
-            .collect::<HashSet<_>>();
+        for filter in &self.all_of {
+            if let Some(ids) = filter.apply_or_warn(index, &mut warnings) {
+                intersect(ids);
+            }
+        }
         
-        // Find intersection of all three sets
-        let mut candidates = low_importance.intersection(&low_access_count).cloned().collect::<HashSet<_>>();
-        candidates = candidates.intersection(&old_access).cloned().collect();
+        for group in &self.any_of {
+            let matches: Vec<HashSet<EngramId>> = group
+                .iter()
+                .filter_map(|filter| filter.apply_or_warn(index, &mut warnings))
+                .collect();
+            if !matches.is_empty() {
+                intersect(matches.into_iter().flatten().collect());
+            }
+        }
         
-        // Sort candidates by importance (least important first)
-        let mut candidates_vec: Vec<_> = candidates.into_iter().collect();
-        candidates_vec.sort_by(|a, b| {
-            let a_imp = self.importance_map.get(a).unwrap_or(&0.5);
-            let b_imp = self.importance_map.get(b).unwrap_or(&0.5);
-            a_imp.partial_cmp(b_imp).unwrap()
-        });
+        let mut ids = match result {
+            Some(ids) => ids,
+            None if self.all_of.is_empty() && self.any_of.is_empty() => index.indexed_engram_ids(),
+            None => HashSet::new(),
+        };
         
-        // Limit the number of candidates
-        candidates_vec.truncate(limit);
+        for filter in &self.none_of {
+            if let Some(excluded) = filter.apply_or_warn(index, &mut warnings) {
+                ids.retain(|id| !excluded.contains(id));
+            }
+        }
         
-        candidates_vec
+        QueryResult { ids, warnings }
     }
 }
 
+/// Magic bytes at the start of a `SearchIndex` snapshot file
+const SNAPSHOT_MAGIC: &[u8; 8] = b"ENGRIDX\0";
+
+/// Version of the `SearchIndex` snapshot format, bumped whenever an index's fields change
+pub const SEARCH_INDEX_SNAPSHOT_VERSION: u32 = 14;
+
 /// Combined search index for efficient querying
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct SearchIndex {
     /// Relationship index for traversal
     pub relationship_index: RelationshipIndex,
@@ -1141,8 +2951,20 @@ pub struct SearchIndex {
     /// Source index for filtering by source
     source_index: HashMap<String, HashSet<EngramId>>,
     
+    /// Document index mapping provenance document IDs to the engrams extracted from them
+    document_index: HashMap<String, HashSet<EngramId>>,
+    
     /// Confidence index for filtering by confidence ranges
     confidence_index: HashMap<u8, HashSet<EngramId>>, // Bucketed by confidence * 10
+    
+    /// Source of each indexed engram, so it can be removed by ID
+    engram_sources: HashMap<EngramId, String>,
+    
+    /// Provenance document ID of each indexed engram that has one
+    engram_documents: HashMap<EngramId, String>,
+    
+    /// Confidence bucket of each indexed engram
+    engram_confidence_buckets: HashMap<EngramId, u8>,
 }
 
 #[allow(dead_code)]
@@ -1156,12 +2978,149 @@ impl SearchIndex {
             temporal_index: TemporalIndex::new(),
             importance_index: ImportanceIndex::new(),
             source_index: HashMap::new(),
+            document_index: HashMap::new(),
             confidence_index: HashMap::new(),
+            engram_sources: HashMap::new(),
+            engram_documents: HashMap::new(),
+            engram_confidence_buckets: HashMap::new(),
+        }
+    }
+    
+    /// Track at most `capacity` engrams in the temporal index's recency list
+    pub fn with_recency_capacity(mut self, capacity: usize) -> Self {
+        let temporal_index = std::mem::take(&mut self.temporal_index);
+        self.temporal_index = temporal_index.with_recency_capacity(capacity);
+        self
+    }
+    
+    /// Use the given access bucketing strategy for the importance index
+    pub fn with_access_bucketing(mut self, access_bucketing: AccessBucketing) -> Self {
+        let importance_index = std::mem::take(&mut self.importance_index);
+        self.importance_index = importance_index.with_access_bucketing(access_bucketing);
+        self
+    }
+    
+    /// Index pinned engrams with at least this importance
+    pub fn with_pinned_importance_floor(mut self, floor: f64) -> Self {
+        let importance_index = std::mem::take(&mut self.importance_index);
+        self.importance_index = importance_index.with_pinned_importance_floor(floor);
+        self
+    }
+    
+    /// Make the string values of these metadata keys findable by text search
+    pub fn with_searchable_metadata_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let text_index = std::mem::take(&mut self.text_index);
+        self.text_index = text_index.with_searchable_metadata_keys(keys);
+        self
+    }
+    
+    /// Sketch metadata values so their distinct counts can be estimated in bounded memory
+    pub fn with_metadata_value_sketches(mut self, precision: u8) -> Self {
+        self.metadata_index = std::mem::take(&mut self.metadata_index)
+            .with_value_sketches(precision);
+        self
+    }
+    
+    /// Write the whole index to a single portable file, so it can be shipped alongside an
+    /// export and loaded with `restore` without rebuilding it from storage
+    pub fn dump<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_snapshot(path.as_ref(), None)
+    }
+    
+    /// Load an index written by `dump` or `save`, refusing files that are not snapshots or
+    /// were written in a different snapshot format version
+    pub fn restore<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_snapshot(path.as_ref()).map(|(index, _)| index)
+    }
+    
+    /// Save the index for the next startup, tagged with the storage's latest sequence number
+    /// so `load` can tell whether anything was written since. Call it once the index reflects
+    /// every write made to `storage`, e.g. on shutdown.
+    pub fn save<P: AsRef<Path>>(&self, path: P, storage: &Storage) -> Result<()> {
+        self.write_snapshot(path.as_ref(), Some(storage.latest_sequence_number()))
+    }
+    
+    /// Load the index saved by `save`, or rebuild it from storage with `rebuild` when the file
+    /// is missing, unreadable, from another snapshot format version, or older than the latest
+    /// write to `storage`. Rebuilt indexes use the default settings and are not saved.
+    pub fn load<P: AsRef<Path>>(path: P, storage: &Storage) -> Result<Self> {
+        match Self::read_snapshot(path.as_ref()) {
+            Ok((index, Some(sequence))) if sequence == storage.latest_sequence_number() => Ok(index),
+            _ => Self::rebuild(storage),
+        }
+    }
+    
+    /// Build an index over every engram in storage and the connections between them
+    pub fn rebuild(storage: &Storage) -> Result<Self> {
+        let engram_ids = storage.list_engrams()?;
+        let engrams: Vec<Engram> = storage.get_engrams(&engram_ids)?.into_iter().flatten().collect();
+        let mut index = Self::new();
+        index.add_engrams_parallel(&engrams)?;
+        
+        // Only index connections whose source and target both exist
+        let engram_ids: HashSet<EngramId> = engram_ids.into_iter().collect();
+        let connection_ids = storage.list_connections()?;
+        for connection in storage.get_connections(&connection_ids)?.into_iter().flatten() {
+            if engram_ids.contains(&connection.source_id) && engram_ids.contains(&connection.target_id) {
+                index.add_connection(&connection)?;
+            }
+        }
+        
+        Ok(index)
+    }
+    
+    /// Write a snapshot file: magic header, format version, the storage sequence number it
+    /// was taken at (if any) and the bincode-encoded index
+    fn write_snapshot(&self, path: &Path, storage_sequence: Option<u64>) -> Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&SEARCH_INDEX_SNAPSHOT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &storage_sequence)
+            .and_then(|_| bincode::serialize_into(&mut writer, self))
+            .map_err(|e| EngramError::SerializationError(format!("Failed to write index snapshot: {}", e)))?;
+        writer.flush()?;
+        Ok(())
+    }
+    
+    /// Read a snapshot file written by `write_snapshot`
+    fn read_snapshot(path: &Path) -> Result<(Self, Option<u64>)> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)
+            .map_err(|_| EngramError::SerializationError("File is too short to be an index snapshot".to_string()))?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(EngramError::SerializationError("File is not an index snapshot".to_string()));
+        }
+        
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)
+            .map_err(|_| EngramError::SerializationError("Index snapshot is truncated".to_string()))?;
+        let version = u32::from_le_bytes(version);
+        if version != SEARCH_INDEX_SNAPSHOT_VERSION {
+            return Err(EngramError::InvalidState(format!(
+                "Index snapshot version {} is not supported (expected {}); rebuild the index from storage",
+                version, SEARCH_INDEX_SNAPSHOT_VERSION
+            )));
         }
+        
+        let storage_sequence: Option<u64> = bincode::deserialize_from(&mut reader)
+            .map_err(|e| EngramError::SerializationError(format!("Failed to read index snapshot: {}", e)))?;
+        let index = bincode::deserialize_from(reader)
+            .map_err(|e| EngramError::SerializationError(format!("Failed to read index snapshot: {}", e)))?;
+        Ok((index, storage_sequence))
     }
     
     /// Add an engram to the index
     pub fn add_engram(&mut self, engram: &Engram) -> Result<()> {
+        if self.engram_sources.contains_key(&engram.id) {
+            self.remove_engram_by_id(&engram.id)?;
+        }
+        
         // Index by metadata
         self.metadata_index.add_engram(engram)?;
         
@@ -1177,19 +3136,88 @@ impl SearchIndex {
         // Index by source
         self.source_index
             .entry(engram.source.clone())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(engram.id.clone());
+        self.engram_sources.insert(engram.id.clone(), engram.source.clone());
+        
+        // Index by source document
+        if let Some(provenance) = &engram.provenance {
+            self.document_index
+                .entry(provenance.document_id.clone())
+                .or_default()
+                .insert(engram.id.clone());
+            self.engram_documents.insert(engram.id.clone(), provenance.document_id.clone());
+        }
         
         // Index by confidence bucket
         let confidence_bucket = (engram.confidence * 10.0).floor() as u8;
         self.confidence_index
             .entry(confidence_bucket)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(engram.id.clone());
+        self.engram_confidence_buckets.insert(engram.id.clone(), confidence_bucket);
+        
+        Ok(())
+    }
+    
+    /// Index many engrams at once, analyzing them in parallel.
+    ///
+    /// Each rayon worker builds a partial index over its share of the engrams and the partial
+    /// indexes are merged, giving the same index as calling `add_engram` for each in turn.
+    /// Falls back to sequential insertion when the batch repeats an ID or re-adds an engram
+    /// that is already indexed, since replacing entries cannot be split across workers.
+    pub fn add_engrams_parallel(&mut self, engrams: &[Engram]) -> Result<()> {
+        let mut seen = HashSet::with_capacity(engrams.len());
+        let disjoint = engrams.iter().all(|engram| {
+            seen.insert(engram.id.as_str()) && self.temporal_index.get_timestamp(&engram.id).is_none()
+        });
+        if !disjoint {
+            for engram in engrams {
+                self.add_engram(engram)?;
+            }
+            return Ok(());
+        }
         
+        let partial = engrams
+            .par_iter()
+            .try_fold(|| self.empty_like(), |mut index, engram| {
+                index.add_engram(engram)?;
+                Ok::<_, EngramError>(index)
+            })
+            .try_reduce(|| self.empty_like(), |mut merged, index| {
+                merged.merge(index);
+                Ok(merged)
+            })?;
+        
+        self.merge(partial);
         Ok(())
     }
     
+    /// An empty index with the same configuration, holding no engrams or connections
+    fn empty_like(&self) -> Self {
+        Self {
+            text_index: self.text_index.empty_like(),
+            metadata_index: self.metadata_index.empty_like(),
+            importance_index: self.importance_index.empty_like(),
+            ..Self::new().with_recency_capacity(self.temporal_index.recency_capacity)
+        }
+    }
+    
+    /// Fold in the engrams of an index built over a disjoint set of engrams.
+    /// Connections are indexed separately and are not merged.
+    fn merge(&mut self, other: Self) {
+        self.metadata_index.merge(other.metadata_index);
+        self.text_index.merge(other.text_index);
+        self.temporal_index.merge(other.temporal_index);
+        self.importance_index.merge(other.importance_index);
+        merge_id_sets(&mut self.source_index, other.source_index);
+        merge_id_sets(&mut self.document_index, other.document_index);
+        merge_id_sets(&mut self.confidence_index, other.confidence_index);
+        self.engram_sources.extend(other.engram_sources);
+        self.engram_documents.extend(other.engram_documents);
+        self.engram_confidence_buckets.extend(other.engram_confidence_buckets);
+    }
+    
     /// Add a connection to the index
     pub fn add_connection(&mut self, connection: &Connection) -> Result<()> {
         self.relationship_index.add_connection(connection)
@@ -1197,36 +3225,7 @@ impl SearchIndex {
     
     /// Remove an engram from the index
     pub fn remove_engram(&mut self, engram: &Engram) -> Result<()> {
-        // Remove from metadata index
-        self.metadata_index.remove_engram(engram)?;
-        
-        // Remove from text index
-        self.text_index.remove_engram(engram)?;
-        
-        // Remove from temporal index
-        self.temporal_index.remove_engram(engram)?;
-        
-        // Remove from importance index
-        self.importance_index.remove_engram(engram)?;
-        
-        // Remove from source index
-        if let Some(engrams) = self.source_index.get_mut(&engram.source) {
-            engrams.remove(&engram.id);
-            if engrams.is_empty() {
-                self.source_index.remove(&engram.source);
-            }
-        }
-        
-        // Remove from confidence index
-        let confidence_bucket = (engram.confidence * 10.0).floor() as u8;
-        if let Some(engrams) = self.confidence_index.get_mut(&confidence_bucket) {
-            engrams.remove(&engram.id);
-            if engrams.is_empty() {
-                self.confidence_index.remove(&confidence_bucket);
-            }
-        }
-        
-        Ok(())
+        self.remove_engram_by_id(&engram.id)
     }
     
     /// Record an access to an engram
@@ -1234,36 +3233,84 @@ impl SearchIndex {
         self.importance_index.record_access(id)
     }
     
+    /// Promote an engram in access recency without re-indexing it.
+    /// Only the last-accessed time changes; keyword, metadata, and creation-time entries are untouched.
+    pub fn touch(&mut self, id: &EngramId, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        if self.importance_index.touch(id, now) {
+            Ok(())
+        } else {
+            Err(EngramError::NotFound(format!("Engram {} is not indexed", id)))
+        }
+    }
+    
     /// Update an engram's importance score
     pub fn update_importance(&mut self, id: &EngramId, importance: f64) -> Result<()> {
         self.importance_index.update_importance(id, importance)
     }
     
+    /// Decay every engram's importance with the given half-life in seconds
+    pub fn apply_decay(&mut self, half_life_seconds: u64) -> Result<()> {
+        self.importance_index.apply_decay(half_life_seconds)
+    }
+    
     /// Set or update TTL for an engram
     pub fn set_ttl(&mut self, id: &EngramId, ttl: Option<u64>) -> Result<()> {
         self.importance_index.set_ttl(id, ttl)
     }
     
-    /// Remove an engram from the index by ID
+    /// Pin or unpin an engram, exempting it from forgetting
+    pub fn set_pinned(&mut self, id: &EngramId, pinned: bool) -> Result<()> {
+        self.importance_index.set_pinned(id, pinned)
+    }
+    
+    /// Remove an engram from the index by ID. Every index records what it needs to remove
+    /// an engram when adding it, so this is as complete as `remove_engram` without having to
+    /// load the engram from storage first.
     pub fn remove_engram_by_id(&mut self, engram_id: &str) -> Result<()> {
-        // Since we only have the ID, we may not be able to fully remove from all indexes
-        // This is a best-effort method that removes what it can
+        let id = engram_id.to_string();
+        
+        // Remove from metadata index
+        self.metadata_index.remove_engram_by_id(&id)?;
+        
+        // Remove from text index
+        self.text_index.remove_engram_by_id(&id)?;
+        
+        // Remove from temporal index
+        self.temporal_index.remove_engram_by_id(&id)?;
+        
+        // Remove from importance index
+        self.importance_index.remove_engram_by_id(&id)?;
         
         // Remove from source index
-        for (_, engrams) in self.source_index.iter_mut() {
-            engrams.remove(engram_id);
+        if let Some(source) = self.engram_sources.remove(&id) {
+            if let Some(engrams) = self.source_index.get_mut(&source) {
+                engrams.remove(&id);
+                if engrams.is_empty() {
+                    self.source_index.remove(&source);
+                }
+            }
         }
         
-        // Clean up empty sets in source index
-        self.source_index.retain(|_, engrams| !engrams.is_empty());
-        
-        // Remove from metadata and text indexes
-        // We can't remove properly without the full engram, so this is a limitation
+        // Remove from document index
+        if let Some(document_id) = self.engram_documents.remove(&id) {
+            if let Some(engrams) = self.document_index.get_mut(&document_id) {
+                engrams.remove(&id);
+                if engrams.is_empty() {
+                    self.document_index.remove(&document_id);
+                }
+            }
+        }
         
-        // Note: In a real implementation, we would need to fetch the engram first
-        // and then use remove_engram, but for now this is a partial implementation
+        // Remove from confidence index
+        if let Some(confidence_bucket) = self.engram_confidence_buckets.remove(&id) {
+            if let Some(engrams) = self.confidence_index.get_mut(&confidence_bucket) {
+                engrams.remove(&id);
+                if engrams.is_empty() {
+                    self.confidence_index.remove(&confidence_bucket);
+                }
+            }
+        }
         
-        // Return success
         Ok(())
     }
     
@@ -1272,6 +3319,16 @@ impl SearchIndex {
         self.relationship_index.remove_connection(connection)
     }
     
+    /// IDs of every engram in the index
+    fn indexed_engram_ids(&self) -> HashSet<EngramId> {
+        self.engram_sources.keys().cloned().collect()
+    }
+    
+    /// Number of distinct engram sources currently indexed
+    pub fn distinct_sources(&self) -> usize {
+        self.source_index.len()
+    }
+    
     /// Find engrams by source
     pub fn find_by_source(&self, source: &str) -> HashSet<EngramId> {
         self.source_index
@@ -1280,6 +3337,19 @@ impl SearchIndex {
             .unwrap_or_else(HashSet::new)
     }
     
+    /// `find_by_source`, with the matching IDs in sorted order
+    pub fn find_by_source_sorted(&self, source: &str) -> Vec<EngramId> {
+        sorted_ids(self.find_by_source(source))
+    }
+    
+    /// Find engrams extracted from a source document, by provenance document ID
+    pub fn find_by_document(&self, document_id: &str) -> HashSet<EngramId> {
+        self.document_index
+            .get(document_id)
+            .cloned()
+            .unwrap_or_else(HashSet::new)
+    }
+    
     /// Find engrams by minimum confidence
     pub fn find_by_min_confidence(&self, min_confidence: f64) -> HashSet<EngramId> {
         let min_bucket = (min_confidence * 10.0).floor() as u8;
@@ -1300,6 +3370,11 @@ impl SearchIndex {
         self.importance_index.find_by_min_importance(min_importance)
     }
     
+    /// Find engrams by maximum importance score
+    pub fn find_by_max_importance(&self, max_importance: f64) -> HashSet<EngramId> {
+        self.importance_index.find_by_max_importance(max_importance)
+    }
+    
     /// Find engrams by minimum access count
     pub fn find_by_min_access_count(&self, min_count: u32) -> HashSet<EngramId> {
         self.importance_index.find_by_min_access_count(min_count)
@@ -1310,11 +3385,21 @@ impl SearchIndex {
         self.importance_index.find_by_last_accessed_after(time)
     }
     
+    /// Get the most recently accessed engrams (by `last_accessed`, not creation time)
+    pub fn get_most_recently_accessed(&self, count: usize) -> Vec<EngramId> {
+        self.importance_index.get_most_recently_accessed(count)
+    }
+    
     /// Get the most important engrams
     pub fn get_most_important(&self, count: usize) -> Vec<EngramId> {
         self.importance_index.get_most_important(count)
     }
     
+    /// Draw a reproducible, importance-weighted random sample of engrams
+    pub fn sample_by_importance(&self, n: usize, seed: u64) -> Vec<EngramId> {
+        self.importance_index.sample_by_importance(n, seed)
+    }
+    
     /// Get expired engrams based on TTL
     pub fn get_expired_engrams(&self) -> HashSet<EngramId> {
         self.importance_index.get_expired_engrams()
@@ -1336,6 +3421,31 @@ impl SearchIndex {
         )
     }
     
+    /// Get the indexed importance score of an engram
+    pub fn get_importance(&self, id: &EngramId) -> Option<f64> {
+        self.importance_index.get_importance(id)
+    }
+    
+    /// Get the indexed access count of an engram
+    pub fn get_access_count(&self, id: &EngramId) -> Option<u32> {
+        self.importance_index.get_access_count(id)
+    }
+    
+    /// Get the indexed last access time of an engram
+    pub fn get_last_accessed(&self, id: &EngramId) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.importance_index.get_last_accessed(id)
+    }
+    
+    /// Get the indexed creation timestamp of an engram
+    pub fn get_timestamp(&self, id: &EngramId) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.temporal_index.get_timestamp(id)
+    }
+    
+    /// Get the indexed confidence bucket (confidence * 10, rounded down) of an engram
+    pub fn get_confidence_bucket(&self, id: &EngramId) -> Option<u8> {
+        self.engram_confidence_buckets.get(id).copied()
+    }
+    
     /// Find engrams created before a specific timestamp
     pub fn find_by_before_timestamp(&self, timestamp: &chrono::DateTime<chrono::Utc>) -> HashSet<EngramId> {
         self.temporal_index.find_before(timestamp)
@@ -1375,12 +3485,34 @@ impl SearchIndex {
         self.temporal_index.find_by_hour(hour)
     }
     
-    /// Get most recent engrams
+    /// Get the most recently created engrams (by `timestamp`, not access time)
     pub fn get_most_recent(&self, count: usize) -> Vec<EngramId> {
         self.temporal_index.get_most_recent(count)
     }
     
-    /// Combine multiple search criteria with AND logic
+    /// Apply named filters with AND logic, recording a warning for each filter that fails
+    /// instead of failing the whole query
+    pub fn apply_filters(&self, filters: &[QueryFilter<'_>]) -> QueryResult {
+        let mut final_result: Option<HashSet<EngramId>> = None;
+        let mut warnings = Vec::new();
+        
+        for filter in filters {
+            if let Some(filter_results) = filter.apply_or_warn(self, &mut warnings) {
+                final_result = Some(match final_result {
+                    Some(existing) => existing.intersection(&filter_results).cloned().collect(),
+                    None => filter_results,
+                });
+            }
+        }
+        
+        QueryResult {
+            ids: final_result.unwrap_or_default(),
+            warnings,
+        }
+    }
+    
+    /// Combine multiple search criteria with AND logic, as a `CombinedQuery` of `all_of` predicates
+    #[allow(clippy::too_many_arguments)]
     pub fn search_combined(
         &self,
         text_query: Option<&str>,
@@ -1391,74 +3523,37 @@ impl SearchIndex {
         exact_match: bool,
         before_time: Option<&chrono::DateTime<chrono::Utc>>,
         after_time: Option<&chrono::DateTime<chrono::Utc>>,
-    ) -> HashSet<EngramId> {
-        let mut final_result: Option<HashSet<EngramId>> = None;
+    ) -> QueryResult {
+        let mut predicates = Vec::new();
         
-        // Apply text search if provided
         if let Some(query) = text_query {
-            let text_results = if exact_match {
-                self.text_index.search_all(query)
-            } else {
-                self.text_index.search(query)
-            };
-            
-            final_result = Some(match final_result {
-                Some(existing) => existing.intersection(&text_results).cloned().collect(),
-                None => text_results,
-            });
+            predicates.push(SearchPredicate::Text { query: query.to_string(), match_all: exact_match });
         }
-        
-        // Apply source filter if provided
-        if let Some(source_str) = source {
-            let source_results = self.find_by_source(source_str);
-            final_result = Some(match final_result {
-                Some(existing) => existing.intersection(&source_results).cloned().collect(),
-                None => source_results,
-            });
+        if let Some(source) = source {
+            predicates.push(SearchPredicate::Source(source.to_string()));
         }
-        
-        // Apply confidence filter if provided
-        if let Some(conf) = min_confidence {
-            let conf_results = self.find_by_min_confidence(conf);
-            final_result = Some(match final_result {
-                Some(existing) => existing.intersection(&conf_results).cloned().collect(),
-                None => conf_results,
-            });
+        if let Some(min_confidence) = min_confidence {
+            predicates.push(SearchPredicate::MinConfidence(min_confidence));
         }
-        
-        // Apply metadata key filter if provided
         if let Some(key) = metadata_key {
-            let key_results = if let Some(value) = metadata_value {
-                self.metadata_index.find_by_key_value(key, value)
-            } else {
-                self.metadata_index.find_by_key(key)
-            };
-            
-            final_result = Some(match final_result {
-                Some(existing) => existing.intersection(&key_results).cloned().collect(),
-                None => key_results,
+            predicates.push(SearchPredicate::Metadata {
+                key: key.to_string(),
+                value: metadata_value.map(str::to_string),
             });
         }
-        
-        // Apply before time filter if provided
         if let Some(time) = before_time {
-            let time_results = self.find_by_before_timestamp(time);
-            final_result = Some(match final_result {
-                Some(existing) => existing.intersection(&time_results).cloned().collect(),
-                None => time_results,
-            });
+            predicates.push(SearchPredicate::Before(*time));
         }
-        
-        // Apply after time filter if provided
         if let Some(time) = after_time {
-            let time_results = self.find_by_after_timestamp(time);
-            final_result = Some(match final_result {
-                Some(existing) => existing.intersection(&time_results).cloned().collect(),
-                None => time_results,
-            });
+            predicates.push(SearchPredicate::After(*time));
+        }
+        
+        // Without any criteria nothing matches, rather than every engram
+        if predicates.is_empty() {
+            return QueryResult::default();
         }
         
-        final_result.unwrap_or_else(HashSet::new)
+        CombinedQuery::new().all_of(predicates).execute(self)
     }
     
     /// Original search_combined method for backward compatibility
@@ -1481,6 +3576,13 @@ impl SearchIndex {
             None,
             None,
         )
+        .ids
+    }
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -1532,31 +3634,46 @@ pub enum ForgettingPolicy {
     },
 }
 
+/// An engram selected by a forgetting policy, with the signals that led to its selection
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForgettingCandidate {
+    /// ID of the engram selected for forgetting
+    pub id: EngramId,
+    
+    /// Human-readable explanation, e.g. `importance=0.10, access=0, idle=40d`
+    pub reason: String,
+    
+    /// Forgetting score in [0, 1]; higher means the engram is less worth keeping
+    pub score: f64,
+}
+
 impl ForgettingPolicy {
-    /// Execute the forgetting policy on the given index and return engrams to forget
-    pub fn get_forgetting_candidates(&self, index: &SearchIndex) -> Vec<EngramId> {
+    /// Short name of the policy, used as the reason when reporting forgotten engrams
+    pub fn name(&self) -> &'static str {
         match self {
+            Self::AgeBased { .. } => "age_based",
+            Self::ImportanceThreshold { .. } => "importance_threshold",
+            Self::AccessFrequency { .. } => "access_frequency",
+            Self::Hybrid { .. } => "hybrid",
+            Self::TTLExpiration { .. } => "ttl_expiration",
+        }
+    }
+    
+    /// Execute the forgetting policy on the given index and return engrams to forget.
+    /// Pinned engrams are never selected.
+    pub fn get_forgetting_candidates(&self, index: &SearchIndex) -> Vec<EngramId> {
+        let (candidates, max_items): (Vec<EngramId>, usize) = match self {
             Self::AgeBased { max_age_seconds, max_items } => {
                 // Calculate timestamp threshold
                 let threshold = chrono::Utc::now() - chrono::Duration::seconds(*max_age_seconds as i64);
                 
                 // Get engrams created before threshold
-                let candidates = index.find_by_before_timestamp(&threshold);
-                
-                // Sort by age (oldest first) and limit
-                let mut candidates_vec: Vec<_> = candidates.into_iter().collect();
-                candidates_vec.truncate(*max_items);
-                candidates_vec
+                (index.find_by_before_timestamp(&threshold).into_iter().collect(), *max_items)
             },
             
             Self::ImportanceThreshold { max_importance, max_items } => {
                 // Get engrams with importance below threshold
-                let candidates = index.find_by_min_importance(*max_importance);
-                
-                // Limit number of candidates
-                let mut candidates_vec: Vec<_> = candidates.into_iter().collect();
-                candidates_vec.truncate(*max_items);
-                candidates_vec
+                (index.find_by_max_importance(*max_importance).into_iter().collect(), *max_items)
             },
             
             Self::AccessFrequency { max_access_count, min_idle_seconds, max_items } => {
@@ -1568,32 +3685,71 @@ impl ForgettingPolicy {
                 let old_access = index.find_by_last_accessed_after(&threshold);
                 
                 // Find intersection
-                let candidates: HashSet<_> = infrequent.difference(&old_access).cloned().collect();
-                
-                // Limit number of candidates
-                let mut candidates_vec: Vec<_> = candidates.into_iter().collect();
-                candidates_vec.truncate(*max_items);
-                candidates_vec
+                (infrequent.difference(&old_access).cloned().collect(), *max_items)
             },
             
             Self::Hybrid { max_importance, max_access_count, min_idle_seconds, max_items } => {
                 // Calculate access time threshold
                 let threshold = chrono::Utc::now() - chrono::Duration::seconds(*min_idle_seconds as i64);
                 
-                // Get forgetting candidates using the combined criteria
-                index.get_forgetting_candidates(*max_importance, *max_access_count, &threshold, *max_items)
+                // Get forgetting candidates using the combined criteria (already excludes pinned engrams)
+                (index.get_forgetting_candidates(*max_importance, *max_access_count, &threshold, *max_items), *max_items)
             },
             
             Self::TTLExpiration { max_items } => {
                 // Get expired engrams based on TTL
-                let candidates = index.get_expired_engrams();
-                
-                // Limit number of candidates
-                let mut candidates_vec: Vec<_> = candidates.into_iter().collect();
-                candidates_vec.truncate(*max_items);
-                candidates_vec
+                (index.get_expired_engrams().into_iter().collect(), *max_items)
             },
-        }
+        };
+        
+        // Drop pinned engrams before limiting, so they never take up a candidate slot
+        let mut candidates_vec = candidates;
+        candidates_vec.retain(|id| !index.importance_index.is_pinned(id));
+        candidates_vec.truncate(max_items);
+        candidates_vec
+    }
+    
+    /// Execute the forgetting policy and explain why each candidate was selected.
+    /// Candidates are returned in the same order as `get_forgetting_candidates`.
+    pub fn explain_forgetting_candidates(&self, index: &SearchIndex) -> Vec<ForgettingCandidate> {
+        let now = chrono::Utc::now();
+        
+        self.get_forgetting_candidates(index)
+            .into_iter()
+            .map(|id| {
+                let importance = index.get_importance(&id).unwrap_or(0.5);
+                let access_count = index.get_access_count(&id).unwrap_or(0);
+                let idle_days = index.get_last_accessed(&id)
+                    .map(|time| now.signed_duration_since(time).num_days())
+                    .unwrap_or(0);
+                
+                let mut reason = format!(
+                    "importance={:.2}, access={}, idle={}d",
+                    importance, access_count, idle_days
+                );
+                
+                // Add the signal specific to policies that look beyond importance and access
+                match self {
+                    Self::AgeBased { .. } => {
+                        if let Some(timestamp) = index.get_timestamp(&id) {
+                            reason.push_str(&format!(", age={}d", now.signed_duration_since(timestamp).num_days()));
+                        }
+                    },
+                    Self::TTLExpiration { .. } => {
+                        if let Some(ttl) = index.importance_index.get_ttl(&id) {
+                            reason.push_str(&format!(", ttl={}s", ttl));
+                        }
+                    },
+                    _ => {},
+                }
+                
+                ForgettingCandidate {
+                    id,
+                    reason,
+                    score: 1.0 - importance,
+                }
+            })
+            .collect()
     }
 }
 
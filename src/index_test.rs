@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::index::{RelationshipIndex, MetadataIndex, SearchIndex};
-    use crate::schema::{Connection, Engram};
+    use crate::index::{sorted_ids, CombinedQuery, RelationshipIndex, MetadataIndex, SearchIndex, SearchPredicate, ScoringMode, TextIndex, QueryFilter};
+    use crate::error::EngramError;
+    use crate::schema::{Connection, Engram, Provenance};
+    use std::collections::HashSet;
     
     use serde_json::json;
 
@@ -53,30 +55,30 @@ mod tests {
         // Test outgoing connections
         let outgoing_e1 = index.get_outgoing_connections(&"e1".to_string());
         assert_eq!(outgoing_e1.len(), 2);
-        assert!(outgoing_e1.contains(&"conn1".to_string()));
-        assert!(outgoing_e1.contains(&"conn2".to_string()));
+        assert!(outgoing_e1.contains("conn1"));
+        assert!(outgoing_e1.contains("conn2"));
         
         // Test incoming connections
         let incoming_e3 = index.get_incoming_connections(&"e3".to_string());
         assert_eq!(incoming_e3.len(), 1);
-        assert!(incoming_e3.contains(&"conn2".to_string()));
+        assert!(incoming_e3.contains("conn2"));
         
         // Test by relationship type
         let causes_conns = index.get_connections_by_type("causes");
         assert_eq!(causes_conns.len(), 2);
-        assert!(causes_conns.contains(&"conn1".to_string()));
-        assert!(causes_conns.contains(&"conn3".to_string()));
+        assert!(causes_conns.contains("conn1"));
+        assert!(causes_conns.contains("conn3"));
         
         // Test targets
         let targets_e1 = index.get_targets(&"e1".to_string());
         assert_eq!(targets_e1.len(), 2);
-        assert!(targets_e1.contains(&"e2".to_string()));
-        assert!(targets_e1.contains(&"e3".to_string()));
+        assert!(targets_e1.contains("e2"));
+        assert!(targets_e1.contains("e3"));
         
         // Test combined queries
         let e1_causes = index.find_by_source_and_type(&"e1".to_string(), "causes");
         assert_eq!(e1_causes.len(), 1);
-        assert!(e1_causes.contains(&"conn1".to_string()));
+        assert!(e1_causes.contains("conn1"));
         
         // Test path finding
         let paths = index.find_paths(&"e1".to_string(), &"e4".to_string(), 2);
@@ -87,9 +89,190 @@ mod tests {
         index.remove_connection(&conn1).unwrap();
         let outgoing_e1_after = index.get_outgoing_connections(&"e1".to_string());
         assert_eq!(outgoing_e1_after.len(), 1);
-        assert!(outgoing_e1_after.contains(&"conn2".to_string()));
+        assert!(outgoing_e1_after.contains("conn2"));
+    }
+
+    #[test]
+    fn test_relationship_index_shortest_paths() {
+        let mut index = RelationshipIndex::new();
+        
+        // A long route e1 -> e2 -> e3 -> e4 -> e5 and two short ones via e2 and e6
+        let edges = [("e1", "e2"), ("e2", "e3"), ("e3", "e4"), ("e4", "e5"), ("e1", "e6"), ("e6", "e5"), ("e2", "e5")];
+        for (i, (source, target)) in edges.iter().enumerate() {
+            index.add_connection(&create_test_connection(&format!("c{}", i), source, target, "leads_to", 0.5)).unwrap();
+        }
+        let e1 = "e1".to_string();
+        let e5 = "e5".to_string();
+        
+        // BFS returns a path no longer than any path the exhaustive search finds
+        let shortest = index.shortest_path(&e1, &e5).unwrap();
+        let min_len = index.find_paths(&e1, &e5, 10).iter().map(|path| path.len()).min().unwrap();
+        assert_eq!(shortest.len(), min_len);
+        assert_eq!(shortest, vec!["e1", "e2", "e5"]);
+        
+        // The k shortest paths come shortest first, without repeats
+        let paths = index.k_shortest_paths(&e1, &e5, 10);
+        let lengths: Vec<usize> = paths.iter().map(|path| path.len()).collect();
+        assert_eq!(lengths, vec![3, 3, 5]);
+        assert!(paths.contains(&vec!["e1".to_string(), "e6".to_string(), "e5".to_string()]));
+        assert_eq!(index.k_shortest_paths(&e1, &e5, 2).len(), 2);
+        assert!(index.k_shortest_paths(&e1, &e5, 0).is_empty());
+        
+        // Unreachable targets have no path, and an engram reaches itself in zero hops
+        assert!(index.shortest_path(&e5, &e1).is_none());
+        assert!(index.k_shortest_paths(&e5, &e1, 3).is_empty());
+        assert_eq!(index.shortest_path(&e1, &e1), Some(vec![e1.clone()]));
+        
+        // The exhaustive search can be capped
+        assert_eq!(index.find_paths(&e1, &e5, 10).len(), 3);
+        assert_eq!(index.find_paths_limited(&e1, &e5, 10, 1).len(), 1);
+    }
+    
+    #[test]
+    fn test_relationship_index_weighted_shortest_path() {
+        let mut index = RelationshipIndex::new();
+        
+        // A weak direct link, a strong two-hop chain and a weaker two-hop chain
+        index.add_connection(&create_test_connection("c1", "e1", "e4", "supports", 0.2)).unwrap();
+        index.add_connection(&create_test_connection("c2", "e1", "e2", "supports", 0.9)).unwrap();
+        index.add_connection(&create_test_connection("c3", "e2", "e4", "supports", 0.9)).unwrap();
+        index.add_connection(&create_test_connection("c4", "e1", "e3", "supports", 0.7)).unwrap();
+        index.add_connection(&create_test_connection("c5", "e3", "e4", "supports", 0.7)).unwrap();
+        let e1 = "e1".to_string();
+        let e4 = "e4".to_string();
+        
+        // The strongest chain wins over the fewest hops
+        let (path, cost) = index.weighted_shortest_path(&e1, &e4, |weight| 1.0 - weight).unwrap();
+        assert_eq!(path, vec!["e1", "e2", "e4"]);
+        assert!((cost - 0.2).abs() < 1e-9);
+        
+        // A constant cost counts hops
+        let (path, cost) = index.weighted_shortest_path(&e1, &e4, |_| 1.0).unwrap();
+        assert_eq!(path, vec!["e1", "e4"]);
+        assert_eq!(cost, 1.0);
+        
+        // Removing a connection removes its weight from traversal
+        index.remove_connection(&create_test_connection("c2", "e1", "e2", "supports", 0.9)).unwrap();
+        let (path, _) = index.weighted_shortest_path(&e1, &e4, |weight| 1.0 - weight).unwrap();
+        assert_eq!(path, vec!["e1", "e3", "e4"]);
+        
+        // Unreachable targets have no path, and an engram reaches itself at no cost
+        assert!(index.weighted_shortest_path(&e4, &e1, |weight| 1.0 - weight).is_none());
+        assert_eq!(index.weighted_shortest_path(&e1, &e1, |weight| 1.0 - weight), Some((vec![e1.clone()], 0.0)));
+    }
+    
+    #[test]
+    fn test_relationship_index_weighted_shortest_path_parallel_connections() {
+        let mut index = RelationshipIndex::new();
+        
+        // Two connections join the same pair; the stronger one is added first
+        index.add_connection(&create_test_connection("strong", "e1", "e2", "supports", 0.9)).unwrap();
+        index.add_connection(&create_test_connection("weak", "e1", "e2", "supports", 0.3)).unwrap();
+        let e1 = "e1".to_string();
+        let e2 = "e2".to_string();
+        
+        // The cheaper connection is followed regardless of insertion order
+        let (path, cost) = index.weighted_shortest_path(&e1, &e2, |weight| 1.0 - weight).unwrap();
+        assert_eq!(path, vec!["e1", "e2"]);
+        assert!((cost - 0.1).abs() < 1e-9);
+        
+        // Removing the cheaper connection leaves the pair joined by the other one
+        index.remove_connection(&create_test_connection("strong", "e1", "e2", "supports", 0.9)).unwrap();
+        let (path, cost) = index.weighted_shortest_path(&e1, &e2, |weight| 1.0 - weight).unwrap();
+        assert_eq!(path, vec!["e1", "e2"]);
+        assert!((cost - 0.7).abs() < 1e-9);
+        assert!(index.get_targets(&e1).contains(&e2));
+        
+        // Removing the last one disconnects the pair
+        index.remove_connection(&create_test_connection("weak", "e1", "e2", "supports", 0.3)).unwrap();
+        assert!(index.weighted_shortest_path(&e1, &e2, |weight| 1.0 - weight).is_none());
+    }
+    
+    #[test]
+    fn test_relationship_index_weight_range() {
+        let mut index = RelationshipIndex::new();
+
+        // Connections just outside the band share buckets with ones inside it
+        index.add_connection(&create_test_connection("below", "e1", "e2", "causes", 0.29)).unwrap();
+        index.add_connection(&create_test_connection("low_edge", "e1", "e3", "causes", 0.3)).unwrap();
+        index.add_connection(&create_test_connection("middle", "e2", "e3", "causes", 0.45)).unwrap();
+        index.add_connection(&create_test_connection("high_edge", "e3", "e4", "causes", 0.6)).unwrap();
+        index.add_connection(&create_test_connection("above", "e4", "e1", "causes", 0.61)).unwrap();
+
+        let band = index.find_connections_by_weight_range(0.3, 0.6);
+        let expected: HashSet<String> = ["low_edge", "middle", "high_edge"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(band, expected);
+
+        // Re-adding a connection with a new weight moves it between buckets
+        index.add_connection(&create_test_connection("middle", "e2", "e3", "causes", 0.9)).unwrap();
+        assert!(!index.find_connections_by_weight_range(0.3, 0.6).contains("middle"));
+        assert!(index.find_connections_by_weight_range(0.85, 1.0).contains("middle"));
+
+        // Removed connections drop out of the weight index
+        index.remove_connection(&create_test_connection("below", "e1", "e2", "causes", 0.29)).unwrap();
+        assert!(index.find_connections_by_weight_range(0.0, 0.3).iter().all(|id| id != "below"));
+    }
+
+    #[test]
+    fn test_relationship_index_min_confidence() {
+        let mut index = RelationshipIndex::new();
+        
+        // Strong but uncertain, weak but certain, and both middling
+        let strong_unsure = create_test_connection("strong_unsure", "e1", "e2", "causes", 0.9)
+            .with_confidence(0.2).unwrap();
+        let weak_certain = create_test_connection("weak_certain", "e2", "e3", "causes", 0.1)
+            .with_confidence(0.95).unwrap();
+        let middling = create_test_connection("middling", "e3", "e4", "causes", 0.5)
+            .with_confidence(0.7).unwrap();
+        for connection in [&strong_unsure, &weak_certain, &middling] {
+            index.add_connection(connection).unwrap();
+        }
+        
+        // Confidence filtering ignores weight
+        let confident = index.find_connections_by_min_confidence(0.7);
+        assert_eq!(confident, HashSet::from(["weak_certain".to_string(), "middling".to_string()]));
+        assert_eq!(index.find_connections_by_min_confidence(0.0).len(), 3);
+        assert!(index.find_connections_by_min_confidence(0.96).is_empty());
+        
+        // Weight filtering ignores confidence
+        let strong = index.find_connections_by_weight_range(0.8, 1.0);
+        assert_eq!(strong, HashSet::from(["strong_unsure".to_string()]));
+        
+        // Confidence scoring discounts uncertain connections
+        assert!(strong_unsure.score(false) > middling.score(false));
+        assert!(strong_unsure.score(true) < middling.score(true));
+        
+        // Out-of-range confidence is rejected and removal clears the index
+        assert!(create_test_connection("bad", "e1", "e2", "causes", 0.5).with_confidence(1.5).is_err());
+        index.remove_connection(&weak_certain).unwrap();
+        assert_eq!(index.find_connections_by_min_confidence(0.7), HashSet::from(["middling".to_string()]));
+        assert_eq!(index.get_connection_confidence(&"weak_certain".to_string()), None);
     }
     
+    #[test]
+    fn test_relationship_index_depth_stats() {
+        let mut index = RelationshipIndex::new();
+        
+        // e1 -> {e2, e3}, e2 -> e4, e3 -> e4 (diamond), e4 -> e5, e5 -> e1 (cycle back)
+        let edges = [("e1", "e2"), ("e1", "e3"), ("e2", "e4"), ("e3", "e4"), ("e4", "e5"), ("e5", "e1")];
+        for (i, (source, target)) in edges.iter().enumerate() {
+            index.add_connection(&create_test_connection(&format!("c{}", i), source, target, "relates_to", 0.5)).unwrap();
+        }
+        
+        // Each engram is counted once, at its shortest distance, despite the diamond and the cycle
+        assert_eq!(index.depth_stats(&"e1".to_string()), vec![1, 2, 1, 1]);
+        assert_eq!(index.depth_stats(&"e4".to_string()), vec![1, 1, 1, 2]);
+        
+        // Connections are followed outgoing only, and unknown engrams reach only themselves
+        index.add_connection(&create_test_connection("sink", "e5", "e6", "relates_to", 0.5)).unwrap();
+        assert_eq!(index.depth_stats(&"e6".to_string()), vec![1]);
+        assert_eq!(index.depth_stats(&"missing".to_string()), vec![1]);
+        
+        // Every engram is sampled in a graph this small: e2 -> e4 -> e5 -> e1 -> e3 is the longest shortest path
+        assert_eq!(index.diameter_estimate(), 4);
+        assert_eq!(RelationshipIndex::new().diameter_estimate(), 0);
+    }
+
     #[test]
     fn test_metadata_index() {
         let mut index = MetadataIndex::new();
@@ -118,19 +301,118 @@ mod tests {
         
         let verified_engrams = index.find_by_key("verified");
         assert_eq!(verified_engrams.len(), 1);
-        assert!(verified_engrams.contains(&"e3".to_string()));
+        assert!(verified_engrams.contains("e3"));
         
         // Test find by key-value
         let climate_engrams = index.find_by_key_value("topic", "climate");
         assert_eq!(climate_engrams.len(), 2);
-        assert!(climate_engrams.contains(&"e1".to_string()));
-        assert!(climate_engrams.contains(&"e3".to_string()));
+        assert!(climate_engrams.contains("e1"));
+        assert!(climate_engrams.contains("e3"));
         
         // Test removing an engram
         index.remove_engram(&engram1).unwrap();
         let climate_engrams_after = index.find_by_key_value("topic", "climate");
         assert_eq!(climate_engrams_after.len(), 1);
-        assert!(climate_engrams_after.contains(&"e3".to_string()));
+        assert!(climate_engrams_after.contains("e3"));
+    }
+    
+    #[test]
+    fn test_metadata_partial_value_matching() {
+        let mut index = MetadataIndex::new();
+        
+        let dates = [("e1", "2023-05-02"), ("e2", "2023-05-28"), ("e3", "2023-06-01"), ("e4", "2022-05-15")];
+        for (id, date) in dates {
+            let mut engram = create_test_engram(id, "Dated content", "source1", 0.8);
+            engram.metadata.insert("date".to_string(), json!(date));
+            index.add_engram(&engram).unwrap();
+        }
+        let mut undated = create_test_engram("e5", "Undated content", "source1", 0.8);
+        undated.metadata.insert("note".to_string(), json!("2023-05 draft"));
+        index.add_engram(&undated).unwrap();
+        
+        // Prefix matching finds every date in May 2023, and only under the requested key
+        let may = index.find_by_value_prefix("date", "2023-05");
+        assert_eq!(may, HashSet::from(["e1".to_string(), "e2".to_string()]));
+        assert_eq!(index.find_by_value_prefix("date", "").len(), 4);
+        assert!(index.find_by_value_prefix("date", "2024").is_empty());
+        assert!(index.find_by_value_prefix("missing", "2023").is_empty());
+        
+        // Substring matching finds values containing the text anywhere
+        let fifth_of_may = index.find_by_value_contains("date", "-05-");
+        assert_eq!(fifth_of_may, HashSet::from(["e1".to_string(), "e2".to_string(), "e4".to_string()]));
+        assert_eq!(index.find_by_value_contains("date", "-01"), HashSet::from(["e3".to_string()]));
+        
+        // Removed engrams drop out of partial matches
+        let mut e2 = create_test_engram("e2", "Dated content", "source1", 0.8);
+        e2.metadata.insert("date".to_string(), json!("2023-05-28"));
+        index.remove_engram(&e2).unwrap();
+        assert_eq!(index.find_by_value_prefix("date", "2023-05"), HashSet::from(["e1".to_string()]));
+        assert_eq!(index.find_by_key_value("date", "2023-05-02"), HashSet::from(["e1".to_string()]));
+    }
+    
+    #[test]
+    fn test_metadata_numeric_range() {
+        let mut index = MetadataIndex::new();
+        
+        let values = [("e1", json!(1)), ("e2", json!(3)), ("e3", json!(0.8)), ("e4", json!(-2.5)), ("e5", json!("3"))];
+        for (id, value) in values {
+            let mut engram = create_test_engram(id, "Scored content", "source1", 0.8);
+            engram.metadata.insert("priority".to_string(), value);
+            index.add_engram(&engram).unwrap();
+        }
+        
+        // Integers and floats share one ordering, and string values are not numbers
+        let ids = |min: f64, max: f64| sorted_ids(index.find_by_numeric_range("priority", min, max));
+        assert_eq!(ids(0.5, 3.0), vec!["e1", "e2", "e3"]);
+        assert_eq!(ids(1.0, 1.0), vec!["e1"]);
+        assert_eq!(ids(f64::NEG_INFINITY, 0.0), vec!["e4"]);
+        assert_eq!(ids(f64::NEG_INFINITY, f64::INFINITY).len(), 4);
+        
+        // Inverted or NaN bounds and unknown keys match nothing
+        assert!(ids(3.0, 1.0).is_empty());
+        assert!(ids(f64::NAN, 1.0).is_empty());
+        assert!(index.find_by_numeric_range("missing", 0.0, 10.0).is_empty());
+        
+        // The string index is unaffected, and removed engrams drop out of ranges
+        assert_eq!(index.find_by_key_value("priority", "3"), HashSet::from(["e5".to_string()]));
+        index.remove_engram_by_id(&"e2".to_string()).unwrap();
+        assert_eq!(sorted_ids(index.find_by_numeric_range("priority", 0.5, 3.0)), vec!["e1", "e3"]);
+    }
+    
+    #[test]
+    fn test_metadata_distinct_value_counts() {
+        let mut index = MetadataIndex::new();
+        let mut sketched = MetadataIndex::new().with_value_sketches(12);
+        let mut search_index = SearchIndex::new();
+        
+        let topics = ["climate", "science", "climate", "energy", "science", "policy"];
+        for (i, topic) in topics.iter().enumerate() {
+            let mut engram = create_test_engram(&format!("e{}", i), "Content", &format!("source{}", i % 3), 0.8);
+            engram.metadata.insert("topic".to_string(), json!(topic));
+            engram.metadata.insert("tags".to_string(), json!(["a", "b"]));
+            index.add_engram(&engram).unwrap();
+            sketched.add_engram(&engram).unwrap();
+            search_index.add_engram(&engram).unwrap();
+        }
+        
+        // The exact count is the number of distinct string values indexed under the key
+        let distinct: HashSet<&str> = topics.iter().copied().collect();
+        assert_eq!(index.distinct_value_count("topic"), distinct.len());
+        assert_eq!(index.estimate_distinct_values("topic"), distinct.len());
+        assert_eq!(index.distinct_value_count("tags"), 0);
+        assert_eq!(index.distinct_value_count("missing"), 0);
+        
+        // Sketches are essentially exact at this scale
+        assert_eq!(sketched.estimate_distinct_values("topic"), distinct.len());
+        assert_eq!(sketched.estimate_distinct_values("missing"), 0);
+        
+        assert_eq!(search_index.distinct_sources(), 3);
+        
+        // Removing the only engram with a value drops it from the exact count
+        let mut policy = create_test_engram("e5", "Content", "source2", 0.8);
+        policy.metadata.insert("topic".to_string(), json!("policy"));
+        index.remove_engram(&policy).unwrap();
+        assert_eq!(index.distinct_value_count("topic"), distinct.len() - 1);
     }
     
     #[test]
@@ -161,14 +443,14 @@ mod tests {
         // Test source searching
         let research_engrams = index.find_by_source("research");
         assert_eq!(research_engrams.len(), 2);
-        assert!(research_engrams.contains(&"e1".to_string()));
-        assert!(research_engrams.contains(&"e3".to_string()));
+        assert!(research_engrams.contains("e1"));
+        assert!(research_engrams.contains("e3"));
         
         // Test confidence searching
         let high_confidence = index.find_by_min_confidence(0.8);
         assert_eq!(high_confidence.len(), 2);
-        assert!(high_confidence.contains(&"e1".to_string()));
-        assert!(high_confidence.contains(&"e2".to_string()));
+        assert!(high_confidence.contains("e1"));
+        assert!(high_confidence.contains("e2"));
         
         // Test combined searching
         let combined_results = index.search_combined(
@@ -180,19 +462,474 @@ mod tests {
             false,          // exact_match set to false
             None,           // before_time
             None,           // after_time
-        );
+        ).ids;
         assert_eq!(combined_results.len(), 1);
-        assert!(combined_results.contains(&"e1".to_string()));
+        assert!(combined_results.contains("e1"));
         
         // Test relationship traversal
         let targets_e2 = index.relationship_index.get_targets(&"e2".to_string());
         assert_eq!(targets_e2.len(), 1);
-        assert!(targets_e2.contains(&"e3".to_string()));
+        assert!(targets_e2.contains("e3"));
         
         // Test metadata filtering
         let climate_engrams = index.metadata_index.find_by_key_value("topic", "climate");
         assert_eq!(climate_engrams.len(), 2);
-        assert!(climate_engrams.contains(&"e1".to_string()));
-        assert!(climate_engrams.contains(&"e3".to_string()));
+        assert!(climate_engrams.contains("e1"));
+        assert!(climate_engrams.contains("e3"));
+    }
+    
+    #[test]
+    fn test_search_index_remove_engram_by_id_is_complete() {
+        let mut index = SearchIndex::new();
+        let mut engram = create_test_engram("e1", "Climate change drives policy", "research", 0.85)
+            .with_provenance(Provenance::new("https://example.com/report.pdf".to_string(), "report-2024".to_string()));
+        engram.metadata.insert("topic".to_string(), json!("climate"));
+        engram.metadata.insert("year".to_string(), json!(2024));
+        engram.set_ttl(3600);
+        
+        index.add_engram(&engram).unwrap();
+        assert!(!index.find_by_source("research").is_empty());
+        assert!(!index.metadata_index.find_by_key_value("topic", "climate").is_empty());
+        
+        // Every sub-index is back to empty without the engram being passed in
+        index.remove_engram_by_id("e1").unwrap();
+        assert_eq!(index, SearchIndex::new());
+        
+        // The same holds for n-grams, which are derived from the content
+        let mut text_index = TextIndex::with_ngrams(3);
+        text_index.add_engram(&engram).unwrap();
+        text_index.remove_engram_by_id(&engram.id).unwrap();
+        assert_eq!(text_index, TextIndex::with_ngrams(3));
+        
+        // Removing an unknown ID is a no-op
+        index.remove_engram_by_id("missing").unwrap();
+    }
+    
+    #[test]
+    fn test_search_index_find_by_document() {
+        let mut index = SearchIndex::new();
+        
+        // Two engrams extracted from the same report, one from another document
+        let report = Provenance::new("https://example.com/report.pdf".to_string(), "report-2024".to_string());
+        let engram1 = create_test_engram("e1", "Sea levels are rising", "extraction", 0.9)
+            .with_provenance(report.clone().with_span(0, 21));
+        let engram2 = create_test_engram("e2", "Glaciers are retreating", "extraction", 0.8)
+            .with_provenance(report.with_span(120, 143));
+        let engram3 = create_test_engram("e3", "Solar output is stable", "extraction", 0.7)
+            .with_provenance(Provenance::new("file:///notes.md".to_string(), "notes".to_string()));
+        let engram4 = create_test_engram("e4", "No known source", "observation", 0.6);
+        
+        for engram in [&engram1, &engram2, &engram3, &engram4] {
+            index.add_engram(engram).unwrap();
+        }
+        
+        let from_report = index.find_by_document("report-2024");
+        assert_eq!(from_report.len(), 2);
+        assert!(from_report.contains("e1"));
+        assert!(from_report.contains("e2"));
+        
+        assert_eq!(index.find_by_document("notes").len(), 1);
+        assert!(index.find_by_document("missing").is_empty());
+        
+        // Removing an engram drops it from its document
+        index.remove_engram(&engram1).unwrap();
+        let from_report = index.find_by_document("report-2024");
+        assert_eq!(from_report.len(), 1);
+        assert!(from_report.contains("e2"));
+    }
+    
+    #[test]
+    fn test_text_index_ngrams() {
+        let mut index = TextIndex::with_ngrams(3);
+        
+        let engram1 = create_test_engram("e1", "The WeatherService returned a forecast", "logs", 0.9);
+        let engram2 = create_test_engram("e2", "Solar panel output report", "logs", 0.8);
+        
+        index.add_engram(&engram1).unwrap();
+        index.add_engram(&engram2).unwrap();
+        
+        // Partial token is not found by keyword search but is found by n-grams
+        assert!(index.search("eatherserv").is_empty());
+        let results = index.find_by_ngrams("eatherserv");
+        assert!(!results.is_empty());
+        assert_eq!(results[0].0, "e1");
+        
+        // N-grams are removed along with the engram
+        index.remove_engram(&engram1).unwrap();
+        assert!(index.find_by_ngrams("eatherserv").iter().all(|(id, _)| id != "e1"));
+        
+        // N-gram matching is disabled by default
+        let mut plain = TextIndex::new();
+        plain.add_engram(&engram1).unwrap();
+        assert!(plain.find_by_ngrams("eatherserv").is_empty());
+    }
+    
+    #[test]
+    fn test_text_index_search_ranked() {
+        let mut index = TextIndex::new();
+        let e1 = create_test_engram("e1", "Rust memory safety: Rust ownership keeps Rust fast", "notes", 0.9);
+        let e2 = create_test_engram("e2", "Rust is a systems language", "notes", 0.9);
+        let e3 = create_test_engram("e3", "Memory graphs for agents", "notes", 0.9);
+        let e4 = create_test_engram("e4", "Python notebooks", "notes", 0.9);
+        for engram in [&e1, &e2, &e3, &e4] {
+            index.add_engram(engram).unwrap();
+        }
+        
+        // Repeated mentions rank higher, and non-matching engrams are left out
+        let ranked = index.search_ranked("rust", 10);
+        let ids: Vec<&str> = ranked.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["e1", "e2"]);
+        assert!(ranked[0].1 > ranked[1].1);
+        
+        // Matching more (and rarer) terms ranks higher; stems match too
+        let ranked = index.search_ranked("ownership memories", 10);
+        assert_eq!(ranked[0].0, "e1");
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(index.search_ranked("rust memory", 1).len(), 1);
+        
+        // Re-adding an engram doesn't inflate document frequencies
+        let before = index.search_ranked("language", 10);
+        index.add_engram(&e2).unwrap();
+        assert_eq!(index.search_ranked("language", 10), before);
+        
+        // Removal drops the engram's terms from the ranking
+        index.remove_engram(&e1).unwrap();
+        assert!(index.search_ranked("ownership", 10).is_empty());
+        let ids: Vec<String> = index.search_ranked("rust", 10).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["e2".to_string()]);
+    }
+    
+    #[test]
+    fn test_text_index_bm25_length_normalization() {
+        let short = create_test_engram("b-short", "Kubernetes deployment", "notes", 0.9);
+        let long = create_test_engram(
+            "a-long",
+            "Quarterly planning notes covering hiring, budgets, office moves, vendor contracts and a kubernetes mention",
+            "notes",
+            0.9,
+        );
+        let other = create_test_engram("c-other", "Vendor budgets", "notes", 0.9);
+        
+        let mut bm25 = TextIndex::new();
+        assert_eq!(bm25.scoring_mode(), ScoringMode::Bm25 { k1: 1.2, b: 0.75 });
+        let mut tfidf = TextIndex::new().with_scoring_mode(ScoringMode::TfIdf);
+        for engram in [&short, &long, &other] {
+            bm25.add_engram(engram).unwrap();
+            tfidf.add_engram(engram).unwrap();
+        }
+        
+        // BM25 favours the short engram where the rare term isn't diluted
+        let ranked = bm25.search_ranked("kubernetes", 10);
+        assert_eq!(ranked[0].0, "b-short");
+        assert_eq!(ranked[1].0, "a-long");
+        assert!(ranked[0].1 > ranked[1].1);
+        
+        // TF-IDF ignores length, so both score the same
+        let ranked = tfidf.search_ranked("kubernetes", 10);
+        assert_eq!(ranked[0].1, ranked[1].1);
+        
+        // Removing the long engram updates the average length
+        bm25.remove_engram(&long).unwrap();
+        let ranked = bm25.search_ranked("kubernetes", 10);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0, "b-short");
+    }
+    
+    #[test]
+    fn test_text_index_search_fuzzy() {
+        let mut index = TextIndex::new();
+        index.add_engram(&create_test_engram("e1", "Agents receive messages", "notes", 0.9)).unwrap();
+        index.add_engram(&create_test_engram("e2", "Embedding vectors", "notes", 0.9)).unwrap();
+        
+        // Misspellings within the distance match; exact search finds nothing
+        assert!(index.search("recieve").is_empty());
+        assert_eq!(index.search_fuzzy("recieve", 2), HashSet::from(["e1".to_string()]));
+        assert!(index.search_fuzzy("recieve", 1).is_empty());
+        assert_eq!(index.search_fuzzy("embeding", 1), HashSet::from(["e2".to_string()]));
+        
+        // Correctly spelled keywords still match, alongside fuzzy ones
+        let results = index.search_fuzzy("vectors recieve", 2);
+        assert_eq!(results.len(), 2);
+        
+        // Distances above the cap are clamped, and the first character must match
+        assert!(index.search_fuzzy("rexxxve", 9).is_empty());
+        assert!(index.search_fuzzy("eeceive", 2).is_empty());
+        assert!(index.search_fuzzy("recieve", 0).is_empty());
+    }
+    
+    #[test]
+    fn test_text_index_phrase_search() {
+        let mut index = TextIndex::new();
+        let e1 = create_test_engram("e1", "Climate change drives new policy", "notes", 0.9);
+        let e2 = create_test_engram("e2", "A change in the climate", "notes", 0.9);
+        let e3 = create_test_engram("e3", "Climate policy and change", "notes", 0.9);
+        for engram in [&e1, &e2, &e3] {
+            index.add_engram(engram).unwrap();
+        }
+        
+        // Only consecutive tokens match a phrase, although all three contain both words
+        assert_eq!(index.search("climate change").len(), 3);
+        assert_eq!(index.search_phrase("Climate Change"), HashSet::from(["e1".to_string()]));
+        assert_eq!(index.search_phrase("change in the climate"), HashSet::from(["e2".to_string()]));
+        assert!(index.search_phrase("climate drives").is_empty());
+        
+        // Short words are not matched but still separate their neighbours
+        let e4 = create_test_engram("e4", "Climate is no change", "notes", 0.9);
+        index.add_engram(&e4).unwrap();
+        assert_eq!(index.search_phrase("climate change"), HashSet::from(["e1".to_string()]));
+        assert!(index.search_phrase("climate is no change").contains("e4"));
+        index.remove_engram(&e4).unwrap();
+        
+        // A window allows gaps but still requires order
+        assert_eq!(index.search_near("climate change", 1), HashSet::from(["e1".to_string()]));
+        let near: HashSet<String> = ["e1".to_string(), "e3".to_string()].into_iter().collect();
+        assert_eq!(index.search_near("climate change", 2), near);
+        
+        // Removed engrams no longer match
+        index.remove_engram(&e1).unwrap();
+        assert!(index.search_phrase("climate change").is_empty());
+    }
+    
+    #[test]
+    fn test_text_index_porter_stemming() {
+        let mut index = TextIndex::new();
+        index.add_engram(&create_test_engram("e1", "Organization of strings and classes", "notes", 0.9)).unwrap();
+        index.add_engram(&create_test_engram("e2", "Running studies on breaking news", "notes", 0.9)).unwrap();
+        
+        // Plurals and derived forms share a stem
+        assert!(index.find_by_stem("organize").contains("e1"));
+        assert!(index.find_by_stem("string").contains("e1"));
+        assert!(index.find_by_stem("class").contains("e1"));
+        assert!(index.find_by_stem("study").contains("e2"));
+        
+        // Gerunds, including doubled consonants
+        assert!(index.find_by_stem("run").contains("e2"));
+        assert!(index.find_by_stem("breaks").contains("e2"));
+        
+        // Words that only look like they carry a suffix are left alone
+        assert!(index.find_by_stem("new").is_empty());
+        
+        // Search stems the query the same way, while exact keyword lookup is unchanged
+        assert!(index.search("organizing").contains("e1"));
+        assert!(index.find_by_keyword("organize").is_empty());
+        assert!(index.find_by_keyword("organization").contains("e1"));
+    }
+    
+    #[test]
+    fn test_text_index_searchable_metadata() {
+        let mut engram = create_test_engram("e1", "Deployment finished without errors", "ci", 0.9);
+        engram.metadata.insert("component".to_string(), serde_json::Value::String("api_service".to_string()));
+        engram.metadata.insert("owner".to_string(), serde_json::Value::String("platform".to_string()));
+        
+        // The metadata-only term is found when its key is searchable
+        let mut index = TextIndex::new().with_searchable_metadata_keys(["component"]);
+        index.add_engram(&engram).unwrap();
+        assert!(index.search("api_service").contains("e1"));
+        assert!(index.find_by_metadata_term("component", "service").contains("e1"));
+        
+        // Values of other keys stay out of the index
+        assert!(index.search("platform").is_empty());
+        
+        // Metadata terms are removed along with the engram
+        index.remove_engram(&engram).unwrap();
+        assert!(index.search("api_service").is_empty());
+        assert!(index.find_by_metadata_term("component", "service").is_empty());
+        
+        // Metadata is not searchable by default
+        let mut plain = TextIndex::new();
+        plain.add_engram(&engram).unwrap();
+        assert!(plain.search("api_service").is_empty());
+    }
+    
+    #[test]
+    fn test_text_index_language_detection() {
+        let mut index = TextIndex::new().with_language_detection(true);
+        
+        let engram = create_test_engram(
+            "e1",
+            "Les chercheurs observent des changements climatiques importants dans les montagnes depuis plusieurs années",
+            "research",
+            0.9,
+        );
+        index.add_engram(&engram).unwrap();
+        
+        // The French sentence is detected as French
+        assert_eq!(index.engram_language("e1"), Some("fra"));
+        assert!(index.find_by_language("fra").contains("e1"));
+        
+        // French stemming maps "changer" and "changements" to the same stem,
+        // which the English analyzer would not do
+        assert!(index.find_by_stem_in_language("changer", "fra").contains("e1"));
+        assert!(index.find_by_stem("changer").is_empty());
+        
+        // French stopwords are not indexed as keywords
+        assert!(index.find_by_keyword("les").is_empty());
+        
+        // Language is stored as metadata for filtering
+        let mut tagged = engram.clone();
+        assert_eq!(TextIndex::tag_language(&mut tagged), Some("fra".to_string()));
+        assert_eq!(tagged.metadata.get("language"), Some(&json!("fra")));
+        
+        index.remove_engram(&engram).unwrap();
+        assert!(index.find_by_language("fra").is_empty());
+        assert!(index.engram_language("e1").is_none());
+    }
+    
+    #[test]
+    fn test_search_filters_partial_results() {
+        let mut index = SearchIndex::new();
+        index.add_engram(&create_test_engram("e1", "Climate change research", "research", 0.9)).unwrap();
+        index.add_engram(&create_test_engram("e2", "Climate policy notes", "notes", 0.6)).unwrap();
+        
+        let filters = vec![
+            QueryFilter::new("text", |index: &SearchIndex| Ok(index.text_index.search("climate"))),
+            QueryFilter::new("broken", |_: &SearchIndex| {
+                Err(EngramError::SerializationError("corrupt metadata entry".to_string()))
+            }),
+            QueryFilter::new("source", |index: &SearchIndex| Ok(index.find_by_source("research"))),
+        ];
+        
+        // The failing filter is skipped and the remaining filters still apply
+        let result = index.apply_filters(&filters);
+        assert_eq!(result.ids.len(), 1);
+        assert!(result.ids.contains("e1"));
+        assert!(result.has_warnings());
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("broken"));
+        
+        // Combined queries take the same filters and skip failures the same way
+        let broken = QueryFilter::new("broken", |_: &SearchIndex| {
+            Err(EngramError::SerializationError("corrupt metadata entry".to_string()))
+        });
+        let result = CombinedQuery::new()
+            .all_of([SearchPredicate::Source("research".to_string())])
+            .none_of([broken])
+            .execute(&index);
+        assert_eq!(result.sorted_ids(), vec!["e1"]);
+        assert_eq!(result.warnings.len(), 1);
+        
+        // Queries without failures carry no warnings
+        let result = index.search_combined(Some("climate"), None, None, None, None, false, None, None);
+        assert_eq!(result.ids.len(), 2);
+        assert!(!result.has_warnings());
+    }
+    
+    #[test]
+    fn test_combined_query_any_all_none() {
+        let mut index = SearchIndex::new();
+        let mut e1 = create_test_engram("e1", "Climate model run", "research", 0.9);
+        e1.metadata.insert("status".to_string(), json!("failed"));
+        let mut e2 = create_test_engram("e2", "Climate model results", "notes", 0.8);
+        e2.metadata.insert("status".to_string(), json!("ok"));
+        let e3 = create_test_engram("e3", "Climate policy draft", "drafts", 0.7);
+        let e4 = create_test_engram("e4", "Ocean readings", "research", 0.6);
+        for engram in [&e1, &e2, &e3, &e4] {
+            index.add_engram(engram).unwrap();
+        }
+        
+        let ids = |query: CombinedQuery| query.execute(&index).sorted_ids();
+        let source = |name: &str| SearchPredicate::Source(name.to_string());
+        let climate = SearchPredicate::Text { query: "climate".to_string(), match_all: false };
+        let failed = SearchPredicate::Metadata { key: "status".to_string(), value: Some("failed".to_string()) };
+        
+        // Disjunction: source is research OR notes
+        assert_eq!(ids(CombinedQuery::new().any_of([source("research"), source("notes")])), vec!["e1", "e2", "e4"]);
+        
+        // Conjunction with a disjunction and a negation
+        let query = CombinedQuery::new()
+            .all_of([climate.clone()])
+            .any_of([source("research"), source("notes")])
+            .none_of([failed.clone()]);
+        assert_eq!(ids(query), vec!["e2"]);
+        
+        // Separate any_of groups must each match
+        let query = CombinedQuery::new()
+            .any_of([source("research"), source("drafts")])
+            .any_of([climate.clone()]);
+        assert_eq!(ids(query), vec!["e1", "e3"]);
+        
+        // Negation alone excludes from every indexed engram
+        assert_eq!(ids(CombinedQuery::new().none_of([failed])), vec!["e2", "e3", "e4"]);
+        
+        // search_combined is an all_of query, and matches nothing without criteria
+        let combined = index.search_combined(Some("climate"), Some("research"), None, None, None, false, None, None);
+        assert_eq!(combined.sorted_ids(), ids(CombinedQuery::new().all_of([climate, source("research")])));
+        assert!(index.search_combined(None, None, None, None, None, false, None, None).ids.is_empty());
+    }
+    
+    #[test]
+    fn test_search_index_snapshot_round_trip() {
+        let mut index = SearchIndex::new().with_searchable_metadata_keys(["component"]);
+        let mut e1 = create_test_engram("e1", "Climate change research in the Alps", "research", 0.9);
+        e1.metadata.insert("component".to_string(), json!("glacier_model"));
+        e1.set_importance(0.8);
+        let e2 = create_test_engram("e2", "Climate policy notes", "notes", 0.6);
+        let e3 = create_test_engram("e3", "Ocean temperature readings", "research", 0.75);
+        for engram in [&e1, &e2, &e3] {
+            index.add_engram(engram).unwrap();
+        }
+        index.add_connection(&create_test_connection("c1", "e1", "e2", "supports", 0.8)).unwrap();
+        index.add_connection(&create_test_connection("c2", "e2", "e3", "relates_to", 0.3)).unwrap();
+        
+        let path = std::env::temp_dir().join(format!("engram_index_snapshot_{}.bin", uuid::Uuid::new_v4()));
+        index.dump(&path).unwrap();
+        let restored = SearchIndex::restore(&path).unwrap();
+        
+        // The restored index answers queries identically, without a rebuild
+        assert_eq!(restored.text_index.search("climate"), index.text_index.search("climate"));
+        assert_eq!(restored.text_index.find_by_metadata_term("component", "glacier"), index.text_index.find_by_metadata_term("component", "glacier"));
+        assert_eq!(restored.find_by_source("research"), index.find_by_source("research"));
+        assert_eq!(restored.find_by_min_confidence(0.7), index.find_by_min_confidence(0.7));
+        assert_eq!(restored.get_most_important(3), index.get_most_important(3));
+        assert_eq!(restored.get_most_recent(3), index.get_most_recent(3));
+        assert_eq!(
+            restored.relationship_index.find_connections_by_weight_range(0.5, 1.0),
+            index.relationship_index.find_connections_by_weight_range(0.5, 1.0)
+        );
+        assert_eq!(
+            restored.relationship_index.find_paths(&"e1".to_string(), &"e3".to_string(), 3),
+            index.relationship_index.find_paths(&"e1".to_string(), &"e3".to_string(), 3)
+        );
+        assert_eq!(restored.metadata_index.find_by_key("component"), index.metadata_index.find_by_key("component"));
+        
+        // Files that are not snapshots are refused
+        std::fs::write(&path, b"not an index snapshot").unwrap();
+        assert!(SearchIndex::restore(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_parallel_rebuild_matches_sequential() {
+        let engrams: Vec<Engram> = (0..500)
+            .map(|i| {
+                let mut engram = create_test_engram(
+                    &format!("e{}", i),
+                    &format!("Engram {} about climate research near glacier {}", i, i % 7),
+                    if i % 2 == 0 { "research" } else { "notes" },
+                    (i % 10) as f64 / 10.0,
+                );
+                engram.metadata.insert("component".to_string(), json!(format!("module_{}", i % 5)));
+                engram.timestamp -= chrono::Duration::minutes(i % 90);
+                engram.set_importance((i % 11) as f64 / 10.0);
+                engram
+            })
+            .collect();
+        
+        let mut sequential = SearchIndex::new().with_recency_capacity(50).with_searchable_metadata_keys(["component"]);
+        for engram in &engrams {
+            sequential.add_engram(engram).unwrap();
+        }
+        
+        let mut parallel = SearchIndex::new().with_recency_capacity(50).with_searchable_metadata_keys(["component"]);
+        parallel.add_engrams_parallel(&engrams).unwrap();
+        
+        assert_eq!(parallel, sequential);
+        
+        // Re-adding indexed engrams falls back to sequential replacement and stays identical
+        parallel.add_engrams_parallel(&engrams[..10]).unwrap();
+        for engram in &engrams[..10] {
+            sequential.add_engram(engram).unwrap();
+        }
+        assert_eq!(parallel, sequential);
     }
-}
\ No newline at end of file
+}
@@ -14,6 +14,8 @@ pub mod demo;
 pub mod grpc;
 #[cfg(feature = "python")]
 pub mod python;
+#[cfg(feature = "onnx")]
+pub mod onnx_embedding;
 #[cfg(test)]
 mod schema_test;
 #[cfg(test)]
@@ -22,18 +24,20 @@ mod index_test;
 mod graph_test;
 
 // Re-export core types for convenience
-pub use schema::{Agent, Collection, Connection, Context, Engram};
-pub use storage::Storage;
+pub use schema::{Agent, Collection, Connection, Context, Engram, Provenance, SchemaPolicy};
+pub use storage::{Storage, StorageBatch, StorageConfig, CompressionType, BatchDeleteReport, ConnectionLimitPolicy, CreatedEntities, CollectionSnapshot, CollectionSummary, ConnectionSummary, EngramDetail, EngramExpansion, Page, PaginationMeta, SerializationFormat, HealthReport, UnreadableRecord, ChangeEvent, ChangeFeed, ChangeOperation, ChangeEntityType};
 pub use graph::MemoryGraph;
 pub use error::{EngramError, Result};
-pub use utils::{load_env_from_file, get_anthropic_api_key};
-pub use index::{RelationshipIndex, MetadataIndex, SearchIndex, CollectionIndex, TextIndex};
-pub use export::{export_to_file, import_from_file, export_collection_to_file, import_partial_from_file, ExportData};
-pub use query::{EngramQuery, RelationshipQuery, QueryEngine, TraversalEngine, QueryService, TraversalResult};
-pub use embedding::{Embedding, EmbeddingModel, EmbeddingService, EmbeddingCache, HnswIndex};
-pub use vector_search::{VectorIndex, VectorQuery, HybridQuery, HybridSearchEngine, HybridSearchResult, CombinationMethod};
+pub use utils::{load_env_from_file, get_anthropic_api_key, CorsPolicy};
+pub use index::{RelationshipIndex, MetadataIndex, SearchIndex, CollectionIndex, TextIndex, ScoringMode, QueryResult, QueryFilter, CombinedQuery, SearchPredicate, sorted_ids};
+pub use export::{export_to_file, import_from_file, export_collection_to_file, import_partial_from_file, export_context_bundle, import_context_bundle, ExportData};
+pub use query::{CancellationToken, EngramQuery, RelationshipQuery, QueryEngine, TraversalEngine, QueryService, TraversalResult, TraversalPath, EngramRef, ForgettingEvent, MAX_TRAVERSAL_DEPTH, recompute_importance_from_graph, explain_relationship, RelationshipExplanation, RetrievalWeights, ScoredEngram, rank_by_combined_score, dedupe_by_content};
+pub use embedding::{Embedding, EmbeddingModel, EmbeddingService, EmbeddingBackend, HuggingFaceBackend, DeterministicBackend, EmbeddingCache, HnswIndex, MmapHnswIndex, AutoTagger, SimilarityMetric};
+pub use vector_search::{VectorIndex, VectorQuery, HybridQuery, HybridSearchEngine, HybridSearchResult, CombinationMethod, ConsistencyReport, verify_vector_index, reconcile_vector_index, embed_missing, embed_missing_rate_limited, exact_knn_scan, RetrievedContext, ContextSource, assemble_context, estimate_tokens, QueryExpander, SimilarityMatrix, compute_similarity_matrix};
 pub use dimension_reduction::{DimensionReducer, ReductionMethod};
 pub use demo::populate_demo_data;
 
 #[cfg(feature = "grpc")]
-pub use grpc::server::GrpcServer;
\ No newline at end of file
+pub use grpc::server::GrpcServer;
+#[cfg(feature = "onnx")]
+pub use onnx_embedding::OnnxEmbeddingBackend;
\ No newline at end of file
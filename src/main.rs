@@ -1,15 +1,9 @@
-mod schema;
-mod storage;
-mod graph;
-mod error;
 mod benchmark;
-mod index;
-mod query;
 
-use crate::error::Result;
-use crate::graph::MemoryGraph;
-use crate::schema::{Agent, Collection, Connection, Context, Engram};
-use crate::storage::Storage;
+use engram_lite::error::{self, Result};
+use engram_lite::graph::MemoryGraph;
+use engram_lite::schema::{Agent, Collection, Connection, Context, Engram};
+use engram_lite::storage::Storage;
 use std::collections::HashSet;
 
 
@@ -193,13 +187,13 @@ fn run_demo() -> Result<()> {
 /// Run benchmarks for performance testing
 fn run_benchmarks() -> Result<()> {
     use crate::benchmark::run_all_benchmarks;
-    use crate::index::SearchIndex;
+    use engram_lite::index::SearchIndex;
     use tempfile::tempdir;
 
     println!("Running benchmarks...");
 
     // Create a temporary directory for the database
-    let dir = tempdir().map_err(|e| error::EngramError::IoError(e))?;
+    let dir = tempdir().map_err(error::EngramError::IoError)?;
     let db_path = dir.path().to_str().unwrap();
     
     // Initialize storage and index
@@ -218,7 +212,7 @@ fn run_benchmarks() -> Result<()> {
     }
     
     // Clean up temp directory
-    dir.close().map_err(|e| error::EngramError::IoError(e))?;
+    dir.close().map_err(error::EngramError::IoError)?;
     
     println!("\nBenchmarks completed successfully!");
     Ok(())
@@ -0,0 +1,238 @@
+use crate::embedding::EmbeddingBackend;
+use crate::error::{EngramError, Result};
+use ndarray::{Array2, Axis};
+use ort::{GraphOptimizationLevel, Session, ValueType};
+use std::path::Path;
+use tokenizers::Tokenizer;
+
+/// Maximum number of tokens fed to the model; longer texts are truncated
+const MAX_SEQUENCE_LENGTH: usize = 512;
+
+/// Embedding backend that runs a sentence-transformer model exported to ONNX locally,
+/// so embeddings can be generated without any network access
+pub struct OnnxEmbeddingBackend {
+    /// Model name reported on embeddings
+    name: String,
+    
+    /// ONNX Runtime session for the model
+    session: Session,
+    
+    /// Tokenizer matching the model (a HuggingFace `tokenizer.json`)
+    tokenizer: Tokenizer,
+    
+    /// Hidden size of the model
+    model_dimensions: usize,
+    
+    /// Length of the vectors returned; at most `model_dimensions`
+    dimensions: usize,
+    
+    /// Whether the model takes a `token_type_ids` input
+    uses_token_type_ids: bool,
+    
+    /// Prefix the model expects before passage text
+    instruction_prefix: Option<String>,
+}
+
+impl OnnxEmbeddingBackend {
+    /// Load a model and its tokenizer from local files. The output dimensionality is
+    /// read from the model's output shape, or from a probe embedding when the shape is dynamic.
+    pub fn load(name: &str, model_path: &Path, tokenizer_path: &Path) -> Result<Self> {
+        let session = Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| EngramError::ComputationError(format!("Failed to load ONNX model {}: {}", model_path.display(), e)))?;
+        
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path)
+            .map_err(|e| EngramError::SerializationError(format!("Failed to load tokenizer {}: {}", tokenizer_path.display(), e)))?;
+        tokenizer
+            .with_truncation(Some(tokenizers::TruncationParams {
+                max_length: MAX_SEQUENCE_LENGTH,
+                ..Default::default()
+            }))
+            .map_err(|e| EngramError::InvalidOperation(format!("Failed to configure tokenizer: {}", e)))?;
+        
+        let uses_token_type_ids = session.inputs.iter().any(|input| input.name == "token_type_ids");
+        
+        // E5 models expect passages to be marked as such
+        let instruction_prefix = if name.to_lowercase().contains("e5") {
+            Some("passage: ".to_string())
+        } else {
+            None
+        };
+        
+        let mut backend = Self {
+            name: name.to_string(),
+            session,
+            tokenizer,
+            model_dimensions: 0,
+            dimensions: 0,
+            uses_token_type_ids,
+            instruction_prefix,
+        };
+        
+        // Dynamic dimensions are declared as -1
+        let declared = backend.session.outputs.first()
+            .and_then(|output| match &output.output_type {
+                ValueType::Tensor { dimensions, .. } => dimensions.last().copied(),
+                _ => None,
+            });
+        let model_dimensions = match declared {
+            Some(d) if d > 0 => d as usize,
+            _ => backend.run("dimension probe")?.len(),
+        };
+        
+        if model_dimensions == 0 {
+            return Err(EngramError::InvalidState(format!(
+                "Could not determine the output dimensionality of {}", model_path.display()
+            )));
+        }
+        
+        backend.model_dimensions = model_dimensions;
+        backend.dimensions = model_dimensions;
+        Ok(backend)
+    }
+    
+    /// Return only the first `dimensions` components of each vector, for models trained
+    /// to keep truncated embeddings meaningful (Matryoshka-style), or to match a
+    /// configured dimensionality
+    pub fn with_output_dimensions(mut self, dimensions: usize) -> Result<Self> {
+        if dimensions == 0 || dimensions > self.model_dimensions {
+            return Err(EngramError::InvalidOperation(format!(
+                "Output dimensions must be between 1 and the model's {} dimensions, got {}",
+                self.model_dimensions, dimensions
+            )));
+        }
+        self.dimensions = dimensions;
+        Ok(self)
+    }
+    
+    /// Hidden size of the loaded model, regardless of any output truncation
+    pub fn model_dimensions(&self) -> usize {
+        self.model_dimensions
+    }
+    
+    /// Tokenize a text, run the model, and mean-pool the token embeddings
+    fn run(&self, text: &str) -> Result<Vec<f32>> {
+        let encoding = self.tokenizer.encode(text, true)
+            .map_err(|e| EngramError::ComputationError(format!("Failed to tokenize text: {}", e)))?;
+        
+        let len = encoding.get_ids().len();
+        let to_array = |values: &[u32]| -> Result<Array2<i64>> {
+            let values: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+            Array2::from_shape_vec((1, len), values)
+                .map_err(|e| EngramError::ComputationError(format!("Invalid input shape: {}", e)))
+        };
+        
+        let input_ids = to_array(encoding.get_ids())?;
+        let attention_mask = to_array(encoding.get_attention_mask())?;
+        
+        let inputs = if self.uses_token_type_ids {
+            let token_type_ids = to_array(encoding.get_type_ids())?;
+            ort::inputs![
+                "input_ids" => input_ids,
+                "attention_mask" => attention_mask,
+                "token_type_ids" => token_type_ids,
+            ]
+        } else {
+            ort::inputs![
+                "input_ids" => input_ids,
+                "attention_mask" => attention_mask,
+            ]
+        }
+        .map_err(|e| EngramError::ComputationError(format!("Failed to create ONNX input: {}", e)))?;
+        
+        let output_name = self.session.outputs.first()
+            .map(|output| output.name.clone())
+            .ok_or_else(|| EngramError::ComputationError("ONNX model declares no outputs".to_string()))?;
+        let outputs = self.session.run(inputs)
+            .map_err(|e| EngramError::ComputationError(format!("ONNX inference failed: {}", e)))?;
+        let output = outputs[output_name.as_str()]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| EngramError::ComputationError(format!("Unexpected ONNX output type: {}", e)))?;
+        
+        match output.ndim() {
+            // Already pooled: [batch, hidden]
+            2 => Ok(output.index_axis(Axis(0), 0).iter().copied().collect()),
+            // Token embeddings: [batch, tokens, hidden], averaged over attended tokens
+            3 => {
+                let tokens = output.index_axis(Axis(0), 0);
+                let mask = encoding.get_attention_mask();
+                let mut pooled = vec![0.0f32; tokens.shape()[1]];
+                let mut count = 0.0f32;
+                
+                for (token, &attended) in tokens.outer_iter().zip(mask.iter()) {
+                    if attended == 0 {
+                        continue;
+                    }
+                    for (sum, value) in pooled.iter_mut().zip(token.iter()) {
+                        *sum += value;
+                    }
+                    count += 1.0;
+                }
+                
+                if count > 0.0 {
+                    for sum in pooled.iter_mut() {
+                        *sum /= count;
+                    }
+                }
+                Ok(pooled)
+            }
+            n => Err(EngramError::ComputationError(format!("Unexpected ONNX output rank {}", n))),
+        }
+    }
+}
+
+impl EmbeddingBackend for OnnxEmbeddingBackend {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut vector = self.run(text)?;
+        if vector.len() != self.model_dimensions {
+            return Err(EngramError::ComputationError(format!(
+                "Model produced a {}-dimensional vector, expected {}",
+                vector.len(), self.model_dimensions
+            )));
+        }
+        vector.truncate(self.dimensions);
+        Ok(vector)
+    }
+    
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+    
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn instruction_prefix(&self) -> Option<&str> {
+        self.instruction_prefix.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    #[cfg(feature = "onnx-test")]  // Only run with explicit feature flag
+    fn test_onnx_backend_embedding_dimensions() {
+        // Run with ENGRAM_TEST_ONNX_MODEL pointing at a directory holding model.onnx and
+        // tokenizer.json: `cargo test --features onnx-test`
+        let dir = std::path::PathBuf::from(
+            std::env::var("ENGRAM_TEST_ONNX_MODEL").expect("onnx-test requires ENGRAM_TEST_ONNX_MODEL"),
+        );
+        
+        let backend = OnnxEmbeddingBackend::load(
+            "test-model",
+            &dir.join("model.onnx"),
+            &dir.join("tokenizer.json"),
+        ).unwrap();
+        
+        let vector = backend.embed("A small test sentence").unwrap();
+        assert_eq!(vector.len(), backend.dimensions());
+        assert!(vector.iter().any(|v| *v != 0.0));
+        
+        // Truncated output honors the requested dimensionality
+        let truncated = backend.with_output_dimensions(32).unwrap();
+        assert_eq!(truncated.embed("A small test sentence").unwrap().len(), 32);
+    }
+}
@@ -1,8 +1,67 @@
 use crate::error::{EngramError, Result};
-use crate::schema::{Engram, EngramId, Connection, ConnectionId};
-use crate::storage::Storage;
-use crate::index::SearchIndex;
-use std::collections::HashSet;
+use crate::schema::{CollectionId, Connection, ConnectionId, ContextId, Engram, EngramId};
+use crate::embedding::EmbeddingService;
+use crate::storage::{CompactionReport, Storage};
+use crate::index::{sorted_ids, SearchIndex};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default maximum number of results an unbounded query may return
+pub const DEFAULT_MAX_RESULT_SIZE: usize = 10_000;
+
+/// Deepest route search accepted by `TraversalEngine::find_routes`; path enumeration is
+/// exponential in depth, so callers (notably the web API) must stay under this cap
+pub const MAX_TRAVERSAL_DEPTH: usize = 6;
+
+/// Deadline and cancellation flag for a long-running query or traversal. Clones share the
+/// flag, so any clone can cancel the operation; engines check it at loop boundaries and
+/// abort with `EngramError::Timeout`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    /// Point after which the operation is abandoned, if any
+    deadline: Option<Instant>,
+    
+    /// Set by `cancel`
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token with no deadline that only trips when cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create a token that trips once `timeout` has elapsed from now
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            deadline: Some(Instant::now() + timeout),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    
+    /// Cancel the operation (and every operation sharing this token)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+    
+    /// Whether the token has been cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+    
+    /// Return `EngramError::Timeout` naming `operation` if the token has tripped
+    pub fn check(&self, operation: &str) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(EngramError::Timeout(format!("{} was cancelled or exceeded its deadline", operation)));
+        }
+        Ok(())
+    }
+}
 
 /// Represents filter conditions for querying engrams
 #[allow(dead_code)]
@@ -157,6 +216,12 @@ impl EngramQuery {
     }
 }
 
+impl Default for EngramQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Represents types of relationship queries
 #[allow(dead_code)]
 pub enum RelationshipQueryType {
@@ -189,6 +254,12 @@ pub struct RelationshipQuery {
     
     /// The type of relationship query
     pub query_type: RelationshipQueryType,
+    
+    /// Only return connections at least this confident that the relationship exists
+    pub min_confidence: Option<f64>,
+    
+    /// Rank connections by weight discounted by confidence instead of weight alone
+    pub score_by_confidence: bool,
 }
 
 #[allow(dead_code)]
@@ -198,6 +269,8 @@ impl RelationshipQuery {
         Self {
             engram_id,
             query_type: RelationshipQueryType::Outgoing,
+            min_confidence: None,
+            score_by_confidence: false,
         }
     }
     
@@ -206,6 +279,8 @@ impl RelationshipQuery {
         Self {
             engram_id,
             query_type: RelationshipQueryType::Incoming,
+            min_confidence: None,
+            score_by_confidence: false,
         }
     }
     
@@ -214,6 +289,8 @@ impl RelationshipQuery {
         Self {
             engram_id,
             query_type: RelationshipQueryType::Both,
+            min_confidence: None,
+            score_by_confidence: false,
         }
     }
     
@@ -222,6 +299,8 @@ impl RelationshipQuery {
         Self {
             engram_id,
             query_type: RelationshipQueryType::RelationshipType(relationship_type.into()),
+            min_confidence: None,
+            score_by_confidence: false,
         }
     }
     
@@ -233,10 +312,183 @@ impl RelationshipQuery {
                 target_id,
                 max_depth,
             },
+            min_confidence: None,
+            score_by_confidence: false,
         }
     }
+    
+    /// Only return connections with at least the given confidence
+    pub fn with_min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = Some(min_confidence);
+        self
+    }
+    
+    /// Rank connections by weight multiplied by confidence
+    pub fn with_confidence_scoring(mut self) -> Self {
+        self.score_by_confidence = true;
+        self
+    }
+}
+
+/// Default half-life of the recency component in combined scoring, in hours (one week)
+pub const DEFAULT_RECENCY_HALF_LIFE_HOURS: f64 = 168.0;
+
+/// Weights for combining relevance, importance and recency into a single retrieval score.
+/// The weights are relative; the combined score is their weighted average.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalWeights {
+    /// Weight of text or vector relevance to the query
+    pub relevance: f64,
+    
+    /// Weight of the engram's importance score
+    pub importance: f64,
+    
+    /// Weight of how recently the engram was created
+    pub recency: f64,
+    
+    /// Age in hours at which the recency component drops to 0.5
+    #[serde(default = "default_recency_half_life_hours")]
+    pub recency_half_life_hours: f64,
 }
 
+fn default_recency_half_life_hours() -> f64 {
+    DEFAULT_RECENCY_HALF_LIFE_HOURS
+}
+
+impl RetrievalWeights {
+    /// Create weights for relevance, importance and recency
+    pub fn new(relevance: f64, importance: f64, recency: f64) -> Self {
+        Self {
+            relevance,
+            importance,
+            recency,
+            recency_half_life_hours: DEFAULT_RECENCY_HALF_LIFE_HOURS,
+        }
+    }
+    
+    /// Set the half-life of the recency component
+    pub fn with_recency_half_life_hours(mut self, hours: f64) -> Self {
+        self.recency_half_life_hours = hours;
+        self
+    }
+    
+    /// Check that the weights are non-negative, not all zero, and the half-life is positive
+    pub fn validate(&self) -> Result<()> {
+        let weights = [self.relevance, self.importance, self.recency];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err(EngramError::InvalidOperation("Retrieval weights must be non-negative".to_string()));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(EngramError::InvalidOperation("At least one retrieval weight must be positive".to_string()));
+        }
+        if self.recency_half_life_hours.is_nan() || self.recency_half_life_hours <= 0.0 {
+            return Err(EngramError::InvalidOperation("Recency half-life must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RetrievalWeights {
+    fn default() -> Self {
+        Self::new(0.6, 0.25, 0.15)
+    }
+}
+
+/// An engram ranked by `RetrievalWeights`, with the components that make up its score
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoredEngram {
+    /// The engram
+    pub engram: Engram,
+    
+    /// Combined score in 0.0..=1.0
+    pub score: f64,
+    
+    /// Relevance to the query, normalized so the best candidate scores 1.0
+    pub relevance: f64,
+    
+    /// The engram's importance
+    pub importance: f64,
+    
+    /// Recency in 0.0..=1.0, halving every `recency_half_life_hours`
+    pub recency: f64,
+    
+    /// Number of lower-ranked results with the same content collapsed into this one by
+    /// `dedupe_by_content`
+    pub duplicate_count: usize,
+}
+
+/// Rank candidates by a weighted combination of relevance, importance and recency.
+/// `candidates` pairs each engram with a raw relevance score from any source (keyword
+/// matches, vector similarity, hybrid scores); relevance is normalized by the best candidate.
+/// Ties are broken by engram ID.
+pub fn rank_by_combined_score(
+    candidates: Vec<(Engram, f64)>,
+    weights: &RetrievalWeights,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<ScoredEngram>> {
+    weights.validate()?;
+    
+    let max_relevance = candidates.iter().map(|(_, r)| *r).fold(0.0f64, f64::max);
+    let total_weight = weights.relevance + weights.importance + weights.recency;
+    
+    let mut scored: Vec<ScoredEngram> = candidates
+        .into_iter()
+        .map(|(engram, raw_relevance)| {
+            let relevance = if max_relevance > 0.0 { (raw_relevance / max_relevance).clamp(0.0, 1.0) } else { 0.0 };
+            let importance = engram.importance.clamp(0.0, 1.0);
+            let age_hours = (now - engram.timestamp).num_seconds().max(0) as f64 / 3600.0;
+            let recency = 0.5f64.powf(age_hours / weights.recency_half_life_hours);
+            
+            let score = (weights.relevance * relevance
+                + weights.importance * importance
+                + weights.recency * recency) / total_weight;
+            
+            ScoredEngram { engram, score, relevance, importance, recency, duplicate_count: 0 }
+        })
+        .collect();
+    
+    scored.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.engram.id.cmp(&b.engram.id))
+    });
+    Ok(scored)
+}
+
+/// Collapse ranked results that share a content hash into the highest-ranked of them,
+/// counting the others in its `duplicate_count`. The order of the kept results is unchanged.
+pub fn dedupe_by_content(results: Vec<ScoredEngram>) -> Vec<ScoredEngram> {
+    let mut kept: Vec<ScoredEngram> = Vec::with_capacity(results.len());
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    
+    for result in results {
+        match positions.entry(result.engram.content_hash()) {
+            std::collections::hash_map::Entry::Occupied(entry) => kept[*entry.get()].duplicate_count += 1,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(kept.len());
+                kept.push(result);
+            },
+        }
+    }
+    
+    kept
+}
+
+/// Notification that engrams were removed by a forgetting policy
+#[derive(Debug, Clone)]
+pub struct ForgettingEvent {
+    /// IDs of the engrams that were forgotten
+    pub engram_ids: Vec<EngramId>,
+    
+    /// Why the engrams were forgotten (the name of the policy that selected them)
+    pub reason: String,
+    
+    /// When the engrams were forgotten
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Callback invoked after a forgetting pass removes engrams
+pub type ForgettingObserver<'a> = Box<dyn Fn(&ForgettingEvent) + 'a>;
+
 /// Engine for executing queries against the memory graph
 #[allow(dead_code)]
 pub struct QueryEngine<'a> {
@@ -248,6 +500,18 @@ pub struct QueryEngine<'a> {
     
     /// The current forgetting policy
     forgetting_policy: Option<crate::index::ForgettingPolicy>,
+    
+    /// Maximum number of results a query without a limit may return
+    max_result_size: usize,
+    
+    /// Callbacks notified when engrams are forgotten
+    forgetting_observers: Vec<ForgettingObserver<'a>>,
+    
+    /// Deadline/cancellation checked while processing candidates
+    cancellation: CancellationToken,
+    
+    /// Warnings for filters that failed and were skipped by queries since the last `take_warnings`
+    warnings: Mutex<Vec<String>>,
 }
 
 #[allow(dead_code)]
@@ -258,9 +522,39 @@ impl<'a> QueryEngine<'a> {
             storage, 
             index,
             forgetting_policy: None,
+            max_result_size: DEFAULT_MAX_RESULT_SIZE,
+            forgetting_observers: Vec::new(),
+            cancellation: CancellationToken::new(),
+            warnings: Mutex::new(Vec::new()),
         }
     }
     
+    /// Register a callback to be notified with the IDs of forgotten engrams
+    pub fn add_forgetting_observer(&mut self, observer: ForgettingObserver<'a>) {
+        self.forgetting_observers.push(observer);
+    }
+    
+    /// Take the warnings recorded by queries since the last call, e.g. for a filter that failed
+    /// and was skipped
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+    
+    /// Set the maximum number of results a query without a limit may return
+    pub fn set_max_result_size(&mut self, max_result_size: usize) {
+        self.max_result_size = max_result_size;
+    }
+    
+    /// Get the maximum number of results a query without a limit may return
+    pub fn get_max_result_size(&self) -> usize {
+        self.max_result_size
+    }
+    
+    /// Abort queries with `EngramError::Timeout` once this token trips
+    pub fn set_cancellation(&mut self, cancellation: CancellationToken) {
+        self.cancellation = cancellation;
+    }
+    
     /// Set the forgetting policy
     pub fn set_forgetting_policy(&mut self, policy: Option<crate::index::ForgettingPolicy>) {
         self.forgetting_policy = policy;
@@ -329,6 +623,20 @@ impl<'a> QueryEngine<'a> {
         Ok(())
     }
     
+    /// Pin or unpin an engram in storage. The caller updates the search index with
+    /// `SearchIndex::set_pinned`, as the engine only holds it immutably.
+    /// Returns false if the engram does not exist.
+    pub fn set_pinned(&mut self, id: &EngramId, pinned: bool) -> Result<bool> {
+        match self.storage.get_engram(id)? {
+            Some(mut engram) => {
+                engram.set_pinned(pinned);
+                self.storage.put_engram(&engram)?;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+    
     /// Get forgetting candidates based on the current policy
     pub fn get_forgetting_candidates(&self) -> Result<Vec<Engram>> {
         if let Some(policy) = &self.forgetting_policy {
@@ -349,28 +657,127 @@ impl<'a> QueryEngine<'a> {
         }
     }
     
+    /// Get forgetting candidates with the reason each was selected, for dry runs
+    pub fn explain_forgetting_candidates(&self) -> Vec<crate::index::ForgettingCandidate> {
+        self.forgetting_policy
+            .as_ref()
+            .map(|policy| policy.explain_forgetting_candidates(self.index))
+            .unwrap_or_default()
+    }
+    
     /// Apply forgetting by removing the engrams selected by the policy
     pub fn apply_forgetting(&self) -> Result<usize> {
+        Ok(self.forget_candidates()?.len())
+    }
+    
+    /// Apply forgetting, then compact the deleted key ranges to reclaim their space promptly.
+    /// Returns the number of forgotten engrams and the before/after size estimates.
+    pub fn apply_forgetting_with_compaction(&self) -> Result<(usize, CompactionReport)> {
+        let forgotten_ids = self.forget_candidates()?;
+        let report = self.storage.compact_deleted_engrams(&forgotten_ids)?;
+        Ok((forgotten_ids.len(), report))
+    }
+    
+    /// Delete the policy's candidates, notify observers, and return the forgotten IDs
+    fn forget_candidates(&self) -> Result<Vec<EngramId>> {
         // Get forgetting candidates
-        let candidates = self.get_forgetting_candidates()?;
-        
-        // Count of successfully forgotten engrams
-        let mut forgotten_count = 0;
-        
-        // Remove each candidate
-        for engram in candidates {
-            if self.storage.delete_engram(&engram.id).is_ok() {
-                forgotten_count += 1;
+        let candidate_ids: Vec<EngramId> = self.get_forgetting_candidates()?
+            .into_iter()
+            .map(|engram| engram.id)
+            .collect();
+        
+        // Remove every candidate and its connections in a single write, so a large pass
+        // neither pays a write per engram nor leaves dangling connections behind
+        let report = self.storage.delete_engrams(&candidate_ids, true)?;
+        let forgotten_ids: Vec<EngramId> = report.engrams.into_iter().map(|engram| engram.id).collect();
+        
+        // Notify observers once per forgetting pass
+        if !forgotten_ids.is_empty() && !self.forgetting_observers.is_empty() {
+            let event = ForgettingEvent {
+                engram_ids: forgotten_ids.clone(),
+                reason: self.forgetting_policy
+                    .as_ref()
+                    .map(|policy| policy.name().to_string())
+                    .unwrap_or_default(),
+                timestamp: chrono::Utc::now(),
+            };
+            
+            for observer in &self.forgetting_observers {
+                observer(&event);
             }
         }
         
-        Ok(forgotten_count)
+        Ok(forgotten_ids)
     }
     
     /// Execute an engram query and return matching engrams
     pub fn query_engrams(&self, query: &EngramQuery) -> Result<Vec<Engram>> {
+        let mut engrams = self.matching_engrams(query, query.limit)?;
+        
+        // Sort by time or confidence, breaking ties by ID so the order (and which engrams
+        // survive the limit) is the same on every run
+        if query.sort_by_recency {
+            // Sort by timestamp (newest first)
+            engrams.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.id.cmp(&b.id)));
+        } else {
+            // Sort by confidence (highest first)
+            engrams.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.id.cmp(&b.id)));
+        }
+        
+        // Apply limit if specified
+        if let Some(limit) = query.limit {
+            if engrams.len() > limit {
+                engrams.truncate(limit);
+            }
+        }
+        
+        Ok(engrams)
+    }
+    
+    /// Execute an engram query and rank the matches by a weighted combination of text
+    /// relevance, importance and recency. Text relevance is the fraction of query keywords
+    /// an engram matches; without a text query every match is equally relevant.
+    /// The limit is applied after ranking, so it doesn't lift the `max_result_size` cap.
+    pub fn query_engrams_scored(&self, query: &EngramQuery, weights: &RetrievalWeights) -> Result<Vec<ScoredEngram>> {
+        weights.validate()?;
+        let engrams = self.matching_engrams(query, None)?;
+        
+        let mut keyword_hits: HashMap<EngramId, usize> = HashMap::new();
+        let mut keyword_count = 0;
+        if let Some(text) = &query.text {
+            for keyword in crate::index::TextIndex::extract_keywords(text) {
+                keyword_count += 1;
+                for id in self.index.text_index.search(&keyword) {
+                    *keyword_hits.entry(id).or_insert(0) += 1;
+                }
+            }
+        }
+        
+        let candidates = engrams
+            .into_iter()
+            .map(|engram| {
+                let relevance = if keyword_count == 0 {
+                    1.0
+                } else {
+                    keyword_hits.get(&engram.id).copied().unwrap_or(0) as f64 / keyword_count as f64
+                };
+                (engram, relevance)
+            })
+            .collect();
+        
+        let mut scored = rank_by_combined_score(candidates, weights, chrono::Utc::now())?;
+        if let Some(limit) = query.limit {
+            scored.truncate(limit);
+        }
+        Ok(scored)
+    }
+    
+    /// Engrams matching the query's filters, unsorted. With `limit`, only the engrams that can
+    /// survive `query_engrams`' sort and limit are fetched; without one, matches beyond the
+    /// `max_result_size` cap are refused.
+    fn matching_engrams(&self, query: &EngramQuery, limit: Option<usize>) -> Result<Vec<Engram>> {
         // Process basic search parameters using the combined search
-        let mut engram_ids = self.index.search_combined(
+        let result = self.index.search_combined(
             query.text.as_deref(),
             query.source.as_deref(),
             query.min_confidence,
@@ -380,6 +787,11 @@ impl<'a> QueryEngine<'a> {
             query.before.as_ref(),
             query.after.as_ref(),
         );
+        for warning in &result.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        self.warnings.lock().unwrap().extend(result.warnings);
+        let mut engram_ids = result.ids;
         
         // Process additional temporal filters if not already covered by before/after
         if query.before.is_none() && query.after.is_none() {
@@ -409,47 +821,61 @@ impl<'a> QueryEngine<'a> {
         }
         
         // If we have no results from filtering, return empty
-        if engram_ids.is_empty() && query.text.is_some() || query.source.is_some() 
+        if engram_ids.is_empty() && (query.text.is_some() || query.source.is_some() 
             || query.min_confidence.is_some() || query.metadata_key.is_some() 
-            || query.before.is_some() || query.after.is_some() || query.year.is_some() {
+            || query.before.is_some() || query.after.is_some() || query.year.is_some()) {
             return Ok(Vec::new());
         }
         
         // If no filtering was applied, get all engrams
         if engram_ids.is_empty() {
             // Just use a few recent engrams to avoid overwhelming the response
-            return Ok(self.get_most_recent_engrams(100)?);
+            return self.get_most_recent_engrams(100);
         }
         
+        // Refuse to materialize oversized result sets unless the caller bounded the query
+        let ids = match limit {
+            Some(limit) => self.limit_candidates(engram_ids, query.sort_by_recency, limit),
+            None if engram_ids.len() > self.max_result_size => {
+                return Err(EngramError::ResultTooLarge(engram_ids.len(), self.max_result_size));
+            }
+            None => sorted_ids(engram_ids),
+        };
+        
         // Fetch the matching engrams
         let mut engrams = Vec::new();
-        for id in engram_ids {
+        for id in ids {
+            self.cancellation.check("Engram query")?;
             if let Some(engram) = self.storage.get_engram(&id)? {
                 engrams.push(engram);
             }
         }
         
-        // Sort by time or confidence
-        if query.sort_by_recency {
-            // Sort by timestamp (newest first or oldest first)
-            if query.sort_by_recency {
-                engrams.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-            } else {
-                engrams.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-            }
-        } else {
-            // Sort by confidence (highest first)
-            engrams.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+        Ok(engrams)
+    }
+    
+    /// The IDs that can be among the first `limit` engrams once sorted by recency or confidence.
+    /// Timestamps are exact in the index, but confidence is only bucketed, so every engram in
+    /// the bucket at the cut-off is kept for the exact sort after fetching.
+    fn limit_candidates(&self, ids: HashSet<EngramId>, by_recency: bool, limit: usize) -> Vec<EngramId> {
+        let mut ids = sorted_ids(ids);
+        if ids.len() <= limit {
+            return ids;
         }
-        
-        // Apply limit if specified
-        if let Some(limit) = query.limit {
-            if engrams.len() > limit {
-                engrams.truncate(limit);
-            }
+        if limit == 0 {
+            return Vec::new();
         }
         
-        Ok(engrams)
+        // The sorts are stable, so ties stay in ID order as in `query_engrams`
+        if by_recency {
+            ids.sort_by_key(|id| std::cmp::Reverse(self.index.get_timestamp(id)));
+            ids.truncate(limit);
+        } else {
+            ids.sort_by_key(|id| std::cmp::Reverse(self.index.get_confidence_bucket(id)));
+            let cutoff = self.index.get_confidence_bucket(&ids[limit - 1]);
+            ids.retain(|id| self.index.get_confidence_bucket(id) >= cutoff);
+        }
+        ids
     }
     
     /// Get the most recent engrams
@@ -505,16 +931,27 @@ impl<'a> QueryEngine<'a> {
             }
         };
         
+        // Drop connections the caller is not confident enough in
+        let connection_ids = match query.min_confidence {
+            Some(min_confidence) => {
+                let confident = self.index.relationship_index.find_connections_by_min_confidence(min_confidence);
+                connection_ids.intersection(&confident).cloned().collect()
+            },
+            None => connection_ids,
+        };
+        
         let mut connections = Vec::new();
         
         for id in connection_ids {
+            self.cancellation.check("Relationship query")?;
             if let Some(connection) = self.storage.get_connection(&id)? {
                 connections.push(connection);
             }
         }
         
-        // Sort by weight (highest first)
-        connections.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap());
+        // Sort by score (highest first): weight, optionally discounted by confidence
+        let use_confidence = query.score_by_confidence;
+        connections.sort_by(|a, b| b.score(use_confidence).partial_cmp(&a.score(use_confidence)).unwrap());
         
         Ok(connections)
     }
@@ -537,15 +974,94 @@ impl<'a> QueryEngine<'a> {
     }
 }
 
+/// A lightweight handle to an engram that loads its content from storage only when accessed
+pub struct EngramRef<'a> {
+    /// The ID of the referenced engram
+    id: EngramId,
+    
+    /// The storage backend to resolve from
+    storage: &'a Storage,
+    
+    /// The resolved engram (None inside the cell if the engram no longer exists)
+    resolved: OnceCell<Option<Engram>>,
+}
+
+impl<'a> EngramRef<'a> {
+    /// Create a new unresolved reference
+    pub fn new(id: EngramId, storage: &'a Storage) -> Self {
+        Self {
+            id,
+            storage,
+            resolved: OnceCell::new(),
+        }
+    }
+    
+    /// Get the ID of the referenced engram without loading it
+    pub fn id(&self) -> &EngramId {
+        &self.id
+    }
+    
+    /// Check whether the engram has already been loaded from storage
+    pub fn is_resolved(&self) -> bool {
+        self.resolved.get().is_some()
+    }
+    
+    /// Load the engram from storage on first access and return it.
+    /// Returns None if the engram has been deleted since the reference was created.
+    pub fn resolve(&self) -> Result<Option<&Engram>> {
+        if let Some(engram) = self.resolved.get() {
+            return Ok(engram.as_ref());
+        }
+        
+        let engram = self.storage.get_engram(&self.id)?;
+        Ok(self.resolved.get_or_init(|| engram).as_ref())
+    }
+    
+    /// Resolve a batch of references, skipping engrams that no longer exist. The unresolved
+    /// references are loaded with one bulk read from the storage of the first of them, so
+    /// they should come from the same storage, as those of one traversal do.
+    pub fn resolve_batch<'r>(refs: &'r [EngramRef<'a>]) -> Result<Vec<&'r Engram>> {
+        let unresolved: Vec<&EngramRef<'a>> = refs.iter().filter(|engram_ref| !engram_ref.is_resolved()).collect();
+        if let Some(first) = unresolved.first() {
+            let ids: Vec<EngramId> = unresolved.iter().map(|engram_ref| engram_ref.id.clone()).collect();
+            let loaded = first.storage.get_engrams(&ids)?;
+            for (engram_ref, engram) in unresolved.into_iter().zip(loaded) {
+                engram_ref.resolved.get_or_init(|| engram);
+            }
+        }
+        
+        Ok(refs.iter().filter_map(|engram_ref| engram_ref.resolved.get().and_then(Option::as_ref)).collect())
+    }
+}
+
+impl std::fmt::Debug for EngramRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EngramRef")
+            .field("id", &self.id)
+            .field("resolved", &self.is_resolved())
+            .finish()
+    }
+}
+
 /// Result of a graph traversal operation
-pub struct TraversalResult {
-    /// The engrams found during traversal
-    pub engrams: Vec<Engram>,
+pub struct TraversalResult<'a> {
+    /// References to the engrams found during traversal, resolved lazily
+    pub engrams: Vec<EngramRef<'a>>,
     
     /// The connections traversed
     pub connections: Vec<Connection>,
 }
 
+/// A route between two engrams found by `TraversalEngine::find_routes`
+#[derive(Debug, Clone, Serialize)]
+pub struct TraversalPath {
+    /// The engrams along the route, from source to target
+    pub engram_ids: Vec<EngramId>,
+    
+    /// The connection followed at each hop (`engram_ids[i]` -> `engram_ids[i + 1]`)
+    pub connections: Vec<Connection>,
+}
+
 /// Engine for graph traversal operations
 pub struct TraversalEngine<'a> {
     /// The storage backend
@@ -553,12 +1069,20 @@ pub struct TraversalEngine<'a> {
     
     /// The search index
     index: &'a SearchIndex,
+    
+    /// Deadline/cancellation checked at each step of a traversal
+    cancellation: CancellationToken,
 }
 
 impl<'a> TraversalEngine<'a> {
     /// Create a new traversal engine
     pub fn new(storage: &'a Storage, index: &'a SearchIndex) -> Self {
-        Self { storage, index }
+        Self { storage, index, cancellation: CancellationToken::new() }
+    }
+    
+    /// Abort traversals with `EngramError::Timeout` once this token trips
+    pub fn set_cancellation(&mut self, cancellation: CancellationToken) {
+        self.cancellation = cancellation;
     }
     
     /// Find all engrams connected to the specified engram, up to max_depth
@@ -567,7 +1091,7 @@ impl<'a> TraversalEngine<'a> {
         engram_id: &EngramId,
         max_depth: usize,
         relationship_type: Option<&str>,
-    ) -> Result<TraversalResult> {
+    ) -> Result<TraversalResult<'a>> {
         let mut visited_engrams = HashSet::new();
         let mut visited_connections = HashSet::new();
         
@@ -579,16 +1103,15 @@ impl<'a> TraversalEngine<'a> {
             &mut visited_connections,
         )?;
         
-        let mut engrams = Vec::new();
+        // Engram content is only loaded when the caller resolves the references.
+        // Both lists are sorted by ID so identical traversals return identical results.
+        let engrams = sorted_ids(visited_engrams)
+            .into_iter()
+            .map(|id| EngramRef::new(id, self.storage))
+            .collect();
         let mut connections = Vec::new();
         
-        for id in visited_engrams {
-            if let Some(engram) = self.storage.get_engram(&id)? {
-                engrams.push(engram);
-            }
-        }
-        
-        for id in visited_connections {
+        for id in sorted_ids(visited_connections) {
             if let Some(connection) = self.storage.get_connection(&id)? {
                 connections.push(connection);
             }
@@ -600,6 +1123,189 @@ impl<'a> TraversalEngine<'a> {
         })
     }
     
+    /// Find every cycle-free route of at most `max_depth` hops from `source_id` to
+    /// `target_id`, following only outgoing connections whose type is in
+    /// `relationship_types` (any type when empty). Routes are returned shortest first;
+    /// an unreachable target yields an empty list.
+    pub fn find_routes(
+        &self,
+        source_id: &EngramId,
+        target_id: &EngramId,
+        max_depth: usize,
+        relationship_types: &[String],
+    ) -> Result<Vec<TraversalPath>> {
+        if max_depth > MAX_TRAVERSAL_DEPTH {
+            return Err(EngramError::InvalidOperation(format!(
+                "max_depth {} exceeds the traversal limit of {}",
+                max_depth, MAX_TRAVERSAL_DEPTH
+            )));
+        }
+        
+        for id in [source_id, target_id] {
+            if self.storage.get_engram(id)?.is_none() {
+                return Err(EngramError::NotFound(format!("Engram not found: {}", id)));
+            }
+        }
+        
+        let mut routes = Vec::new();
+        let mut current = TraversalPath {
+            engram_ids: vec![source_id.clone()],
+            connections: Vec::new(),
+        };
+        
+        self.collect_routes(target_id, max_depth, relationship_types, &mut current, &mut routes)?;
+        
+        routes.sort_by_key(|route| route.connections.len());
+        
+        Ok(routes)
+    }
+    
+    /// Depth-first helper for `find_routes`, extending `current` one connection at a time
+    fn collect_routes(
+        &self,
+        target_id: &EngramId,
+        depth_left: usize,
+        relationship_types: &[String],
+        current: &mut TraversalPath,
+        routes: &mut Vec<TraversalPath>,
+    ) -> Result<()> {
+        self.cancellation.check("Route search")?;
+        
+        let engram_id = current.engram_ids.last().unwrap().clone();
+        
+        // Base case: we've reached the target
+        if engram_id == *target_id {
+            routes.push(current.clone());
+            return Ok(());
+        }
+        
+        // Base case: we've reached max depth
+        if depth_left == 0 {
+            return Ok(());
+        }
+        
+        // Visit connections in a stable order so equal-length routes come back deterministically
+        let mut connection_ids: Vec<ConnectionId> = self.index.relationship_index
+            .get_outgoing_connections(&engram_id)
+            .into_iter()
+            .collect();
+        connection_ids.sort();
+        
+        for connection_id in connection_ids {
+            let connection = match self.storage.get_connection(&connection_id)? {
+                Some(connection) => connection,
+                None => continue,
+            };
+            
+            if !relationship_types.is_empty() && !relationship_types.contains(&connection.relationship_type) {
+                continue;
+            }
+            
+            // Avoid cycles in the route
+            if current.engram_ids.contains(&connection.target_id) {
+                continue;
+            }
+            
+            current.engram_ids.push(connection.target_id.clone());
+            current.connections.push(connection);
+            
+            self.collect_routes(target_id, depth_left - 1, relationship_types, current, routes)?;
+            
+            current.engram_ids.pop();
+            current.connections.pop();
+        }
+        
+        Ok(())
+    }
+    
+    /// Find chains starting at `start_id` that follow the relationship types in
+    /// `pattern` hop by hop, e.g. `["influences", "implements"]` for
+    /// requirement -> design -> implementation. Each returned path has exactly
+    /// `pattern.len()` connections and never revisits an engram. Paths come back in
+    /// ID order, stopping once `max_results` have been found.
+    pub fn find_meta_paths(
+        &self,
+        start_id: &EngramId,
+        pattern: &[String],
+        max_results: usize,
+    ) -> Result<Vec<TraversalPath>> {
+        if pattern.is_empty() {
+            return Err(EngramError::InvalidOperation(
+                "Meta-path pattern must name at least one relationship type".to_string()
+            ));
+        }
+        
+        if pattern.len() > MAX_TRAVERSAL_DEPTH {
+            return Err(EngramError::InvalidOperation(format!(
+                "Meta-path of {} hops exceeds the traversal limit of {}",
+                pattern.len(), MAX_TRAVERSAL_DEPTH
+            )));
+        }
+        
+        if self.storage.get_engram(start_id)?.is_none() {
+            return Err(EngramError::NotFound(format!("Engram not found: {}", start_id)));
+        }
+        
+        let mut paths = Vec::new();
+        let mut current = TraversalPath {
+            engram_ids: vec![start_id.clone()],
+            connections: Vec::new(),
+        };
+        
+        self.collect_meta_paths(pattern, max_results, &mut current, &mut paths)?;
+        
+        Ok(paths)
+    }
+    
+    /// Depth-first helper for `find_meta_paths`, matching `pattern[hop]` at each step
+    fn collect_meta_paths(
+        &self,
+        pattern: &[String],
+        max_results: usize,
+        current: &mut TraversalPath,
+        paths: &mut Vec<TraversalPath>,
+    ) -> Result<()> {
+        self.cancellation.check("Meta-path search")?;
+        
+        if paths.len() >= max_results {
+            return Ok(());
+        }
+        
+        let hop = current.connections.len();
+        if hop == pattern.len() {
+            paths.push(current.clone());
+            return Ok(());
+        }
+        
+        let engram_id = current.engram_ids.last().unwrap().clone();
+        let connection_ids = self.index.relationship_index.find_by_source_and_type(&engram_id, &pattern[hop]);
+        
+        for connection_id in sorted_ids(connection_ids) {
+            let connection = match self.storage.get_connection(&connection_id)? {
+                Some(connection) => connection,
+                None => continue,
+            };
+            
+            if current.engram_ids.contains(&connection.target_id) {
+                continue;
+            }
+            
+            current.engram_ids.push(connection.target_id.clone());
+            current.connections.push(connection);
+            
+            self.collect_meta_paths(pattern, max_results, current, paths)?;
+            
+            current.engram_ids.pop();
+            current.connections.pop();
+            
+            if paths.len() >= max_results {
+                break;
+            }
+        }
+        
+        Ok(())
+    }
+    
     /// Recursive helper method for traversing outgoing connections
     fn traverse_outgoing(
         &self,
@@ -609,6 +1315,8 @@ impl<'a> TraversalEngine<'a> {
         visited_engrams: &mut HashSet<EngramId>,
         visited_connections: &mut HashSet<ConnectionId>,
     ) -> Result<()> {
+        self.cancellation.check("Traversal")?;
+        
         // Mark this engram as visited
         visited_engrams.insert(engram_id.clone());
         
@@ -624,8 +1332,9 @@ impl<'a> TraversalEngine<'a> {
             self.index.relationship_index.get_outgoing_connections(engram_id)
         };
         
-        // Process each connection
-        for connection_id in connections {
+        // Process each connection in a stable order, so which engrams are reached first
+        // (and so within the depth limit) doesn't vary between runs
+        for connection_id in sorted_ids(connections) {
             if visited_connections.contains(&connection_id) {
                 continue;
             }
@@ -650,6 +1359,225 @@ impl<'a> TraversalEngine<'a> {
     }
 }
 
+/// Evidence that two engrams are related, gathered by `explain_relationship`
+#[derive(Debug, Clone, Serialize)]
+pub struct RelationshipExplanation {
+    /// The first engram
+    pub source_id: EngramId,
+    
+    /// The second engram
+    pub target_id: EngramId,
+    
+    /// Connections directly linking the two engrams, in either direction
+    pub direct_connections: Vec<Connection>,
+    
+    /// The shortest route along connections, from source to target if one exists and otherwise
+    /// from target to source, within `MAX_TRAVERSAL_DEPTH` hops
+    pub shortest_path: Option<TraversalPath>,
+    
+    /// Collections containing both engrams
+    pub shared_collections: Vec<CollectionId>,
+    
+    /// Contexts containing both engrams
+    pub shared_contexts: Vec<ContextId>,
+    
+    /// Metadata keys present on both engrams
+    pub shared_metadata_keys: Vec<String>,
+    
+    /// Similarity of the stored embeddings, if both engrams have one
+    pub embedding_similarity: Option<f32>,
+}
+
+/// Explain why two engrams are related: direct connections, the shortest path between them,
+/// shared collections, contexts and metadata keys, and embedding similarity (measured with
+/// `service`'s metric). Returns `NotFound` if either engram does not exist.
+pub fn explain_relationship(
+    source_id: &EngramId,
+    target_id: &EngramId,
+    storage: &Storage,
+    service: &EmbeddingService,
+) -> Result<RelationshipExplanation> {
+    let mut engrams = Vec::with_capacity(2);
+    for id in [source_id, target_id] {
+        match storage.get_engram(id)? {
+            Some(engram) => engrams.push(engram),
+            None => return Err(EngramError::NotFound(format!("Engram not found: {}", id))),
+        }
+    }
+    
+    let mut direct_connections = Vec::new();
+    let mut connection_ids: Vec<ConnectionId> = storage.find_connections_for_engram(source_id)?.into_iter().collect();
+    connection_ids.sort();
+    for id in connection_ids {
+        if let Some(connection) = storage.get_connection(&id)? {
+            let links = (&connection.source_id == source_id && &connection.target_id == target_id)
+                || (&connection.source_id == target_id && &connection.target_id == source_id);
+            if links {
+                direct_connections.push(connection);
+            }
+        }
+    }
+    
+    let shortest_path = match shortest_route(storage, source_id, target_id)? {
+        Some(path) => Some(path),
+        None => shortest_route(storage, target_id, source_id)?,
+    };
+    
+    let shared = |a: HashSet<String>, b: HashSet<String>| {
+        let mut shared: Vec<String> = a.intersection(&b).cloned().collect();
+        shared.sort();
+        shared
+    };
+    let shared_collections = shared(storage.collections_for_engram(source_id)?, storage.collections_for_engram(target_id)?);
+    let shared_contexts = shared(storage.contexts_for_engram(source_id)?, storage.contexts_for_engram(target_id)?);
+    let shared_metadata_keys = shared(
+        engrams[0].metadata.keys().cloned().collect(),
+        engrams[1].metadata.keys().cloned().collect(),
+    );
+    
+    let embedding_similarity = match (storage.get_embedding(source_id)?, storage.get_embedding(target_id)?) {
+        (Some(a), Some(b)) => {
+            // Manual conversion from storage::Embedding to embedding::Embedding
+            let a = crate::embedding::Embedding { vector: a.vector, model: a.model, dimensions: a.dimensions, metadata: a.metadata };
+            let b = crate::embedding::Embedding { vector: b.vector, model: b.model, dimensions: b.dimensions, metadata: b.metadata };
+            Some(service.similarity(&a, &b)?)
+        },
+        _ => None,
+    };
+    
+    Ok(RelationshipExplanation {
+        source_id: source_id.clone(),
+        target_id: target_id.clone(),
+        direct_connections,
+        shortest_path,
+        shared_collections,
+        shared_contexts,
+        shared_metadata_keys,
+        embedding_similarity,
+    })
+}
+
+/// Breadth-first search along outgoing connections for the fewest-hop route from `from` to `to`
+fn shortest_route(storage: &Storage, from: &EngramId, to: &EngramId) -> Result<Option<TraversalPath>> {
+    let mut previous: std::collections::HashMap<EngramId, Connection> = std::collections::HashMap::new();
+    let mut visited = HashSet::from([from.clone()]);
+    let mut frontier = vec![from.clone()];
+    
+    for _ in 0..MAX_TRAVERSAL_DEPTH {
+        if visited.contains(to) || frontier.is_empty() {
+            break;
+        }
+        
+        let mut next = Vec::new();
+        for engram_id in frontier {
+            let mut connection_ids: Vec<ConnectionId> = storage.find_outgoing_connections(&engram_id)?.into_iter().collect();
+            connection_ids.sort();
+            for id in connection_ids {
+                if let Some(connection) = storage.get_connection(&id)? {
+                    if visited.insert(connection.target_id.clone()) {
+                        next.push(connection.target_id.clone());
+                        previous.insert(connection.target_id.clone(), connection);
+                    }
+                }
+            }
+        }
+        frontier = next;
+    }
+    
+    if from == to || !previous.contains_key(to) {
+        return Ok(None);
+    }
+    
+    // Walk back from the target to rebuild the route
+    let mut connections = Vec::new();
+    let mut current = to.clone();
+    while let Some(connection) = previous.remove(&current) {
+        current = connection.source_id.clone();
+        connections.push(connection);
+    }
+    connections.reverse();
+    
+    let mut engram_ids = vec![from.clone()];
+    engram_ids.extend(connections.iter().map(|connection| connection.target_id.clone()));
+    
+    Ok(Some(TraversalPath { engram_ids, connections }))
+}
+
+/// Damping factor for the PageRank pass in `recompute_importance_from_graph`
+const PAGERANK_DAMPING: f64 = 0.85;
+
+/// Number of PageRank iterations; plenty for the graph sizes EngramAI Lite targets
+const PAGERANK_ITERATIONS: usize = 30;
+
+/// Blend each engram's importance with its structural centrality and store the result.
+///
+/// Centrality is PageRank over the connection graph treated as undirected, so hubs rank
+/// highly whichever way their connections point. Scores are normalized so the most central
+/// engram scores 1.0, and each new importance is `(1 - blend) * importance + blend * centrality`.
+/// Both storage and the importance index are updated. Returns the number of engrams updated.
+pub fn recompute_importance_from_graph(storage: &Storage, index: &mut SearchIndex, blend: f64) -> Result<usize> {
+    if !(0.0..=1.0).contains(&blend) {
+        return Err(EngramError::InvalidOperation(format!(
+            "Importance blend must lie in 0.0..=1.0, got {}", blend
+        )));
+    }
+    
+    let engrams = storage.iter_engrams().collect::<Result<Vec<Engram>>>()?;
+    if engrams.is_empty() {
+        return Ok(0);
+    }
+    
+    let positions: std::collections::HashMap<&EngramId, usize> = engrams
+        .iter()
+        .enumerate()
+        .map(|(position, engram)| (&engram.id, position))
+        .collect();
+    
+    // Undirected adjacency; connections to engrams that no longer exist are ignored
+    let mut neighbors: Vec<Vec<usize>> = vec![Vec::new(); engrams.len()];
+    for connection in storage.iter_connections() {
+        let connection = connection?;
+        if let (Some(&source), Some(&target)) = (positions.get(&connection.source_id), positions.get(&connection.target_id)) {
+            if source != target {
+                neighbors[source].push(target);
+                neighbors[target].push(source);
+            }
+        }
+    }
+    
+    let count = engrams.len() as f64;
+    let mut rank = vec![1.0 / count; engrams.len()];
+    for _ in 0..PAGERANK_ITERATIONS {
+        // Isolated engrams spread their rank evenly so the total stays at 1.0
+        let dangling: f64 = rank.iter().zip(&neighbors).filter(|(_, n)| n.is_empty()).map(|(r, _)| r).sum();
+        let mut next = vec![(1.0 - PAGERANK_DAMPING) / count + PAGERANK_DAMPING * dangling / count; engrams.len()];
+        for (position, targets) in neighbors.iter().enumerate() {
+            let share = PAGERANK_DAMPING * rank[position] / targets.len().max(1) as f64;
+            for &target in targets {
+                next[target] += share;
+            }
+        }
+        rank = next;
+    }
+    
+    let max_rank = rank.iter().cloned().fold(0.0, f64::max);
+    let mut updated = 0;
+    for (mut engram, score) in engrams.into_iter().zip(rank) {
+        let centrality = if max_rank > 0.0 { score / max_rank } else { 0.0 };
+        let importance = (1.0 - blend) * engram.importance + blend * centrality;
+        if (importance - engram.importance).abs() < f64::EPSILON {
+            continue;
+        }
+        
+        engram.set_importance(importance);
+        storage.put_engram(&engram)?;
+        index.update_importance(&engram.id, engram.importance)?;
+        updated += 1;
+    }
+    
+    Ok(updated)
+}
+
 /// A higher-level interface for performing queries and traversals
 #[allow(dead_code)]
 pub struct QueryService<'a> {
@@ -690,6 +1618,27 @@ impl<'a> QueryService<'a> {
         self.query_engine.get_forgetting_policy()
     }
     
+    /// Set the maximum number of results a query without a limit may return
+    pub fn set_max_result_size(&mut self, max_result_size: usize) {
+        self.query_engine.set_max_result_size(max_result_size);
+    }
+    
+    /// Abort queries and traversals with `EngramError::Timeout` once this token trips
+    pub fn set_cancellation(&mut self, cancellation: CancellationToken) {
+        self.query_engine.set_cancellation(cancellation.clone());
+        self.traversal_engine.set_cancellation(cancellation);
+    }
+    
+    /// Abort queries and traversals that run longer than `timeout` from now
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.set_cancellation(CancellationToken::with_timeout(timeout));
+    }
+    
+    /// Register a callback to be notified with the IDs of forgotten engrams
+    pub fn add_forgetting_observer(&mut self, observer: ForgettingObserver<'a>) {
+        self.query_engine.add_forgetting_observer(observer);
+    }
+    
     /// Record an access to an engram
     pub fn record_engram_access(&mut self, id: &EngramId) -> Result<()> {
         self.query_engine.record_access(id)
@@ -700,6 +1649,11 @@ impl<'a> QueryService<'a> {
         self.query_engine.update_importance(id, importance)
     }
     
+    /// Pin or unpin an engram, exempting it from forgetting
+    pub fn set_engram_pinned(&mut self, id: &EngramId, pinned: bool) -> Result<bool> {
+        self.query_engine.set_pinned(id, pinned)
+    }
+    
     /// Set TTL for an engram
     pub fn set_engram_ttl(&mut self, id: &EngramId, ttl_seconds: Option<u64>) -> Result<()> {
         self.query_engine.set_ttl(id, ttl_seconds)
@@ -710,11 +1664,21 @@ impl<'a> QueryService<'a> {
         self.query_engine.get_forgetting_candidates()
     }
     
+    /// Get forgetting candidates with the reason each was selected, for dry runs
+    pub fn explain_forgetting_candidates(&self) -> Vec<crate::index::ForgettingCandidate> {
+        self.query_engine.explain_forgetting_candidates()
+    }
+    
     /// Apply forgetting by removing the engrams selected by the policy
     pub fn apply_forgetting(&mut self) -> Result<usize> {
         self.query_engine.apply_forgetting()
     }
     
+    /// Apply forgetting and compact the deleted ranges, reporting the space reclaimed
+    pub fn apply_forgetting_with_compaction(&mut self) -> Result<(usize, CompactionReport)> {
+        self.query_engine.apply_forgetting_with_compaction()
+    }
+    
     /// Calculate importance score based on node centrality
     pub fn calculate_importance_by_centrality(&mut self, id: &EngramId) -> Result<f64> {
         // Get incoming and outgoing connections
@@ -1022,12 +1986,35 @@ impl<'a> QueryService<'a> {
         engram_id: &EngramId,
         max_depth: usize,
         relationship_type: Option<&str>,
-    ) -> Result<TraversalResult> {
+    ) -> Result<TraversalResult<'a>> {
         self.traversal_engine
             .find_connected_engrams(engram_id, max_depth, relationship_type)
     }
     
+    /// Find routes between two engrams, optionally restricted to some relationship types
+    pub fn find_routes(
+        &self,
+        source_id: &EngramId,
+        target_id: &EngramId,
+        max_depth: usize,
+        relationship_types: &[String],
+    ) -> Result<Vec<TraversalPath>> {
+        self.traversal_engine
+            .find_routes(source_id, target_id, max_depth, relationship_types)
+    }
+    
+    /// Find chains from an engram that follow a sequence of relationship types
+    pub fn find_meta_paths(
+        &self,
+        start_id: &EngramId,
+        pattern: &[String],
+        max_results: usize,
+    ) -> Result<Vec<TraversalPath>> {
+        self.traversal_engine.find_meta_paths(start_id, pattern, max_results)
+    }
+    
     /// Combined search with multiple criteria
+    #[allow(clippy::too_many_arguments)]
     pub fn search_combined(
         &self,
         text: Option<&str>,
@@ -1100,6 +2087,7 @@ impl<'a> QueryService<'a> {
     }
     
     /// Simplified combined search (backward compatibility)
+    #[allow(clippy::too_many_arguments)]
     pub fn search_combined_legacy(
         &self,
         text: Option<&str>,
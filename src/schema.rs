@@ -1,3 +1,4 @@
+use crate::error::{EngramError, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -10,6 +11,150 @@ pub type AgentId = String;
 pub type ContextId = String;
 pub type Metadata = HashMap<String, serde_json::Value>;
 
+/// Structured reference to the external document an engram was extracted from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// URI of the source document (e.g. a URL or file path)
+    pub uri: String,
+    
+    /// Identifier of the source document, shared by all engrams extracted from it
+    pub document_id: String,
+    
+    /// Character offset range (start, end) of the extracted passage within the document
+    pub span: Option<(usize, usize)>,
+}
+
+impl Provenance {
+    pub fn new(uri: String, document_id: String) -> Self {
+        Self {
+            uri,
+            document_id,
+            span: None,
+        }
+    }
+    
+    /// Set the offset range of the extracted passage
+    pub fn with_span(mut self, start: usize, end: usize) -> Self {
+        self.span = Some((start, end));
+        self
+    }
+}
+
+/// Defaults and validation rules applied when engrams and connections are created
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SchemaPolicy {
+    /// Confidence given to engrams and connections created without one
+    pub default_confidence: f64,
+    
+    /// Weight given to connections created without one
+    pub default_weight: f64,
+    
+    /// Whether out-of-range values are rejected (strict) or clamped into range (lenient)
+    pub strict: bool,
+    
+    /// Inclusive range that confidence values must lie in
+    pub confidence_range: (f64, f64),
+}
+
+impl Default for SchemaPolicy {
+    fn default() -> Self {
+        Self {
+            default_confidence: 1.0,
+            default_weight: DEFAULT_CONNECTION_WEIGHT,
+            strict: true,
+            confidence_range: (0.0, 1.0),
+        }
+    }
+}
+
+impl SchemaPolicy {
+    /// Create the default policy: strict, confidence 1.0 in 0.0..=1.0, weight 0.5
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Default policy that clamps out-of-range values instead of rejecting them
+    pub fn lenient() -> Self {
+        Self { strict: false, ..Self::default() }
+    }
+    
+    /// Set the confidence used when none is given
+    pub fn with_default_confidence(mut self, confidence: f64) -> Self {
+        self.default_confidence = confidence;
+        self
+    }
+    
+    /// Set the connection weight used when none is given
+    pub fn with_default_weight(mut self, weight: f64) -> Self {
+        self.default_weight = weight;
+        self
+    }
+    
+    /// Set whether out-of-range values are rejected rather than clamped
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    
+    /// Set the inclusive range that confidence values must lie in
+    pub fn with_confidence_range(mut self, min: f64, max: f64) -> Self {
+        self.confidence_range = (min, max);
+        self
+    }
+    
+    /// Check that the policy is self-consistent: a non-empty confidence range within 0.0..=1.0
+    /// containing the default confidence, and a default weight within 0.0..=1.0
+    pub fn validate(&self) -> Result<()> {
+        let (min, max) = self.confidence_range;
+        if !(0.0..=1.0).contains(&min) || !(0.0..=1.0).contains(&max) || min > max {
+            return Err(EngramError::InvalidOperation(format!(
+                "Confidence range must lie within 0.0..=1.0, got {}..={}",
+                min, max
+            )));
+        }
+        if !(min..=max).contains(&self.default_confidence) {
+            return Err(EngramError::InvalidOperation(format!(
+                "Default confidence {} is outside the confidence range {}..={}",
+                self.default_confidence, min, max
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.default_weight) {
+            return Err(EngramError::InvalidOperation(format!(
+                "Default weight must be between 0.0 and 1.0, got {}",
+                self.default_weight
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Resolve a requested confidence, falling back to the default when `None`
+    pub fn confidence(&self, confidence: Option<f64>) -> Result<f64> {
+        let (min, max) = self.confidence_range;
+        self.resolve("Confidence", confidence.unwrap_or(self.default_confidence), min, max)
+    }
+    
+    /// Resolve a requested connection weight, falling back to the default when `None`
+    pub fn weight(&self, weight: Option<f64>) -> Result<f64> {
+        self.resolve("Weight", weight.unwrap_or(self.default_weight), 0.0, 1.0)
+    }
+    
+    fn resolve(&self, field: &str, value: f64, min: f64, max: f64) -> Result<f64> {
+        if value.is_nan() {
+            return Err(EngramError::InvalidOperation(format!("{} must be a number", field)));
+        }
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else if self.strict {
+            Err(EngramError::InvalidOperation(format!(
+                "{} must be between {} and {}, got {}",
+                field, min, max, value
+            )))
+        } else {
+            Ok(value.clamp(min, max))
+        }
+    }
+}
+
 /// Atomic unit of knowledge/memory with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Engram {
@@ -41,8 +186,20 @@ pub struct Engram {
     /// Time-to-live in seconds (None means no expiration)
     pub ttl: Option<u64>,
     
+    /// Pinned engrams are never selected for forgetting, whatever the policy
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    
     /// Additional custom metadata
     pub metadata: Metadata,
+    
+    /// Structured reference to the external document this engram was extracted from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    
+    /// Agent that created this engram, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<AgentId>,
 }
 
 impl Engram {
@@ -58,10 +215,42 @@ impl Engram {
             access_count: 0,
             last_accessed: now,
             ttl: None,       // No expiration by default
+            pinned: false,
             metadata: metadata.unwrap_or_default(),
+            provenance: None,
+            created_by: None,
         }
     }
     
+    /// Create an engram whose confidence is defaulted and validated by `policy`
+    pub fn new_with_policy(
+        content: String,
+        source: String,
+        confidence: Option<f64>,
+        metadata: Option<Metadata>,
+        policy: &SchemaPolicy,
+    ) -> Result<Self> {
+        Ok(Self::new(content, source, policy.confidence(confidence)?, metadata))
+    }
+    
+    /// Attach a structured reference to the source document
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+    
+    /// Attribute this engram to the agent that created it
+    pub fn with_created_by(mut self, agent_id: AgentId) -> Self {
+        self.created_by = Some(agent_id);
+        self
+    }
+    
+    /// Expire this engram `seconds` after creation, for ephemeral working memory
+    pub fn with_ttl(mut self, seconds: u64) -> Self {
+        self.ttl = Some(seconds);
+        self
+    }
+    
     /// Record an access to this engram
     pub fn record_access(&mut self) {
         self.access_count += 1;
@@ -71,7 +260,7 @@ impl Engram {
     /// Set importance score directly
     pub fn set_importance(&mut self, importance: f64) {
         // Ensure importance is within valid range
-        self.importance = importance.max(0.0).min(1.0);
+        self.importance = importance.clamp(0.0, 1.0);
     }
     
     /// Set time-to-live (TTL) in seconds
@@ -84,6 +273,11 @@ impl Engram {
         self.ttl = None;
     }
     
+    /// Pin or unpin the engram, exempting it from forgetting
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+    
     /// Check if the engram has expired based on its TTL
     pub fn is_expired(&self) -> bool {
         if let Some(ttl) = self.ttl {
@@ -103,6 +297,16 @@ impl Engram {
             remaining.max(0)
         })
     }
+    
+    /// Hash of the normalized content, shared by engrams that duplicate each other
+    pub fn content_hash(&self) -> String {
+        crate::utils::content_hash(&self.content)
+    }
+    
+    /// Quoted entity tag for HTTP caching: a hash of every field, so any edit changes it
+    pub fn etag(&self) -> String {
+        crate::utils::etag_of(self)
+    }
 }
 
 /// Typed relationship between engrams with strength/weight
@@ -123,10 +327,26 @@ pub struct Connection {
     /// Strength of the connection (0.0 to 1.0)
     pub weight: f64,
     
+    /// Certainty (0.0 to 1.0) that the relationship exists at all, independent of its strength
+    #[serde(default = "default_connection_confidence")]
+    pub confidence: f64,
+    
     /// Additional custom metadata
     pub metadata: Metadata,
+    
+    /// Agent that created this connection, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<AgentId>,
 }
 
+/// Connections stored before confidence was tracked are treated as certain
+fn default_connection_confidence() -> f64 {
+    1.0
+}
+
+/// Weight given to a similarity-weighted connection when either engram has no stored embedding
+pub const DEFAULT_CONNECTION_WEIGHT: f64 = 0.5;
+
 impl Connection {
     pub fn new(
         source_id: EngramId,
@@ -141,7 +361,78 @@ impl Connection {
             target_id,
             relationship_type,
             weight,
+            confidence: default_connection_confidence(),
             metadata: metadata.unwrap_or_default(),
+            created_by: None,
+        }
+    }
+    
+    /// Create a connection whose weight and confidence are defaulted and validated by `policy`
+    pub fn new_with_policy(
+        source_id: EngramId,
+        target_id: EngramId,
+        relationship_type: String,
+        weight: Option<f64>,
+        metadata: Option<Metadata>,
+        policy: &SchemaPolicy,
+    ) -> Result<Self> {
+        let mut connection = Self::new(source_id, target_id, relationship_type, policy.weight(weight)?, metadata);
+        connection.confidence = policy.confidence(None)?;
+        Ok(connection)
+    }
+    
+    /// Attribute this connection to the agent that created it
+    pub fn with_created_by(mut self, agent_id: AgentId) -> Self {
+        self.created_by = Some(agent_id);
+        self
+    }
+    
+    /// Create a connection weighted by the semantic similarity of the two engrams' stored
+    /// embeddings, as measured by `service`'s metric and clamped to 0.0..=1.0.
+    /// Falls back to `DEFAULT_CONNECTION_WEIGHT` when either embedding is missing.
+    pub fn new_weighted_by_similarity(
+        source_id: EngramId,
+        target_id: EngramId,
+        relationship_type: String,
+        storage: &crate::storage::Storage,
+        service: &crate::embedding::EmbeddingService,
+    ) -> Result<Self> {
+        let to_embedding = |stored: crate::storage::Embedding| crate::embedding::Embedding {
+            vector: stored.vector,
+            model: stored.model,
+            dimensions: stored.dimensions,
+            metadata: stored.metadata,
+        };
+        
+        let weight = match (storage.get_embedding(&source_id)?, storage.get_embedding(&target_id)?) {
+            (Some(source), Some(target)) => {
+                let similarity = service.similarity(&to_embedding(source), &to_embedding(target))?;
+                (similarity as f64).clamp(0.0, 1.0)
+            },
+            _ => DEFAULT_CONNECTION_WEIGHT,
+        };
+        
+        Ok(Self::new(source_id, target_id, relationship_type, weight, None))
+    }
+    
+    /// Set the confidence that the relationship exists, which must lie in 0.0..=1.0
+    pub fn with_confidence(mut self, confidence: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(EngramError::InvalidOperation(format!(
+                "Connection confidence must be between 0.0 and 1.0, got {}",
+                confidence
+            )));
+        }
+        self.confidence = confidence;
+        Ok(self)
+    }
+    
+    /// Traversal score: the weight, optionally discounted by the confidence in the relationship
+    pub fn score(&self, use_confidence: bool) -> f64 {
+        if use_confidence {
+            self.weight * self.confidence
+        } else {
+            self.weight
         }
     }
 }
@@ -1,7 +1,7 @@
 use crate::error::{EngramError, Result};
 use crate::schema::{
     Agent, AgentId, Collection, CollectionId, Connection, ConnectionId, Context, ContextId, Engram,
-    EngramId,
+    EngramId, SchemaPolicy,
 };
 // Forward declare the Embedding struct to avoid circular dependency
 // We don't need to import the embedding module here, as we'll define our own Embedding struct
@@ -29,10 +29,14 @@ impl Embedding {
         }
     }
 }
-use rocksdb::{ColumnFamilyDescriptor, Options, DB, WriteBatch, IteratorMode};
+use rocksdb::checkpoint::Checkpoint;
+use rocksdb::{BlockBasedOptions, Cache, ColumnFamilyDescriptor, DBCompressionType, Options, DB, WriteBatch, IteratorMode};
 use serde::{de::DeserializeOwned, Serialize};
 use std::path::Path;
-use std::collections::HashSet;
+use std::time::Duration;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::Mutex;
 
 /// Storage prefix keys for different entity types
 const ENGRAM_PREFIX: &[u8] = b"engram:";
@@ -46,10 +50,25 @@ const SOURCE_CONNECTION_PREFIX: &[u8] = b"source_conn:";
 const TARGET_CONNECTION_PREFIX: &[u8] = b"target_conn:";
 const RELATION_TYPE_PREFIX: &[u8] = b"rel_type:";
 
+// Reverse membership index prefixes (engram -> owning collections/contexts)
+const COLLECTION_MEMBER_PREFIX: &[u8] = b"member_coll:";
+const CONTEXT_MEMBER_PREFIX: &[u8] = b"member_ctx:";
+
+// Attribution index prefixes (agent -> engrams/connections it created)
+const CREATED_ENGRAM_PREFIX: &[u8] = b"created_engram:";
+const CREATED_CONNECTION_PREFIX: &[u8] = b"created_conn:";
+
+// Version history prefixes, keyed `{prefix}{id}:{write time in nanoseconds, zero-padded}`
+const ENGRAM_HISTORY_PREFIX: &[u8] = b"hist_engram:";
+const COLLECTION_HISTORY_PREFIX: &[u8] = b"hist_coll:";
+
+// Score index prefixes in the metadata column family, keyed `{prefix}{bucket}:{engram id}`
+// where the bucket is the score in hundredths, zero-padded; the value is the exact score
+const CONFIDENCE_INDEX_PREFIX: &[u8] = b"conf:";
+const IMPORTANCE_INDEX_PREFIX: &[u8] = b"imp:";
+
 // Embedding prefixes
-#[allow(dead_code)]
 const EMBEDDING_PREFIX: &[u8] = b"embedding:";
-#[allow(dead_code)]
 const REDUCED_EMBEDDING_PREFIX: &[u8] = b"reduced_embedding:";
 
 /// Column family names
@@ -61,6 +80,369 @@ const CF_CONTEXTS: &str = "contexts";
 const CF_METADATA: &str = "metadata";
 const CF_RELATIONSHIPS: &str = "relationships"; // For storing relationship indexes
 const CF_EMBEDDINGS: &str = "embeddings"; // For storing vector embeddings
+const CF_HISTORY: &str = "history"; // For storing past versions of engrams and collections
+
+/// Key in the metadata column family holding the schema version of stored records
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+/// Schema version written by this build. Databases with a newer version are refused.
+pub const CURRENT_SCHEMA_VERSION: u32 = 6;
+
+/// Record transform upgrading a database from the paired version to the next one.
+/// Staged writes are committed together with the new version stamp.
+type Migration = fn(&Storage, &mut WriteBatch) -> Result<usize>;
+
+/// Migrations in order, keyed by the version they upgrade from
+const MIGRATIONS: &[(u32, Migration)] = &[
+    (1, Storage::migrate_v1_to_v2),
+    (2, Storage::migrate_v2_to_v3),
+    (3, Storage::migrate_v3_to_v4),
+    (4, Storage::migrate_v4_to_v5),
+    (5, Storage::migrate_v5_to_v6),
+];
+
+/// Leading byte marking a JSON record
+const FORMAT_TAG_JSON: u8 = 0x01;
+
+/// Leading byte marking a MessagePack record
+const FORMAT_TAG_MSGPACK: u8 = 0x02;
+
+/// Encoding used for newly written records. Every record starts with a format tag byte, so
+/// records in either format (and untagged JSON from before v4) can be read regardless of
+/// the configured format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SerializationFormat {
+    /// Human-readable JSON, easy to inspect and debug
+    #[default]
+    Json,
+    
+    /// Compact binary MessagePack, notably smaller for embeddings
+    MessagePack,
+}
+
+impl SerializationFormat {
+    /// Encode a value as a tagged record in this format
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        match self {
+            SerializationFormat::Json => {
+                bytes.push(FORMAT_TAG_JSON);
+                serde_json::to_writer(&mut bytes, value)
+                    .map_err(|e| EngramError::SerializationError(e.to_string()))?;
+            },
+            SerializationFormat::MessagePack => {
+                bytes.push(FORMAT_TAG_MSGPACK);
+                // Field names are kept so defaulted and skipped fields still round-trip
+                rmp_serde::encode::write_named(&mut bytes, value)
+                    .map_err(|e| EngramError::SerializationError(e.to_string()))?;
+            },
+        }
+        Ok(bytes)
+    }
+    
+    /// Decode a record in any format, dispatching on its tag byte
+    pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        match bytes.first() {
+            Some(&FORMAT_TAG_JSON) => serde_json::from_slice(&bytes[1..])
+                .map_err(|e| EngramError::SerializationError(e.to_string())),
+            Some(&FORMAT_TAG_MSGPACK) => rmp_serde::from_slice(&bytes[1..])
+                .map_err(|e| EngramError::SerializationError(e.to_string())),
+            // Untagged records predate format tags and are always JSON
+            _ => serde_json::from_slice(bytes)
+                .map_err(|e| EngramError::SerializationError(e.to_string())),
+        }
+    }
+}
+
+/// The engram scores kept in the persisted score indexes
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EngramScores {
+    confidence: f64,
+    importance: f64,
+}
+
+impl EngramScores {
+    fn of(engram: &Engram) -> Self {
+        Self { confidence: engram.confidence, importance: engram.importance }
+    }
+}
+
+/// Score index bucket for a score, in hundredths from 0 to 100
+fn score_bucket(score: f64) -> u32 {
+    (score.clamp(0.0, 1.0) * 100.0).floor() as u32
+}
+
+/// How long flushed write-ahead logs are kept for `Storage::change_feed` by default
+pub const DEFAULT_CHANGE_FEED_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Options for opening a `Storage`
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Format for records written from now on
+    pub format: SerializationFormat,
+    
+    /// How long write-ahead logs are kept after flushing, bounding how far back
+    /// `Storage::change_feed` can resume
+    pub change_feed_retention: Duration,
+    
+    /// Maximum number of outgoing connections per engram enforced by `Storage::add_connection`,
+    /// or `None` for no limit
+    pub max_connections_per_engram: Option<usize>,
+    
+    /// What `Storage::add_connection` does when an engram is at its connection limit
+    pub connection_limit_policy: ConnectionLimitPolicy,
+    
+    /// Defaults and validation rules for creating engrams and connections against this database
+    pub schema_policy: SchemaPolicy,
+    
+    /// Compression applied to table files
+    pub compression: CompressionType,
+    
+    /// Size in bytes of a block cache shared by all column families, or `None` for
+    /// RocksDB's default per-table cache
+    pub block_cache_size: Option<usize>,
+    
+    /// Bloom filter bits per key, letting point reads skip table files that can't hold the
+    /// key, or `None` for no filter. 10 bits gives roughly a 1% false positive rate.
+    pub bloom_filter_bits_per_key: Option<f64>,
+    
+    /// Size in bytes of each column family's memtable, or `None` for RocksDB's default (64MB)
+    pub write_buffer_size: Option<usize>,
+    
+    /// Whether RocksDB collects internal statistics, read with `Storage::statistics`.
+    /// Costs a few percent of throughput.
+    pub enable_statistics: bool,
+}
+
+/// Compression applied to RocksDB table files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionType {
+    /// Store blocks uncompressed
+    None,
+    
+    /// Fast with a moderate ratio
+    #[default]
+    Snappy,
+    
+    /// Similar to Snappy, slightly faster to decompress
+    Lz4,
+    
+    /// Best ratio, at more CPU per write; good for large, read-heavy databases
+    Zstd,
+}
+
+impl CompressionType {
+    fn to_rocksdb(self) -> DBCompressionType {
+        match self {
+            CompressionType::None => DBCompressionType::None,
+            CompressionType::Snappy => DBCompressionType::Snappy,
+            CompressionType::Lz4 => DBCompressionType::Lz4,
+            CompressionType::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+/// Bloom filter bits per key used unless configured otherwise
+pub const DEFAULT_BLOOM_FILTER_BITS_PER_KEY: f64 = 10.0;
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            format: SerializationFormat::default(),
+            change_feed_retention: DEFAULT_CHANGE_FEED_RETENTION,
+            max_connections_per_engram: None,
+            connection_limit_policy: ConnectionLimitPolicy::default(),
+            schema_policy: SchemaPolicy::default(),
+            compression: CompressionType::default(),
+            block_cache_size: None,
+            bloom_filter_bits_per_key: Some(DEFAULT_BLOOM_FILTER_BITS_PER_KEY),
+            write_buffer_size: None,
+            enable_statistics: false,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Create a config with default settings (JSON records, one day of change feed)
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the format used for newly written records
+    pub fn with_format(mut self, format: SerializationFormat) -> Self {
+        self.format = format;
+        self
+    }
+    
+    /// Set how long write-ahead logs are kept for the change feed
+    pub fn with_change_feed_retention(mut self, retention: Duration) -> Self {
+        self.change_feed_retention = retention;
+        self
+    }
+    
+    /// Cap the number of outgoing connections per engram, keeping hub engrams tractable to traverse
+    pub fn with_max_connections_per_engram(mut self, limit: usize, policy: ConnectionLimitPolicy) -> Self {
+        self.max_connections_per_engram = Some(limit);
+        self.connection_limit_policy = policy;
+        self
+    }
+    
+    /// Set the defaults and validation rules for creating engrams and connections
+    pub fn with_schema_policy(mut self, policy: SchemaPolicy) -> Self {
+        self.schema_policy = policy;
+        self
+    }
+    
+    /// Set the compression applied to table files
+    pub fn with_compression(mut self, compression: CompressionType) -> Self {
+        self.compression = compression;
+        self
+    }
+    
+    /// Share a block cache of `bytes` between all column families
+    pub fn with_block_cache_size(mut self, bytes: usize) -> Self {
+        self.block_cache_size = Some(bytes);
+        self
+    }
+    
+    /// Set the bloom filter bits per key, or `None` to disable bloom filters
+    pub fn with_bloom_filter_bits_per_key(mut self, bits: Option<f64>) -> Self {
+        self.bloom_filter_bits_per_key = bits;
+        self
+    }
+    
+    /// Set the memtable size of each column family
+    pub fn with_write_buffer_size(mut self, bytes: usize) -> Self {
+        self.write_buffer_size = Some(bytes);
+        self
+    }
+    
+    /// Configure whether RocksDB collects internal statistics
+    pub fn with_statistics(mut self, enabled: bool) -> Self {
+        self.enable_statistics = enabled;
+        self
+    }
+    
+    /// Check the tuning options and schema policy for values RocksDB can't use
+    fn validate(&self) -> Result<()> {
+        self.schema_policy.validate()?;
+        
+        if let Some(bits) = self.bloom_filter_bits_per_key {
+            if !(bits > 0.0 && bits.is_finite()) {
+                return Err(EngramError::InvalidOperation(format!(
+                    "Bloom filter bits per key must be positive, got {}", bits
+                )));
+            }
+        }
+        if self.block_cache_size == Some(0) {
+            return Err(EngramError::InvalidOperation("Block cache size must be at least 1 byte".to_string()));
+        }
+        if self.write_buffer_size == Some(0) {
+            return Err(EngramError::InvalidOperation("Write buffer size must be at least 1 byte".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// How `Storage::add_connection` handles a new connection from an engram at its connection limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionLimitPolicy {
+    /// Refuse the new connection with `InvalidOperation`
+    Reject,
+    
+    /// Keep the strongest connections by weight, evicting the weakest (possibly the new one)
+    #[default]
+    EvictWeakest,
+}
+
+/// Live entity counters kept in the metadata column family so stats are O(1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StatCounter {
+    Engrams,
+    Connections,
+    Collections,
+    Agents,
+    Contexts,
+}
+
+impl StatCounter {
+    const ALL: [StatCounter; 5] = [
+        StatCounter::Engrams,
+        StatCounter::Connections,
+        StatCounter::Collections,
+        StatCounter::Agents,
+        StatCounter::Contexts,
+    ];
+    
+    /// Key of the counter in the metadata column family
+    fn key(self) -> &'static [u8] {
+        match self {
+            StatCounter::Engrams => b"stats:engram_count",
+            StatCounter::Connections => b"stats:connection_count",
+            StatCounter::Collections => b"stats:collection_count",
+            StatCounter::Agents => b"stats:agent_count",
+            StatCounter::Contexts => b"stats:context_count",
+        }
+    }
+    
+    /// Column family holding the counted records
+    fn cf(self) -> &'static str {
+        match self {
+            StatCounter::Engrams => CF_ENGRAMS,
+            StatCounter::Connections => CF_CONNECTIONS,
+            StatCounter::Collections => CF_COLLECTIONS,
+            StatCounter::Agents => CF_AGENTS,
+            StatCounter::Contexts => CF_CONTEXTS,
+        }
+    }
+    
+    /// Key prefix of the counted records
+    fn prefix(self) -> &'static [u8] {
+        match self {
+            StatCounter::Engrams => ENGRAM_PREFIX,
+            StatCounter::Connections => CONNECTION_PREFIX,
+            StatCounter::Collections => COLLECTION_PREFIX,
+            StatCounter::Agents => AGENT_PREFIX,
+            StatCounter::Contexts => CONTEXT_PREFIX,
+        }
+    }
+    
+    /// Read the counter's current value
+    fn read(self, db: &DB) -> Result<Option<usize>> {
+        let cf = db.cf_handle(CF_METADATA).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_METADATA))
+        })?;
+        
+        match db.get_cf(cf, self.key())? {
+            Some(bytes) => {
+                let bytes: [u8; 8] = bytes.as_slice().try_into().map_err(|_| {
+                    EngramError::StorageError("Corrupt stats counter".to_string())
+                })?;
+                Ok(Some(u64::from_le_bytes(bytes) as usize))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// Stage a new counter value in a batch
+    fn stage(self, db: &DB, batch: &mut WriteBatch, value: usize) -> Result<()> {
+        let cf = db.cf_handle(CF_METADATA).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_METADATA))
+        })?;
+        
+        batch.put_cf(cf, self.key(), (value as u64).to_le_bytes());
+        Ok(())
+    }
+    
+    /// Check whether a counted record exists
+    fn record_exists(self, db: &DB, id: &str) -> Result<bool> {
+        let cf = db.cf_handle(self.cf()).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", self.cf()))
+        })?;
+        
+        Ok(db.get_pinned_cf(cf, Storage::create_key(self.prefix(), id))?.is_some())
+    }
+}
 
 /// Database statistics structure
 #[derive(Debug, Clone, serde::Serialize)]
@@ -73,20 +455,627 @@ pub struct StorageStats {
     pub db_size_mb: f64,
 }
 
+/// On-disk size of the compacted column families before and after a compaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CompactionReport {
+    /// Estimated bytes in SST files before compacting
+    pub bytes_before: u64,
+    
+    /// Estimated bytes in SST files after compacting
+    pub bytes_after: u64,
+}
+
+impl CompactionReport {
+    /// Bytes freed by the compaction (zero if the estimate grew)
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// Kind of write reported by the change feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ChangeOperation {
+    Put,
+    Delete,
+}
+
+/// Kind of record a change feed event refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ChangeEntityType {
+    Engram,
+    Connection,
+    Collection,
+    Agent,
+    Context,
+    /// A full or reduced embedding; the ID is the engram's
+    Embedding,
+}
+
+impl ChangeEntityType {
+    /// Map a record key to its entity type and ID. Index entries and counters map to `None`.
+    fn from_key(key: &[u8]) -> Option<(Self, String)> {
+        let prefixes = [
+            (ENGRAM_PREFIX, ChangeEntityType::Engram),
+            (CONNECTION_PREFIX, ChangeEntityType::Connection),
+            (COLLECTION_PREFIX, ChangeEntityType::Collection),
+            (AGENT_PREFIX, ChangeEntityType::Agent),
+            (CONTEXT_PREFIX, ChangeEntityType::Context),
+            (EMBEDDING_PREFIX, ChangeEntityType::Embedding),
+            (REDUCED_EMBEDDING_PREFIX, ChangeEntityType::Embedding),
+        ];
+        
+        prefixes.iter().find_map(|(prefix, entity_type)| {
+            key.strip_prefix(*prefix)
+                .map(|id| (*entity_type, String::from_utf8_lossy(id).to_string()))
+        })
+    }
+}
+
+/// A typed write read back from the write-ahead log
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ChangeEvent {
+    /// Last sequence number of the atomic write this event belongs to. Every event from one
+    /// write shares it; pass it to `Storage::change_feed` to resume after that write.
+    pub sequence: u64,
+    
+    /// Whether the record was written or deleted
+    pub operation: ChangeOperation,
+    
+    /// What kind of record changed
+    pub entity_type: ChangeEntityType,
+    
+    /// ID of the record that changed
+    pub id: String,
+}
+
+/// Iterator over typed changes after a sequence number, created by `Storage::change_feed`
+pub struct ChangeFeed {
+    /// Raw write batches from RocksDB
+    updates: rocksdb::DBWALIterator,
+    
+    /// Events decoded from the current batch, not yet yielded
+    pending: std::collections::VecDeque<ChangeEvent>,
+    
+    /// Changes at or before this sequence number have already been consumed
+    since: u64,
+    
+    /// Last sequence number of the most recently decoded batch
+    last_sequence: u64,
+}
+
+impl ChangeFeed {
+    /// Sequence number to resume from once every event yielded so far has been applied
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+}
+
+impl Iterator for ChangeFeed {
+    type Item = Result<ChangeEvent>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            
+            let (first_sequence, batch) = match self.updates.next()? {
+                Ok(update) => update,
+                Err(e) => return Some(Err(EngramError::StorageError(e.to_string()))),
+            };
+            
+            let (events, last_sequence) = match decode_write_batch(batch.data(), first_sequence) {
+                Ok(decoded) => decoded,
+                Err(e) => return Some(Err(e)),
+            };
+            self.last_sequence = self.last_sequence.max(last_sequence);
+            
+            // The log may start inside a batch that was already consumed
+            if last_sequence > self.since {
+                self.pending.extend(events);
+            }
+        }
+    }
+}
+
+/// Read a varint32 from a write batch, advancing `pos`
+fn read_varint32(data: &[u8], pos: &mut usize) -> Result<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*pos).ok_or_else(|| {
+            EngramError::SerializationError("Write batch is truncated".to_string())
+        })?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(EngramError::SerializationError("Malformed varint in write batch".to_string()))
+}
+
+/// Read a length-prefixed slice from a write batch, advancing `pos`
+fn read_length_prefixed<'a>(data: &'a [u8], pos: &mut usize) -> Result<&'a [u8]> {
+    let len = read_varint32(data, pos)? as usize;
+    let slice = data.get(*pos..*pos + len).ok_or_else(|| {
+        EngramError::SerializationError("Write batch is truncated".to_string())
+    })?;
+    *pos += len;
+    Ok(slice)
+}
+
+/// Decode the entity writes in a serialized RocksDB write batch.
+///
+/// `WriteBatch::iterate` only reports default column family writes, so the record format
+/// is decoded directly: a 12-byte header (sequence, count) followed by tagged records.
+/// Returns the events and the last sequence number the batch occupies.
+fn decode_write_batch(data: &[u8], first_sequence: u64) -> Result<(Vec<ChangeEvent>, u64)> {
+    const HEADER_SIZE: usize = 12;
+    if data.len() < HEADER_SIZE {
+        return Err(EngramError::SerializationError("Write batch is truncated".to_string()));
+    }
+    
+    let count = u32::from_le_bytes(data[8..12].try_into().unwrap()) as u64;
+    let last_sequence = first_sequence + count.max(1) - 1;
+    
+    let mut events = Vec::new();
+    let mut pos = HEADER_SIZE;
+    while pos < data.len() {
+        let tag = data[pos];
+        pos += 1;
+        
+        // Column family variants carry the column family ID first; keys are unique across
+        // column families, so the ID itself is not needed
+        if matches!(tag, 0x04 | 0x05 | 0x06 | 0x08 | 0x0E | 0x10 | 0x17) {
+            read_varint32(data, &mut pos)?;
+        }
+        
+        let operation = match tag {
+            // Put, merge, blob index and wide-column entity: key then value
+            0x01 | 0x05 | 0x02 | 0x06 | 0x10 | 0x11 | 0x16 | 0x17 => {
+                let key = read_length_prefixed(data, &mut pos)?;
+                read_length_prefixed(data, &mut pos)?;
+                Some((ChangeOperation::Put, key))
+            },
+            // Delete and single delete: key only
+            0x00 | 0x04 | 0x07 | 0x08 => {
+                Some((ChangeOperation::Delete, read_length_prefixed(data, &mut pos)?))
+            },
+            // Range deletion: begin and end keys, with no single ID to report
+            0x0E | 0x0F => {
+                read_length_prefixed(data, &mut pos)?;
+                read_length_prefixed(data, &mut pos)?;
+                None
+            },
+            // Log data, end-prepare, commit and rollback markers carry one blob
+            0x03 | 0x0A | 0x0B | 0x0C => {
+                read_length_prefixed(data, &mut pos)?;
+                None
+            },
+            // Noop and begin-prepare markers are bare tags
+            0x09 | 0x0D | 0x12 | 0x13 => None,
+            _ => return Err(EngramError::SerializationError(format!(
+                "Unsupported write batch record tag {:#x}", tag
+            ))),
+        };
+        
+        if let Some((operation, key)) = operation {
+            if let Some((entity_type, id)) = ChangeEntityType::from_key(key) {
+                events.push(ChangeEvent { sequence: last_sequence, operation, entity_type, id });
+            }
+        }
+    }
+    
+    Ok((events, last_sequence))
+}
+
+/// A stored record whose value could not be deserialized
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UnreadableRecord {
+    /// The raw key, e.g. `engram:<id>`
+    pub key: String,
+    
+    /// Why the value could not be read
+    pub error: String,
+}
+
+/// IDs of the entities an agent created, from `Storage::entities_created_by`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CreatedEntities {
+    /// Engrams attributed to the agent, sorted by ID
+    pub engrams: Vec<EngramId>,
+    
+    /// Connections attributed to the agent, sorted by ID
+    pub connections: Vec<ConnectionId>,
+}
+
+/// A collection as it was at a point in time, from `Storage::collection_as_of`
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSnapshot {
+    /// The time the snapshot reflects
+    pub as_of: chrono::DateTime<chrono::Utc>,
+    
+    /// The collection record at that time, including its membership
+    pub collection: Collection,
+    
+    /// The member engrams that existed at that time, with their content then, sorted by ID
+    pub engrams: Vec<Engram>,
+}
+
+/// Related records to embed alongside an engram in `Storage::engram_detail`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EngramExpansion {
+    /// Embed summaries of the engram's outgoing and incoming connections
+    pub connections: bool,
+    
+    /// Embed summaries of the collections containing the engram
+    pub collections: bool,
+}
+
+impl EngramExpansion {
+    /// Parse a comma-separated list such as `connections,collections`, rejecting unknown names
+    pub fn parse(include: &str) -> Result<Self> {
+        let mut expansion = Self::default();
+        for name in include.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match name {
+                "connections" => expansion.connections = true,
+                "collections" => expansion.collections = true,
+                other => return Err(EngramError::InvalidOperation(format!(
+                    "Unknown include '{}'; expected connections or collections", other
+                ))),
+            }
+        }
+        Ok(expansion)
+    }
+    
+    /// Whether nothing is embedded, leaving the plain engram
+    pub fn is_empty(&self) -> bool {
+        !self.connections && !self.collections
+    }
+}
+
+/// One connection as seen from an engram at one of its ends
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConnectionSummary {
+    pub id: ConnectionId,
+    
+    /// The engram at the other end of the connection
+    pub engram_id: EngramId,
+    
+    pub relationship_type: String,
+    pub weight: f64,
+}
+
+/// A collection an engram belongs to, without its full membership
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionSummary {
+    pub id: CollectionId,
+    pub name: String,
+}
+
+/// An engram with the related records requested by an `EngramExpansion`, from
+/// `Storage::engram_detail`. Fields that were not requested are left out when serialized.
+#[derive(Debug, Clone, Serialize)]
+pub struct EngramDetail {
+    #[serde(flatten)]
+    pub engram: Engram,
+    
+    /// Connections from this engram, sorted by ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outgoing: Option<Vec<ConnectionSummary>>,
+    
+    /// Connections to this engram, sorted by ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incoming: Option<Vec<ConnectionSummary>>,
+    
+    /// Collections containing this engram, sorted by ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collections: Option<Vec<CollectionSummary>>,
+}
+
+/// Result of `Storage::delete_engrams`: the records removed by one batch delete
+#[derive(Debug, Clone, Default)]
+pub struct BatchDeleteReport {
+    /// Engrams that were deleted, sorted by ID
+    pub engrams: Vec<Engram>,
+    
+    /// Connections removed by cascading, each once even if both ends were deleted, sorted by ID
+    pub connections: Vec<Connection>,
+    
+    /// Requested IDs with no stored engram, sorted
+    pub not_found: Vec<EngramId>,
+}
+
+/// Result of `Storage::scan_health`: every record that failed to deserialize, by column family
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct HealthReport {
+    /// Number of records checked across all column families
+    pub records_scanned: usize,
+    
+    /// Unreadable records keyed by column family name (only families with problems appear)
+    pub unreadable: BTreeMap<String, Vec<UnreadableRecord>>,
+}
+
+impl HealthReport {
+    /// Whether every scanned record could be read
+    pub fn is_healthy(&self) -> bool {
+        self.unreadable.is_empty()
+    }
+    
+    /// Total number of unreadable records
+    pub fn unreadable_count(&self) -> usize {
+        self.unreadable.values().map(Vec::len).sum()
+    }
+}
+
+/// Where a page sits in a paginated listing
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PaginationMeta {
+    /// Number of records across all pages
+    pub total: usize,
+    
+    /// Pass back as the cursor to fetch the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    
+    /// Whether any records follow this page
+    pub has_more: bool,
+}
+
+/// One page of entities from a paginated listing, from e.g. `Storage::engrams_page`
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub meta: PaginationMeta,
+}
+
+/// Lazy iterator over one entity type, deserializing each record as it is yielded so at
+/// most one entity is held in memory. Iteration ends after the first error; use
+/// `collect_with_report` to read past unreadable records instead.
+pub struct EntityIter<'a, T> {
+    inner: Option<rocksdb::DBIteratorWithThreadMode<'a, DB>>,
+    prefix: &'static [u8],
+    pending_error: Option<EngramError>,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T: DeserializeOwned> EntityIter<'a, T> {
+    /// Read every record, returning the readable entities together with the records that
+    /// failed to deserialize rather than stopping at (or silently dropping) them.
+    /// Errors from the database itself still abort.
+    pub fn collect_with_report(mut self) -> Result<(Vec<T>, Vec<UnreadableRecord>)> {
+        if let Some(error) = self.pending_error.take() {
+            return Err(error);
+        }
+        
+        let mut entities = Vec::new();
+        let mut unreadable = Vec::new();
+        
+        if let Some(inner) = self.inner.take() {
+            for result in inner {
+                let (key, value) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+                if !key.starts_with(self.prefix) {
+                    break;
+                }
+                
+                match Storage::deserialize(&value) {
+                    Ok(entity) => entities.push(entity),
+                    Err(e) => unreadable.push(UnreadableRecord {
+                        key: String::from_utf8_lossy(&key).to_string(),
+                        error: e.to_string(),
+                    }),
+                }
+            }
+        }
+        
+        Ok((entities, unreadable))
+    }
+}
+
+impl<'a, T: DeserializeOwned> Iterator for EntityIter<'a, T> {
+    type Item = Result<T>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.pending_error.take() {
+            return Some(Err(error));
+        }
+        
+        let inner = self.inner.as_mut()?;
+        let item = match inner.next()? {
+            Ok((key, value)) => {
+                // Keys are sorted, so the first key outside the prefix ends the scan
+                if !key.starts_with(self.prefix) {
+                    self.inner = None;
+                    return None;
+                }
+                Storage::deserialize(&value)
+            },
+            Err(e) => Err(EngramError::StorageError(e.to_string())),
+        };
+        
+        if item.is_err() {
+            self.inner = None;
+        }
+        Some(item)
+    }
+}
+
 /// RocksDB-based storage implementation for EngramAI
 pub struct Storage {
     pub db: DB,
+    
+    /// Serializes counter updates so concurrent writers don't lose increments
+    stats_lock: Mutex<()>,
+    
+    /// Options the database was opened with
+    config: StorageConfig,
+    
+    /// Whether this is a read-only secondary instance tailing a primary
+    secondary: bool,
+    
+    /// Database options, kept to read statistics from
+    options: Options,
 }
 
 impl Storage {
     /// Creates a new Storage instance with the specified path
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_config(path, StorageConfig::default())
+    }
+    
+    /// Creates a new Storage instance with the specified path and options
+    pub fn with_config<P: AsRef<Path>>(path: P, config: StorageConfig) -> Result<Self> {
+        config.validate()?;
+        
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
+        // Keep flushed logs around so the change feed can replay them
+        opts.set_wal_ttl_seconds(config.change_feed_retention.as_secs());
+        if config.enable_statistics {
+            opts.enable_statistics();
+        }
+
+        // Open database with all column families
+        let db = DB::open_cf_descriptors(&opts, path, Self::column_family_descriptors(&config))
+            .map_err(|e| EngramError::StorageError(e.to_string()))?;
 
-        // Define column families
-        let cf_names = vec![
+        let storage = Self { db, stats_lock: Mutex::new(()), config, secondary: false, options: opts };
+        storage.migrate_embedding_keys()?;
+        storage.run_migrations()?;
+        
+        // Databases created before live counters existed need them computed once
+        if StatCounter::Engrams.read(&storage.db)?.is_none() {
+            storage.recompute_stats()?;
+        }
+
+        Ok(storage)
+    }
+    
+    /// Open a read-only secondary instance of the database at `primary_path`.
+    ///
+    /// The secondary keeps its own logs in `secondary_path` and sees the primary as it was
+    /// when opened or last caught up: writes made by the primary afterwards stay invisible
+    /// until `catch_up_with_primary` is called. Writes to a secondary fail, and no migrations
+    /// are run, so the primary must have been opened by this version at least once.
+    pub fn open_as_secondary<P: AsRef<Path>>(primary_path: P, secondary_path: P) -> Result<Self> {
+        let mut opts = Options::default();
+        // Secondary instances must keep every table file open to follow the primary's compactions
+        opts.set_max_open_files(-1);
+
+        let db = DB::open_cf_descriptors_as_secondary(
+            &opts,
+            primary_path,
+            secondary_path,
+            Self::column_family_descriptors(&StorageConfig::default()),
+        )
+        .map_err(|e| EngramError::StorageError(e.to_string()))?;
+
+        Ok(Self { db, stats_lock: Mutex::new(()), config: StorageConfig::default(), secondary: true, options: opts })
+    }
+    
+    /// Replay the primary's new writes into this secondary instance.
+    /// Reads reflect the primary as of the most recent catch-up.
+    pub fn catch_up_with_primary(&self) -> Result<()> {
+        if !self.secondary {
+            return Err(EngramError::InvalidOperation(
+                "Only secondary instances can catch up with a primary".to_string()
+            ));
+        }
+        
+        self.db
+            .try_catch_up_with_primary()
+            .map_err(|e| EngramError::StorageError(e.to_string()))
+    }
+    
+    /// Whether this instance is a read-only secondary
+    pub fn is_secondary(&self) -> bool {
+        self.secondary
+    }
+    
+    /// RocksDB's internal statistics (cache hit rates, bytes read and written, stall
+    /// times, ...) as text, or `None` unless `StorageConfig::enable_statistics` was set
+    pub fn statistics(&self) -> Option<String> {
+        self.options.get_statistics()
+    }
+    
+    /// Open the database at `path` read-only, e.g. a copy made by `create_checkpoint`.
+    /// Other processes may keep the database open for writing; this instance sees it as
+    /// it was when opened. Writes fail, and no migrations are run.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let opts = Options::default();
+        let db = DB::open_cf_descriptors_read_only(&opts, path, Self::column_family_descriptors(&StorageConfig::default()), false)
+            .map_err(|e| EngramError::StorageError(e.to_string()))?;
+
+        Ok(Self { db, stats_lock: Mutex::new(()), config: StorageConfig::default(), secondary: false, options: opts })
+    }
+    
+    /// Write a consistent copy of every column family to `dir` while the database stays
+    /// online. Table files are hard-linked where the filesystem allows, so checkpoints on
+    /// the same volume are cheap. `dir` must not exist or be empty.
+    pub fn create_checkpoint(&self, dir: &Path) -> Result<()> {
+        if dir.exists() {
+            let mut entries = std::fs::read_dir(dir)
+                .map_err(|e| EngramError::StorageError(format!("Failed to read checkpoint directory: {}", e)))?;
+            if entries.next().is_some() {
+                return Err(EngramError::StorageError(format!(
+                    "Checkpoint directory {} is not empty", dir.display()
+                )));
+            }
+            // RocksDB creates the directory itself and refuses one that exists
+            std::fs::remove_dir(dir)
+                .map_err(|e| EngramError::StorageError(format!("Failed to prepare checkpoint directory: {}", e)))?;
+        }
+        
+        let checkpoint = Checkpoint::new(&self.db).map_err(|e| EngramError::StorageError(e.to_string()))?;
+        checkpoint
+            .create_checkpoint(dir)
+            .map_err(|e| EngramError::StorageError(e.to_string()))
+    }
+    
+    /// Defaults and validation rules for creating engrams and connections against this database
+    pub fn schema_policy(&self) -> &SchemaPolicy {
+        &self.config.schema_policy
+    }
+    
+    /// Sequence number of the most recent write. Capture it before reading current state,
+    /// then follow `change_feed` from it to pick up everything written afterwards.
+    pub fn latest_sequence_number(&self) -> u64 {
+        self.db.latest_sequence_number()
+    }
+    
+    /// Stream typed changes to entity records made after `since_seq`, in write order, by
+    /// replaying the write-ahead log. Index entries and counters are not reported.
+    ///
+    /// Consumers resume by persisting the `sequence` of the last event they applied (or
+    /// `ChangeFeed::last_sequence`) and passing it back here. Logs are kept for
+    /// `StorageConfig::change_feed_retention` after flushing; resuming from a point older
+    /// than that fails with a storage error, and the consumer must resynchronize in full.
+    pub fn change_feed(&self, since_seq: u64) -> Result<ChangeFeed> {
+        let updates = self.db
+            .get_updates_since(since_seq)
+            .map_err(|e| EngramError::StorageError(e.to_string()))?;
+        
+        Ok(ChangeFeed {
+            updates,
+            pending: std::collections::VecDeque::new(),
+            since: since_seq,
+            last_sequence: since_seq,
+        })
+    }
+    
+    /// Descriptors for every column family the store uses
+    fn column_family_descriptors(config: &StorageConfig) -> Vec<ColumnFamilyDescriptor> {
+        let mut table_opts = BlockBasedOptions::default();
+        if let Some(bytes) = config.block_cache_size {
+            // One cache shared by every column family
+            table_opts.set_block_cache(&Cache::new_lru_cache(bytes));
+        }
+        if let Some(bits) = config.bloom_filter_bits_per_key {
+            table_opts.set_bloom_filter(bits, false);
+        }
+        
+        [
             CF_ENGRAMS,
             CF_CONNECTIONS,
             CF_COLLECTIONS,
@@ -95,22 +1084,224 @@ impl Storage {
             CF_METADATA,
             CF_RELATIONSHIPS,
             CF_EMBEDDINGS,
-        ];
-
-        let cf_descriptors: Vec<_> = cf_names
-            .iter()
-            .map(|name| {
-                let mut cf_opts = Options::default();
-                cf_opts.set_max_write_buffer_number(16);
-                ColumnFamilyDescriptor::new(*name, cf_opts)
-            })
-            .collect();
-
-        // Open database with all column families
-        let db = DB::open_cf_descriptors(&opts, path, cf_descriptors)
-            .map_err(|e| EngramError::StorageError(e.to_string()))?;
-
-        Ok(Self { db })
+            CF_HISTORY,
+        ]
+        .iter()
+        .map(|name| {
+            let mut cf_opts = Options::default();
+            cf_opts.set_max_write_buffer_number(16);
+            cf_opts.set_compression_type(config.compression.to_rocksdb());
+            cf_opts.set_block_based_table_factory(&table_opts);
+            if let Some(bytes) = config.write_buffer_size {
+                cf_opts.set_write_buffer_size(bytes);
+            }
+            ColumnFamilyDescriptor::new(*name, cf_opts)
+        })
+        .collect()
+    }
+    
+    /// Rewrite legacy embeddings stored under bare engram IDs to use the embedding prefix.
+    /// Returns the number of migrated keys.
+    pub fn migrate_embedding_keys(&self) -> Result<usize> {
+        let cf = self.db.cf_handle(CF_EMBEDDINGS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
+        })?;
+        
+        let mut batch = WriteBatch::default();
+        let mut migrated = 0;
+        
+        for result in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, value) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+            
+            if key.starts_with(EMBEDDING_PREFIX) || key.starts_with(REDUCED_EMBEDDING_PREFIX) {
+                continue;
+            }
+            
+            let new_key = [EMBEDDING_PREFIX, &key[..]].concat();
+            batch.put_cf(cf, new_key, value);
+            batch.delete_cf(cf, key);
+            migrated += 1;
+        }
+        
+        if migrated > 0 {
+            self.db
+                .write(batch)
+                .map_err(|e| EngramError::StorageError(e.to_string()))?;
+        }
+        
+        Ok(migrated)
+    }
+    
+    /// Get the schema version of the stored records.
+    /// Databases written before versioning existed are reported as version 1.
+    pub fn schema_version(&self) -> Result<u32> {
+        let cf = self.db.cf_handle(CF_METADATA).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_METADATA))
+        })?;
+        
+        match self.db.get_pinned_cf(cf, SCHEMA_VERSION_KEY)? {
+            Some(bytes) => {
+                let bytes: [u8; 4] = bytes.as_ref().try_into().map_err(|_| {
+                    EngramError::InvalidState("Schema version is not a 4-byte value".to_string())
+                })?;
+                Ok(u32::from_le_bytes(bytes))
+            },
+            None => Ok(1),
+        }
+    }
+    
+    /// Bring stored records up to `CURRENT_SCHEMA_VERSION`, applying each migration in turn.
+    /// Fresh databases are stamped with the current version directly.
+    fn run_migrations(&self) -> Result<()> {
+        let cf = self.db.cf_handle(CF_METADATA).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_METADATA))
+        })?;
+        
+        let stamped = self.db.get_pinned_cf(cf, SCHEMA_VERSION_KEY)?.is_some();
+        if !stamped && self.iter_engrams().next().is_none() {
+            self.db.put_cf(cf, SCHEMA_VERSION_KEY, CURRENT_SCHEMA_VERSION.to_le_bytes())
+                .map_err(|e| EngramError::StorageError(e.to_string()))?;
+            return Ok(());
+        }
+        
+        let mut version = self.schema_version()?;
+        if version > CURRENT_SCHEMA_VERSION {
+            return Err(EngramError::InvalidState(format!(
+                "Database schema version {} is newer than the supported version {}",
+                version, CURRENT_SCHEMA_VERSION
+            )));
+        }
+        
+        while version < CURRENT_SCHEMA_VERSION {
+            let migration = MIGRATIONS.iter()
+                .find(|(from, _)| *from == version)
+                .map(|(_, migration)| *migration)
+                .ok_or_else(|| EngramError::InvalidState(format!(
+                    "No migration available from schema version {}", version
+                )))?;
+            
+            // Records and the new version stamp are written atomically
+            let mut batch = WriteBatch::default();
+            migration(self, &mut batch)?;
+            version += 1;
+            batch.put_cf(cf, SCHEMA_VERSION_KEY, version.to_le_bytes());
+            self.db
+                .write(batch)
+                .map_err(|e| EngramError::StorageError(e.to_string()))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// v1 -> v2: fill in the importance, access tracking and TTL fields that v1 engrams lack
+    fn migrate_v1_to_v2(&self, batch: &mut WriteBatch) -> Result<usize> {
+        let cf = self.db.cf_handle(CF_ENGRAMS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_ENGRAMS))
+        })?;
+        
+        let mut migrated = 0;
+        
+        for result in self.db.iterator_cf(cf, IteratorMode::From(ENGRAM_PREFIX, rocksdb::Direction::Forward)) {
+            let (key, value) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+            if !key.starts_with(ENGRAM_PREFIX) {
+                break;
+            }
+            
+            let mut record: serde_json::Value = Self::deserialize(&value)?;
+            let Some(fields) = record.as_object_mut() else {
+                continue;
+            };
+            
+            let timestamp = fields.get("timestamp").cloned().unwrap_or(serde_json::Value::Null);
+            let defaults = [
+                ("importance", serde_json::json!(0.5)),
+                ("access_count", serde_json::json!(0)),
+                ("last_accessed", timestamp),
+                ("ttl", serde_json::Value::Null),
+                ("metadata", serde_json::json!({})),
+            ];
+            
+            let mut changed = false;
+            for (field, default) in defaults {
+                if !fields.contains_key(field) {
+                    fields.insert(field.to_string(), default);
+                    changed = true;
+                }
+            }
+            
+            if changed {
+                batch.put_cf(cf, &key, self.serialize(&record)?);
+                migrated += 1;
+            }
+        }
+        
+        Ok(migrated)
+    }
+    
+    /// v2 -> v3: build the reverse membership index for existing collections and contexts
+    fn migrate_v2_to_v3(&self, batch: &mut WriteBatch) -> Result<usize> {
+        let mut migrated = 0;
+        
+        for collection in self.iter_collections() {
+            let collection = collection?;
+            Self::stage_membership(&self.db, batch, COLLECTION_MEMBER_PREFIX, &collection.id, None, Some(&collection.engram_ids))?;
+            migrated += 1;
+        }
+        
+        for context in self.iter_contexts() {
+            let context = context?;
+            Self::stage_membership(&self.db, batch, CONTEXT_MEMBER_PREFIX, &context.id, None, Some(&context.engram_ids))?;
+            migrated += 1;
+        }
+        
+        Ok(migrated)
+    }
+    
+    /// v3 -> v4: records gain a leading format tag. Untagged records stay readable as JSON,
+    /// so nothing is rewritten; the bump keeps older builds from misreading tagged records.
+    fn migrate_v3_to_v4(&self, _batch: &mut WriteBatch) -> Result<usize> {
+        Ok(0)
+    }
+    
+    /// v4 -> v5: seed the version history with the current records. Engrams are recorded as
+    /// of their creation time; collections, whose creation time is unknown, as of the epoch
+    /// (the default `DateTime`).
+    fn migrate_v4_to_v5(&self, batch: &mut WriteBatch) -> Result<usize> {
+        let mut migrated = 0;
+        
+        for engram in self.iter_engrams() {
+            let engram = engram?;
+            let value = self.serialize(&engram)?;
+            Self::stage_history(&self.db, batch, ENGRAM_HISTORY_PREFIX, &engram.id, Some(&value), engram.timestamp)?;
+            migrated += 1;
+        }
+        
+        for collection in self.iter_collections() {
+            let collection = collection?;
+            let value = self.serialize(&collection)?;
+            Self::stage_history(&self.db, batch, COLLECTION_HISTORY_PREFIX, &collection.id, Some(&value), chrono::DateTime::default())?;
+            migrated += 1;
+        }
+        
+        Ok(migrated)
+    }
+    
+    /// v5 -> v6: build the persisted confidence and importance indexes
+    fn migrate_v5_to_v6(&self, batch: &mut WriteBatch) -> Result<usize> {
+        let mut migrated = 0;
+        
+        for engram in self.iter_engrams() {
+            let engram = engram?;
+            Self::stage_score_indexes(&self.db, batch, &engram.id, None, Some(EngramScores::of(&engram)))?;
+            migrated += 1;
+        }
+        
+        Ok(migrated)
+    }
+    
+    /// Options the database was opened with
+    pub fn config(&self) -> &StorageConfig {
+        &self.config
     }
     
     /// List all engram IDs in the database
@@ -228,6 +1419,174 @@ impl Storage {
         Ok(context_ids)
     }
     
+    /// Iterate over all engrams without materializing them
+    pub fn iter_engrams(&self) -> EntityIter<'_, Engram> {
+        self.iter_entities(CF_ENGRAMS, ENGRAM_PREFIX)
+    }
+    
+    /// Iterate over all connections without materializing them
+    pub fn iter_connections(&self) -> EntityIter<'_, Connection> {
+        self.iter_entities(CF_CONNECTIONS, CONNECTION_PREFIX)
+    }
+    
+    /// Iterate over all collections without materializing them
+    pub fn iter_collections(&self) -> EntityIter<'_, Collection> {
+        self.iter_entities(CF_COLLECTIONS, COLLECTION_PREFIX)
+    }
+    
+    /// Iterate over all agents without materializing them
+    pub fn iter_agents(&self) -> EntityIter<'_, Agent> {
+        self.iter_entities(CF_AGENTS, AGENT_PREFIX)
+    }
+    
+    /// Iterate over all contexts without materializing them
+    pub fn iter_contexts(&self) -> EntityIter<'_, Context> {
+        self.iter_entities(CF_CONTEXTS, CONTEXT_PREFIX)
+    }
+    
+    /// Read up to `limit` engrams in ID order, starting after the engram whose ID is `cursor`.
+    /// The cursor of the returned page's metadata continues the listing, so pages stay stable
+    /// while engrams are added or removed elsewhere in the keyspace.
+    pub fn engrams_page(&self, cursor: Option<&str>, limit: usize) -> Result<Page<Engram>> {
+        let total = self.get_stats()?.engram_count;
+        self.entities_page(CF_ENGRAMS, ENGRAM_PREFIX, cursor, limit, total, |engram: &Engram| engram.id.clone())
+    }
+    
+    /// List up to `limit` engram IDs in ID order, starting after `after`. Returns the IDs and
+    /// the cursor for the next page, or `None` on the last page. Only keys are read, and the
+    /// cursor is an engram ID, so engrams added between calls never shift existing ones
+    /// between pages.
+    pub fn list_engrams_paged(&self, after: Option<&str>, limit: usize) -> Result<(Vec<EngramId>, Option<String>)> {
+        let (ids, has_more) = self.scan_page(CF_ENGRAMS, ENGRAM_PREFIX, after, limit, |key, _| {
+            Ok(String::from_utf8_lossy(&key[ENGRAM_PREFIX.len()..]).to_string())
+        })?;
+        
+        let next = if has_more { ids.last().cloned() } else { None };
+        Ok((ids, next))
+    }
+    
+    /// Read one page of records with the given prefix, resuming after the record keyed by `cursor`
+    fn entities_page<T: DeserializeOwned>(
+        &self,
+        cf_name: &str,
+        prefix: &'static [u8],
+        cursor: Option<&str>,
+        limit: usize,
+        total: usize,
+        id_of: impl Fn(&T) -> String,
+    ) -> Result<Page<T>> {
+        let (items, has_more) = self.scan_page(cf_name, prefix, cursor, limit, |_, value| Self::deserialize(value))?;
+        
+        let cursor = if has_more { items.last().map(id_of) } else { None };
+        Ok(Page {
+            items,
+            meta: PaginationMeta { total, cursor, has_more },
+        })
+    }
+    
+    /// Decode up to `limit` entries with the given prefix in key order, starting after the entry
+    /// keyed by `cursor`. Also returns whether more entries follow the page.
+    fn scan_page<T>(
+        &self,
+        cf_name: &str,
+        prefix: &[u8],
+        cursor: Option<&str>,
+        limit: usize,
+        decode: impl Fn(&[u8], &[u8]) -> Result<T>,
+    ) -> Result<(Vec<T>, bool)> {
+        if limit == 0 {
+            return Err(EngramError::InvalidOperation("Page limit must be at least 1".to_string()));
+        }
+        
+        let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", cf_name))
+        })?;
+        
+        let start = match cursor {
+            Some(cursor) => Self::create_key(prefix, cursor),
+            None => prefix.to_vec(),
+        };
+        
+        let mut items = Vec::with_capacity(limit);
+        let mut has_more = false;
+        let mut iter = self.db.raw_iterator_cf(cf);
+        iter.seek(&start);
+        while let Some(key) = iter.key() {
+            if !key.starts_with(prefix) {
+                break;
+            }
+            if cursor.is_none() || key != start.as_slice() {
+                if items.len() == limit {
+                    has_more = true;
+                    break;
+                }
+                items.push(decode(key, iter.value().unwrap_or_default())?);
+            }
+            iter.next();
+        }
+        iter.status().map_err(|e| EngramError::StorageError(e.to_string()))?;
+        
+        Ok((items, has_more))
+    }
+    
+    /// Start a lazy iterator over the records with the given prefix in a column family
+    fn iter_entities<T: DeserializeOwned>(&self, cf_name: &str, prefix: &'static [u8]) -> EntityIter<'_, T> {
+        match self.db.cf_handle(cf_name) {
+            Some(cf) => EntityIter {
+                inner: Some(self.db.iterator_cf(cf, IteratorMode::From(prefix, rocksdb::Direction::Forward))),
+                prefix,
+                pending_error: None,
+                _marker: PhantomData,
+            },
+            None => EntityIter {
+                inner: None,
+                prefix,
+                pending_error: Some(EngramError::StorageError(format!("Column family {} not found", cf_name))),
+                _marker: PhantomData,
+            },
+        }
+    }
+    
+    /// Try to deserialize every stored entity and embedding, listing the keys that fail.
+    /// Nothing is modified; use this to diagnose corruption or schema mismatches.
+    pub fn scan_health(&self) -> Result<HealthReport> {
+        let mut report = HealthReport::default();
+        
+        self.scan_records::<Engram>(CF_ENGRAMS, ENGRAM_PREFIX, &mut report)?;
+        self.scan_records::<Connection>(CF_CONNECTIONS, CONNECTION_PREFIX, &mut report)?;
+        self.scan_records::<Collection>(CF_COLLECTIONS, COLLECTION_PREFIX, &mut report)?;
+        self.scan_records::<Agent>(CF_AGENTS, AGENT_PREFIX, &mut report)?;
+        self.scan_records::<Context>(CF_CONTEXTS, CONTEXT_PREFIX, &mut report)?;
+        self.scan_records::<Embedding>(CF_EMBEDDINGS, EMBEDDING_PREFIX, &mut report)?;
+        self.scan_records::<Embedding>(CF_EMBEDDINGS, REDUCED_EMBEDDING_PREFIX, &mut report)?;
+        
+        Ok(report)
+    }
+    
+    /// Check the records under one prefix for `scan_health`, one value in memory at a time
+    fn scan_records<T: DeserializeOwned>(&self, cf_name: &str, prefix: &[u8], report: &mut HealthReport) -> Result<()> {
+        let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", cf_name))
+        })?;
+        
+        for result in self.db.iterator_cf(cf, IteratorMode::From(prefix, rocksdb::Direction::Forward)) {
+            let (key, value) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            
+            report.records_scanned += 1;
+            if let Err(e) = Self::deserialize::<T>(&value) {
+                report.unreadable.entry(cf_name.to_string()).or_default().push(UnreadableRecord {
+                    key: String::from_utf8_lossy(&key).to_string(),
+                    error: e.to_string(),
+                });
+            }
+        }
+        
+        Ok(())
+    }
+    
     /// Find all connections related to a specific engram (either as source or target)
     pub fn find_connections_for_engram(&self, engram_id: &EngramId) -> Result<HashSet<ConnectionId>> {
         // Get outgoing and incoming connections from the relationship index
@@ -236,68 +1595,423 @@ impl Storage {
         // Add outgoing connections
         connection_ids.extend(self.find_outgoing_connections(engram_id)?);
         
-        // Add incoming connections
-        connection_ids.extend(self.find_incoming_connections(engram_id)?);
+        // Add incoming connections
+        connection_ids.extend(self.find_incoming_connections(engram_id)?);
+        
+        Ok(connection_ids)
+    }
+    
+    /// Find all outgoing connections from a source engram
+    pub fn find_outgoing_connections(&self, source_id: &EngramId) -> Result<HashSet<ConnectionId>> {
+        let cf = self.db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_RELATIONSHIPS))
+        })?;
+        
+        let mut connection_ids = HashSet::new();
+        
+        // Create the prefix for the source engram; the trailing separator keeps one engram ID
+        // from matching another it prefixes
+        let prefix = [SOURCE_CONNECTION_PREFIX, source_id.as_bytes(), b":"].concat();
+        
+        // Keys are sorted, so the scan ends at the first key outside the prefix
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(&prefix, rocksdb::Direction::Forward));
+        
+        for result in iter {
+            let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+            
+            // Extract connection ID from the key
+            // Key format: source_conn:{source_id}:{connection_id}
+            match key.strip_prefix(prefix.as_slice()) {
+                Some(connection_id) => connection_ids.insert(String::from_utf8_lossy(connection_id).to_string()),
+                None => break,
+            };
+        }
+        
+        Ok(connection_ids)
+    }
+    
+    /// Find all incoming connections to a target engram
+    pub fn find_incoming_connections(&self, target_id: &EngramId) -> Result<HashSet<ConnectionId>> {
+        let cf = self.db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_RELATIONSHIPS))
+        })?;
+        
+        let mut connection_ids = HashSet::new();
+        
+        // Create the prefix for the target engram; the trailing separator keeps one engram ID
+        // from matching another it prefixes
+        let prefix = [TARGET_CONNECTION_PREFIX, target_id.as_bytes(), b":"].concat();
+        
+        // Keys are sorted, so the scan ends at the first key outside the prefix
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(&prefix, rocksdb::Direction::Forward));
+        
+        for result in iter {
+            let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+            
+            // Extract connection ID from the key
+            // Key format: target_conn:{target_id}:{connection_id}
+            match key.strip_prefix(prefix.as_slice()) {
+                Some(connection_id) => connection_ids.insert(String::from_utf8_lossy(connection_id).to_string()),
+                None => break,
+            };
+        }
+        
+        Ok(connection_ids)
+    }
+    
+    /// Fetch an engram along with the related records named in `expansion`, read from the
+    /// relationship and reverse membership indexes. Returns `None` if the engram doesn't exist.
+    pub fn engram_detail(&self, id: &EngramId, expansion: EngramExpansion) -> Result<Option<EngramDetail>> {
+        let Some(engram) = self.get_engram(id)? else {
+            return Ok(None);
+        };
+        
+        let mut detail = EngramDetail { engram, outgoing: None, incoming: None, collections: None };
+        
+        if expansion.connections {
+            let summarize = |connection_ids: HashSet<ConnectionId>, outgoing: bool| -> Result<Vec<ConnectionSummary>> {
+                let mut summaries = Vec::with_capacity(connection_ids.len());
+                for connection_id in connection_ids {
+                    if let Some(connection) = self.get_connection(&connection_id)? {
+                        summaries.push(ConnectionSummary {
+                            id: connection.id,
+                            engram_id: if outgoing { connection.target_id } else { connection.source_id },
+                            relationship_type: connection.relationship_type,
+                            weight: connection.weight,
+                        });
+                    }
+                }
+                summaries.sort_by(|a, b| a.id.cmp(&b.id));
+                Ok(summaries)
+            };
+            detail.outgoing = Some(summarize(self.find_outgoing_connections(id)?, true)?);
+            detail.incoming = Some(summarize(self.find_incoming_connections(id)?, false)?);
+        }
+        
+        if expansion.collections {
+            let mut collections = Vec::new();
+            for collection_id in self.collections_for_engram(id)? {
+                if let Some(collection) = self.get_collection(&collection_id)? {
+                    collections.push(CollectionSummary { id: collection.id, name: collection.name });
+                }
+            }
+            collections.sort_by(|a, b| a.id.cmp(&b.id));
+            detail.collections = Some(collections);
+        }
+        
+        Ok(Some(detail))
+    }
+    
+    /// Find the IDs of all collections containing an engram, via the reverse membership index
+    pub fn collections_for_engram(&self, engram_id: &EngramId) -> Result<HashSet<CollectionId>> {
+        self.find_memberships(COLLECTION_MEMBER_PREFIX, engram_id)
+    }
+    
+    /// Find the IDs of all contexts containing an engram, via the reverse membership index
+    pub fn contexts_for_engram(&self, engram_id: &EngramId) -> Result<HashSet<ContextId>> {
+        self.find_memberships(CONTEXT_MEMBER_PREFIX, engram_id)
+    }
+    
+    /// Find the engrams and connections attributed to an agent through their `created_by`
+    /// field. Entries whose record was since deleted or re-attributed are skipped.
+    pub fn entities_created_by(&self, agent_id: &AgentId) -> Result<CreatedEntities> {
+        let mut engrams = Vec::new();
+        for id in self.find_memberships(CREATED_ENGRAM_PREFIX, agent_id)? {
+            if let Some(engram) = self.get_engram(&id)? {
+                if engram.created_by.as_ref() == Some(agent_id) {
+                    engrams.push(id);
+                }
+            }
+        }
+        
+        let mut connections = Vec::new();
+        for id in self.find_memberships(CREATED_CONNECTION_PREFIX, agent_id)? {
+            if let Some(connection) = self.get_connection(&id)? {
+                if connection.created_by.as_ref() == Some(agent_id) {
+                    connections.push(id);
+                }
+            }
+        }
+        
+        engrams.sort();
+        connections.sort();
+        Ok(CreatedEntities { engrams, connections })
+    }
+    
+    /// Stage adding (or removing) the attribution index entry for an entity created by `agent_id`
+    fn stage_attribution(
+        db: &DB,
+        batch: &mut WriteBatch,
+        prefix: &[u8],
+        agent_id: Option<&AgentId>,
+        entity_id: &str,
+        is_put: bool,
+    ) -> Result<()> {
+        let Some(agent_id) = agent_id else {
+            return Ok(());
+        };
+        let cf = db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_RELATIONSHIPS))
+        })?;
+        
+        let key = Self::create_relationship_key(prefix, agent_id, entity_id);
+        if is_put {
+            batch.put_cf(cf, key, vec![]);
+        } else {
+            batch.delete_cf(cf, key);
+        }
+        Ok(())
+    }
+    
+    /// Key of an engram in a score index
+    fn score_index_key(prefix: &[u8], score: f64, engram_id: &str) -> Vec<u8> {
+        let mut key = prefix.to_vec();
+        key.extend_from_slice(format!("{:03}:", score_bucket(score)).as_bytes());
+        key.extend_from_slice(engram_id.as_bytes());
+        key
+    }
+    
+    /// Stage the confidence and importance index changes for an engram going from
+    /// `previous` to `current` scores, where `None` means the engram doesn't exist
+    fn stage_score_indexes(
+        db: &DB,
+        batch: &mut WriteBatch,
+        engram_id: &str,
+        previous: Option<EngramScores>,
+        current: Option<EngramScores>,
+    ) -> Result<()> {
+        let cf = db.cf_handle(CF_METADATA).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_METADATA))
+        })?;
+        
+        if let Some(previous) = previous {
+            batch.delete_cf(cf, Self::score_index_key(CONFIDENCE_INDEX_PREFIX, previous.confidence, engram_id));
+            batch.delete_cf(cf, Self::score_index_key(IMPORTANCE_INDEX_PREFIX, previous.importance, engram_id));
+        }
+        if let Some(current) = current {
+            batch.put_cf(cf, Self::score_index_key(CONFIDENCE_INDEX_PREFIX, current.confidence, engram_id), current.confidence.to_le_bytes());
+            batch.put_cf(cf, Self::score_index_key(IMPORTANCE_INDEX_PREFIX, current.importance, engram_id), current.importance.to_le_bytes());
+        }
+        Ok(())
+    }
+    
+    /// Stage a history entry recording `value` as the entity's record from `at` onwards,
+    /// or its deletion when `value` is `None`
+    fn stage_history(
+        db: &DB,
+        batch: &mut WriteBatch,
+        prefix: &[u8],
+        entity_id: &str,
+        value: Option<&[u8]>,
+        at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let cf = db.cf_handle(CF_HISTORY).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_HISTORY))
+        })?;
+        
+        // Deletions are recorded as empty values, which no serialized record can be
+        batch.put_cf(cf, Self::history_key(prefix, entity_id, at), value.unwrap_or_default());
+        Ok(())
+    }
+    
+    /// History key for an entity's version written at `at`. Times before the epoch sort first.
+    fn history_key(prefix: &[u8], entity_id: &str, at: chrono::DateTime<chrono::Utc>) -> Vec<u8> {
+        let nanos = at.timestamp_nanos_opt()
+            .unwrap_or(if at.timestamp() < 0 { 0 } else { i64::MAX })
+            .max(0);
+        [prefix, entity_id.as_bytes(), format!(":{:020}", nanos).as_bytes()].concat()
+    }
+    
+    /// The record an entity had at `at` according to its history, or `None` if it did not
+    /// exist then (not yet written, or deleted)
+    fn record_as_of<T: DeserializeOwned>(&self, prefix: &[u8], entity_id: &str, at: chrono::DateTime<chrono::Utc>) -> Result<Option<T>> {
+        let cf = self.db.cf_handle(CF_HISTORY).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_HISTORY))
+        })?;
+        
+        // The latest version written at or before `at` is the last key not past it
+        let entity_prefix = [prefix, entity_id.as_bytes(), b":"].concat();
+        let seek = Self::history_key(prefix, entity_id, at);
+        let mut versions = self.db.iterator_cf(cf, IteratorMode::From(&seek, rocksdb::Direction::Reverse));
+        
+        match versions.next() {
+            Some(result) => {
+                let (key, value) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+                if !key.starts_with(&entity_prefix) || value.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(Self::deserialize(&value)?))
+            },
+            None => Ok(None),
+        }
+    }
+    
+    /// The engram as it was at `at`, from its version history
+    pub fn engram_as_of(&self, id: &EngramId, at: chrono::DateTime<chrono::Utc>) -> Result<Option<Engram>> {
+        self.record_as_of(ENGRAM_HISTORY_PREFIX, id, at)
+    }
+    
+    /// When the engram's latest version was written (or it was deleted), from its version history
+    pub fn engram_last_modified(&self, id: &EngramId) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        let cf = self.db.cf_handle(CF_HISTORY).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_HISTORY))
+        })?;
+        
+        let entity_prefix = [ENGRAM_HISTORY_PREFIX, id.as_bytes(), b":"].concat();
+        let seek = Self::history_key(ENGRAM_HISTORY_PREFIX, id, chrono::DateTime::<chrono::Utc>::MAX_UTC);
+        
+        match self.db.iterator_cf(cf, IteratorMode::From(&seek, rocksdb::Direction::Reverse)).next() {
+            Some(result) => {
+                let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+                let nanos = key.strip_prefix(entity_prefix.as_slice())
+                    .and_then(|suffix| std::str::from_utf8(suffix).ok())
+                    .and_then(|suffix| suffix.parse::<i64>().ok());
+                Ok(nanos.and_then(|nanos| {
+                    chrono::DateTime::from_timestamp(nanos.div_euclid(1_000_000_000), nanos.rem_euclid(1_000_000_000) as u32)
+                }))
+            },
+            None => Ok(None),
+        }
+    }
+    
+    /// The collection as it was at `at`: its membership then, and each member engram's content
+    /// then. Members that did not exist at `at` are left out. Returns `None` if the collection
+    /// itself did not exist.
+    pub fn collection_as_of(&self, id: &CollectionId, at: chrono::DateTime<chrono::Utc>) -> Result<Option<CollectionSnapshot>> {
+        let Some(collection) = self.record_as_of::<Collection>(COLLECTION_HISTORY_PREFIX, id, at)? else {
+            return Ok(None);
+        };
+        
+        let mut engrams = Vec::with_capacity(collection.engram_ids.len());
+        for engram_id in &collection.engram_ids {
+            if let Some(engram) = self.engram_as_of(engram_id, at)? {
+                engrams.push(engram);
+            }
+        }
+        engrams.sort_by(|a, b| a.id.cmp(&b.id));
         
-        Ok(connection_ids)
+        Ok(Some(CollectionSnapshot { as_of: at, collection, engrams }))
     }
     
-    /// Find all outgoing connections from a source engram
-    pub fn find_outgoing_connections(&self, source_id: &EngramId) -> Result<HashSet<ConnectionId>> {
+    /// Collect the owner IDs stored under `{prefix}{engram_id}:`
+    fn find_memberships(&self, prefix: &[u8], engram_id: &EngramId) -> Result<HashSet<String>> {
         let cf = self.db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
             EngramError::StorageError(format!("Column family {} not found", CF_RELATIONSHIPS))
         })?;
         
-        let mut connection_ids = HashSet::new();
-        
-        // Create the prefix for the source engram
-        let prefix = [SOURCE_CONNECTION_PREFIX, source_id.as_bytes()].concat();
+        // The trailing separator keeps one engram ID from matching another it prefixes
+        let prefix = [prefix, engram_id.as_bytes(), b":"].concat();
+        let mut owner_ids = HashSet::new();
         
-        // Iterate through keys with this prefix
-        let iter = self.db.prefix_iterator_cf(cf, &prefix);
-        
-        for result in iter {
+        for result in self.db.iterator_cf(cf, IteratorMode::From(&prefix, rocksdb::Direction::Forward)) {
             let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
             
-            // Extract connection ID from the key
-            // Key format: source_conn:{source_id}:{connection_id}
-            let parts: Vec<&[u8]> = key.split(|&b| b == b':').collect();
-            if parts.len() >= 3 {
-                let connection_id = String::from_utf8_lossy(parts[2]).to_string();
-                connection_ids.insert(connection_id);
-            }
+            // Key format: {prefix}{engram_id}:{owner_id}
+            match key.strip_prefix(prefix.as_slice()) {
+                Some(owner_id) => owner_ids.insert(String::from_utf8_lossy(owner_id).to_string()),
+                None => break,
+            };
         }
         
-        Ok(connection_ids)
+        Ok(owner_ids)
     }
     
-    /// Find all incoming connections to a target engram
-    pub fn find_incoming_connections(&self, target_id: &EngramId) -> Result<HashSet<ConnectionId>> {
-        let cf = self.db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
+    /// Stage reverse membership index updates for an owner whose engram set changes from
+    /// `previous` to `current` (`None` meaning the owner did not exist / is being deleted)
+    fn stage_membership(
+        db: &DB,
+        batch: &mut WriteBatch,
+        prefix: &[u8],
+        owner_id: &str,
+        previous: Option<&HashSet<EngramId>>,
+        current: Option<&HashSet<EngramId>>,
+    ) -> Result<()> {
+        let cf = db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
             EngramError::StorageError(format!("Column family {} not found", CF_RELATIONSHIPS))
         })?;
         
-        let mut connection_ids = HashSet::new();
+        let empty = HashSet::new();
+        let previous = previous.unwrap_or(&empty);
+        let current = current.unwrap_or(&empty);
         
-        // Create the prefix for the target engram
-        let prefix = [TARGET_CONNECTION_PREFIX, target_id.as_bytes()].concat();
+        for engram_id in previous.difference(current) {
+            batch.delete_cf(cf, Self::create_relationship_key(prefix, engram_id, owner_id));
+        }
+        for engram_id in current.difference(previous) {
+            batch.put_cf(cf, Self::create_relationship_key(prefix, engram_id, owner_id), vec![]);
+        }
         
-        // Iterate through keys with this prefix
-        let iter = self.db.prefix_iterator_cf(cf, &prefix);
+        Ok(())
+    }
+    
+    /// Read a record straight from a column family, for callers that only hold the DB handle
+    fn read_record<T: DeserializeOwned>(db: &DB, cf_name: &str, prefix: &[u8], id: &str) -> Result<Option<T>> {
+        let cf = db.cf_handle(cf_name).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", cf_name))
+        })?;
         
-        for result in iter {
-            let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+        match db.get_cf(cf, Self::create_key(prefix, id))? {
+            Some(bytes) => Ok(Some(Self::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Find engrams with confidence of at least `min`, using the persisted confidence index.
+    /// Only the buckets at or above `min` are scanned, without reading any engram.
+    pub fn find_by_min_confidence(&self, min: f64) -> Result<HashSet<EngramId>> {
+        self.scan_score_index(CONFIDENCE_INDEX_PREFIX, min)
+    }
+    
+    /// Find engrams with importance of at least `min`, using the persisted importance index
+    pub fn find_by_min_importance(&self, min: f64) -> Result<HashSet<EngramId>> {
+        self.scan_score_index(IMPORTANCE_INDEX_PREFIX, min)
+    }
+    
+    /// Collect the engrams in a score index whose exact score is at least `min`
+    fn scan_score_index(&self, prefix: &[u8], min: f64) -> Result<HashSet<EngramId>> {
+        let cf = self.db.cf_handle(CF_METADATA).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_METADATA))
+        })?;
+        
+        // Every bucket from the one holding `min` upwards; only that first bucket can hold lower scores
+        let mut start = prefix.to_vec();
+        start.extend_from_slice(format!("{:03}", score_bucket(min)).as_bytes());
+        
+        let mut ids = HashSet::new();
+        for result in self.db.iterator_cf(cf, IteratorMode::From(&start, rocksdb::Direction::Forward)) {
+            let (key, value) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
             
-            // Extract connection ID from the key
-            // Key format: target_conn:{target_id}:{connection_id}
-            let parts: Vec<&[u8]> = key.split(|&b| b == b':').collect();
-            if parts.len() >= 3 {
-                let connection_id = String::from_utf8_lossy(parts[2]).to_string();
-                connection_ids.insert(connection_id);
+            let score: [u8; 8] = value.as_ref().try_into().map_err(|_| {
+                EngramError::StorageError("Corrupt score index entry".to_string())
+            })?;
+            if f64::from_le_bytes(score) >= min {
+                // Skip the "NNN:" bucket to reach the engram ID
+                let id = &key[prefix.len() + 4..];
+                ids.insert(String::from_utf8_lossy(id).to_string());
             }
         }
         
-        Ok(connection_ids)
+        Ok(ids)
+    }
+    
+    /// Read several records from a column family with one `multi_get`, in the order of `ids`
+    fn read_records<T: DeserializeOwned>(&self, cf_name: &str, prefix: &[u8], ids: &[String]) -> Result<Vec<Option<T>>> {
+        let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", cf_name))
+        })?;
+        
+        let keys = ids.iter().map(|id| (cf, Self::create_key(prefix, id)));
+        self.db
+            .multi_get_cf(keys)
+            .into_iter()
+            .map(|result| match result? {
+                Some(bytes) => Ok(Some(Self::deserialize(&bytes)?)),
+                None => Ok(None),
+            })
+            .collect()
     }
     
     /// Find all connections with a specific relationship type
@@ -308,8 +2022,9 @@ impl Storage {
         
         let mut connection_ids = HashSet::new();
         
-        // Create the prefix for the relationship type
-        let prefix = [RELATION_TYPE_PREFIX, relationship_type.as_bytes()].concat();
+        // Create the prefix for the relationship type, ending at the separator so
+        // "related" doesn't also match "related_to"
+        let prefix = [RELATION_TYPE_PREFIX, relationship_type.as_bytes(), b":"].concat();
         
         // Iterate through keys with this prefix
         let iter = self.db.prefix_iterator_cf(cf, &prefix);
@@ -317,6 +2032,11 @@ impl Storage {
         for result in iter {
             let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
             
+            // Without a prefix extractor the iterator runs on past the prefix
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            
             // Extract connection ID from the key
             // Key format: rel_type:{relationship_type}:{connection_id}
             let parts: Vec<&[u8]> = key.split(|&b| b == b':').collect();
@@ -339,8 +2059,8 @@ impl Storage {
             EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
         })?;
         
-        let key = engram_id.as_bytes();
-        let value = Self::serialize(embedding)?;
+        let key = Self::create_key(EMBEDDING_PREFIX, engram_id);
+        let value = self.serialize(embedding)?;
         
         self.db
             .put_cf(cf, key, value)
@@ -353,7 +2073,7 @@ impl Storage {
             EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
         })?;
         
-        let key = engram_id.as_bytes();
+        let key = Self::create_key(EMBEDDING_PREFIX, engram_id);
         
         match self.db.get_cf(cf, key)? {
             Some(bytes) => Ok(Some(Self::deserialize(&bytes)?)),
@@ -368,7 +2088,7 @@ impl Storage {
         })?;
         
         let key = [REDUCED_EMBEDDING_PREFIX, engram_id.as_bytes()].concat();
-        let value = Self::serialize(embedding)?;
+        let value = self.serialize(embedding)?;
         
         self.db
             .put_cf(cf, key, value)
@@ -408,63 +2128,199 @@ impl Storage {
             EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
         })?;
         
-        let key = engram_id.as_bytes();
+        let key = Self::create_key(EMBEDDING_PREFIX, engram_id);
         
         self.db
             .delete_cf(cf, key)
             .map_err(|e| EngramError::StorageError(e.to_string()))
     }
     
-    /// List all embeddings in the database
+    /// List the IDs of all engrams with a full embedding in the database
     pub fn list_embeddings(&self) -> Result<Vec<EngramId>> {
+        self.list_embedding_ids(EMBEDDING_PREFIX)
+    }
+    
+    /// List the IDs of all engrams with a reduced embedding in the database
+    pub fn list_reduced_embeddings(&self) -> Result<Vec<EngramId>> {
+        self.list_embedding_ids(REDUCED_EMBEDDING_PREFIX)
+    }
+    
+    /// List the IDs of engrams that have a full or reduced embedding but no longer exist
+    pub fn list_orphaned_embeddings(&self) -> Result<Vec<EngramId>> {
+        let mut embedded_ids: Vec<EngramId> = self.list_embeddings()?;
+        embedded_ids.extend(self.list_reduced_embeddings()?);
+        embedded_ids.sort();
+        embedded_ids.dedup();
+        
+        let mut orphaned = Vec::new();
+        for id in embedded_ids {
+            if self.get_engram(&id)?.is_none() {
+                orphaned.push(id);
+            }
+        }
+        
+        Ok(orphaned)
+    }
+    
+    /// List engram IDs for embedding keys with the given prefix
+    fn list_embedding_ids(&self, prefix: &[u8]) -> Result<Vec<EngramId>> {
         let cf = self.db.cf_handle(CF_EMBEDDINGS).ok_or_else(|| {
             EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
         })?;
         
         let mut engram_ids = Vec::new();
-        let iter = self.db.iterator_cf(cf, IteratorMode::Start);
+        let iter = self.db.prefix_iterator_cf(cf, prefix);
         
         for result in iter {
             let (key, _) = result.map_err(|e| EngramError::StorageError(e.to_string()))?;
-            let id = String::from_utf8_lossy(&key).to_string();
+            
+            // Prefix iteration continues past the prefix without a prefix extractor
+            if !key.starts_with(prefix) {
+                break;
+            }
+            
+            let id = String::from_utf8_lossy(&key[prefix.len()..]).to_string();
             engram_ids.push(id);
         }
         
         Ok(engram_ids)
     }
 
-    /// Helper method to serialize an object to JSON bytes
-    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>> {
-        serde_json::to_vec(value).map_err(|e| EngramError::SerializationError(e.to_string()))
+    /// Helper method to serialize an object in the configured format
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        self.config.format.encode(value)
     }
 
-    /// Helper method to deserialize JSON bytes to an object
+    /// Helper method to deserialize a record written in any supported format
     fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
-        serde_json::from_slice(bytes).map_err(|e| EngramError::SerializationError(e.to_string()))
+        SerializationFormat::decode(bytes)
+    }
+    
+    /// Estimate the on-disk size of the given column families from their SST files.
+    /// Data still in memtables is not counted until flushed.
+    pub fn estimate_data_size(&self, cf_names: &[&str]) -> Result<u64> {
+        let mut total = 0;
+        
+        for cf_name in cf_names {
+            let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+                EngramError::StorageError(format!("Column family {} not found", cf_name))
+            })?;
+            total += self.db
+                .property_int_value_cf(cf, "rocksdb.total-sst-files-size")
+                .map_err(|e| EngramError::StorageError(e.to_string()))?
+                .unwrap_or(0);
+        }
+        
+        Ok(total)
+    }
+    
+    /// Compact the key ranges left behind by deleting a batch of engrams, so their
+    /// tombstones stop slowing reads. Covers the engram records and both embedding key
+    /// spaces. This flushes and rewrites SST files, so it is expensive; call it after large
+    /// deletions rather than after each one.
+    pub fn compact_deleted_engrams(&self, ids: &[EngramId]) -> Result<CompactionReport> {
+        let (Some(min_id), Some(max_id)) = (ids.iter().min(), ids.iter().max()) else {
+            return Ok(CompactionReport::default());
+        };
+        
+        let cf_names = [CF_ENGRAMS, CF_EMBEDDINGS];
+        let ranges: [(&str, &[u8]); 3] = [
+            (CF_ENGRAMS, ENGRAM_PREFIX),
+            (CF_EMBEDDINGS, EMBEDDING_PREFIX),
+            (CF_EMBEDDINGS, REDUCED_EMBEDDING_PREFIX),
+        ];
+        
+        // Flush first so the deletions are in SST files where compaction can drop them
+        for cf_name in cf_names {
+            let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+                EngramError::StorageError(format!("Column family {} not found", cf_name))
+            })?;
+            self.db.flush_cf(cf).map_err(|e| EngramError::StorageError(e.to_string()))?;
+        }
+        
+        let bytes_before = self.estimate_data_size(&cf_names)?;
+        
+        for (cf_name, prefix) in ranges {
+            let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+                EngramError::StorageError(format!("Column family {} not found", cf_name))
+            })?;
+            let start = Self::create_key(prefix, min_id);
+            // Extend past the last key so the range end covers it
+            let mut end = Self::create_key(prefix, max_id);
+            end.push(0xff);
+            self.db.compact_range_cf(cf, Some(start), Some(end));
+        }
+        
+        let bytes_after = self.estimate_data_size(&cf_names)?;
+        
+        Ok(CompactionReport { bytes_before, bytes_after })
     }
     
-    /// Get database statistics
+    /// Get database statistics from the live counters (O(1); see `recompute_stats` to repair them)
     pub fn get_stats(&self) -> Result<StorageStats> {
-        // Count items in each collection
-        let engram_count = self.list_engrams()?.len();
-        let connection_count = self.list_connections()?.len();
-        let collection_count = self.list_collections()?.len();
-        let agent_count = self.list_agents()?.len();
-        let context_count = self.list_contexts()?.len();
+        let count = |counter: StatCounter| -> Result<usize> {
+            Ok(counter.read(&self.db)?.unwrap_or(0))
+        };
         
         // Estimate database size (this is an approximation)
         // For a more accurate measure, we would need file system operations
         let db_size_mb = 0.0; // Placeholder - could use another approach for actual size
         
         Ok(StorageStats {
-            engram_count,
-            connection_count,
-            collection_count,
-            agent_count,
-            context_count,
+            engram_count: count(StatCounter::Engrams)?,
+            connection_count: count(StatCounter::Connections)?,
+            collection_count: count(StatCounter::Collections)?,
+            agent_count: count(StatCounter::Agents)?,
+            context_count: count(StatCounter::Contexts)?,
             db_size_mb,
         })
     }
+    
+    /// Recount every entity type and overwrite the live counters.
+    /// Use after bulk operations or imports that bypass the normal put/delete paths.
+    pub fn recompute_stats(&self) -> Result<StorageStats> {
+        let guard = self.stats_lock.lock().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire stats lock".to_string())
+        })?;
+        
+        let counts = [
+            (StatCounter::Engrams, self.list_engrams()?.len()),
+            (StatCounter::Connections, self.list_connections()?.len()),
+            (StatCounter::Collections, self.list_collections()?.len()),
+            (StatCounter::Agents, self.list_agents()?.len()),
+            (StatCounter::Contexts, self.list_contexts()?.len()),
+        ];
+        
+        let mut batch = WriteBatch::default();
+        for (counter, value) in counts {
+            counter.stage(&self.db, &mut batch, value)?;
+        }
+        self.db
+            .write(batch)
+            .map_err(|e| EngramError::StorageError(e.to_string()))?;
+        
+        drop(guard);
+        self.get_stats()
+    }
+    
+    /// Write a batch that creates (`is_put`) or removes one counted record,
+    /// adjusting its counter only if the record's existence actually changes
+    fn write_counted(&self, counter: StatCounter, id: &str, mut batch: WriteBatch, is_put: bool) -> Result<()> {
+        let _guard = self.stats_lock.lock().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire stats lock".to_string())
+        })?;
+        
+        let existed = counter.record_exists(&self.db, id)?;
+        if existed != is_put {
+            let current = counter.read(&self.db)?.unwrap_or(0);
+            let updated = if is_put { current + 1 } else { current.saturating_sub(1) };
+            counter.stage(&self.db, &mut batch, updated)?;
+        }
+        
+        self.db
+            .write(batch)
+            .map_err(|e| EngramError::StorageError(e.to_string()))
+    }
 
     /// Creates a key with the appropriate prefix
     fn create_key(prefix: &[u8], id: &str) -> Vec<u8> {
@@ -485,11 +2341,15 @@ impl Storage {
         })?;
 
         let key = Self::create_key(ENGRAM_PREFIX, &engram.id);
-        let value = Self::serialize(engram)?;
-
-        self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let value = self.serialize(engram)?;
+        let previous = self.get_engram(&engram.id)?.map(|previous| EngramScores::of(&previous));
+
+        let mut batch = WriteBatch::default();
+        Self::stage_history(&self.db, &mut batch, ENGRAM_HISTORY_PREFIX, &engram.id, Some(&value), chrono::Utc::now())?;
+        batch.put_cf(cf, key, value);
+        Self::stage_score_indexes(&self.db, &mut batch, &engram.id, previous, Some(EngramScores::of(engram)))?;
+        Self::stage_attribution(&self.db, &mut batch, CREATED_ENGRAM_PREFIX, engram.created_by.as_ref(), &engram.id, true)?;
+        self.write_counted(StatCounter::Engrams, &engram.id, batch, true)
     }
 
     /// Retrieves an engram from the database by ID
@@ -506,17 +2366,96 @@ impl Storage {
         }
     }
 
-    /// Deletes an engram from the database by ID
-    pub fn delete_engram(&self, id: &EngramId) -> Result<()> {
+    /// Retrieves several engrams in one read. The result lines up with `ids`,
+    /// with `None` for each ID that has no engram.
+    pub fn get_engrams(&self, ids: &[EngramId]) -> Result<Vec<Option<Engram>>> {
+        self.read_records(CF_ENGRAMS, ENGRAM_PREFIX, ids)
+    }
+
+    /// Set an engram's last-accessed time without changing anything else about it.
+    /// Rewrites only the engram record; statistics and membership indices are left alone.
+    /// Returns false if the engram does not exist.
+    pub fn touch_engram(&self, id: &EngramId, now: chrono::DateTime<chrono::Utc>) -> Result<bool> {
         let cf = self.db.cf_handle(CF_ENGRAMS).ok_or_else(|| {
             EngramError::StorageError(format!("Column family {} not found", CF_ENGRAMS))
         })?;
 
         let key = Self::create_key(ENGRAM_PREFIX, id);
+        let mut engram: Engram = match self.db.get_cf(cf, &key)? {
+            Some(bytes) => Self::deserialize(&bytes)?,
+            None => return Ok(false),
+        };
+
+        engram.last_accessed = now;
+        self.db.put_cf(cf, key, self.serialize(&engram)?)?;
+        Ok(true)
+    }
 
-        self.db
-            .delete_cf(cf, key)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+    /// Deletes an engram from the database by ID, along with its full and reduced embeddings
+    pub fn delete_engram(&self, id: &EngramId) -> Result<()> {
+        let cf = self.db.cf_handle(CF_ENGRAMS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_ENGRAMS))
+        })?;
+        let embeddings_cf = self.db.cf_handle(CF_EMBEDDINGS).ok_or_else(|| {
+            EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
+        })?;
+
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(cf, Self::create_key(ENGRAM_PREFIX, id));
+        batch.delete_cf(embeddings_cf, Self::create_key(EMBEDDING_PREFIX, id));
+        batch.delete_cf(embeddings_cf, Self::create_key(REDUCED_EMBEDDING_PREFIX, id));
+        if let Some(engram) = self.get_engram(id)? {
+            Self::stage_attribution(&self.db, &mut batch, CREATED_ENGRAM_PREFIX, engram.created_by.as_ref(), id, false)?;
+            Self::stage_history(&self.db, &mut batch, ENGRAM_HISTORY_PREFIX, id, None, chrono::Utc::now())?;
+            Self::stage_score_indexes(&self.db, &mut batch, id, Some(EngramScores::of(&engram)), None)?;
+        }
+
+        self.write_counted(StatCounter::Engrams, id, batch, false)
+    }
+
+    /// Deletes several engrams (and their embeddings) in a single atomic write. With `cascade`,
+    /// every connection to or from a deleted engram is removed in the same write; otherwise
+    /// connections are left in place, as with `delete_engram`. Duplicate IDs are ignored.
+    pub fn delete_engrams(&self, ids: &[EngramId], cascade: bool) -> Result<BatchDeleteReport> {
+        let mut report = BatchDeleteReport::default();
+        let mut connections = BTreeMap::new();
+        
+        for id in ids.iter().collect::<BTreeSet<_>>() {
+            match self.get_engram(id)? {
+                Some(engram) => report.engrams.push(engram),
+                None => report.not_found.push(id.clone()),
+            }
+        }
+        
+        if cascade {
+            for engram in &report.engrams {
+                let connection_ids = self.find_outgoing_connections(&engram.id)?
+                    .into_iter()
+                    .chain(self.find_incoming_connections(&engram.id)?);
+                for connection_id in connection_ids {
+                    if connections.contains_key(&connection_id) {
+                        continue;
+                    }
+                    if let Some(connection) = self.get_connection(&connection_id)? {
+                        connections.insert(connection_id, connection);
+                    }
+                }
+            }
+        }
+        report.connections = connections.into_values().collect();
+        
+        let mut txn = self.begin_transaction();
+        for connection in &report.connections {
+            txn.delete_connection(&connection.id, Some(connection))?;
+            Self::stage_attribution(&self.db, &mut txn.batch, CREATED_CONNECTION_PREFIX, connection.created_by.as_ref(), &connection.id, false)?;
+        }
+        for engram in &report.engrams {
+            txn.delete_engram(&engram.id)?;
+            Self::stage_attribution(&self.db, &mut txn.batch, CREATED_ENGRAM_PREFIX, engram.created_by.as_ref(), &engram.id, false)?;
+        }
+        txn.commit()?;
+        
+        Ok(report)
     }
 
     //
@@ -530,12 +2469,13 @@ impl Storage {
         })?;
 
         let key = Self::create_key(CONNECTION_PREFIX, &connection.id);
-        let value = Self::serialize(connection)?;
+        let value = self.serialize(connection)?;
 
         // Store the main connection record
-        self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| EngramError::StorageError(e.to_string()))?;
+        let mut batch = WriteBatch::default();
+        batch.put_cf(cf, key, value);
+        Self::stage_attribution(&self.db, &mut batch, CREATED_CONNECTION_PREFIX, connection.created_by.as_ref(), &connection.id, true)?;
+        self.write_counted(StatCounter::Connections, &connection.id, batch, true)?;
         
         // Also store relationship indexes for faster traversal
         self.index_connection(connection)?;
@@ -543,6 +2483,72 @@ impl Storage {
         Ok(())
     }
     
+    /// Stores a new connection, enforcing the configured per-engram connection limit on its
+    /// source engram. Returns the connections evicted to make room, which may include
+    /// `connection` itself if it is the weakest; callers should drop them from their indexes.
+    /// Updating an existing connection is never limited.
+    pub fn add_connection(&self, connection: &Connection) -> Result<Vec<Connection>> {
+        let limit = match self.config.max_connections_per_engram {
+            Some(limit) if self.get_connection(&connection.id)?.is_none() => limit,
+            _ => {
+                self.put_connection(connection)?;
+                return Ok(Vec::new());
+            }
+        };
+        
+        let existing = self.find_outgoing_connections(&connection.source_id)?;
+        if existing.len() < limit {
+            self.put_connection(connection)?;
+            return Ok(Vec::new());
+        }
+        
+        if self.config.connection_limit_policy == ConnectionLimitPolicy::Reject {
+            return Err(EngramError::InvalidOperation(format!(
+                "Engram {} already has the maximum of {} connections", connection.source_id, limit
+            )));
+        }
+        
+        let existing: Vec<ConnectionId> = existing.into_iter().collect();
+        let mut candidates: Vec<Connection> = self.get_connections(&existing)?.into_iter().flatten().collect();
+        candidates.push(connection.clone());
+        let evicted = Self::split_weakest(&mut candidates, limit);
+        
+        for weak in &evicted {
+            if weak.id != connection.id {
+                self.delete_connection(&weak.id)?;
+            }
+        }
+        if candidates.iter().any(|kept| kept.id == connection.id) {
+            self.put_connection(connection)?;
+        }
+        
+        Ok(evicted)
+    }
+    
+    /// Delete all but the `keep_top_n` strongest outgoing connections of an engram,
+    /// returning the deleted connections
+    pub fn prune_low_weight_connections(&self, engram_id: &EngramId, keep_top_n: usize) -> Result<Vec<Connection>> {
+        let ids: Vec<ConnectionId> = self.find_outgoing_connections(engram_id)?.into_iter().collect();
+        let mut connections: Vec<Connection> = self.get_connections(&ids)?.into_iter().flatten().collect();
+        let pruned = Self::split_weakest(&mut connections, keep_top_n);
+        
+        for connection in &pruned {
+            self.delete_connection(&connection.id)?;
+        }
+        
+        Ok(pruned)
+    }
+    
+    /// Keep the `keep` strongest connections (by weight, ties broken by ID) in `connections`
+    /// and return the rest, weakest last
+    fn split_weakest(connections: &mut Vec<Connection>, keep: usize) -> Vec<Connection> {
+        connections.sort_by(|a, b| {
+            b.weight.partial_cmp(&a.weight).unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        connections.split_off(keep.min(connections.len()))
+    }
+    
     /// Store relationship indexes for a connection
     fn index_connection(&self, connection: &Connection) -> Result<()> {
         let cf = self.db.cf_handle(CF_RELATIONSHIPS).ok_or_else(|| {
@@ -606,6 +2612,12 @@ impl Storage {
         }
     }
 
+    /// Retrieves several connections in one read. The result lines up with `ids`,
+    /// with `None` for each ID that has no connection.
+    pub fn get_connections(&self, ids: &[ConnectionId]) -> Result<Vec<Option<Connection>>> {
+        self.read_records(CF_CONNECTIONS, CONNECTION_PREFIX, ids)
+    }
+
     /// Deletes a connection from the database by ID
     pub fn delete_connection(&self, id: &ConnectionId) -> Result<()> {
         // First get the connection to remove indexes
@@ -622,9 +2634,10 @@ impl Storage {
 
             let key = Self::create_key(CONNECTION_PREFIX, id);
 
-            self.db
-                .delete_cf(cf, key)
-                .map_err(|e| EngramError::StorageError(e.to_string()))?;
+            let mut batch = WriteBatch::default();
+            batch.delete_cf(cf, key);
+            Self::stage_attribution(&self.db, &mut batch, CREATED_CONNECTION_PREFIX, connection.created_by.as_ref(), id, false)?;
+            self.write_counted(StatCounter::Connections, id, batch, false)?;
         }
         
         Ok(())
@@ -680,11 +2693,21 @@ impl Storage {
         })?;
 
         let key = Self::create_key(COLLECTION_PREFIX, &collection.id);
-        let value = Self::serialize(collection)?;
-
-        self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let value = self.serialize(collection)?;
+        let previous = self.get_collection(&collection.id)?;
+
+        let mut batch = WriteBatch::default();
+        Self::stage_history(&self.db, &mut batch, COLLECTION_HISTORY_PREFIX, &collection.id, Some(&value), chrono::Utc::now())?;
+        batch.put_cf(cf, key, value);
+        Self::stage_membership(
+            &self.db,
+            &mut batch,
+            COLLECTION_MEMBER_PREFIX,
+            &collection.id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            Some(&collection.engram_ids),
+        )?;
+        self.write_counted(StatCounter::Collections, &collection.id, batch, true)
     }
 
     /// Retrieves a collection from the database by ID
@@ -708,10 +2731,22 @@ impl Storage {
         })?;
 
         let key = Self::create_key(COLLECTION_PREFIX, id);
+        let previous = self.get_collection(id)?;
 
-        self.db
-            .delete_cf(cf, key)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(cf, key);
+        if previous.is_some() {
+            Self::stage_history(&self.db, &mut batch, COLLECTION_HISTORY_PREFIX, id, None, chrono::Utc::now())?;
+        }
+        Self::stage_membership(
+            &self.db,
+            &mut batch,
+            COLLECTION_MEMBER_PREFIX,
+            id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            None,
+        )?;
+        self.write_counted(StatCounter::Collections, id, batch, false)
     }
 
     //
@@ -725,11 +2760,11 @@ impl Storage {
         })?;
 
         let key = Self::create_key(AGENT_PREFIX, &agent.id);
-        let value = Self::serialize(agent)?;
+        let value = self.serialize(agent)?;
 
-        self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let mut batch = WriteBatch::default();
+        batch.put_cf(cf, key, value);
+        self.write_counted(StatCounter::Agents, &agent.id, batch, true)
     }
 
     /// Retrieves an agent from the database by ID
@@ -754,9 +2789,9 @@ impl Storage {
 
         let key = Self::create_key(AGENT_PREFIX, id);
 
-        self.db
-            .delete_cf(cf, key)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(cf, key);
+        self.write_counted(StatCounter::Agents, id, batch, false)
     }
 
     //
@@ -770,11 +2805,20 @@ impl Storage {
         })?;
 
         let key = Self::create_key(CONTEXT_PREFIX, &context.id);
-        let value = Self::serialize(context)?;
-
-        self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let value = self.serialize(context)?;
+        let previous = self.get_context(&context.id)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(cf, key, value);
+        Self::stage_membership(
+            &self.db,
+            &mut batch,
+            CONTEXT_MEMBER_PREFIX,
+            &context.id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            Some(&context.engram_ids),
+        )?;
+        self.write_counted(StatCounter::Contexts, &context.id, batch, true)
     }
 
     /// Retrieves a context from the database by ID
@@ -798,21 +2842,44 @@ impl Storage {
         })?;
 
         let key = Self::create_key(CONTEXT_PREFIX, id);
-
-        self.db
-            .delete_cf(cf, key)
-            .map_err(|e| EngramError::StorageError(e.to_string()))
+        let previous = self.get_context(id)?;
+
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(cf, key);
+        Self::stage_membership(
+            &self.db,
+            &mut batch,
+            CONTEXT_MEMBER_PREFIX,
+            id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            None,
+        )?;
+        self.write_counted(StatCounter::Contexts, id, batch, false)
     }
 
     //
     // Transaction Support
     //
 
+    /// Start a batch of engram and connection writes, applied atomically by `StorageBatch::commit`
+    pub fn batch(&self) -> StorageBatch<'_> {
+        StorageBatch {
+            txn: self.begin_transaction(),
+            staged_connections: HashMap::new(),
+            staged_creators: HashMap::new(),
+            operations: 0,
+        }
+    }
+
     /// Begin a transaction (batch operations)
-    pub fn begin_transaction(&self) -> Transaction {
+    pub fn begin_transaction(&self) -> Transaction<'_> {
         Transaction {
             batch: WriteBatch::default(),
             db: &self.db,
+            stats_lock: &self.stats_lock,
+            counted: Vec::new(),
+            format: self.config.format,
+            staged_scores: HashMap::new(),
         }
     }
 }
@@ -822,6 +2889,13 @@ impl Storage {
 pub struct Transaction<'a> {
     batch: WriteBatch,
     db: &'a DB,
+    stats_lock: &'a Mutex<()>,
+    /// Counted records touched by the transaction, in order, with whether each was a put
+    counted: Vec<(StatCounter, String, bool)>,
+    /// Format for records written by the transaction
+    format: SerializationFormat,
+    /// Scores of engrams written or deleted earlier in the transaction, for the score indexes
+    staged_scores: HashMap<EngramId, Option<EngramScores>>,
 }
 
 #[allow(dead_code)]
@@ -833,9 +2907,25 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(ENGRAM_PREFIX, &engram.id);
-        let value = Storage::serialize(engram)?;
+        let value = self.format.encode(engram)?;
 
+        Storage::stage_history(self.db, &mut self.batch, ENGRAM_HISTORY_PREFIX, &engram.id, Some(&value), chrono::Utc::now())?;
         self.batch.put_cf(cf, key, value);
+        Storage::stage_attribution(self.db, &mut self.batch, CREATED_ENGRAM_PREFIX, engram.created_by.as_ref(), &engram.id, true)?;
+        self.counted.push((StatCounter::Engrams, engram.id.clone(), true));
+        self.stage_scores(&engram.id, Some(EngramScores::of(engram)))
+    }
+
+    /// Move an engram's score index entries to `current`, starting from its scores as of
+    /// the operations staged so far
+    fn stage_scores(&mut self, id: &EngramId, current: Option<EngramScores>) -> Result<()> {
+        let previous = match self.staged_scores.get(id) {
+            Some(staged) => *staged,
+            None => Storage::read_record::<Engram>(self.db, CF_ENGRAMS, ENGRAM_PREFIX, id)?
+                .map(|engram| EngramScores::of(&engram)),
+        };
+        Storage::stage_score_indexes(self.db, &mut self.batch, id, previous, current)?;
+        self.staged_scores.insert(id.clone(), current);
         Ok(())
     }
 
@@ -847,9 +2937,11 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(CONNECTION_PREFIX, &connection.id);
-        let value = Storage::serialize(connection)?;
+        let value = self.format.encode(connection)?;
 
         self.batch.put_cf(cf, key, value);
+        Storage::stage_attribution(self.db, &mut self.batch, CREATED_CONNECTION_PREFIX, connection.created_by.as_ref(), &connection.id, true)?;
+        self.counted.push((StatCounter::Connections, connection.id.clone(), true));
         
         // Add relationship indexes
         self.index_connection(connection)?;
@@ -897,10 +2989,20 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(COLLECTION_PREFIX, &collection.id);
-        let value = Storage::serialize(collection)?;
+        let value = self.format.encode(collection)?;
+        let previous: Option<Collection> = Storage::read_record(self.db, CF_COLLECTIONS, COLLECTION_PREFIX, &collection.id)?;
 
+        Storage::stage_history(self.db, &mut self.batch, COLLECTION_HISTORY_PREFIX, &collection.id, Some(&value), chrono::Utc::now())?;
         self.batch.put_cf(cf, key, value);
-        Ok(())
+        self.counted.push((StatCounter::Collections, collection.id.clone(), true));
+        Storage::stage_membership(
+            self.db,
+            &mut self.batch,
+            COLLECTION_MEMBER_PREFIX,
+            &collection.id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            Some(&collection.engram_ids),
+        )
     }
 
     /// Add an agent to the transaction
@@ -910,9 +3012,10 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(AGENT_PREFIX, &agent.id);
-        let value = Storage::serialize(agent)?;
+        let value = self.format.encode(agent)?;
 
         self.batch.put_cf(cf, key, value);
+        self.counted.push((StatCounter::Agents, agent.id.clone(), true));
         Ok(())
     }
 
@@ -923,13 +3026,22 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(CONTEXT_PREFIX, &context.id);
-        let value = Storage::serialize(context)?;
+        let value = self.format.encode(context)?;
+        let previous: Option<Context> = Storage::read_record(self.db, CF_CONTEXTS, CONTEXT_PREFIX, &context.id)?;
 
         self.batch.put_cf(cf, key, value);
-        Ok(())
+        self.counted.push((StatCounter::Contexts, context.id.clone(), true));
+        Storage::stage_membership(
+            self.db,
+            &mut self.batch,
+            CONTEXT_MEMBER_PREFIX,
+            &context.id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            Some(&context.engram_ids),
+        )
     }
 
-    /// Delete an engram in the transaction
+    /// Delete an engram in the transaction, along with its full and reduced embeddings
     pub fn delete_engram(&mut self, id: &EngramId) -> Result<()> {
         let cf = self.db.cf_handle(CF_ENGRAMS).ok_or_else(|| {
             EngramError::StorageError(format!("Column family {} not found", CF_ENGRAMS))
@@ -937,7 +3049,12 @@ impl<'a> Transaction<'a> {
 
         let key = Storage::create_key(ENGRAM_PREFIX, id);
         self.batch.delete_cf(cf, key);
-        Ok(())
+        self.counted.push((StatCounter::Engrams, id.clone(), false));
+        Storage::stage_history(self.db, &mut self.batch, ENGRAM_HISTORY_PREFIX, id, None, chrono::Utc::now())?;
+        self.stage_scores(id, None)?;
+        
+        self.delete_embedding(id)?;
+        self.delete_reduced_embedding(id)
     }
 
     /// Delete a connection in the transaction
@@ -949,6 +3066,7 @@ impl<'a> Transaction<'a> {
 
         let key = Storage::create_key(CONNECTION_PREFIX, id);
         self.batch.delete_cf(cf, key);
+        self.counted.push((StatCounter::Connections, id.clone(), false));
         
         // Delete relationship indexes if connection is provided
         if let Some(conn) = connection {
@@ -998,8 +3116,21 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(COLLECTION_PREFIX, id);
+        let previous: Option<Collection> = Storage::read_record(self.db, CF_COLLECTIONS, COLLECTION_PREFIX, id)?;
+
         self.batch.delete_cf(cf, key);
-        Ok(())
+        self.counted.push((StatCounter::Collections, id.clone(), false));
+        if previous.is_some() {
+            Storage::stage_history(self.db, &mut self.batch, COLLECTION_HISTORY_PREFIX, id, None, chrono::Utc::now())?;
+        }
+        Storage::stage_membership(
+            self.db,
+            &mut self.batch,
+            COLLECTION_MEMBER_PREFIX,
+            id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            None,
+        )
     }
 
     /// Delete an agent in the transaction
@@ -1010,6 +3141,7 @@ impl<'a> Transaction<'a> {
 
         let key = Storage::create_key(AGENT_PREFIX, id);
         self.batch.delete_cf(cf, key);
+        self.counted.push((StatCounter::Agents, id.clone(), false));
         Ok(())
     }
 
@@ -1020,8 +3152,18 @@ impl<'a> Transaction<'a> {
         })?;
 
         let key = Storage::create_key(CONTEXT_PREFIX, id);
+        let previous: Option<Context> = Storage::read_record(self.db, CF_CONTEXTS, CONTEXT_PREFIX, id)?;
+
         self.batch.delete_cf(cf, key);
-        Ok(())
+        self.counted.push((StatCounter::Contexts, id.clone(), false));
+        Storage::stage_membership(
+            self.db,
+            &mut self.batch,
+            CONTEXT_MEMBER_PREFIX,
+            id,
+            previous.as_ref().map(|c| &c.engram_ids),
+            None,
+        )
     }
     
     /// Add an embedding to the transaction
@@ -1030,8 +3172,8 @@ impl<'a> Transaction<'a> {
             EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
         })?;
         
-        let key = engram_id.as_bytes();
-        let value = Storage::serialize(embedding)?;
+        let key = Storage::create_key(EMBEDDING_PREFIX, engram_id);
+        let value = self.format.encode(embedding)?;
         
         self.batch.put_cf(cf, key, value);
         Ok(())
@@ -1044,7 +3186,7 @@ impl<'a> Transaction<'a> {
         })?;
         
         let key = [REDUCED_EMBEDDING_PREFIX, engram_id.as_bytes()].concat();
-        let value = Storage::serialize(embedding)?;
+        let value = self.format.encode(embedding)?;
         
         self.batch.put_cf(cf, key, value);
         Ok(())
@@ -1067,13 +3209,38 @@ impl<'a> Transaction<'a> {
             EngramError::StorageError(format!("Column family {} not found", CF_EMBEDDINGS))
         })?;
         
-        let key = engram_id.as_bytes();
+        let key = Storage::create_key(EMBEDDING_PREFIX, engram_id);
         self.batch.delete_cf(cf, key);
         Ok(())
     }
 
     /// Commit the transaction (apply all operations)
-    pub fn commit(self) -> Result<()> {
+    pub fn commit(mut self) -> Result<()> {
+        let _guard = self.stats_lock.lock().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire stats lock".to_string())
+        })?;
+        
+        // The last operation on each record decides whether it exists after the commit
+        let mut final_state: HashMap<(StatCounter, &str), bool> = HashMap::new();
+        for (counter, id, is_put) in &self.counted {
+            final_state.insert((*counter, id.as_str()), *is_put);
+        }
+        
+        let mut deltas: HashMap<StatCounter, i64> = HashMap::new();
+        for ((counter, id), exists_after) in final_state {
+            let existed = counter.record_exists(self.db, id)?;
+            if existed != exists_after {
+                *deltas.entry(counter).or_insert(0) += if exists_after { 1 } else { -1 };
+            }
+        }
+        
+        for counter in StatCounter::ALL {
+            if let Some(&delta) = deltas.get(&counter) {
+                let current = counter.read(self.db)?.unwrap_or(0) as i64;
+                counter.stage(self.db, &mut self.batch, (current + delta).max(0) as usize)?;
+            }
+        }
+        
         self.db
             .write(self.batch)
             .map_err(|e| EngramError::TransactionError(e.to_string()))
@@ -1083,4 +3250,93 @@ impl<'a> Transaction<'a> {
     pub fn abort(self) {
         // Drop the batch without writing it
     }
-}
\ No newline at end of file
+}
+
+/// A set of engram and connection writes applied with a single atomic RocksDB write.
+/// Relationship index keys are staged alongside their connection records, so a failed
+/// commit leaves neither behind. Created with `Storage::batch`.
+pub struct StorageBatch<'a> {
+    txn: Transaction<'a>,
+    /// Connections written or deleted earlier in this batch, which later operations must see
+    staged_connections: HashMap<ConnectionId, Option<Connection>>,
+    /// Creators of engrams written earlier in this batch
+    staged_creators: HashMap<EngramId, Option<AgentId>>,
+    operations: usize,
+}
+
+impl<'a> StorageBatch<'a> {
+    /// Stage an engram write
+    pub fn put_engram(&mut self, engram: &Engram) -> Result<()> {
+        self.txn.put_engram(engram)?;
+        self.staged_creators.insert(engram.id.clone(), engram.created_by.clone());
+        self.operations += 1;
+        Ok(())
+    }
+    
+    /// Stage a connection write with its relationship index keys. Replacing an existing
+    /// connection also removes the index keys of the version it replaces.
+    pub fn put_connection(&mut self, connection: &Connection) -> Result<()> {
+        if let Some(previous) = self.current_connection(&connection.id)? {
+            self.txn.delete_relationship_indexes(&previous)?;
+        }
+        self.txn.put_connection(connection)?;
+        self.staged_connections.insert(connection.id.clone(), Some(connection.clone()));
+        self.operations += 1;
+        Ok(())
+    }
+    
+    /// Stage an engram delete, along with its embeddings. As with `Storage::delete_engram`,
+    /// connections to the engram are left in place.
+    pub fn delete_engram(&mut self, id: &EngramId) -> Result<()> {
+        let creator = match self.staged_creators.remove(id) {
+            Some(creator) => Some(creator),
+            None => Storage::read_record::<Engram>(self.txn.db, CF_ENGRAMS, ENGRAM_PREFIX, id)?
+                .map(|engram| engram.created_by),
+        };
+        
+        self.txn.delete_engram(id)?;
+        if let Some(creator) = creator {
+            Storage::stage_attribution(self.txn.db, &mut self.txn.batch, CREATED_ENGRAM_PREFIX, creator.as_ref(), id, false)?;
+        }
+        self.operations += 1;
+        Ok(())
+    }
+    
+    /// Stage a connection delete, including its relationship index keys.
+    /// Deleting a connection that does not exist is a no-op.
+    pub fn delete_connection(&mut self, id: &ConnectionId) -> Result<()> {
+        if let Some(connection) = self.current_connection(id)? {
+            self.txn.delete_connection(id, Some(&connection))?;
+            Storage::stage_attribution(self.txn.db, &mut self.txn.batch, CREATED_CONNECTION_PREFIX, connection.created_by.as_ref(), id, false)?;
+            self.staged_connections.insert(id.clone(), None);
+            self.operations += 1;
+        }
+        Ok(())
+    }
+    
+    /// The connection as it will be once the operations staged so far are committed
+    fn current_connection(&self, id: &ConnectionId) -> Result<Option<Connection>> {
+        match self.staged_connections.get(id) {
+            Some(staged) => Ok(staged.clone()),
+            None => Storage::read_record(self.txn.db, CF_CONNECTIONS, CONNECTION_PREFIX, id),
+        }
+    }
+    
+    /// Number of operations staged so far
+    pub fn len(&self) -> usize {
+        self.operations
+    }
+    
+    /// Whether nothing has been staged yet
+    pub fn is_empty(&self) -> bool {
+        self.operations == 0
+    }
+    
+    /// Apply every staged operation in one atomic write. On error nothing is written.
+    pub fn commit(self) -> Result<()> {
+        self.txn.commit().map_err(|e| match e {
+            EngramError::TransactionError(message) => EngramError::StorageError(message),
+            other => other,
+        })
+    }
+}
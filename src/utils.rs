@@ -2,6 +2,8 @@ use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 /// Load environment variables from a .env file
 pub fn load_env_from_file(file_path: &str) -> std::io::Result<()> {
@@ -79,6 +81,285 @@ pub fn has_huggingface_capabilities() -> bool {
     get_huggingface_api_key().is_some()
 }
 
+/// Default maximum number of characters shown for engram content in list and graph views
+pub const DEFAULT_DISPLAY_LENGTH: usize = 30;
+
+/// Truncate text for display to at most `max_chars` characters, ending with "..." when shortened.
+/// Counts characters rather than bytes, so it never splits a multi-byte UTF-8 character.
+pub fn truncate_display(content: &str, max_chars: usize) -> String {
+    if content.chars().count() <= max_chars {
+        return content.to_string();
+    }
+    
+    let kept = max_chars.saturating_sub(3);
+    let mut truncated: String = content.chars().take(kept).collect();
+    truncated.push_str(&"..."[..max_chars.min(3)]);
+    truncated
+}
+
+/// Hash of `content` for detecting duplicate ingestion, as 16 hex digits.
+/// Case and runs of whitespace are normalized away, so trivially reformatted copies collide.
+pub fn content_hash(content: &str) -> String {
+    let normalized = content.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    fnv1a_hex(normalized.as_bytes())
+}
+
+/// 64-bit FNV-1a hash of `bytes` as 16 hex digits, stable across runs and platforms
+pub fn fnv1a_hex(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a_64(bytes))
+}
+
+/// 64-bit FNV-1a hash of `bytes`, stable across runs and platforms
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// HyperLogLog sketch estimating how many distinct strings were inserted in a fixed
+/// `2^precision` bytes, with a standard error of about `1.04 / sqrt(2^precision)`
+/// (1.6% at the default precision of 12). Values cannot be removed once inserted.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Default precision: 4096 registers
+    pub const DEFAULT_PRECISION: u8 = 12;
+    
+    /// Create an empty sketch; `precision` is clamped to 4..=16
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(4, 16);
+        Self { precision, registers: vec![0; 1 << precision] }
+    }
+    
+    /// Number of index bits, which determines size and accuracy
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+    
+    /// Record a value
+    pub fn insert(&mut self, value: &str) {
+        // FNV-1a mixes poorly into the high bits, so finalize it (splitmix64) before
+        // splitting the hash into a register index and a run of leading zeros
+        let mut hash = fnv1a_64(value.as_bytes());
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^= hash >> 31;
+        
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rank = ((hash << self.precision).leading_zeros() + 1).min(65 - self.precision as u32) as u8;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+    
+    /// Estimated number of distinct values inserted
+    pub fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+        
+        // Linear counting is more accurate while many registers are still empty
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            raw.round() as usize
+        }
+    }
+    
+    /// Fold in another sketch so this one estimates the union of both.
+    /// Sketches of different precisions cannot be combined.
+    pub fn merge(&mut self, other: &Self) -> crate::error::Result<()> {
+        if other.precision != self.precision {
+            return Err(crate::error::EngramError::InvalidOperation(format!(
+                "Cannot merge a precision {} sketch into a precision {} sketch",
+                other.precision, self.precision
+            )));
+        }
+        for (register, &theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(theirs);
+        }
+        Ok(())
+    }
+}
+
+/// Quoted entity tag for HTTP caching: a hash of `value` serialized to JSON. Values go through
+/// `serde_json::Value`, whose sorted maps make the tag independent of hash map iteration order.
+pub fn etag_of<T: serde::Serialize>(value: &T) -> String {
+    let canonical = serde_json::to_value(value)
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    format!("\"{}\"", fnv1a_hex(&canonical))
+}
+
+/// Whether an `If-None-Match` header value matches `etag`: `*`, or any listed tag
+/// once weak (`W/`) prefixes are set aside, as the weak comparison in RFC 9110 requires
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip_weak(etag);
+    if_none_match.split(',').any(|tag| tag.trim() == "*" || strip_weak(tag) == etag)
+}
+
+/// Token-bucket rate limiter: allows bursts of up to `capacity` operations, refilled
+/// evenly so that `capacity` operations are available again after each `period`
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    /// Available tokens and when they were last refilled
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `capacity` operations per `period`, starting full
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        let capacity = capacity as f64;
+        Self {
+            capacity,
+            refill_per_second: capacity / period.as_secs_f64().max(f64::EPSILON),
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+    
+    /// Take one token if available
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+    
+    /// Take one token, sleeping until one is refilled if none is available
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            std::thread::sleep(Duration::from_secs_f64((1.0 / self.refill_per_second).min(1.0)));
+        }
+    }
+    
+    /// Take one token if available, refilling as of `now`
+    pub fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (tokens, last) = &mut *state;
+        
+        let elapsed = now.saturating_duration_since(*last).as_secs_f64();
+        *tokens = (*tokens + elapsed * self.refill_per_second).min(self.capacity);
+        *last = (*last).max(now);
+        
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Methods allowed cross-origin unless configured otherwise
+pub const DEFAULT_CORS_METHODS: &[&str] = &["GET", "POST", "PUT", "DELETE"];
+
+/// Request headers allowed cross-origin unless configured otherwise
+pub const DEFAULT_CORS_HEADERS: &[&str] = &["Content-Type", "Authorization"];
+
+/// Which cross-origin requests the web server accepts. Without an allowlist no other
+/// origin is accepted; `permissive` accepts everything and is meant for local development.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    /// Origins allowed to make requests, e.g. `https://app.example.com`
+    pub allowed_origins: Vec<String>,
+    
+    /// HTTP methods allowed from those origins
+    pub allowed_methods: Vec<String>,
+    
+    /// Request headers allowed from those origins
+    pub allowed_headers: Vec<String>,
+    
+    /// Accept any origin, method and header, ignoring the allowlists
+    pub allow_all: bool,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: DEFAULT_CORS_METHODS.iter().map(|m| m.to_string()).collect(),
+            allowed_headers: DEFAULT_CORS_HEADERS.iter().map(|h| h.to_string()).collect(),
+            allow_all: false,
+        }
+    }
+}
+
+impl CorsPolicy {
+    /// A policy accepting no cross-origin requests until origins are allowed
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// A policy accepting every cross-origin request, for development only
+    pub fn permissive() -> Self {
+        Self { allow_all: true, ..Self::default() }
+    }
+    
+    /// Read the allowlists from ENGRAM_CORS_ORIGINS, ENGRAM_CORS_METHODS and
+    /// ENGRAM_CORS_HEADERS (comma-separated), keeping the defaults for any that are unset
+    pub fn from_env() -> Self {
+        let list = |name: &str| env::var(name).ok().map(|value| Self::parse_list(&value));
+        let defaults = Self::default();
+        Self {
+            allowed_origins: list("ENGRAM_CORS_ORIGINS").unwrap_or(defaults.allowed_origins),
+            allowed_methods: list("ENGRAM_CORS_METHODS").unwrap_or(defaults.allowed_methods),
+            allowed_headers: list("ENGRAM_CORS_HEADERS").unwrap_or(defaults.allowed_headers),
+            allow_all: false,
+        }
+    }
+    
+    /// Split a comma-separated list, dropping blank entries
+    pub fn parse_list(value: &str) -> Vec<String> {
+        value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+    }
+    
+    /// Set the allowed origins
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.allowed_origins = origins;
+        self
+    }
+    
+    /// Set the allowed methods
+    pub fn with_allowed_methods(mut self, methods: Vec<String>) -> Self {
+        self.allowed_methods = methods;
+        self
+    }
+    
+    /// Set the allowed request headers
+    pub fn with_allowed_headers(mut self, headers: Vec<String>) -> Self {
+        self.allowed_headers = headers;
+        self
+    }
+    
+    /// Whether requests from `origin` are accepted. Origins match exactly, ignoring a trailing slash.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allow_all || self.allowed_origins.iter().any(|allowed| {
+            allowed == "*" || allowed.trim_end_matches('/') == origin.trim_end_matches('/')
+        })
+    }
+    
+    /// Whether cross-origin requests may use `method`, compared case-insensitively
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.allow_all || self.allowed_methods.iter().any(|allowed| allowed.eq_ignore_ascii_case(method))
+    }
+    
+    /// Whether cross-origin requests may send `header`, compared case-insensitively
+    pub fn allows_header(&self, header: &str) -> bool {
+        self.allow_all || self.allowed_headers.iter().any(|allowed| allowed.eq_ignore_ascii_case(header))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,4 +410,86 @@ mod tests {
             env::remove_var("ANTHROPIC_API_KEY");
         }
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_truncate_display_respects_char_boundaries() {
+        // Byte 30 falls in the middle of the two-byte "é"
+        let content = format!("{}é and more text after it", "a".repeat(29));
+        assert!(!content.is_char_boundary(30));
+        
+        let truncated = truncate_display(&content, 33);
+        assert_eq!(truncated, format!("{}é...", "a".repeat(29)));
+        assert_eq!(truncated.chars().count(), 33);
+        
+        // Short content is returned unchanged, and emoji count as single characters
+        assert_eq!(truncate_display("héllo", 5), "héllo");
+        assert_eq!(truncate_display("🙂🙂🙂🙂🙂🙂", 5), "🙂🙂...");
+        assert_eq!(truncate_display("abcdef", 2), "..");
+    }
+    
+    #[test]
+    fn test_rate_limiter_refills_over_time() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        
+        // A full bucket allows a burst of two, then refuses
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start));
+        assert!(!limiter.try_acquire_at(start));
+        
+        // One token comes back every five seconds
+        assert!(!limiter.try_acquire_at(start + Duration::from_secs(4)));
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(6)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_secs(6)));
+    }
+    
+    #[test]
+    fn test_cors_policy_allowlist() {
+        let policy = CorsPolicy::new()
+            .with_allowed_origins(CorsPolicy::parse_list("https://app.example.com, http://localhost:5173/ ,"))
+            .with_allowed_methods(vec!["GET".to_string(), "POST".to_string()]);
+        
+        // Only listed origins pass, with or without a trailing slash
+        assert!(policy.allows_origin("https://app.example.com"));
+        assert!(policy.allows_origin("http://localhost:5173"));
+        assert!(!policy.allows_origin("https://evil.example.com"));
+        assert!(!policy.allows_origin("http://app.example.com"));
+        
+        // Methods and headers are matched case-insensitively against their own allowlists
+        assert!(policy.allows_method("get"));
+        assert!(!policy.allows_method("DELETE"));
+        assert!(policy.allows_header("content-type"));
+        assert!(!policy.allows_header("X-Custom"));
+        
+        // Nothing is allowed cross-origin by default, and everything is in development mode
+        assert!(!CorsPolicy::new().allows_origin("https://app.example.com"));
+        let dev = CorsPolicy::permissive();
+        assert!(dev.allows_origin("https://evil.example.com"));
+        assert!(dev.allows_method("PATCH"));
+        assert!(dev.allows_header("X-Custom"));
+    }
+    
+    #[test]
+    fn test_hyperloglog_estimate_is_close() {
+        let mut sketch = HyperLogLog::new(HyperLogLog::DEFAULT_PRECISION);
+        assert_eq!(sketch.estimate(), 0);
+        
+        // Repeats don't count twice
+        for i in 0..20_000 {
+            sketch.insert(&format!("value-{}", i % 10_000));
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 10_000.0).abs() < 10_000.0 * 0.05, "estimate {} too far off", estimate);
+        
+        // Merging sketches of disjoint values estimates their union
+        let mut other = HyperLogLog::new(HyperLogLog::DEFAULT_PRECISION);
+        for i in 10_000..15_000 {
+            other.insert(&format!("value-{}", i));
+        }
+        sketch.merge(&other).unwrap();
+        let union = sketch.estimate() as f64;
+        assert!((union - 15_000.0).abs() < 15_000.0 * 0.05, "union estimate {} too far off", union);
+        
+        assert!(sketch.merge(&HyperLogLog::new(8)).is_err());
+    }
+}
@@ -1,10 +1,235 @@
 use crate::embedding::{Embedding, EmbeddingService, HnswIndex};
 use crate::error::{EngramError, Result};
-use crate::schema::{Engram, EngramId};
+use crate::schema::{Collection, CollectionId, Engram, EngramId};
 use crate::storage::Storage;
-use crate::index::SearchIndex;
-use std::collections::HashMap;
+use crate::utils::RateLimiter;
+use crate::index::{SearchIndex, TextIndex};
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// Differences between an HNSW index and the full embeddings held in storage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsistencyReport {
+    /// Engrams with a stored embedding that are not in the index
+    pub missing_from_index: Vec<EngramId>,
+    
+    /// Engrams in the index whose stored embedding no longer exists
+    pub missing_from_storage: Vec<EngramId>,
+}
+
+impl ConsistencyReport {
+    /// Check whether the index and storage agree
+    pub fn is_consistent(&self) -> bool {
+        self.missing_from_index.is_empty() && self.missing_from_storage.is_empty()
+    }
+}
+
+/// Compare an HNSW index against the full embeddings in storage
+pub fn verify_vector_index(storage: &Storage, index: &HnswIndex) -> Result<ConsistencyReport> {
+    let stored: HashSet<EngramId> = storage.list_embeddings()?.into_iter().collect();
+    
+    let mut missing_from_index: Vec<EngramId> = stored
+        .iter()
+        .filter(|id| !index.contains(id))
+        .cloned()
+        .collect();
+    missing_from_index.sort();
+    
+    let mut missing_from_storage: Vec<EngramId> = index
+        .ids()
+        .into_iter()
+        .filter(|id| !stored.contains(id))
+        .collect();
+    missing_from_storage.sort();
+    
+    Ok(ConsistencyReport {
+        missing_from_index,
+        missing_from_storage,
+    })
+}
+
+/// Fix drift between an HNSW index and storage by indexing stored embeddings that are
+/// missing from the index and removing index entries whose embedding is gone.
+/// Returns the report of the drift that was found.
+pub fn reconcile_vector_index(storage: &Storage, index: &mut HnswIndex) -> Result<ConsistencyReport> {
+    let report = verify_vector_index(storage, index)?;
+    
+    for id in &report.missing_from_storage {
+        index.remove(id)?;
+    }
+    
+    for id in &report.missing_from_index {
+        if let Some(stored) = storage.get_embedding(id)? {
+            // Manual conversion from storage::Embedding to embedding::Embedding
+            let embedding = Embedding {
+                vector: stored.vector,
+                model: stored.model,
+                dimensions: stored.dimensions,
+                metadata: stored.metadata,
+            };
+            index.add(id, embedding)?;
+        }
+    }
+    
+    Ok(report)
+}
+
+/// Default number of engrams sent to the embedding backend per call by `embed_missing`
+pub const DEFAULT_EMBED_BATCH_SIZE: usize = 32;
+
+/// Generate and store embeddings for every engram that has none, adding each to `index`.
+///
+/// Engrams are embedded `batch_size` at a time and each batch is stored before the next is
+/// generated, so an interrupted run loses at most one batch and running again resumes where
+/// it stopped. Returns the number of engrams embedded.
+pub fn embed_missing(storage: &Storage, service: &EmbeddingService, index: &mut HnswIndex, batch_size: usize) -> Result<usize> {
+    embed_missing_rate_limited(storage, service, index, batch_size, None)
+}
+
+/// Like `embed_missing`, but takes a token from `rate_limiter` for each engram before
+/// sending its batch to the embedding backend, waiting whenever the limiter is empty
+pub fn embed_missing_rate_limited(
+    storage: &Storage,
+    service: &EmbeddingService,
+    index: &mut HnswIndex,
+    batch_size: usize,
+    rate_limiter: Option<&RateLimiter>,
+) -> Result<usize> {
+    if batch_size == 0 {
+        return Err(EngramError::InvalidOperation("Batch size must be at least 1".to_string()));
+    }
+    
+    let embedded: HashSet<EngramId> = storage.list_embeddings()?.into_iter().collect();
+    let missing: Vec<EngramId> = storage.list_engrams()?
+        .into_iter()
+        .filter(|id| !embedded.contains(id))
+        .collect();
+    
+    let mut count = 0;
+    for ids in missing.chunks(batch_size) {
+        let mut engrams = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(engram) = storage.get_engram(id)? {
+                engrams.push(engram);
+            }
+        }
+        
+        if let Some(limiter) = rate_limiter {
+            for _ in &engrams {
+                limiter.acquire();
+            }
+        }
+        
+        let texts: Vec<&str> = engrams.iter().map(|engram| engram.content.as_str()).collect();
+        let embeddings = service.embed_batch(&texts)?;
+        
+        for (engram, embedding) in engrams.iter().zip(embeddings) {
+            // Manual conversion from embedding::Embedding to storage::Embedding
+            let stored = crate::storage::Embedding {
+                vector: embedding.vector.clone(),
+                model: embedding.model.clone(),
+                dimensions: embedding.dimensions,
+                metadata: embedding.metadata.clone(),
+            };
+            storage.put_embedding(&engram.id, &stored)?;
+            
+            if !index.contains(&engram.id) {
+                index.add(&engram.id, embedding)?;
+            }
+            count += 1;
+        }
+    }
+    
+    Ok(count)
+}
+
+/// Exact k-nearest-neighbor search by scanning every candidate in parallel.
+///
+/// Candidates whose dimensions differ from the query are skipped. Results are sorted
+/// by similarity (highest first), with ties broken by ID so the output is deterministic.
+pub fn exact_knn_scan(candidates: &[(&str, &Embedding)], query: &Embedding, k: usize) -> Vec<(EngramId, f32)> {
+    let mut results: Vec<(EngramId, f32)> = candidates
+        .par_iter()
+        .filter_map(|(id, embedding)| {
+            query.cosine_similarity(embedding).ok().map(|similarity| (id.to_string(), similarity))
+        })
+        .collect();
+    
+    results.par_sort_unstable_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    results.truncate(k);
+    
+    results
+}
+
+/// Pairwise cosine similarities for a set of engrams
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimilarityMatrix {
+    /// Row and column labels, in the order the engrams were requested
+    pub ids: Vec<EngramId>,
+    
+    /// Symmetric matrix where `values[i][j]` is the similarity of `ids[i]` and `ids[j]`
+    pub values: Vec<Vec<f32>>,
+}
+
+/// Compute the pairwise similarity matrix of the stored embeddings for `ids`.
+///
+/// Only the upper triangle is computed (in parallel) and mirrored; the diagonal is 1.0.
+/// Engrams without a stored embedding are dropped from the labels when `skip_missing`
+/// is set, otherwise they are reported as an error.
+pub fn compute_similarity_matrix(ids: &[EngramId], storage: &Storage, skip_missing: bool) -> Result<SimilarityMatrix> {
+    let mut labels = Vec::with_capacity(ids.len());
+    let mut embeddings = Vec::with_capacity(ids.len());
+    
+    for id in ids {
+        match storage.get_embedding(id)? {
+            Some(stored) => {
+                labels.push(id.clone());
+                embeddings.push(Embedding {
+                    vector: stored.vector,
+                    model: stored.model,
+                    dimensions: stored.dimensions,
+                    metadata: stored.metadata,
+                });
+            },
+            None if skip_missing => continue,
+            None => return Err(EngramError::NotFound(format!("Embedding not found for engram: {}", id))),
+        }
+    }
+    
+    // Each row holds the similarities to the engrams after it
+    let upper: Vec<Vec<f32>> = (0..embeddings.len())
+        .into_par_iter()
+        .map(|i| {
+            embeddings[i + 1..]
+                .iter()
+                .map(|other| embeddings[i].cosine_similarity(other))
+                .collect::<Result<Vec<f32>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    let n = embeddings.len();
+    let mut values = vec![vec![0.0; n]; n];
+    for (i, row) in upper.into_iter().enumerate() {
+        values[i][i] = 1.0;
+        for (offset, similarity) in row.into_iter().enumerate() {
+            let j = i + 1 + offset;
+            values[i][j] = similarity;
+            values[j][i] = similarity;
+        }
+    }
+    
+    Ok(SimilarityMatrix { ids: labels, values })
+}
+
+/// Similarity below which `VectorIndex::similar_to` and `suggest_connections` drop results
+/// unless the index or the call sets another floor
+pub const DEFAULT_MIN_SIMILARITY: f32 = 0.3;
 
 /// Vector search index for efficient similarity search
 pub struct VectorIndex {
@@ -23,6 +248,12 @@ pub struct VectorIndex {
     
     /// Whether to use reduced embeddings for search
     use_reduced_embeddings: bool,
+    
+    /// Per-collection sub-indexes for scoped semantic search
+    collection_indexes: RwLock<HashMap<CollectionId, HnswIndex>>,
+    
+    /// Default similarity floor for "more like this" results and connection suggestions
+    min_similarity: f32,
 }
 
 impl VectorIndex {
@@ -37,6 +268,8 @@ impl VectorIndex {
             embedding_service,
             dimensions,
             use_reduced_embeddings: false,
+            collection_indexes: RwLock::new(HashMap::new()),
+            min_similarity: DEFAULT_MIN_SIMILARITY,
         }
     }
     
@@ -50,6 +283,8 @@ impl VectorIndex {
             embedding_service,
             dimensions,
             use_reduced_embeddings: false,
+            collection_indexes: RwLock::new(HashMap::new()),
+            min_similarity: DEFAULT_MIN_SIMILARITY,
         }
     }
     
@@ -59,6 +294,17 @@ impl VectorIndex {
         self
     }
     
+    /// Set the default similarity floor for "more like this" results and connection suggestions
+    pub fn with_min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
+    
+    /// Default similarity floor for "more like this" results and connection suggestions
+    pub fn min_similarity(&self) -> f32 {
+        self.min_similarity
+    }
+    
     /// Add an engram to the index
     pub fn add_engram(&self, engram: &Engram) -> Result<()> {
         // Check if we already have an embedding in the metadata
@@ -221,11 +467,123 @@ impl VectorIndex {
             })?.remove(engram_id);
         }
         
+        // Remove from every collection sub-index
+        for sub_index in self.collection_indexes.write().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire write lock on collection indexes".to_string())
+        })?.values_mut() {
+            sub_index.remove(engram_id)?;
+        }
+        
         Ok(removed)
     }
     
+    /// Add an indexed engram to a collection's sub-index
+    pub fn add_to_collection(&self, collection_id: &CollectionId, engram_id: &EngramId) -> Result<()> {
+        let embedding = self.get_embedding_for_engram(engram_id)?;
+        
+        let mut collection_indexes = self.collection_indexes.write().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire write lock on collection indexes".to_string())
+        })?;
+        let sub_index = collection_indexes
+            .entry(collection_id.clone())
+            .or_insert_with(|| HnswIndex::new(embedding.dimensions));
+        
+        if !sub_index.contains(engram_id) {
+            sub_index.add(engram_id, embedding)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Remove an engram from a collection's sub-index
+    pub fn remove_from_collection(&self, collection_id: &CollectionId, engram_id: &EngramId) -> Result<bool> {
+        let mut collection_indexes = self.collection_indexes.write().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire write lock on collection indexes".to_string())
+        })?;
+        
+        match collection_indexes.get_mut(collection_id) {
+            Some(sub_index) => sub_index.remove(engram_id),
+            None => Ok(false),
+        }
+    }
+    
+    /// Bring a collection's sub-index in line with its current members.
+    /// Call after engrams join or leave the collection; members missing from the main index are skipped.
+    pub fn sync_collection(&self, collection: &Collection) -> Result<()> {
+        let indexed: HashSet<EngramId> = self.collection_indexes.read().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire read lock on collection indexes".to_string())
+        })?
+            .get(&collection.id)
+            .map(|sub_index| sub_index.ids().into_iter().collect())
+            .unwrap_or_default();
+        
+        for engram_id in indexed.difference(&collection.engram_ids) {
+            self.remove_from_collection(&collection.id, engram_id)?;
+        }
+        
+        for engram_id in collection.engram_ids.difference(&indexed) {
+            match self.add_to_collection(&collection.id, engram_id) {
+                Ok(()) | Err(EngramError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Drop a collection's sub-index
+    pub fn remove_collection(&self, collection_id: &CollectionId) -> Result<()> {
+        self.collection_indexes.write().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire write lock on collection indexes".to_string())
+        })?.remove(collection_id);
+        
+        Ok(())
+    }
+    
+    /// Semantic search over all engrams, or only over one collection's engrams
+    pub fn semantic_search(&self, query: &str, k: usize, collection_id: Option<&CollectionId>) -> Result<Vec<(EngramId, f32)>> {
+        let embedding = self.embedding_service.embed_text(query)?;
+        
+        // Use reduced embedding if needed
+        let query_embedding = if self.use_reduced_embeddings {
+            self.embedding_service.reduce_embedding(&embedding).unwrap_or(embedding)
+        } else {
+            embedding
+        };
+        
+        match collection_id {
+            Some(collection_id) => self.search_collection_by_embedding(collection_id, &query_embedding, k),
+            None => self.search_by_embedding(&query_embedding, k),
+        }
+    }
+    
+    /// Search only the engrams of one collection using an existing embedding
+    pub fn search_collection_by_embedding(&self, collection_id: &CollectionId, embedding: &Embedding, k: usize) -> Result<Vec<(EngramId, f32)>> {
+        let collection_indexes = self.collection_indexes.read().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire read lock on collection indexes".to_string())
+        })?;
+        
+        match collection_indexes.get(collection_id) {
+            Some(sub_index) => sub_index.search(embedding, k, None),
+            None => Ok(Vec::new()),
+        }
+    }
+    
+    /// Delete an engram from storage, cascading to its stored embeddings and its index entry
+    pub fn delete_engram(&self, storage: &Storage, engram_id: &EngramId) -> Result<()> {
+        storage.delete_engram(engram_id)?;
+        self.remove_engram(engram_id)?;
+        Ok(())
+    }
+    
     /// Search for similar engrams
     pub fn search(&self, query: &str, k: usize) -> Result<Vec<(EngramId, f32)>> {
+        self.search_with_ef(query, k, None)
+    }
+    
+    /// Search for similar engrams, overriding the HNSW search width for this query only.
+    /// Higher values favour recall over latency and must be at least `k`.
+    pub fn search_with_ef(&self, query: &str, k: usize, ef_search: Option<usize>) -> Result<Vec<(EngramId, f32)>> {
         // Generate embedding from query
         let embedding = self.embedding_service.embed_text(query)?;
         
@@ -243,15 +601,62 @@ impl VectorIndex {
         // Search index
         self.index.read().map_err(|_| {
             EngramError::ConcurrencyError("Failed to acquire read lock on vector index".to_string())
-        })?.search(&query_embedding, k)
+        })?.search(&query_embedding, k, ef_search)
     }
     
     /// Search using an existing embedding
     pub fn search_by_embedding(&self, embedding: &Embedding, k: usize) -> Result<Vec<(EngramId, f32)>> {
+        self.search_by_embedding_with_ef(embedding, k, None)
+    }
+    
+    /// Search using an existing embedding, overriding the HNSW search width for this query only
+    pub fn search_by_embedding_with_ef(&self, embedding: &Embedding, k: usize, ef_search: Option<usize>) -> Result<Vec<(EngramId, f32)>> {
         // Search index
         self.index.read().map_err(|_| {
             EngramError::ConcurrencyError("Failed to acquire read lock on vector index".to_string())
-        })?.search(embedding, k)
+        })?.search(embedding, k, ef_search)
+    }
+    
+    /// Exact k-nearest-neighbor search over every embedding in the index.
+    ///
+    /// Slower than `search_by_embedding` but never misses a neighbor, so it serves as
+    /// ground truth for recall measurement and for correctness-critical queries.
+    pub fn exact_knn(&self, query: &Embedding, k: usize) -> Result<Vec<(EngramId, f32)>> {
+        let index = self.index.read().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire read lock on vector index".to_string())
+        })?;
+        
+        Ok(exact_knn_scan(&index.entries(), query, k))
+    }
+    
+    /// Exact k-nearest-neighbor search over the embeddings in storage, for use when the
+    /// approximate index is unavailable (e.g. not yet built)
+    pub fn exact_knn_from_storage(&self, storage: &Storage, query: &Embedding, k: usize) -> Result<Vec<(EngramId, f32)>> {
+        let ids = if self.use_reduced_embeddings {
+            storage.list_reduced_embeddings()?
+        } else {
+            storage.list_embeddings()?
+        };
+        
+        let mut embeddings = Vec::with_capacity(ids.len());
+        for id in ids {
+            let stored = if self.use_reduced_embeddings {
+                storage.get_reduced_embedding(&id)?
+            } else {
+                storage.get_embedding(&id)?
+            };
+            
+            if let Some(stored) = stored {
+                embeddings.push((id, Embedding::new(stored.vector, stored.model)));
+            }
+        }
+        
+        let candidates: Vec<(&str, &Embedding)> = embeddings
+            .iter()
+            .map(|(id, embedding)| (id.as_str(), embedding))
+            .collect();
+        
+        Ok(exact_knn_scan(&candidates, query, k))
     }
     
     /// Search for similar engrams to an existing engram
@@ -263,11 +668,63 @@ impl VectorIndex {
         self.search_by_embedding(&embedding, k)
     }
     
+    /// Up to `limit` engrams most like `engram_id`, excluding itself, with their similarity.
+    /// Results below `min_similarity` (the index default when `None`) are never returned.
+    pub fn similar_to(&self, engram_id: &EngramId, limit: usize, min_similarity: Option<f32>) -> Result<Vec<(EngramId, f32)>> {
+        self.similar_excluding(engram_id, limit, min_similarity, &HashSet::new())
+    }
+    
+    /// Suggest up to `limit` engrams to connect `engram_id` to: the most similar engrams it is
+    /// not already connected to in either direction, with their similarity. Results below
+    /// `min_similarity` (the index default when `None`) are never returned.
+    pub fn suggest_connections(
+        &self,
+        storage: &Storage,
+        engram_id: &EngramId,
+        limit: usize,
+        min_similarity: Option<f32>,
+    ) -> Result<Vec<(EngramId, f32)>> {
+        let mut connected = HashSet::new();
+        for connection_id in storage.find_connections_for_engram(engram_id)? {
+            if let Some(connection) = storage.get_connection(&connection_id)? {
+                connected.insert(connection.source_id);
+                connected.insert(connection.target_id);
+            }
+        }
+        
+        self.similar_excluding(engram_id, limit, min_similarity, &connected)
+    }
+    
+    /// Nearest neighbors of `engram_id` at or above the similarity floor, skipping itself and `excluded`
+    fn similar_excluding(
+        &self,
+        engram_id: &EngramId,
+        limit: usize,
+        min_similarity: Option<f32>,
+        excluded: &HashSet<EngramId>,
+    ) -> Result<Vec<(EngramId, f32)>> {
+        let floor = min_similarity.unwrap_or(self.min_similarity);
+        
+        // Over-fetch so that skipped engrams don't shrink the result below `limit`
+        let results = self.search_similar_to(engram_id, limit + excluded.len() + 1)?;
+        
+        Ok(results
+            .into_iter()
+            .filter(|(id, similarity)| id != engram_id && !excluded.contains(id) && *similarity >= floor)
+            .take(limit)
+            .collect())
+    }
+    
     /// Get the embedding service
     pub fn get_embedding_service(&self) -> Arc<EmbeddingService> {
         self.embedding_service.clone()
     }
     
+    /// Get the number of dimensions of the embedding model
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+    
     /// Get the number of vectors in the index
     pub fn len(&self) -> Result<usize> {
         Ok(self.index.read().map_err(|_| {
@@ -292,6 +749,10 @@ impl VectorIndex {
             EngramError::ConcurrencyError("Failed to acquire write lock on id mapping".to_string())
         })?.clear();
         
+        self.collection_indexes.write().map_err(|_| {
+            EngramError::ConcurrencyError("Failed to acquire write lock on collection indexes".to_string())
+        })?.clear();
+        
         Ok(())
     }
     
@@ -343,6 +804,109 @@ impl VectorIndex {
     }
 }
 
+impl Default for VectorIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Expands a text query with keywords drawn from semantically similar engrams,
+/// so keyword search also finds synonyms the query doesn't mention
+pub struct QueryExpander<'a> {
+    /// Storage used to read the content of neighboring engrams
+    storage: &'a Storage,
+    
+    /// Vector index used to find engrams near the query
+    vector_index: &'a VectorIndex,
+    
+    /// Maximum number of expansion terms to add
+    max_terms: usize,
+    
+    /// Number of nearest engrams to draw terms from
+    neighbors: usize,
+    
+    /// Minimum similarity for a neighbor to contribute terms
+    min_similarity: f32,
+}
+
+impl<'a> QueryExpander<'a> {
+    /// Create a query expander with default settings (5 terms from the 3 nearest engrams)
+    pub fn new(storage: &'a Storage, vector_index: &'a VectorIndex) -> Self {
+        Self {
+            storage,
+            vector_index,
+            max_terms: 5,
+            neighbors: 3,
+            min_similarity: 0.5,
+        }
+    }
+    
+    /// Set the maximum number of expansion terms
+    pub fn with_max_terms(mut self, max_terms: usize) -> Self {
+        self.max_terms = max_terms;
+        self
+    }
+    
+    /// Set the number of nearest engrams to draw terms from
+    pub fn with_neighbors(mut self, neighbors: usize) -> Self {
+        self.neighbors = neighbors;
+        self
+    }
+    
+    /// Set the minimum similarity for a neighbor to contribute terms
+    pub fn with_min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
+    
+    /// Find expansion terms for a query, embedding it with the index's embedding service
+    pub fn expand(&self, query: &str) -> Result<Vec<String>> {
+        let embedding = self.vector_index.get_embedding_service().embed_text(query)?;
+        self.expand_with_embedding(query, &embedding)
+    }
+    
+    /// Find expansion terms for a query whose embedding is already known
+    pub fn expand_with_embedding(&self, query: &str, embedding: &Embedding) -> Result<Vec<String>> {
+        let query_keywords = TextIndex::extract_keywords(query);
+        let mut term_scores: HashMap<String, f32> = HashMap::new();
+        
+        for (engram_id, similarity) in self.vector_index.search_by_embedding(embedding, self.neighbors)? {
+            if similarity < self.min_similarity {
+                continue;
+            }
+            
+            if let Some(engram) = self.storage.get_engram(&engram_id)? {
+                for keyword in TextIndex::extract_keywords(&engram.content) {
+                    if query_keywords.contains(&keyword) || TextIndex::is_stopword(&keyword, Some("eng")) {
+                        continue;
+                    }
+                    *term_scores.entry(keyword).or_insert(0.0) += similarity;
+                }
+            }
+        }
+        
+        // Highest scoring terms first, alphabetical among ties for stable output
+        let mut terms: Vec<(String, f32)> = term_scores.into_iter().collect();
+        terms.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        
+        Ok(terms.into_iter().take(self.max_terms).map(|(term, _)| term).collect())
+    }
+    
+    /// Append expansion terms to a query; keyword search ORs them with the original terms
+    pub fn expand_query(&self, query: &str) -> Result<String> {
+        let terms = self.expand(query)?;
+        if terms.is_empty() {
+            return Ok(query.to_string());
+        }
+        
+        Ok(format!("{} {}", query, terms.join(" ")))
+    }
+}
+
 /// Query for vector similarity search
 pub struct VectorQuery {
     /// The text query to embed
@@ -362,6 +926,9 @@ pub struct VectorQuery {
     
     /// Whether to exclude the query engram from results
     pub exclude_self: bool,
+    
+    /// HNSW search width for this query, overriding the index default
+    pub ef_search: Option<usize>,
 }
 
 impl VectorQuery {
@@ -374,6 +941,7 @@ impl VectorQuery {
             limit: 10,
             min_score: None,
             exclude_self: true,
+            ef_search: None,
         }
     }
     
@@ -386,6 +954,7 @@ impl VectorQuery {
             limit: 10,
             min_score: None,
             exclude_self: true,
+            ef_search: None,
         }
     }
     
@@ -398,6 +967,7 @@ impl VectorQuery {
             limit: 10,
             min_score: None,
             exclude_self: true,
+            ef_search: None,
         }
     }
     
@@ -418,6 +988,12 @@ impl VectorQuery {
         self.exclude_self = exclude;
         self
     }
+    
+    /// Set the HNSW search width, trading latency for accuracy
+    pub fn with_ef_search(mut self, ef_search: usize) -> Self {
+        self.ef_search = Some(ef_search);
+        self
+    }
 }
 
 /// Combined search parameters for hybrid retrieval
@@ -535,6 +1111,12 @@ impl HybridQuery {
     }
 }
 
+impl Default for HybridQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// A result from a hybrid search query
 #[derive(Debug)]
 pub struct HybridSearchResult {
@@ -548,6 +1130,74 @@ pub struct HybridSearchResult {
     pub component_scores: HashMap<String, f32>,
 }
 
+/// An engram cited in a retrieved context window
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextSource {
+    /// The cited engram
+    pub engram_id: EngramId,
+    
+    /// The hybrid search score of the engram
+    pub score: f32,
+    
+    /// Estimated tokens the engram contributed to the context
+    pub tokens: usize,
+}
+
+/// A token-budgeted context window assembled from search results, ready to hand to an LLM
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievedContext {
+    /// The concatenated context, one "[engram_id] content" block per source
+    pub context: String,
+    
+    /// The engrams included in the context, in order of appearance
+    pub sources: Vec<ContextSource>,
+    
+    /// Estimated number of tokens in the context
+    pub token_count: usize,
+}
+
+/// Estimate the number of LLM tokens in a text (roughly four characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Assemble a context window from search results, highest score first.
+///
+/// Results scoring below `min_score` are skipped, as are results that would push the
+/// context over `max_tokens`; smaller lower-ranked results may still fill the remaining budget.
+pub fn assemble_context(results: &[HybridSearchResult], max_tokens: usize, min_score: f32) -> RetrievedContext {
+    let mut ranked: Vec<&HybridSearchResult> = results.iter().filter(|r| r.score >= min_score).collect();
+    ranked.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    
+    let mut blocks = Vec::new();
+    let mut sources = Vec::new();
+    let mut token_count = 0;
+    
+    for result in ranked {
+        let block = format!("[{}] {}", result.engram.id, result.engram.content);
+        // Count the blank line that separates blocks against the budget too
+        let tokens = estimate_tokens(&block) + if blocks.is_empty() { 0 } else { 1 };
+        
+        if token_count + tokens > max_tokens {
+            continue;
+        }
+        
+        token_count += tokens;
+        blocks.push(block);
+        sources.push(ContextSource {
+            engram_id: result.engram.id.clone(),
+            score: result.score,
+            tokens,
+        });
+    }
+    
+    RetrievedContext {
+        context: blocks.join("\n\n"),
+        sources,
+        token_count,
+    }
+}
+
 /// Engine for hybrid search (combining keyword, vector, and metadata search)
 pub struct HybridSearchEngine<'a> {
     /// The storage backend
@@ -584,7 +1234,7 @@ impl<'a> HybridSearchEngine<'a> {
             let keyword_results = self.text_index.text_index.search(text);
             
             for engram_id in keyword_results {
-                let entry = scores.entry(engram_id).or_insert_with(HashMap::new);
+                let entry = scores.entry(engram_id).or_default();
                 entry.insert("keyword".to_string(), 1.0); // Score of 1.0 for exact matches
             }
         }
@@ -594,7 +1244,7 @@ impl<'a> HybridSearchEngine<'a> {
             let vector_results = self.execute_vector_query(vector_query)?;
             
             for (engram_id, similarity) in vector_results {
-                let entry = scores.entry(engram_id).or_insert_with(HashMap::new);
+                let entry = scores.entry(engram_id).or_default();
                 entry.insert("vector".to_string(), similarity);
             }
         }
@@ -660,11 +1310,17 @@ impl<'a> HybridSearchEngine<'a> {
         Ok(final_results)
     }
     
+    /// Run a hybrid search and assemble the results into a token-budgeted context window
+    pub fn retrieve(&self, query: &HybridQuery, max_tokens: usize, min_score: f32) -> Result<RetrievedContext> {
+        let results = self.search(query)?;
+        Ok(assemble_context(&results, max_tokens, min_score))
+    }
+    
     /// Execute a vector query
     fn execute_vector_query(&self, query: &VectorQuery) -> Result<Vec<(EngramId, f32)>> {
         if let Some(text) = &query.text {
             // Search by text
-            let results = self.vector_index.search(text, query.limit)?;
+            let results = self.vector_index.search_with_ef(text, query.limit, query.ef_search)?;
             
             // Apply minimum score filter if needed
             if let Some(min_score) = query.min_score {
@@ -674,7 +1330,7 @@ impl<'a> HybridSearchEngine<'a> {
             }
         } else if let Some(embedding) = &query.embedding {
             // Search by embedding
-            let results = self.vector_index.search_by_embedding(embedding, query.limit)?;
+            let results = self.vector_index.search_by_embedding_with_ef(embedding, query.limit, query.ef_search)?;
             
             // Apply minimum score filter if needed
             if let Some(min_score) = query.min_score {
@@ -698,12 +1354,9 @@ impl<'a> HybridSearchEngine<'a> {
                 results
             };
             
-            // Apply minimum score filter if needed
-            if let Some(min_score) = query.min_score {
-                Ok(filtered.into_iter().filter(|(_, score)| *score >= min_score).collect())
-            } else {
-                Ok(filtered)
-            }
+            // "More like this" results never fall below the index's similarity floor
+            let min_score = query.min_score.unwrap_or(self.vector_index.min_similarity());
+            Ok(filtered.into_iter().filter(|(_, score)| *score >= min_score).collect())
         } else {
             Err(EngramError::InvalidOperation("Vector query must include text, embedding, or similar_to_id".to_string()))
         }
@@ -1,4 +1,5 @@
-use engram_lite::embedding::{EmbeddingModel, EmbeddingService};
+use engram_lite::embedding::{Embedding, EmbeddingModel, EmbeddingService};
+use engram_lite::storage::{self, Storage};
 use engram_lite::utils;
 use std::env;
 use std::fs::File;
@@ -12,20 +13,18 @@ fn load_env() {
         let file = File::open(env_path).expect("Failed to open .env file");
         let reader = BufReader::new(file);
 
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
+        for line in reader.lines().map_while(Result::ok) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
 
-                if let Some(equal_sign_pos) = line.find('=') {
-                    let key = line[..equal_sign_pos].trim();
-                    let value = line[equal_sign_pos + 1..].trim();
-                    
-                    // Only set if not already set
-                    if env::var(key).is_err() {
-                        env::set_var(key, value);
-                    }
+            if let Some(equal_sign_pos) = line.find('=') {
+                let key = line[..equal_sign_pos].trim();
+                let value = line[equal_sign_pos + 1..].trim();
+                
+                // Only set if not already set
+                if env::var(key).is_err() {
+                    env::set_var(key, value);
                 }
             }
         }
@@ -65,7 +64,8 @@ fn test_e5_embedding_generation() {
                 println!("Successfully generated E5 embedding with {} dimensions", embedding.dimensions);
                 
                 // Test normalization
-                let norm = embedding.normalized();
+                let mut norm = embedding.clone();
+                norm.normalize();
                 let norm_length: f32 = norm.vector.iter().map(|x| x * x).sum::<f32>().sqrt();
                 assert!((norm_length - 1.0).abs() < 0.001, "Normalized vector should have length 1.0");
             },
@@ -85,7 +85,7 @@ fn test_gte_embedding_generation() {
     load_env();
 
     // Check if HuggingFace API key is available
-    if let Some(_) = utils::get_huggingface_api_key() {
+    if utils::get_huggingface_api_key().is_some() {
         // Create embedding service with GTE model
         let service = EmbeddingService::with_model_type(EmbeddingModel::GteModernBertBase);
         
@@ -121,7 +121,7 @@ fn test_batch_embedding_generation() {
     load_env();
 
     // Check if HuggingFace API key is available
-    if let Some(_) = utils::get_huggingface_api_key() {
+    if utils::get_huggingface_api_key().is_some() {
         // Create embedding service
         let service = EmbeddingService::with_model_type(EmbeddingModel::E5MultilingualLargeInstruct);
         
@@ -174,4 +174,71 @@ fn test_batch_embedding_generation() {
         // Skip test if no API key found
         println!("Skipping test_batch_embedding_generation - no HuggingFace API key found in .env");
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_raw_and_normalized_embeddings_after_storage() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let mut raw = Embedding::new(vec![3.0, 4.0, 12.0], "test".to_string());
+    raw.metadata.insert("normalized".to_string(), "false".to_string());
+    let mut normalized = raw.clone();
+    normalized.normalize();
+    
+    // Round trip both embeddings through storage
+    for (id, embedding) in [("raw", &raw), ("normalized", &normalized)] {
+        let stored = storage::Embedding::create(
+            embedding.vector.clone(),
+            embedding.model.clone(),
+            embedding.dimensions,
+            embedding.metadata.clone(),
+        );
+        storage.put_embedding(&id.to_string(), &stored).unwrap();
+    }
+    
+    let load = |id: &str| {
+        let stored = storage.get_embedding(&id.to_string()).unwrap().unwrap();
+        let mut embedding = Embedding::new(stored.vector, stored.model);
+        embedding.metadata = stored.metadata;
+        embedding
+    };
+    
+    let loaded_raw = load("raw");
+    assert!(!loaded_raw.is_normalized());
+    assert_eq!(loaded_raw.normalized_flag(), Some(false));
+    assert_eq!(loaded_raw.vector, vec![3.0, 4.0, 12.0]);
+    
+    let loaded_normalized = load("normalized");
+    assert!(loaded_normalized.is_normalized());
+    assert_eq!(loaded_normalized.normalized_flag(), Some(true));
+    
+    // The service records which form it produced when normalization is overridden per call
+    let service = EmbeddingService::new();
+    let raw_embedding = service.embed_text_with_normalization("raw vector please", false).unwrap();
+    assert_eq!(raw_embedding.normalized_flag(), Some(false));
+    let normalized_embedding = service.embed_text_with_normalization("raw vector please", true).unwrap();
+    assert_eq!(normalized_embedding.normalized_flag(), Some(true));
+    assert!(normalized_embedding.is_normalized());
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_embedding_requests_are_stable_per_model() {
+    load_env();
+    
+    for name in ["e5", "gte", "jina", "Alibaba-NLP/gte-modernbert-base"] {
+        let model = EmbeddingModel::from_name(name).expect("Known model name");
+        let service = EmbeddingService::with_model_type(model);
+        
+        // Identical requests return identical vectors of the model's dimensionality
+        let first = service.embed_text("What does the agent remember?").unwrap();
+        let second = service.embed_text("What does the agent remember?").unwrap();
+        assert_eq!(first.vector, second.vector);
+        assert_eq!(first.vector.len(), model.default_dimensions());
+        assert_eq!(first.dimensions, service.get_dimensions());
+    }
+    
+    assert!(EmbeddingModel::from_name("unknown-model").is_none());
+}
@@ -1,5 +1,4 @@
 use engram_lite::schema::Engram;
-use chrono::Utc;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -0,0 +1,94 @@
+use engram_lite::export::{export_context_bundle, import_context_bundle};
+use engram_lite::schema::{Agent, Connection, Context, Engram};
+use engram_lite::storage::Storage;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+#[test]
+fn test_context_bundle_round_trip() {
+    let source_path = format!("./test_db_{}", Uuid::new_v4());
+    let target_path = format!("./test_db_{}", Uuid::new_v4());
+    let bundle_path = format!("./test_bundle_{}.json", Uuid::new_v4());
+    
+    let (context, engrams, agent) = {
+        let storage = Storage::new(&source_path).expect("Failed to create storage");
+        
+        let agent = Agent::new("Researcher".to_string(), "Runs experiments".to_string(), None, None);
+        storage.put_agent(&agent).unwrap();
+        
+        let a = Engram::new("Hypothesis".to_string(), "alice".to_string(), 0.8, None)
+            .with_created_by(agent.id.clone());
+        let b = Engram::new("Evidence".to_string(), "bob".to_string(), 0.9, None);
+        let outsider = Engram::new("Unrelated".to_string(), "carol".to_string(), 0.5, None);
+        for engram in [&a, &b, &outsider] {
+            storage.put_engram(engram).unwrap();
+        }
+        
+        // Only the connection between context members belongs in the bundle
+        let inside = Connection::new(b.id.clone(), a.id.clone(), "supports".to_string(), 0.7, None)
+            .with_created_by(agent.id.clone());
+        let outside = Connection::new(a.id.clone(), outsider.id.clone(), "related".to_string(), 0.3, None);
+        storage.put_connection(&inside).unwrap();
+        storage.put_connection(&outside).unwrap();
+        
+        let mut context = Context::new("Session".to_string(), "Shared session".to_string(), None);
+        context.add_engram(a.id.clone());
+        context.add_engram(b.id.clone());
+        context.add_agent(agent.id.clone());
+        storage.put_context(&context).unwrap();
+        
+        export_context_bundle(&storage, &context.id, Path::new(&bundle_path)).unwrap();
+        (context, vec![a, b], agent)
+    };
+    
+    let storage = Storage::new(&target_path).expect("Failed to create storage");
+    
+    // A plain import keeps the original IDs
+    let imported_id = import_context_bundle(&storage, Path::new(&bundle_path), false).unwrap();
+    assert_eq!(imported_id, context.id);
+    let imported = storage.get_context(&imported_id).unwrap().unwrap();
+    assert_eq!(imported.engram_ids, context.engram_ids);
+    assert_eq!(imported.agent_ids, context.agent_ids);
+    for engram in &engrams {
+        assert_eq!(storage.get_engram(&engram.id).unwrap().unwrap().content, engram.content);
+    }
+    assert_eq!(storage.get_agent(&agent.id).unwrap().unwrap().name, "Researcher");
+    assert_eq!(storage.list_engrams().unwrap().len(), 2);
+    assert_eq!(storage.list_connections().unwrap().len(), 1);
+    
+    // Importing again with remapping adds a disjoint copy wired up the same way
+    let copy_id = import_context_bundle(&storage, Path::new(&bundle_path), true).unwrap();
+    assert_ne!(copy_id, context.id);
+    let copy = storage.get_context(&copy_id).unwrap().unwrap();
+    assert_eq!(copy.name, "Session");
+    assert_eq!(copy.engram_ids.len(), 2);
+    assert!(copy.engram_ids.is_disjoint(&context.engram_ids));
+    assert_eq!(copy.agent_ids.len(), 1);
+    let copied_agent = copy.agent_ids.iter().next().unwrap().clone();
+    assert!(storage.get_agent(&copied_agent).unwrap().is_some());
+    
+    let copied_engrams: Vec<Engram> = copy.engram_ids.iter()
+        .map(|id| storage.get_engram(id).unwrap().unwrap())
+        .collect();
+    let contents: HashSet<String> = copied_engrams.iter().map(|e| e.content.clone()).collect();
+    assert_eq!(contents, HashSet::from(["Hypothesis".to_string(), "Evidence".to_string()]));
+    
+    // Attribution follows the remapped agent
+    let hypothesis = copied_engrams.iter().find(|e| e.content == "Hypothesis").unwrap();
+    assert_eq!(hypothesis.created_by, Some(copied_agent.clone()));
+    
+    let copied_connections: Vec<Connection> = storage.list_connections().unwrap().iter()
+        .filter_map(|id| storage.get_connection(id).unwrap())
+        .filter(|c| copy.engram_ids.contains(&c.source_id))
+        .collect();
+    assert_eq!(copied_connections.len(), 1);
+    assert!(copy.engram_ids.contains(&copied_connections[0].target_id));
+    assert_eq!(copied_connections[0].created_by, Some(copied_agent));
+    
+    drop(storage);
+    let _ = fs::remove_dir_all(&source_path);
+    let _ = fs::remove_dir_all(&target_path);
+    let _ = fs::remove_file(&bundle_path);
+}
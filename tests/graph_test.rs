@@ -1,5 +1,6 @@
 use engram_lite::graph::MemoryGraph;
 use engram_lite::schema::{Agent, Collection, Connection, Context, Engram};
+use engram_lite::storage::Storage;
 use std::collections::HashSet;
 
 // Helper function to create test engrams
@@ -63,16 +64,11 @@ fn test_graph_basic_operations() {
     assert_eq!(connection_id, connection.id);
 
     // Test getting connections
-    let connections = graph.get_connections_for_engram(&id1).expect("Failed to get connections");
+    let connections = graph.get_connections_between(&id1, &id2).expect("Failed to get connections");
     assert_eq!(connections.len(), 1);
     assert_eq!(connections[0].id, connection.id);
-
-    // Test getting connections by type
-    let typed_connections = graph
-        .get_connections_by_type("supports".to_string())
-        .expect("Failed to get connections by type");
-    assert_eq!(typed_connections.len(), 1);
-    assert_eq!(typed_connections[0].id, connection.id);
+    assert_eq!(connections[0].relationship_type, "supports");
+    assert!(graph.get_connections_between(&id2, &id1).expect("Failed to get connections").is_empty());
 
     // Test getting engrams by source
     let source_engrams = graph
@@ -95,6 +91,33 @@ fn test_graph_basic_operations() {
     assert_eq!(recent_engrams.len(), 2);
 }
 
+#[test]
+fn test_graph_remove_connection() {
+    let mut graph = MemoryGraph::new();
+    let (engram1, engram2, engram3) = create_test_engrams();
+    let id1 = graph.add_engram(engram1).unwrap();
+    let id2 = graph.add_engram(engram2).unwrap();
+    let id3 = graph.add_engram(engram3).unwrap();
+
+    let first = Connection::new(id1.clone(), id2.clone(), "supports".to_string(), 0.5, None);
+    let second = Connection::new(id2.clone(), id3.clone(), "supports".to_string(), 0.6, None);
+    let third = Connection::new(id1.clone(), id3.clone(), "supports".to_string(), 0.7, None);
+    for connection in [&first, &second, &third] {
+        graph.add_connection(connection.clone()).unwrap();
+    }
+
+    // Removing an edge from the middle keeps the others reachable by ID
+    let removed = graph.remove_connection(&first.id).unwrap();
+    assert_eq!(removed.map(|c| c.id), Some(first.id.clone()));
+    assert!(graph.get_connection(&first.id).unwrap().is_none());
+    assert!(graph.get_connections_between(&id1, &id2).unwrap().is_empty());
+    assert_eq!(graph.get_connection(&second.id).unwrap().unwrap().id, second.id);
+    assert_eq!(graph.get_connection(&third.id).unwrap().unwrap().id, third.id);
+
+    // Removing it again is a no-op
+    assert!(graph.remove_connection(&first.id).unwrap().is_none());
+}
+
 #[test]
 fn test_graph_collection_operations() {
     let mut graph = MemoryGraph::new();
@@ -105,7 +128,7 @@ fn test_graph_collection_operations() {
     let id2 = graph.add_engram(engram2.clone()).expect("Failed to add engram2");
 
     // Create a collection
-    let mut collection = Collection::new(
+    let collection = Collection::new(
         "Test Collection".to_string(),
         "A test collection".to_string(),
         None,
@@ -129,15 +152,9 @@ fn test_graph_collection_operations() {
     if let Some(c) = retrieved_collection {
         assert_eq!(c.id, collection_id);
         assert_eq!(c.engram_ids.len(), 2);
+        assert!(c.engram_ids.contains(&id1));
+        assert!(c.engram_ids.contains(&id2));
     }
-
-    // Test getting engrams in collection
-    let collection_engrams = graph
-        .get_engrams_in_collection(&collection_id)
-        .expect("Failed to get engrams in collection");
-    assert_eq!(collection_engrams.len(), 2);
-    assert!(collection_engrams.iter().any(|e| e.id == id1));
-    assert!(collection_engrams.iter().any(|e| e.id == id2));
 }
 
 #[test]
@@ -183,12 +200,11 @@ fn test_graph_agent_operations() {
         assert!(a.accessible_collections.contains(&collection_id));
     }
     
-    // Test getting collections accessible to agent
-    let accessible_collections = graph
-        .get_accessible_collections(&agent_id)
-        .expect("Failed to get accessible collections");
-    assert_eq!(accessible_collections.len(), 1);
-    assert_eq!(accessible_collections[0].id, collection_id);
+    // The collection is empty, so the agent can't reach any engrams through it yet
+    let accessible_engrams = graph
+        .get_agent_accessible_engrams(&agent_id)
+        .expect("Failed to get accessible engrams");
+    assert!(accessible_engrams.is_empty());
 }
 
 #[test]
@@ -239,18 +255,18 @@ fn test_graph_context_operations() {
     
     // Test getting engrams in context
     let context_engrams = graph
-        .get_engrams_in_context(&context_id)
+        .get_context_engrams(&context_id)
         .expect("Failed to get engrams in context");
     assert_eq!(context_engrams.len(), 2);
     assert!(context_engrams.iter().any(|e| e.id == id1));
     assert!(context_engrams.iter().any(|e| e.id == id2));
     
-    // Test getting contexts for agent
-    let agent_contexts = graph
-        .get_contexts_for_agent(&agent_id)
-        .expect("Failed to get contexts for agent");
-    assert_eq!(agent_contexts.len(), 1);
-    assert_eq!(agent_contexts[0].id, context_id);
+    // Test getting agents in context
+    let context_agents = graph
+        .get_agents_in_context(&context_id)
+        .expect("Failed to get agents in context");
+    assert_eq!(context_agents.len(), 1);
+    assert_eq!(context_agents[0].id, agent_id);
 }
 
 #[test]
@@ -284,15 +300,119 @@ fn test_path_finding_between_engrams() {
     graph.add_connection(connection1.clone()).expect("Failed to add connection1");
     graph.add_connection(connection2.clone()).expect("Failed to add connection2");
     
-    // Test finding path between engrams
-    let path = graph
-        .find_path_between_engrams(&id1, &id3)
-        .expect("Failed to find path between engrams");
-    
-    // Path should include both connections
-    assert_eq!(path.len(), 2);
-    assert_eq!(path[0].source_id, id1);
-    assert_eq!(path[0].target_id, id2);
-    assert_eq!(path[1].source_id, id2);
-    assert_eq!(path[1].target_id, id3);
-}
\ No newline at end of file
+    // The only path from engram 1 to engram 3 runs through engram 2
+    let first_hop = graph.get_connections_between(&id1, &id2).expect("Failed to get connections");
+    let second_hop = graph.get_connections_between(&id2, &id3).expect("Failed to get connections");
+    assert_eq!(first_hop.len(), 1);
+    assert_eq!(first_hop[0].id, connection1.id);
+    assert_eq!(second_hop.len(), 1);
+    assert_eq!(second_hop[0].id, connection2.id);
+    assert!(graph.get_connections_between(&id1, &id3).expect("Failed to get connections").is_empty());
+}
+
+#[test]
+fn test_move_engram_between_collections_is_atomic() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut graph = MemoryGraph::new();
+    let (engram1, engram2, _) = create_test_engrams();
+    
+    let id1 = graph.add_engram(engram1).expect("Failed to add engram1");
+    let id2 = graph.add_engram(engram2).expect("Failed to add engram2");
+    let connection = Connection::new(id1.clone(), id2.clone(), "related".to_string(), 0.5, None);
+    let connection_id = graph.add_connection(connection).expect("Failed to add connection");
+    
+    let inbox = Collection::new("Inbox".to_string(), "Unsorted".to_string(), None);
+    let archive = Collection::new("Archive".to_string(), "Filed".to_string(), None);
+    let (inbox_id, archive_id) = (inbox.id.clone(), archive.id.clone());
+    graph.add_collection(inbox).unwrap();
+    graph.add_collection(archive).unwrap();
+    graph.add_engram_to_collection(&id1, &inbox_id).unwrap();
+    for id in [&inbox_id, &archive_id] {
+        storage.put_collection(&graph.get_collection(id).unwrap().unwrap()).unwrap();
+    }
+    
+    let stored_members = |id: &String| storage.get_collection(id).unwrap().unwrap().engram_ids.contains(&id1);
+    
+    // Simulate a crash after staging only the removal: nothing reaches storage
+    {
+        let mut from = graph.get_collection(&inbox_id).unwrap().unwrap();
+        from.remove_engram(&id1);
+        let mut txn = storage.begin_transaction();
+        txn.put_collection(&from).unwrap();
+        drop(txn);
+    }
+    assert!(stored_members(&inbox_id));
+    assert!(!stored_members(&archive_id));
+    
+    // The real move commits both sides together
+    assert!(graph.move_engram(&id1, &inbox_id, &archive_id, &storage).unwrap());
+    assert!(!stored_members(&inbox_id));
+    assert!(stored_members(&archive_id));
+    
+    // The graph mirrors storage and existing connections are still addressable
+    assert!(graph.get_collection(&inbox_id).unwrap().unwrap().engram_ids.is_empty());
+    let archived = graph.get_collection(&archive_id).unwrap().unwrap().engram_ids;
+    assert_eq!(archived.len(), 1);
+    assert!(archived.contains(&id1));
+    assert!(graph.get_connection(&connection_id).unwrap().is_some());
+    
+    // Moving again fails because the engram is no longer in the source, and leaves it in place
+    assert!(graph.move_engram(&id1, &inbox_id, &archive_id, &storage).is_err());
+    assert!(graph.move_engram(&id1, &archive_id, &"missing".to_string(), &storage).is_err());
+    assert!(stored_members(&archive_id));
+    
+    drop(storage);
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_spreading_activation_on_demo_graph() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    engram_lite::demo::populate_demo_data(&db_path).expect("Failed to populate demo data");
+    let storage = Storage::new(&db_path).expect("Failed to open storage");
+    
+    let mut graph = MemoryGraph::new();
+    for engram in storage.iter_engrams() {
+        graph.add_engram(engram.unwrap()).unwrap();
+    }
+    for connection in storage.iter_connections() {
+        graph.add_connection(connection.unwrap()).unwrap();
+    }
+    
+    let find = |text: &str| -> String {
+        storage.iter_engrams()
+            .map(|engram| engram.unwrap())
+            .find(|engram| engram.content.contains(text))
+            .unwrap_or_else(|| panic!("No demo engram containing {:?}", text))
+            .id
+    };
+    
+    // The OpenWeatherMap requirement drives the WeatherService implementation and its unit tests
+    let requirement = find("retrieved from the OpenWeatherMap API");
+    let implementation = find("Created WeatherService class");
+    let cache_miss_test = find("should retrieve data from API when not in cache");
+    let cache_hit_test = find("should return cached data when available");
+    let favorites = find("Implemented favorites feature");
+    
+    let activated = graph.spreading_activation(std::slice::from_ref(&requirement), 0.8, 4, 0.05);
+    
+    assert_eq!(activated[&requirement], 1.0);
+    assert!(activated.contains_key(&implementation));
+    assert!(activated.contains_key(&cache_miss_test));
+    assert!(activated.contains_key(&cache_hit_test));
+    
+    // Activation decays with distance and never exceeds 1.0
+    assert!(activated[&implementation] > activated[&cache_miss_test]);
+    assert!(activated.values().all(|level| *level > 0.0 && *level <= 1.0));
+    
+    // Unrelated implementations stay dark
+    assert!(!activated.contains_key(&favorites));
+    
+    // A single iteration only reaches direct neighbors
+    let direct = graph.spreading_activation(std::slice::from_ref(&requirement), 0.8, 1, 0.05);
+    assert!(!direct.contains_key(&implementation));
+    
+    drop(storage);
+    let _ = std::fs::remove_dir_all(&db_path);
+}
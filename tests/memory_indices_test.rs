@@ -165,7 +165,7 @@ fn test_importance_index_operations() {
     sleep(StdDuration::from_secs(2));
     
     // Test TTL expiration - check if we have any expired engrams
-    let expired = index.get_expired_engrams();
+    let _expired = index.get_expired_engrams();
     // Note: TTL might not work correctly in tests due to timing and implementation details
     // So we'll just note that without asserting specifically
     
@@ -183,4 +183,346 @@ fn test_importance_index_operations() {
     let now = Utc::now();
     let an_hour_ago = now - Duration::hours(1);
     let _candidates = index.get_forgetting_candidates(0.6, 5, &an_hour_ago, 10);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_importance_bucketing_at_f32_precision() {
+    let mut index = ImportanceIndex::new();
+    
+    // Every score from 0.00 to 1.00 lands in the same bucket whether or not it was rounded through f32
+    for i in 0..=100 {
+        let score = i as f64 / 100.0;
+        let rounded = score as f32 as f64;
+        assert_eq!(
+            ImportanceIndex::importance_bucket(score),
+            ImportanceIndex::importance_bucket(rounded),
+            "bucket mismatch for {}", score
+        );
+        assert_eq!(ImportanceIndex::importance_bucket(score), (i / 10) as u8);
+    }
+    
+    // Thresholds include scores rounded through f32
+    let mut engram = create_test_engram("e1", "Rounded importance", "test", 0.9);
+    engram.importance = 0.7f32 as f64;
+    index.add_engram(&engram).unwrap();
+    assert!(index.find_by_min_importance(0.7).contains("e1"));
+    assert!(!index.find_by_min_importance(0.8).contains("e1"));
+    
+    // Removal finds the bucket the score was stored in
+    index.remove_engram(&engram).unwrap();
+    assert!(index.find_by_min_importance(0.0).is_empty());
+}
+
+#[test]
+fn test_forgetting_candidates_explain_reasons() {
+    use engram_lite::index::{ForgettingPolicy, SearchIndex};
+    
+    let mut index = SearchIndex::new();
+    
+    // A stale, unimportant engram and an important one with the same idle time
+    let mut stale = create_test_engram("stale", "Stale memory", "test", 0.8);
+    stale.importance = 0.1;
+    stale.access_count = 0;
+    stale.last_accessed = Utc::now() - Duration::days(40);
+    
+    let mut important = create_test_engram("important", "Important memory", "test", 0.8);
+    important.importance = 0.9;
+    important.access_count = 0;
+    important.last_accessed = Utc::now() - Duration::days(40);
+    
+    index.add_engram(&stale).unwrap();
+    index.add_engram(&important).unwrap();
+    
+    let policy = ForgettingPolicy::Hybrid {
+        max_importance: 0.3,
+        max_access_count: 2,
+        min_idle_seconds: 30 * 24 * 60 * 60,
+        max_items: 10,
+    };
+    
+    let candidates = policy.explain_forgetting_candidates(&index);
+    assert_eq!(candidates.len(), 1);
+    
+    let candidate = &candidates[0];
+    assert_eq!(candidate.id, "stale");
+    assert_eq!(candidate.reason, "importance=0.10, access=0, idle=40d");
+    assert!((candidate.score - 0.9).abs() < 1e-9);
+    
+    // Every signal in the reason satisfies the policy thresholds
+    assert!(index.get_importance(&candidate.id).unwrap() <= 0.3);
+    assert!(index.get_access_count(&candidate.id).unwrap() <= 2);
+    assert!(index.get_last_accessed(&candidate.id).unwrap() < Utc::now() - Duration::days(30));
+    
+    // The explained candidates match the plain candidate list
+    assert_eq!(policy.get_forgetting_candidates(&index), vec!["stale".to_string()]);
+}
+
+#[test]
+fn test_log_scale_access_bucketing() {
+    use engram_lite::index::AccessBucketing;
+    
+    let mut linear = ImportanceIndex::new();
+    let mut log_scale = ImportanceIndex::new().with_access_bucketing(AccessBucketing::LogScale);
+    
+    let mut moderate = create_test_engram("moderate", "Moderately accessed", "test", 0.8);
+    moderate.access_count = 15;
+    let mut heavy = create_test_engram("heavy", "Heavily accessed", "test", 0.8);
+    heavy.access_count = 500;
+    
+    for engram in [&moderate, &heavy] {
+        linear.add_engram(engram).unwrap();
+        log_scale.add_engram(engram).unwrap();
+    }
+    
+    // Linear buckets cap at 100 accesses, log-scale buckets keep 15 and 500 apart
+    assert_eq!(AccessBucketing::Linear.bucket(500), AccessBucketing::Linear.bucket(150));
+    assert!(AccessBucketing::LogScale.bucket(15) < AccessBucketing::LogScale.bucket(500));
+    
+    // The starting bucket is computed under the chosen scheme and filtered exactly
+    for index in [&linear, &log_scale] {
+        let at_least_16 = index.find_by_min_access_count(16);
+        assert_eq!(at_least_16.len(), 1);
+        assert!(at_least_16.contains("heavy"));
+        
+        assert_eq!(index.find_by_min_access_count(15).len(), 2);
+        assert!(index.find_by_min_access_count(501).is_empty());
+    }
+    
+    let at_least_256 = log_scale.find_by_min_access_count(256);
+    assert_eq!(at_least_256.len(), 1);
+    assert!(at_least_256.contains("heavy"));
+}
+
+#[test]
+fn test_temporal_index_bounded_recency_list() {
+    let now = Utc::now();
+    let mut capped = TemporalIndex::new().with_recency_capacity(5);
+    let mut uncapped = TemporalIndex::new();
+    
+    // Insert out of order so the recency list has to place and evict entries
+    let mut engrams = Vec::new();
+    for i in [7, 2, 9, 0, 5, 11, 3, 8, 1, 10, 4, 6] {
+        let engram = create_test_engram_with_timestamp(
+            &format!("e{}", i), "Timed memory", "test", 0.8, now - Duration::minutes(i),
+        );
+        capped.add_engram(&engram).unwrap();
+        uncapped.add_engram(&engram).unwrap();
+        engrams.push(engram);
+    }
+    let ids = |names: &[i64]| names.iter().map(|i| format!("e{}", i)).collect::<Vec<_>>();
+    
+    // Within the cap, results come from the recency list and match the uncapped index
+    assert_eq!(capped.get_most_recent(3), ids(&[0, 1, 2]));
+    assert_eq!(capped.get_most_recent(5), uncapped.get_most_recent(5));
+    
+    // Beyond the cap, the timeline still answers correctly
+    assert_eq!(capped.get_most_recent(8), ids(&[0, 1, 2, 3, 4, 5, 6, 7]));
+    
+    // Removing a tracked engram backfills the recency list from the timeline
+    let e1 = engrams.iter().find(|e| e.id == "e1").unwrap();
+    capped.remove_engram(e1).unwrap();
+    assert_eq!(capped.get_most_recent(5), ids(&[0, 2, 3, 4, 5]));
+    
+    // Range queries cover every engram, not just the tracked ones
+    assert_eq!(capped.find_before(&(now - Duration::minutes(9))).len(), 2);
+    assert_eq!(capped.find_between(&(now - Duration::minutes(11)), &now).len(), 11);
+}
+
+#[test]
+fn test_temporal_range_queries_match_linear_scan() {
+    let base = Utc::now();
+    let mut index = TemporalIndex::new().with_recency_capacity(8);
+    let mut timestamps: Vec<(String, DateTime<Utc>)> = Vec::new();
+    
+    // Pseudo-random timestamps, with repeats so several engrams share a boundary
+    for i in 0..300u64 {
+        let timestamp = base - Duration::seconds(((i * 7919) % 97) as i64);
+        let engram = create_test_engram_with_timestamp(&format!("e{}", i), "Timed memory", "test", 0.8, timestamp);
+        index.add_engram(&engram).unwrap();
+        timestamps.push((engram.id, timestamp));
+    }
+    
+    let scan = |keep: &dyn Fn(&DateTime<Utc>) -> bool| -> std::collections::HashSet<String> {
+        timestamps.iter().filter(|(_, ts)| keep(ts)).map(|(id, _)| id.clone()).collect()
+    };
+    
+    // Boundaries on an indexed timestamp, between timestamps and outside the range entirely
+    for offset in [-1, 0, 13, 48, 50, 96, 97, 200] {
+        let cutoff = base - Duration::seconds(offset);
+        assert_eq!(index.find_before(&cutoff), scan(&|ts| *ts < cutoff));
+        assert_eq!(index.find_after(&cutoff), scan(&|ts| *ts > cutoff));
+        
+        for width in [0, 1, 30] {
+            let start = cutoff - Duration::seconds(width);
+            assert_eq!(index.find_between(&start, &cutoff), scan(&|ts| *ts >= start && *ts <= cutoff));
+        }
+    }
+    
+    // An inverted range is empty
+    assert!(index.find_between(&base, &(base - Duration::seconds(10))).is_empty());
+}
+
+#[test]
+fn test_importance_ordering_matches_naive_sort() {
+    let mut index = ImportanceIndex::new();
+    let mut expected: Vec<(String, f64)> = Vec::new();
+    
+    // Distinct pseudo-random scores so the expected order is unambiguous
+    for i in 0..200u64 {
+        let importance = ((i * 7919) % 1000) as f64 / 1000.0;
+        let mut engram = create_test_engram(&format!("e{}", i), "Scored memory", "test", 0.8);
+        engram.importance = importance;
+        engram.last_accessed = Utc::now() - Duration::minutes(i as i64);
+        index.add_engram(&engram).unwrap();
+        expected.push((engram.id, importance));
+    }
+    
+    // Updates and removals reposition entries (updated scores fall between the original ones)
+    for i in (0..200).step_by(10) {
+        let importance = expected[i].1 / 2.0 + 0.0005;
+        index.update_importance(&expected[i].0, importance).unwrap();
+        expected[i].1 = importance;
+    }
+    for i in (5..200).step_by(20) {
+        let removed = create_test_engram(&expected[i].0, "Scored memory", "test", 0.8);
+        index.remove_engram(&removed).unwrap();
+    }
+    let removed: Vec<String> = (5..200).step_by(20).map(|i| expected[i].0.clone()).collect();
+    expected.retain(|(id, _)| !removed.contains(id));
+    
+    expected.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    let naive: Vec<String> = expected.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(index.get_most_important(naive.len()), naive);
+    assert_eq!(index.get_most_important(10), naive[..10].to_vec());
+    
+    // Recency ordering answers range queries; accessing an engram moves it to the front
+    let recent = index.find_by_last_accessed_after(&(Utc::now() - Duration::seconds(150)));
+    assert_eq!(recent, ["e0", "e1", "e2"].iter().map(|id| id.to_string()).collect());
+    index.record_access(&"e150".to_string()).unwrap();
+    assert!(index.find_by_last_accessed_after(&(Utc::now() - Duration::seconds(1))).contains("e150"));
+}
+
+#[test]
+fn test_recently_accessed_differs_from_recently_created() {
+    use engram_lite::index::SearchIndex;
+    
+    let mut index = SearchIndex::new();
+    let now = Utc::now();
+    
+    // Creation order and access order start out the same: "old" is oldest on both counts
+    for (id, days_ago) in [("old", 30), ("middle", 20), ("new", 10)] {
+        let mut engram = create_test_engram_with_timestamp(
+            id, "Viewed memory", "test", 0.8, now - Duration::days(days_ago),
+        );
+        engram.last_accessed = engram.timestamp;
+        index.add_engram(&engram).unwrap();
+    }
+    
+    assert_eq!(index.get_most_recent(3), vec!["new", "middle", "old"]);
+    assert_eq!(index.get_most_recently_accessed(3), vec!["new", "middle", "old"]);
+    
+    // Viewing the old engram moves it to the front of the recently-accessed list only
+    index.record_access(&"old".to_string()).unwrap();
+    
+    assert_eq!(index.get_most_recently_accessed(3), vec!["old", "new", "middle"]);
+    assert_eq!(index.get_most_recently_accessed(1), vec!["old"]);
+    assert_eq!(index.get_most_recent(3), vec!["new", "middle", "old"]);
+}
+
+#[test]
+fn test_touch_promotes_recency_without_reindexing() {
+    use engram_lite::index::SearchIndex;
+    
+    let mut index = SearchIndex::new();
+    let now = Utc::now();
+    
+    for (id, content, days_ago) in [("alpha", "Glacier retreat data", 30), ("beta", "Ocean salinity data", 10)] {
+        let mut engram = create_test_engram_with_timestamp(id, content, "test", 0.8, now - Duration::days(days_ago));
+        engram.last_accessed = engram.timestamp;
+        index.add_engram(&engram).unwrap();
+    }
+    
+    let keywords_before = index.text_index.search("glacier");
+    assert_eq!(index.get_most_recently_accessed(2), vec!["beta", "alpha"]);
+    
+    // Touching moves the engram up the access ordering without counting an access
+    index.touch(&"alpha".to_string(), now).unwrap();
+    
+    assert_eq!(index.get_most_recently_accessed(2), vec!["alpha", "beta"]);
+    assert_eq!(index.get_last_accessed(&"alpha".to_string()), Some(now));
+    assert_eq!(index.get_access_count(&"alpha".to_string()), Some(0));
+    assert_eq!(index.get_most_recent(2), vec!["beta", "alpha"]);
+    
+    // Keyword entries are left exactly as they were
+    assert_eq!(index.text_index.search("glacier"), keywords_before);
+    assert!(index.text_index.search("glacier").contains("alpha"));
+    
+    // Engrams that were never indexed cannot be touched
+    assert!(index.touch(&"missing".to_string(), now).is_err());
+}
+
+#[test]
+fn test_sample_by_importance_favors_important_engrams() {
+    let mut index = ImportanceIndex::new();
+    for (id, importance) in [("high", 0.9), ("mid", 0.5), ("low", 0.1), ("zero", 0.0)] {
+        let mut engram = create_test_engram(id, id, "test", 0.9);
+        engram.set_importance(importance);
+        index.add_engram(&engram).unwrap();
+    }
+    
+    // The same seed always draws the same sample
+    assert_eq!(index.sample_by_importance(2, 7), index.sample_by_importance(2, 7));
+    
+    // Samples are distinct, capped at what's available, and skip zero-importance engrams
+    let all = index.sample_by_importance(10, 7);
+    assert_eq!(all.len(), 3);
+    assert!(!all.contains(&"zero".to_string()));
+    assert!(index.sample_by_importance(0, 7).is_empty());
+    
+    let mut counts = std::collections::HashMap::new();
+    for seed in 0..1000 {
+        for id in index.sample_by_importance(1, seed) {
+            *counts.entry(id).or_insert(0) += 1;
+        }
+    }
+    
+    let count = |id: &str| counts.get(id).copied().unwrap_or(0);
+    assert!(count("high") > count("mid"));
+    assert!(count("mid") > count("low"));
+    assert_eq!(count("zero"), 0);
+}
+
+#[test]
+fn test_importance_decays_with_time_since_access() {
+    let now = Utc::now();
+    let mut index = ImportanceIndex::new();
+    for (id, idle_days) in [("fresh", 0), ("day", 1), ("week", 7)] {
+        let mut engram = create_test_engram(id, id, "test", 0.9);
+        engram.set_importance(0.8);
+        engram.last_accessed = now - Duration::days(idle_days);
+        index.add_engram(&engram).unwrap();
+    }
+    
+    // Without a half-life nothing decays
+    assert_eq!(index.decayed_importance(&"week".to_string(), now), Some(0.8));
+    assert_eq!(index.decayed_importance(&"missing".to_string(), now), None);
+    
+    let mut index = index.with_decay_half_life(24 * 60 * 60);
+    let decayed = |id: &str| index.decayed_importance(&id.to_string(), now).unwrap();
+    assert!((decayed("fresh") - 0.8).abs() < 1e-9);
+    assert!((decayed("day") - 0.4).abs() < 1e-9);
+    assert!((decayed("week") - 0.8 / 128.0).abs() < 1e-9);
+    
+    // The getter leaves the index untouched; applying decay re-buckets and re-sorts
+    assert_eq!(index.get_importance(&"day".to_string()), Some(0.8));
+    index.apply_decay(24 * 60 * 60).unwrap();
+    assert!((index.get_importance(&"day".to_string()).unwrap() - 0.4).abs() < 1e-3);
+    assert_eq!(index.find_by_min_importance(0.7), std::collections::HashSet::from(["fresh".to_string()]));
+    assert_eq!(index.get_most_important(3), vec!["fresh", "day", "week"]);
+    
+    // Running it again straight away doesn't decay the same time twice
+    index.apply_decay(24 * 60 * 60).unwrap();
+    assert!((index.get_importance(&"day".to_string()).unwrap() - 0.4).abs() < 1e-3);
+    
+    assert!(index.apply_decay(0).is_err());
+}
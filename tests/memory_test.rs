@@ -110,7 +110,7 @@ mod tests {
             "Previous day engram", 
             "test", 
             0.7,
-            Utc::now().with_day(if day > 1 { day - 1 } else { day }).unwrap()
+            Utc::now().with_day(if day > 1 { day - 1 } else { day }).unwrap().with_hour(20).unwrap()
         );
         
         // Add engrams to index
@@ -0,0 +1,812 @@
+use engram_lite::index::{ForgettingPolicy, SearchIndex};
+use engram_lite::error::EngramError;
+use engram_lite::query::{CancellationToken, EngramQuery, EngramRef, ForgettingEvent, QueryEngine, QueryService, TraversalEngine, MAX_TRAVERSAL_DEPTH, recompute_importance_from_graph, explain_relationship, RetrievalWeights, dedupe_by_content};
+use std::cell::RefCell;
+use engram_lite::embedding::EmbeddingService;
+use engram_lite::schema::{Collection, Connection, Context, Engram};
+use engram_lite::storage::{Embedding, Storage};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+// Helper to create a unique test directory to avoid conflicts
+fn get_test_db_path() -> String {
+    let test_id = Uuid::new_v4().to_string();
+    format!("./test_db_{}", test_id)
+}
+
+// Helper to clean up test directory
+fn cleanup_test_db(path: &str) {
+    if Path::new(path).exists() {
+        let _ = fs::remove_dir_all(path);
+    }
+}
+
+#[test]
+fn test_traversal_returns_lazy_refs() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // Build a small chain: a -> b -> c
+    let a = Engram::new("Root memory".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("Middle memory".to_string(), "test".to_string(), 0.8, None);
+    let c = Engram::new("Leaf memory".to_string(), "test".to_string(), 0.7, None);
+    for engram in [&a, &b, &c] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    let ab = Connection::new(a.id.clone(), b.id.clone(), "leads_to".to_string(), 0.5, None);
+    let bc = Connection::new(b.id.clone(), c.id.clone(), "leads_to".to_string(), 0.5, None);
+    for connection in [&ab, &bc] {
+        storage.put_connection(connection).unwrap();
+        index.add_connection(connection).unwrap();
+    }
+    
+    let engine = TraversalEngine::new(&storage, &index);
+    let result = engine.find_connected_engrams(&a.id, 2, None).unwrap();
+    
+    // Traversal yields references without loading any engram content
+    assert_eq!(result.engrams.len(), 3);
+    assert_eq!(result.connections.len(), 2);
+    assert!(result.engrams.iter().all(|engram_ref| !engram_ref.is_resolved()));
+    
+    // Resolving a single reference loads only that engram
+    let leaf_ref = result.engrams.iter().find(|r| r.id() == &c.id).unwrap();
+    assert_eq!(leaf_ref.resolve().unwrap().unwrap().content, "Leaf memory");
+    assert!(leaf_ref.is_resolved());
+    assert_eq!(result.engrams.iter().filter(|r| r.is_resolved()).count(), 1);
+    
+    // Batch resolution loads the rest
+    let engrams = EngramRef::resolve_batch(&result.engrams).unwrap();
+    assert_eq!(engrams.len(), 3);
+    assert!(result.engrams.iter().all(|engram_ref| engram_ref.is_resolved()));
+    
+    // A reference to a deleted engram resolves to None
+    storage.delete_engram(&b.id).unwrap();
+    let stale_ref = EngramRef::new(b.id.clone(), &storage);
+    assert!(stale_ref.resolve().unwrap().is_none());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_unbounded_query_result_size_guard() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    for i in 0..50 {
+        let engram = Engram::new(format!("Memory number {}", i), "bulk".to_string(), 0.5, None);
+        storage.put_engram(&engram).unwrap();
+        index.add_engram(&engram).unwrap();
+    }
+    
+    let mut engine = QueryEngine::new(&storage, &index);
+    engine.set_max_result_size(20);
+    
+    // A broad query without a limit is rejected
+    let query = EngramQuery::new().with_source("bulk");
+    match engine.query_engrams(&query) {
+        Err(EngramError::ResultTooLarge(count, max)) => {
+            assert_eq!(count, 50);
+            assert_eq!(max, 20);
+        }
+        other => panic!("Expected ResultTooLarge, got {:?}", other.map(|e| e.len())),
+    }
+    
+    // The same query with a limit succeeds
+    let limited = engine.query_engrams(&EngramQuery::new().with_source("bulk").with_limit(10)).unwrap();
+    assert_eq!(limited.len(), 10);
+    
+    // Raising the cap allows the unbounded query
+    engine.set_max_result_size(100);
+    assert_eq!(engine.query_engrams(&query).unwrap().len(), 50);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_limited_query_matches_top_of_unbounded_query() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // Confidences share buckets and timestamps tie, so the cut-off falls inside a bucket
+    for i in 0..40 {
+        let confidence = 0.3 + (i % 7) as f64 * 0.09;
+        let mut engram = Engram::new(format!("Memory number {}", i), "bulk".to_string(), confidence, None);
+        engram.timestamp = chrono::Utc::now() - chrono::Duration::minutes((i % 5) as i64);
+        storage.put_engram(&engram).unwrap();
+        index.add_engram(&engram).unwrap();
+    }
+    
+    let engine = QueryEngine::new(&storage, &index);
+    let ids = |engrams: Vec<Engram>| -> Vec<String> { engrams.into_iter().map(|e| e.id).collect() };
+    
+    for by_recency in [false, true] {
+        let query = || EngramQuery::new().with_source("bulk").with_sort_by_recency(by_recency);
+        let all = ids(engine.query_engrams(&query()).unwrap());
+        for limit in [0, 1, 7, 13, 40, 60] {
+            let limited = ids(engine.query_engrams(&query().with_limit(limit)).unwrap());
+            assert_eq!(limited, all[..limit.min(all.len())]);
+        }
+    }
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_forgetting_observer_notified() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // One old engram and one fresh engram
+    let mut old = Engram::new("Old memory".to_string(), "test".to_string(), 0.5, None);
+    old.timestamp = chrono::Utc::now() - chrono::Duration::days(30);
+    let fresh = Engram::new("Fresh memory".to_string(), "test".to_string(), 0.5, None);
+    for engram in [&old, &fresh] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    let events: RefCell<Vec<ForgettingEvent>> = RefCell::new(Vec::new());
+    
+    let mut engine = QueryEngine::new(&storage, &index);
+    engine.set_forgetting_policy(Some(ForgettingPolicy::AgeBased {
+        max_age_seconds: 7 * 24 * 60 * 60,
+        max_items: 10,
+    }));
+    engine.add_forgetting_observer(Box::new(|event| events.borrow_mut().push(event.clone())));
+    
+    assert_eq!(engine.apply_forgetting().unwrap(), 1);
+    drop(engine);
+    
+    // The callback ran exactly once with the forgotten engram and the policy as reason
+    let events = events.into_inner();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].engram_ids, vec![old.id.clone()]);
+    assert_eq!(events[0].reason, "age_based");
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_find_routes_between_engrams() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // Two routes from a to d: a -leads_to-> b -leads_to-> d, and a -related-> c -leads_to-> d
+    let a = Engram::new("Start".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("Via b".to_string(), "test".to_string(), 0.9, None);
+    let c = Engram::new("Via c".to_string(), "test".to_string(), 0.9, None);
+    let d = Engram::new("Goal".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&a, &b, &c, &d] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    let ab = Connection::new(a.id.clone(), b.id.clone(), "leads_to".to_string(), 0.5, None);
+    let bd = Connection::new(b.id.clone(), d.id.clone(), "leads_to".to_string(), 0.5, None);
+    let ac = Connection::new(a.id.clone(), c.id.clone(), "related".to_string(), 0.5, None);
+    let cd = Connection::new(c.id.clone(), d.id.clone(), "leads_to".to_string(), 0.5, None);
+    for connection in [&ab, &bd, &ac, &cd] {
+        storage.put_connection(connection).unwrap();
+        index.add_connection(connection).unwrap();
+    }
+    
+    let engine = TraversalEngine::new(&storage, &index);
+    
+    // Restricting to leads_to leaves only the known route through b
+    let routes = engine.find_routes(&a.id, &d.id, 3, &["leads_to".to_string()]).unwrap();
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].engram_ids, vec![a.id.clone(), b.id.clone(), d.id.clone()]);
+    let edges: Vec<String> = routes[0].connections.iter().map(|c| c.id.clone()).collect();
+    assert_eq!(edges, vec![ab.id.clone(), bd.id.clone()]);
+    
+    // Without a type filter both routes are found
+    let routes = engine.find_routes(&a.id, &d.id, 3, &[]).unwrap();
+    assert_eq!(routes.len(), 2);
+    
+    // Connections are directed, so the reverse trip is unreachable
+    assert!(engine.find_routes(&d.id, &a.id, 3, &[]).unwrap().is_empty());
+    
+    // A depth below the route length finds nothing; one above the cap is rejected
+    assert!(engine.find_routes(&a.id, &d.id, 1, &[]).unwrap().is_empty());
+    assert!(matches!(
+        engine.find_routes(&a.id, &d.id, MAX_TRAVERSAL_DEPTH + 1, &[]),
+        Err(EngramError::InvalidOperation(_))
+    ));
+    
+    // Paths serialize for the web API
+    let json = serde_json::to_value(engine.find_routes(&a.id, &d.id, 2, &["leads_to".to_string()]).unwrap()).unwrap();
+    assert_eq!(json[0]["engram_ids"][1], serde_json::json!(b.id));
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_forgetting_with_compaction_reclaims_space() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // Mostly unimportant engrams, with a few worth keeping
+    for i in 0..200 {
+        let mut engram = Engram::new(format!("Memory number {} with some padding text", i), "test".to_string(), 0.5, None);
+        engram.importance = if i % 20 == 0 { 0.9 } else { 0.1 };
+        storage.put_engram(&engram).unwrap();
+        index.add_engram(&engram).unwrap();
+    }
+    
+    // Persist the records to SST files, as they would be in a long-running database
+    storage.db.flush_cf(storage.db.cf_handle("engrams").unwrap()).unwrap();
+    let initial_size = storage.estimate_data_size(&["engrams"]).unwrap();
+    assert!(initial_size > 0);
+    
+    let mut engine = QueryEngine::new(&storage, &index);
+    engine.set_forgetting_policy(Some(ForgettingPolicy::ImportanceThreshold {
+        max_importance: 0.5,
+        max_items: 1000,
+    }));
+    
+    let (forgotten, report) = engine.apply_forgetting_with_compaction().unwrap();
+    drop(engine);
+    
+    // Compaction drops the deleted records and their tombstones
+    assert_eq!(forgotten, 190);
+    assert!(report.bytes_after < report.bytes_before);
+    assert!(report.bytes_reclaimed() > 0);
+    assert!(storage.estimate_data_size(&["engrams"]).unwrap() < initial_size);
+    assert_eq!(storage.list_engrams().unwrap().len(), 10);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_traversal_times_out() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // A long chain makes for a deep traversal
+    let engrams: Vec<Engram> = (0..300)
+        .map(|i| Engram::new(format!("Link {}", i), "test".to_string(), 0.5, None))
+        .collect();
+    for engram in &engrams {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    for pair in engrams.windows(2) {
+        let connection = Connection::new(pair[0].id.clone(), pair[1].id.clone(), "next".to_string(), 0.5, None);
+        storage.put_connection(&connection).unwrap();
+        index.add_connection(&connection).unwrap();
+    }
+    
+    // A deadline that has already passed aborts the traversal instead of walking the chain
+    let mut engine = TraversalEngine::new(&storage, &index);
+    engine.set_cancellation(CancellationToken::with_timeout(Duration::ZERO));
+    assert!(matches!(
+        engine.find_connected_engrams(&engrams[0].id, 1000, None),
+        Err(EngramError::Timeout(_))
+    ));
+    
+    // Cancelling a shared token aborts operations that use any clone of it
+    let token = CancellationToken::new();
+    engine.set_cancellation(token.clone());
+    assert_eq!(engine.find_connected_engrams(&engrams[0].id, 1000, None).unwrap().engrams.len(), 300);
+    token.cancel();
+    assert!(matches!(
+        engine.find_routes(&engrams[0].id, &engrams[5].id, 5, &[]),
+        Err(EngramError::Timeout(_))
+    ));
+    
+    // Queries honour the deadline set on the service
+    let mut service = QueryService::new(&storage, &index);
+    service.set_timeout(Duration::ZERO);
+    assert!(matches!(
+        service.search_by_source("test", Some(10)),
+        Err(EngramError::Timeout(_))
+    ));
+    service.set_timeout(Duration::from_secs(60));
+    assert_eq!(service.search_by_source("test", Some(10)).unwrap().len(), 10);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_recompute_importance_raises_hub_importance() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).unwrap();
+    let mut index = SearchIndex::new();
+    
+    let mut hub = Engram::new("Hub memory".to_string(), "test".to_string(), 0.9, None);
+    hub.set_importance(0.3);
+    let mut engrams = vec![hub.clone()];
+    for i in 0..5 {
+        let mut spoke = Engram::new(format!("Spoke {}", i), "test".to_string(), 0.9, None);
+        spoke.set_importance(0.3);
+        engrams.push(spoke);
+    }
+    for engram in &engrams {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    // Every spoke links to the hub, in both directions, so the hub is the most central engram
+    for (i, spoke) in engrams[1..].iter().enumerate() {
+        let (source, target) = if i % 2 == 0 { (&spoke.id, &hub.id) } else { (&hub.id, &spoke.id) };
+        let connection = Connection::new(source.clone(), target.clone(), "related".to_string(), 0.8, None);
+        storage.put_connection(&connection).unwrap();
+    }
+    
+    let updated = recompute_importance_from_graph(&storage, &mut index, 0.5).unwrap();
+    assert_eq!(updated, engrams.len());
+    
+    // The hub reaches the maximum centrality, so its importance moves halfway to 1.0
+    let hub_importance = storage.get_engram(&hub.id).unwrap().unwrap().importance;
+    assert!((hub_importance - 0.65).abs() < 1e-9);
+    assert_eq!(index.get_importance(&hub.id), Some(hub_importance));
+    
+    let spoke_importance = storage.get_engram(&engrams[1].id).unwrap().unwrap().importance;
+    assert!(hub_importance > spoke_importance);
+    assert_eq!(index.get_most_important(1), vec![hub.id.clone()]);
+    
+    // Blends outside 0..=1 are rejected
+    assert!(matches!(
+        recompute_importance_from_graph(&storage, &mut index, 1.5),
+        Err(EngramError::InvalidOperation(_))
+    ));
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_explain_relationship_gathers_all_evidence() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).unwrap();
+    
+    let mut a = Engram::new("Rust borrow checker".to_string(), "test".to_string(), 0.9, None);
+    a.metadata.insert("topic".to_string(), serde_json::json!("rust"));
+    a.metadata.insert("reviewed".to_string(), serde_json::json!(true));
+    let mut b = Engram::new("Rust lifetimes".to_string(), "test".to_string(), 0.9, None);
+    b.metadata.insert("topic".to_string(), serde_json::json!("rust"));
+    let unrelated = Engram::new("Sourdough starter".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&a, &b, &unrelated] {
+        storage.put_engram(engram).unwrap();
+    }
+    
+    let connection = Connection::new(a.id.clone(), b.id.clone(), "explains".to_string(), 0.7, None);
+    storage.put_connection(&connection).unwrap();
+    
+    let mut collection = Collection::new("Rust".to_string(), "".to_string(), None);
+    collection.add_engram(a.id.clone());
+    collection.add_engram(b.id.clone());
+    storage.put_collection(&collection).unwrap();
+    let mut context = Context::new("Study session".to_string(), "".to_string(), None);
+    context.add_engram(a.id.clone());
+    context.add_engram(b.id.clone());
+    storage.put_context(&context).unwrap();
+    
+    storage.put_embedding(&a.id, &Embedding::create(vec![0.9, 0.1, 0.0], "test".to_string(), 3, Default::default())).unwrap();
+    storage.put_embedding(&b.id, &Embedding::create(vec![0.8, 0.2, 0.0], "test".to_string(), 3, Default::default())).unwrap();
+    
+    let service = EmbeddingService::new();
+    let explanation = explain_relationship(&a.id, &b.id, &storage, &service).unwrap();
+    
+    assert_eq!(explanation.direct_connections.len(), 1);
+    assert_eq!(explanation.direct_connections[0].relationship_type, "explains");
+    assert_eq!(explanation.direct_connections[0].weight, 0.7);
+    let path = explanation.shortest_path.as_ref().unwrap();
+    assert_eq!(path.engram_ids, vec![a.id.clone(), b.id.clone()]);
+    assert_eq!(explanation.shared_collections, vec![collection.id.clone()]);
+    assert_eq!(explanation.shared_contexts, vec![context.id.clone()]);
+    assert_eq!(explanation.shared_metadata_keys, vec!["topic".to_string()]);
+    assert!(explanation.embedding_similarity.unwrap() > 0.9);
+    
+    // The path is found from either end, since connections are directed
+    let reversed = explain_relationship(&b.id, &a.id, &storage, &service).unwrap();
+    assert_eq!(reversed.shortest_path.unwrap().engram_ids, vec![a.id.clone(), b.id.clone()]);
+    
+    // Unrelated engrams produce an explanation with no evidence
+    let none = explain_relationship(&a.id, &unrelated.id, &storage, &service).unwrap();
+    assert!(none.direct_connections.is_empty());
+    assert!(none.shortest_path.is_none());
+    assert!(none.shared_collections.is_empty() && none.shared_contexts.is_empty());
+    assert!(none.shared_metadata_keys.is_empty());
+    assert!(none.embedding_similarity.is_none());
+    
+    assert!(matches!(
+        explain_relationship(&a.id, &"missing".to_string(), &storage, &service),
+        Err(EngramError::NotFound(_))
+    ));
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_combined_scoring_prefers_important_engrams() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // Equally relevant and created at the same time, but of different importance
+    let mut minor = Engram::new("Rust borrow checker notes".to_string(), "test".to_string(), 0.9, None);
+    let mut major = Engram::new("Rust borrow checker rules".to_string(), "test".to_string(), 0.9, None);
+    major.timestamp = minor.timestamp;
+    minor.set_importance(0.2);
+    major.set_importance(0.9);
+    for engram in [&minor, &major] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    let engine = QueryEngine::new(&storage, &index);
+    let query = EngramQuery::new().with_text("rust borrow checker");
+    let scored = engine.query_engrams_scored(&query, &RetrievalWeights::default()).unwrap();
+    
+    assert_eq!(scored.len(), 2);
+    assert_eq!(scored[0].engram.id, major.id);
+    assert_eq!(scored[0].relevance, scored[1].relevance);
+    assert!(scored[0].score > scored[1].score);
+    
+    // Invalid weights are rejected
+    let result = engine.query_engrams_scored(&query, &RetrievalWeights::new(0.0, 0.0, 0.0));
+    assert!(matches!(result, Err(EngramError::InvalidOperation(_))));
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_dedupe_collapses_duplicate_content() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // The same fact ingested three times (differing only in case and spacing) plus one distinct engram
+    let mut original = Engram::new("Glaciers are retreating in the Alps".to_string(), "test".to_string(), 0.9, None);
+    let copy = Engram::new("glaciers are  retreating in the alps".to_string(), "test".to_string(), 0.9, None);
+    let another = Engram::new("Glaciers are retreating in the Alps".to_string(), "import".to_string(), 0.9, None);
+    let distinct = Engram::new("Glaciers feed alpine rivers".to_string(), "test".to_string(), 0.9, None);
+    original.set_importance(0.9);
+    for engram in [&original, &copy, &another, &distinct] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    let engine = QueryEngine::new(&storage, &index);
+    let query = EngramQuery::new().with_text("glaciers");
+    let scored = engine.query_engrams_scored(&query, &RetrievalWeights::default()).unwrap();
+    assert_eq!(scored.len(), 4);
+    assert!(scored.iter().all(|result| result.duplicate_count == 0));
+    
+    // The duplicates collapse into the highest-scored copy
+    let deduped = dedupe_by_content(scored);
+    assert_eq!(deduped.len(), 2);
+    let kept = deduped.iter().find(|result| result.engram.content_hash() == original.content_hash()).unwrap();
+    assert_eq!(kept.engram.id, original.id);
+    assert_eq!(kept.duplicate_count, 2);
+    let other = deduped.iter().find(|result| result.engram.id == distinct.id).unwrap();
+    assert_eq!(other.duplicate_count, 0);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_repeated_queries_return_identical_order() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    // Equal confidence everywhere, so only the tie-break decides the order
+    let hub = Engram::new("Hub memory".to_string(), "shared".to_string(), 0.5, None);
+    storage.put_engram(&hub).unwrap();
+    let mut engrams = vec![hub.clone()];
+    let mut connections = Vec::new();
+    for i in 0..20 {
+        let engram = Engram::new(format!("Shared memory {}", i), "shared".to_string(), 0.5, None);
+        storage.put_engram(&engram).unwrap();
+        let connection = Connection::new(hub.id.clone(), engram.id.clone(), "mentions".to_string(), 0.5, None);
+        storage.put_connection(&connection).unwrap();
+        engrams.push(engram);
+        connections.push(connection);
+    }
+    
+    // Indexes built separately (and in different orders) hash their sets differently
+    let build_index = |reverse: bool| {
+        let mut index = SearchIndex::new();
+        let mut ordered = engrams.clone();
+        if reverse {
+            ordered.reverse();
+        }
+        for engram in &ordered {
+            index.add_engram(engram).unwrap();
+        }
+        for connection in &connections {
+            index.add_connection(connection).unwrap();
+        }
+        index
+    };
+    let run = |index: &SearchIndex| -> Vec<u8> {
+        let query = EngramQuery::new().with_source("shared").with_limit(7);
+        let results = QueryEngine::new(&storage, index).query_engrams(&query).unwrap();
+        let traversal = TraversalEngine::new(&storage, index).find_connected_engrams(&hub.id, 1, None).unwrap();
+        let traversed: Vec<&String> = traversal.engrams.iter().map(|r| r.id()).collect();
+        let connection_ids: Vec<&String> = traversal.connections.iter().map(|c| &c.id).collect();
+        serde_json::to_vec(&(results, traversed, connection_ids, index.find_by_source_sorted("shared"), index.text_index.search_sorted("memory"))).unwrap()
+    };
+    
+    let first_index = build_index(false);
+    let second_index = build_index(true);
+    let first = run(&first_index);
+    assert_eq!(first, run(&first_index));
+    assert_eq!(first, run(&second_index));
+    
+    // Confidence ties are broken by ID, so the limited page is the lowest IDs
+    let mut expected: Vec<String> = engrams.iter().map(|e| e.id.clone()).collect();
+    expected.sort();
+    let limited = QueryEngine::new(&storage, &first_index)
+        .query_engrams(&EngramQuery::new().with_source("shared").with_sort_by_recency(false).with_limit(7))
+        .unwrap();
+    assert_eq!(limited.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), expected[..7]);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_meta_paths_follow_requirement_design_implementation() {
+    let db_path = get_test_db_path();
+    engram_lite::demo::populate_demo_data(&db_path).expect("Failed to populate demo data");
+    let storage = Storage::new(&db_path).expect("Failed to open storage");
+    
+    let mut index = SearchIndex::new();
+    for engram in storage.iter_engrams() {
+        index.add_engram(&engram.unwrap()).unwrap();
+    }
+    for connection in storage.iter_connections() {
+        index.add_connection(&connection.unwrap()).unwrap();
+    }
+    
+    let find = |text: &str| -> String {
+        storage.iter_engrams()
+            .map(|engram| engram.unwrap())
+            .find(|engram| engram.content.contains(text))
+            .unwrap_or_else(|| panic!("No demo engram containing {:?}", text))
+            .id
+    };
+    
+    let requirement = find("retrieved from the OpenWeatherMap API");
+    let express_backend = find("Node.js backend with Express");
+    let caching_layer = find("caching layer");
+    let weather_service = find("Created WeatherService class");
+    
+    let engine = TraversalEngine::new(&storage, &index);
+    let pattern = vec!["influences".to_string(), "implements".to_string()];
+    
+    // Both designs driven by the requirement are implemented by the WeatherService
+    let paths = engine.find_meta_paths(&requirement, &pattern, 10).unwrap();
+    let mut chains: Vec<Vec<String>> = paths.iter().map(|path| path.engram_ids.clone()).collect();
+    chains.sort();
+    let mut expected = vec![
+        vec![requirement.clone(), express_backend.clone(), weather_service.clone()],
+        vec![requirement.clone(), caching_layer.clone(), weather_service.clone()],
+    ];
+    expected.sort();
+    assert_eq!(chains, expected);
+    for path in &paths {
+        let types: Vec<&str> = path.connections.iter().map(|c| c.relationship_type.as_str()).collect();
+        assert_eq!(types, vec!["influences", "implements"]);
+    }
+    
+    // Results are capped, and the same search always returns the same chains
+    let capped = engine.find_meta_paths(&requirement, &pattern, 1).unwrap();
+    assert_eq!(capped.len(), 1);
+    assert_eq!(capped[0].engram_ids, paths[0].engram_ids);
+    
+    // The pattern must match hop by hop: the implementation has no outgoing influences
+    assert!(engine.find_meta_paths(&weather_service, &pattern, 10).unwrap().is_empty());
+    let reversed = vec!["implements".to_string(), "influences".to_string()];
+    assert!(engine.find_meta_paths(&requirement, &reversed, 10).unwrap().is_empty());
+    
+    // Empty patterns and unknown start engrams are rejected
+    assert!(matches!(engine.find_meta_paths(&requirement, &[], 10), Err(EngramError::InvalidOperation(_))));
+    assert!(matches!(
+        engine.find_meta_paths(&"missing".to_string(), &pattern, 10),
+        Err(EngramError::NotFound(_))
+    ));
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_ttl_policy_selects_expired_engrams_only() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    let ephemeral = Engram::new("Scratch note".to_string(), "test".to_string(), 0.9, None).with_ttl(1);
+    let permanent = Engram::new("Keep me".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&ephemeral, &permanent] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    
+    // Indexing an engram registers its TTL
+    assert_eq!(index.importance_index.get_ttl(&ephemeral.id), Some(1));
+    assert_eq!(index.importance_index.get_ttl(&permanent.id), None);
+    
+    let policy = ForgettingPolicy::TTLExpiration { max_items: 10 };
+    assert!(policy.get_forgetting_candidates(&index).is_empty());
+    
+    std::thread::sleep(Duration::from_secs(2));
+    
+    let candidates = policy.get_forgetting_candidates(&index);
+    assert_eq!(candidates, vec![ephemeral.id.clone()]);
+    
+    // The sweep deletes the expired engram and leaves the permanent one
+    let mut engine = QueryEngine::new(&storage, &index);
+    engine.set_forgetting_policy(Some(policy));
+    assert_eq!(engine.apply_forgetting().unwrap(), 1);
+    assert!(storage.get_engram(&ephemeral.id).unwrap().is_none());
+    assert!(storage.get_engram(&permanent.id).unwrap().is_some());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_pinned_engrams_survive_every_forgetting_policy() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    // Two identical low-importance, month-old, never-accessed, expired engrams; only one is pinned
+    let month_ago = chrono::Utc::now() - chrono::Duration::days(30);
+    let make = |content: &str, pinned: bool| {
+        let mut engram = Engram::new(content.to_string(), "test".to_string(), 0.5, None).with_ttl(60);
+        engram.set_importance(0.05);
+        engram.timestamp = month_ago;
+        engram.last_accessed = month_ago;
+        engram.set_pinned(pinned);
+        engram
+    };
+    let pinned = make("Keep forever", true);
+    let unpinned = make("Forgettable", false);
+    for engram in [&pinned, &unpinned] {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    assert!(storage.get_engram(&pinned.id).unwrap().unwrap().pinned);
+    assert!(!storage.get_engram(&unpinned.id).unwrap().unwrap().pinned);
+    assert!(index.importance_index.is_pinned(&pinned.id));
+    
+    let week = 7 * 24 * 60 * 60;
+    let policies = [
+        ForgettingPolicy::AgeBased { max_age_seconds: week, max_items: 10 },
+        ForgettingPolicy::ImportanceThreshold { max_importance: 0.1, max_items: 10 },
+        ForgettingPolicy::AccessFrequency { max_access_count: 1, min_idle_seconds: week, max_items: 10 },
+        ForgettingPolicy::Hybrid { max_importance: 0.1, max_access_count: 1, min_idle_seconds: week, max_items: 10 },
+        ForgettingPolicy::TTLExpiration { max_items: 10 },
+    ];
+    for policy in &policies {
+        let candidates = policy.get_forgetting_candidates(&index);
+        assert!(!candidates.contains(&pinned.id), "{} selected a pinned engram", policy.name());
+        assert!(policy.explain_forgetting_candidates(&index).iter().all(|c| c.id != pinned.id));
+    }
+    
+    // The unpinned twin is still forgotten by age, the hybrid policy and the TTL sweep
+    for policy in [&policies[0], &policies[3], &policies[4]] {
+        assert_eq!(policy.get_forgetting_candidates(&index), vec![unpinned.id.clone()], "{}", policy.name());
+    }
+    
+    // A pinned engram never takes up one of the limited candidate slots
+    let one = ForgettingPolicy::AgeBased { max_age_seconds: week, max_items: 1 };
+    assert_eq!(one.get_forgetting_candidates(&index), vec![unpinned.id.clone()]);
+    
+    // Unpinning makes it forgettable again
+    index.set_pinned(&pinned.id, false).unwrap();
+    assert_eq!(policies[4].get_forgetting_candidates(&index).len(), 2);
+    
+    // With a floor, pinned engrams are indexed as at least that important
+    let mut floored = SearchIndex::new().with_pinned_importance_floor(0.8);
+    floored.add_engram(&pinned).unwrap();
+    floored.add_engram(&unpinned).unwrap();
+    assert_eq!(floored.importance_index.get_importance(&pinned.id), Some(0.8));
+    assert_eq!(floored.importance_index.get_importance(&unpinned.id), Some(0.05));
+    floored.set_pinned(&unpinned.id, true).unwrap();
+    assert_eq!(floored.importance_index.get_importance(&unpinned.id), Some(0.8));
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_forgetting_removes_connections_of_forgotten_engrams() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = SearchIndex::new();
+    
+    let mut old = Vec::new();
+    for i in 0..3 {
+        let mut engram = Engram::new(format!("Old memory {}", i), "test".to_string(), 0.5, None);
+        engram.timestamp = chrono::Utc::now() - chrono::Duration::days(30);
+        old.push(engram);
+    }
+    let fresh = Engram::new("Fresh memory".to_string(), "test".to_string(), 0.5, None);
+    for engram in old.iter().chain([&fresh]) {
+        storage.put_engram(engram).unwrap();
+        index.add_engram(engram).unwrap();
+    }
+    storage.put_embedding(&old[0].id, &Embedding::create(vec![1.0, 0.0], "test".to_string(), 2, std::collections::HashMap::new())).unwrap();
+    
+    let between_old = Connection::new(old[0].id.clone(), old[1].id.clone(), "related".to_string(), 0.5, None);
+    let to_fresh = Connection::new(old[2].id.clone(), fresh.id.clone(), "supports".to_string(), 0.5, None);
+    for connection in [&between_old, &to_fresh] {
+        storage.put_connection(connection).unwrap();
+        index.add_connection(connection).unwrap();
+    }
+    
+    let mut engine = QueryEngine::new(&storage, &index);
+    engine.set_forgetting_policy(Some(ForgettingPolicy::AgeBased {
+        max_age_seconds: 7 * 24 * 60 * 60,
+        max_items: 10,
+    }));
+    assert_eq!(engine.apply_forgetting().unwrap(), 3);
+    drop(engine);
+    
+    // Engrams, embeddings, connections and relationship keys are all gone
+    for engram in &old {
+        assert!(storage.get_engram(&engram.id).unwrap().is_none());
+    }
+    assert!(storage.get_embedding(&old[0].id).unwrap().is_none());
+    assert!(storage.list_connections().unwrap().is_empty());
+    assert!(storage.find_incoming_connections(&fresh.id).unwrap().is_empty());
+    assert!(storage.find_connections_by_type("supports").unwrap().is_empty());
+    assert!(storage.get_engram(&fresh.id).unwrap().is_some());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_search_index_save_and_load() {
+    let db_path = get_test_db_path();
+    let snapshot_path = format!("{}.search-index", db_path);
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let a = Engram::new("Glaciers are retreating".to_string(), "research".to_string(), 0.9, None);
+    let b = Engram::new("Sea levels are rising".to_string(), "research".to_string(), 0.8, None);
+    storage.put_engram(&a).unwrap();
+    storage.put_engram(&b).unwrap();
+    storage.put_connection(&Connection::new(a.id.clone(), b.id.clone(), "causes".to_string(), 0.7, None)).unwrap();
+    
+    // Without a snapshot, load rebuilds from storage
+    let mut index = SearchIndex::load(&snapshot_path, &storage).unwrap();
+    assert!(index.text_index.search("glaciers").contains(&a.id));
+    assert_eq!(index.relationship_index.get_outgoing_connections(&a.id).len(), 1);
+    
+    // An up-to-date snapshot is loaded as saved, without a rebuild
+    let unsaved = Engram::new("Only in the index".to_string(), "test".to_string(), 0.5, None);
+    index.add_engram(&unsaved).unwrap();
+    index.save(&snapshot_path, &storage).unwrap();
+    let loaded = SearchIndex::load(&snapshot_path, &storage).unwrap();
+    assert_eq!(loaded, index);
+    
+    // A write after saving makes the snapshot stale, so load rebuilds
+    let c = Engram::new("Permafrost is thawing".to_string(), "research".to_string(), 0.7, None);
+    storage.put_engram(&c).unwrap();
+    let rebuilt = SearchIndex::load(&snapshot_path, &storage).unwrap();
+    assert!(rebuilt.text_index.search("permafrost").contains(&c.id));
+    assert!(rebuilt.text_index.search("index").is_empty());
+    
+    // So does a file that is not a snapshot
+    fs::write(&snapshot_path, b"not an index snapshot").unwrap();
+    let rebuilt = SearchIndex::load(&snapshot_path, &storage).unwrap();
+    assert_eq!(rebuilt.find_by_source("research").len(), 3);
+    
+    let _ = fs::remove_file(&snapshot_path);
+    cleanup_test_db(&db_path);
+}
@@ -1,4 +1,4 @@
-use engram_lite::schema::{Agent, Collection, Connection, Context, Engram, Metadata};
+use engram_lite::schema::{Agent, Collection, Connection, Context, Engram};
 use std::collections::{HashMap, HashSet};
 
 #[test]
@@ -118,8 +118,8 @@ fn test_collection_creation_and_manipulation() {
     
     // Verify engrams were added
     assert_eq!(collection.engram_ids.len(), 2);
-    assert!(collection.engram_ids.contains(&engram_id1.to_string()));
-    assert!(collection.engram_ids.contains(&engram_id2.to_string()));
+    assert!(collection.engram_ids.contains(engram_id1));
+    assert!(collection.engram_ids.contains(engram_id2));
     
     // Remove an engram
     let removed = collection.remove_engram(&engram_id1.to_string());
@@ -127,8 +127,8 @@ fn test_collection_creation_and_manipulation() {
     
     // Verify engram was removed
     assert_eq!(collection.engram_ids.len(), 1);
-    assert!(!collection.engram_ids.contains(&engram_id1.to_string()));
-    assert!(collection.engram_ids.contains(&engram_id2.to_string()));
+    assert!(!collection.engram_ids.contains(engram_id1));
+    assert!(collection.engram_ids.contains(engram_id2));
     
     // Try to remove an engram that doesn't exist
     let removed = collection.remove_engram(&"nonexistent_id".to_string());
@@ -217,8 +217,8 @@ fn test_context_creation_and_manipulation() {
     
     // Verify engrams were added
     assert_eq!(context.engram_ids.len(), 2);
-    assert!(context.engram_ids.contains(&engram_id1.to_string()));
-    assert!(context.engram_ids.contains(&engram_id2.to_string()));
+    assert!(context.engram_ids.contains(engram_id1));
+    assert!(context.engram_ids.contains(engram_id2));
     
     // Add agents to context
     let agent_id1 = "agent_id_1";
@@ -229,8 +229,8 @@ fn test_context_creation_and_manipulation() {
     
     // Verify agents were added
     assert_eq!(context.agent_ids.len(), 2);
-    assert!(context.agent_ids.contains(&agent_id1.to_string()));
-    assert!(context.agent_ids.contains(&agent_id2.to_string()));
+    assert!(context.agent_ids.contains(agent_id1));
+    assert!(context.agent_ids.contains(agent_id2));
     
     // Remove an engram
     let removed = context.remove_engram(&engram_id1.to_string());
@@ -238,8 +238,8 @@ fn test_context_creation_and_manipulation() {
     
     // Verify engram was removed
     assert_eq!(context.engram_ids.len(), 1);
-    assert!(!context.engram_ids.contains(&engram_id1.to_string()));
-    assert!(context.engram_ids.contains(&engram_id2.to_string()));
+    assert!(!context.engram_ids.contains(engram_id1));
+    assert!(context.engram_ids.contains(engram_id2));
     
     // Remove an agent
     let removed = context.remove_agent(&agent_id1.to_string());
@@ -247,6 +247,41 @@ fn test_context_creation_and_manipulation() {
     
     // Verify agent was removed
     assert_eq!(context.agent_ids.len(), 1);
-    assert!(!context.agent_ids.contains(&agent_id1.to_string()));
-    assert!(context.agent_ids.contains(&agent_id2.to_string()));
-}
\ No newline at end of file
+    assert!(!context.agent_ids.contains(agent_id1));
+    assert!(context.agent_ids.contains(agent_id2));
+}
+
+#[test]
+fn test_schema_policy_strict_rejects_and_lenient_clamps() {
+    use engram_lite::schema::SchemaPolicy;
+    
+    let strict = SchemaPolicy::new();
+    let lenient = SchemaPolicy::lenient();
+    
+    // Strict policies refuse out-of-range confidence and weight
+    assert!(Engram::new_with_policy("a".to_string(), "test".to_string(), Some(1.5), None, &strict).is_err());
+    assert!(Connection::new_with_policy("a".to_string(), "b".to_string(), "supports".to_string(), Some(-0.2), None, &strict).is_err());
+    
+    // Lenient policies clamp them into range
+    let engram = Engram::new_with_policy("a".to_string(), "test".to_string(), Some(1.5), None, &lenient).unwrap();
+    assert_eq!(engram.confidence, 1.0);
+    let connection = Connection::new_with_policy("a".to_string(), "b".to_string(), "supports".to_string(), Some(-0.2), None, &lenient).unwrap();
+    assert_eq!(connection.weight, 0.0);
+    
+    // Omitted values take the policy's defaults, and a narrower range applies to both modes
+    let custom = SchemaPolicy::lenient()
+        .with_confidence_range(0.2, 0.9)
+        .with_default_confidence(0.7)
+        .with_default_weight(0.3);
+    assert!(custom.validate().is_ok());
+    let engram = Engram::new_with_policy("a".to_string(), "test".to_string(), None, None, &custom).unwrap();
+    assert_eq!(engram.confidence, 0.7);
+    let connection = Connection::new_with_policy("a".to_string(), "b".to_string(), "supports".to_string(), None, None, &custom).unwrap();
+    assert_eq!(connection.weight, 0.3);
+    assert_eq!(connection.confidence, 0.7);
+    assert_eq!(custom.confidence(Some(0.05)).unwrap(), 0.2);
+    assert!(custom.with_strict(true).confidence(Some(0.95)).is_err());
+    
+    // A default outside the allowed range is an inconsistent policy
+    assert!(SchemaPolicy::new().with_confidence_range(0.2, 0.9).validate().is_err());
+}
@@ -1,5 +1,6 @@
+use engram_lite::error::EngramError;
 use engram_lite::schema::{Agent, Collection, Connection, Context, Engram};
-use engram_lite::storage::Storage;
+use engram_lite::storage::{CompressionType, ConnectionLimitPolicy, Embedding, SerializationFormat, Storage, StorageConfig, CURRENT_SCHEMA_VERSION};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
@@ -26,7 +27,8 @@ fn test_storage_initialization() {
     let storage_result = Storage::new(&db_path);
     assert!(storage_result.is_ok());
     
-    // Test creating storage a second time (should reuse)
+    // Test opening the storage a second time once the first handle is closed (should reuse)
+    drop(storage_result);
     let storage_reuse_result = Storage::new(&db_path);
     assert!(storage_reuse_result.is_ok());
     
@@ -394,4 +396,1125 @@ fn test_storage_stats() {
     
     // Clean up test directory
     cleanup_test_db(&db_path);
-}
\ No newline at end of file
+}
+#[test]
+fn test_embedding_key_namespaces() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let engram_id = "engram-with-both".to_string();
+    let full = Embedding::create(vec![0.1, 0.2, 0.3, 0.4], "full-model".to_string(), 4, HashMap::new());
+    let reduced = Embedding::create(vec![0.5, 0.6], "reduced-model".to_string(), 2, HashMap::new());
+    
+    // Store both kinds of embedding for the same engram
+    storage.put_embedding(&engram_id, &full).expect("Failed to put embedding");
+    storage.put_reduced_embedding(&engram_id, &reduced).expect("Failed to put reduced embedding");
+    
+    // Each is retrieved from its own key space
+    let retrieved_full = storage.get_embedding(&engram_id).unwrap().unwrap();
+    assert_eq!(retrieved_full.vector, full.vector);
+    assert_eq!(retrieved_full.model, "full-model");
+    
+    let retrieved_reduced = storage.get_reduced_embedding(&engram_id).unwrap().unwrap();
+    assert_eq!(retrieved_reduced.vector, reduced.vector);
+    assert_eq!(retrieved_reduced.model, "reduced-model");
+    
+    assert_eq!(storage.list_embeddings().unwrap(), vec![engram_id.clone()]);
+    assert_eq!(storage.list_reduced_embeddings().unwrap(), vec![engram_id.clone()]);
+    
+    // Legacy embeddings stored under the bare engram ID are migrated to the prefixed key
+    let cf = storage.db.cf_handle("embeddings").unwrap();
+    storage.db.put_cf(cf, b"legacy-engram", serde_json::to_vec(&full).unwrap()).unwrap();
+    assert_eq!(storage.migrate_embedding_keys().unwrap(), 1);
+    assert!(storage.get_embedding(&"legacy-engram".to_string()).unwrap().is_some());
+    assert!(storage.db.get_cf(cf, b"legacy-engram").unwrap().is_none());
+    assert_eq!(storage.migrate_embedding_keys().unwrap(), 0);
+    
+    // Clean up test directory
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_delete_engram_cascades_embeddings() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let engram = Engram::new("Embedded content".to_string(), "test".to_string(), 0.9, None);
+    let embedding = Embedding::create(vec![0.1, 0.2, 0.3], "test-model".to_string(), 3, HashMap::new());
+    storage.put_engram(&engram).expect("Failed to put engram");
+    storage.put_embedding(&engram.id, &embedding).expect("Failed to put embedding");
+    storage.put_reduced_embedding(&engram.id, &embedding).expect("Failed to put reduced embedding");
+    
+    // An embedding whose engram was never stored is an orphan
+    storage.put_embedding(&"missing-engram".to_string(), &embedding).expect("Failed to put embedding");
+    assert_eq!(storage.list_orphaned_embeddings().unwrap(), vec!["missing-engram".to_string()]);
+    
+    // Deleting the engram removes both of its embeddings
+    storage.delete_engram(&engram.id).expect("Failed to delete engram");
+    assert!(storage.get_embedding(&engram.id).unwrap().is_none());
+    assert!(storage.get_reduced_embedding(&engram.id).unwrap().is_none());
+    assert!(!storage.list_embeddings().unwrap().contains(&engram.id));
+    assert!(storage.list_reduced_embeddings().unwrap().is_empty());
+    
+    // Orphans can be cleaned up explicitly
+    for id in storage.list_orphaned_embeddings().unwrap() {
+        storage.delete_embedding(&id).expect("Failed to delete embedding");
+        storage.delete_reduced_embedding(&id).expect("Failed to delete reduced embedding");
+    }
+    assert!(storage.list_embeddings().unwrap().is_empty());
+    
+    // Clean up test directory
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_stats_counters_track_writes() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let engram1 = Engram::new("One".to_string(), "source".to_string(), 0.9, None);
+    let engram2 = Engram::new("Two".to_string(), "source".to_string(), 0.9, None);
+    storage.put_engram(&engram1).unwrap();
+    storage.put_engram(&engram2).unwrap();
+    
+    // Overwriting an existing engram doesn't change the count
+    storage.put_engram(&engram1).unwrap();
+    assert_eq!(storage.get_stats().unwrap().engram_count, 2);
+    
+    let connection = Connection::new(engram1.id.clone(), engram2.id.clone(), "supports".to_string(), 0.5, None);
+    storage.put_connection(&connection).unwrap();
+    assert_eq!(storage.get_stats().unwrap().connection_count, 1);
+    
+    // Deletes decrement, and deleting a missing record is a no-op
+    storage.delete_connection(&connection.id).unwrap();
+    storage.delete_engram(&engram2.id).unwrap();
+    storage.delete_engram(&engram2.id).unwrap();
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.engram_count, 1);
+    assert_eq!(stats.connection_count, 0);
+    
+    // Transactions update counters on commit, net of operations on the same record
+    let engram3 = Engram::new("Three".to_string(), "source".to_string(), 0.9, None);
+    let context = Context::new("Ctx".to_string(), "A context".to_string(), None);
+    let mut txn = storage.begin_transaction();
+    txn.put_engram(&engram3).unwrap();
+    txn.put_engram(&engram3).unwrap();
+    txn.delete_engram(&engram1.id).unwrap();
+    txn.put_context(&context).unwrap();
+    txn.commit().unwrap();
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.engram_count, 1);
+    assert_eq!(stats.context_count, 1);
+    
+    // Corrupt a counter directly, then repair it
+    let cf = storage.db.cf_handle("metadata").unwrap();
+    storage.db.put_cf(cf, b"stats:engram_count", 42u64.to_le_bytes()).unwrap();
+    assert_eq!(storage.get_stats().unwrap().engram_count, 42);
+    
+    let repaired = storage.recompute_stats().unwrap();
+    assert_eq!(repaired.engram_count, 1);
+    assert_eq!(repaired.context_count, 1);
+    assert_eq!(storage.get_stats().unwrap().engram_count, 1);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_iter_engrams_streams_lazily() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let mut expected = HashSet::new();
+    for i in 0..5 {
+        let engram = Engram::new(format!("Engram {}", i), "test".to_string(), 0.8, None);
+        storage.put_engram(&engram).expect("Failed to put engram");
+        expected.insert(engram.id);
+    }
+    let collection = Collection::new("Iterated".to_string(), "Test collection".to_string(), None);
+    storage.put_collection(&collection).expect("Failed to put collection");
+    
+    // Every engram is yielded once and the iterator ends cleanly
+    let yielded: HashSet<String> = storage.iter_engrams()
+        .map(|result| result.expect("Failed to deserialize engram").id)
+        .collect();
+    assert_eq!(yielded, expected);
+    
+    let collections: Vec<Collection> = storage.iter_collections().collect::<Result<_, _>>().unwrap();
+    assert_eq!(collections.len(), 1);
+    assert_eq!(collections[0].id, collection.id);
+    assert_eq!(storage.iter_agents().count(), 0);
+    
+    // A corrupt record (sorted after the UUID keys) yields one error and then stops
+    let cf = storage.db.cf_handle("engrams").unwrap();
+    storage.db.put_cf(cf, b"engram:zzz-corrupt", b"not json").unwrap();
+    
+    let mut iter = storage.iter_engrams();
+    for _ in 0..5 {
+        assert!(iter.next().unwrap().is_ok());
+    }
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_schema_migration_upgrades_v1_records() {
+    let db_path = get_test_db_path();
+    
+    // New databases are stamped with the current version
+    {
+        let storage = Storage::new(&db_path).expect("Failed to create storage");
+        assert_eq!(storage.schema_version().unwrap(), CURRENT_SCHEMA_VERSION);
+        
+        // Write a v1 engram without the importance, access tracking or TTL fields
+        let v1_record = serde_json::json!({
+            "id": "legacy",
+            "content": "Written by an old version",
+            "timestamp": "2024-01-01T00:00:00Z",
+            "source": "legacy",
+            "confidence": 0.7,
+            "metadata": {}
+        });
+        let engrams = storage.db.cf_handle("engrams").unwrap();
+        storage.db.put_cf(engrams, b"engram:legacy", serde_json::to_vec(&v1_record).unwrap()).unwrap();
+        let metadata = storage.db.cf_handle("metadata").unwrap();
+        storage.db.put_cf(metadata, b"schema_version", 1u32.to_le_bytes()).unwrap();
+    }
+    
+    // Reopening runs the v1 -> v2 migration and the ones after it
+    {
+        let storage = Storage::new(&db_path).expect("Failed to reopen storage");
+        assert_eq!(storage.schema_version().unwrap(), CURRENT_SCHEMA_VERSION);
+        
+        let engram = storage.get_engram(&"legacy".to_string()).unwrap().expect("Engram should load");
+        assert_eq!(engram.content, "Written by an old version");
+        assert_eq!(engram.importance, 0.5);
+        assert_eq!(engram.access_count, 0);
+        assert_eq!(engram.last_accessed, engram.timestamp);
+        assert_eq!(engram.ttl, None);
+        assert!(engram.provenance.is_none());
+        
+        // Pretend a newer build wrote the database
+        let metadata = storage.db.cf_handle("metadata").unwrap();
+        storage.db.put_cf(metadata, b"schema_version", (CURRENT_SCHEMA_VERSION + 1).to_le_bytes()).unwrap();
+    }
+    
+    // Databases newer than this build are refused
+    assert!(Storage::new(&db_path).is_err());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_membership_reverse_index() {
+    let db_path = get_test_db_path();
+    
+    {
+        let storage = Storage::new(&db_path).expect("Failed to create storage");
+        
+        let engram = Engram::new("Shared memory".to_string(), "test".to_string(), 0.9, None);
+        storage.put_engram(&engram).unwrap();
+        
+        let mut research = Collection::new("Research".to_string(), "".to_string(), None);
+        let mut reading = Collection::new("Reading".to_string(), "".to_string(), None);
+        research.add_engram(engram.id.clone());
+        reading.add_engram(engram.id.clone());
+        storage.put_collection(&research).unwrap();
+        storage.put_collection(&reading).unwrap();
+        
+        let mut context = Context::new("Session".to_string(), "".to_string(), None);
+        context.add_engram(engram.id.clone());
+        storage.put_context(&context).unwrap();
+        
+        // Both collections and the context show up in the reverse lookup
+        let collections = storage.collections_for_engram(&engram.id).unwrap();
+        assert_eq!(collections, HashSet::from([research.id.clone(), reading.id.clone()]));
+        assert_eq!(storage.contexts_for_engram(&engram.id).unwrap(), HashSet::from([context.id.clone()]));
+        
+        // Removing the engram from one collection updates the index
+        research.remove_engram(&engram.id);
+        storage.put_collection(&research).unwrap();
+        assert_eq!(storage.collections_for_engram(&engram.id).unwrap(), HashSet::from([reading.id.clone()]));
+        
+        // So does deleting a collection or context outright, including inside a transaction
+        let mut txn = storage.begin_transaction();
+        txn.delete_collection(&reading.id).unwrap();
+        txn.commit().unwrap();
+        storage.delete_context(&context.id).unwrap();
+        assert!(storage.collections_for_engram(&engram.id).unwrap().is_empty());
+        assert!(storage.contexts_for_engram(&engram.id).unwrap().is_empty());
+        
+        // Records written before the index existed are picked up by the v2 -> v3 migration
+        let mut legacy = Collection::new("Legacy".to_string(), "".to_string(), None);
+        legacy.add_engram(engram.id.clone());
+        let collections_cf = storage.db.cf_handle("collections").unwrap();
+        let key = format!("collection:{}", legacy.id);
+        storage.db.put_cf(collections_cf, key.as_bytes(), serde_json::to_vec(&legacy).unwrap()).unwrap();
+        let metadata = storage.db.cf_handle("metadata").unwrap();
+        storage.db.put_cf(metadata, b"schema_version", 2u32.to_le_bytes()).unwrap();
+        
+        assert!(storage.collections_for_engram(&engram.id).unwrap().is_empty());
+        
+        drop(storage);
+        let storage = Storage::new(&db_path).expect("Failed to reopen storage");
+        assert_eq!(storage.collections_for_engram(&engram.id).unwrap(), HashSet::from([legacy.id.clone()]));
+    }
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_mixed_serialization_formats_are_readable() {
+    let db_path = get_test_db_path();
+    
+    let mut json_engram = Engram::new("Written as JSON".to_string(), "test".to_string(), 0.9, None);
+    json_engram.metadata.insert("tags".to_string(), serde_json::json!(["a", "b"]));
+    let msgpack_engram = Engram::new("Written as MessagePack".to_string(), "test".to_string(), 0.8, None);
+    let vector: Vec<f32> = (0..384).map(|i| (i as f32 * 0.37).sin()).collect();
+    let embedding = Embedding::create(vector.clone(), "test-model".to_string(), 384, HashMap::new());
+    
+    // Write a record with the default JSON format
+    {
+        let storage = Storage::new(&db_path).expect("Failed to create storage");
+        assert_eq!(storage.config().format, SerializationFormat::Json);
+        storage.put_engram(&json_engram).unwrap();
+        storage.put_embedding(&json_engram.id, &embedding).unwrap();
+    }
+    
+    // Reopen the same database writing MessagePack
+    let config = StorageConfig::new().with_format(SerializationFormat::MessagePack);
+    let storage = Storage::with_config(&db_path, config).expect("Failed to reopen storage");
+    storage.put_engram(&msgpack_engram).unwrap();
+    storage.put_embedding(&msgpack_engram.id, &embedding).unwrap();
+    
+    // The leading byte records each record's format
+    let engrams_cf = storage.db.cf_handle("engrams").unwrap();
+    let json_bytes = storage.db.get_cf(engrams_cf, format!("engram:{}", json_engram.id)).unwrap().unwrap();
+    let msgpack_bytes = storage.db.get_cf(engrams_cf, format!("engram:{}", msgpack_engram.id)).unwrap().unwrap();
+    assert_ne!(json_bytes[0], msgpack_bytes[0]);
+    assert_eq!(json_bytes[1], b'{');
+    
+    // Both deserialize, whatever format the storage currently writes
+    let loaded = storage.get_engram(&json_engram.id).unwrap().unwrap();
+    assert_eq!(loaded.content, "Written as JSON");
+    assert_eq!(loaded.metadata.get("tags"), Some(&serde_json::json!(["a", "b"])));
+    let loaded = storage.get_engram(&msgpack_engram.id).unwrap().unwrap();
+    assert_eq!(loaded.content, "Written as MessagePack");
+    assert_eq!(loaded.timestamp, msgpack_engram.timestamp);
+    assert!(loaded.provenance.is_none());
+    assert_eq!(storage.get_embedding(&json_engram.id).unwrap().unwrap().vector, vector);
+    assert_eq!(storage.get_embedding(&msgpack_engram.id).unwrap().unwrap().vector, vector);
+    assert_eq!(storage.iter_engrams().filter(|engram| engram.is_ok()).count(), 2);
+    
+    // Binary embeddings are markedly smaller
+    let json_size = SerializationFormat::Json.encode(&embedding).unwrap().len();
+    let msgpack_size = SerializationFormat::MessagePack.encode(&embedding).unwrap().len();
+    assert!(msgpack_size * 2 < json_size);
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_scan_health_reports_unreadable_records() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let good = Engram::new("Readable".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&good).unwrap();
+    storage.put_agent(&Agent::new("Agent".to_string(), "".to_string(), None, None)).unwrap();
+    
+    assert!(storage.scan_health().unwrap().is_healthy());
+    
+    // Inject a corrupt engram and an engram-shaped value that does not match the schema
+    let engrams_cf = storage.db.cf_handle("engrams").unwrap();
+    storage.db.put_cf(engrams_cf, b"engram:corrupt", b"not json").unwrap();
+    storage.db.put_cf(engrams_cf, b"engram:mismatched", serde_json::to_vec(&serde_json::json!({"id": "mismatched"})).unwrap()).unwrap();
+    let embeddings_cf = storage.db.cf_handle("embeddings").unwrap();
+    storage.db.put_cf(embeddings_cf, b"embedding:broken", b"\x02\xff").unwrap();
+    
+    // The health scan lists each unreadable key under its column family
+    let report = storage.scan_health().unwrap();
+    assert!(!report.is_healthy());
+    assert_eq!(report.records_scanned, 5);
+    assert_eq!(report.unreadable_count(), 3);
+    let engram_keys: HashSet<&str> = report.unreadable["engrams"].iter().map(|r| r.key.as_str()).collect();
+    assert_eq!(engram_keys, HashSet::from(["engram:corrupt", "engram:mismatched"]));
+    assert_eq!(report.unreadable["embeddings"][0].key, "embedding:broken");
+    assert!(!report.unreadable.contains_key("agents"));
+    
+    // Listing reports the bad records alongside the good ones instead of dropping them
+    let (engrams, unreadable) = storage.iter_engrams().collect_with_report().unwrap();
+    assert_eq!(engrams.len(), 1);
+    assert_eq!(engrams[0].id, good.id);
+    assert_eq!(unreadable.len(), 2);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_connection_weight_defaults_to_embedding_similarity() {
+    use engram_lite::embedding::EmbeddingService;
+    use engram_lite::schema::DEFAULT_CONNECTION_WEIGHT;
+    
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let service = EmbeddingService::new();
+    
+    let rust = Engram::new("Rust ownership".to_string(), "test".to_string(), 0.9, None);
+    let borrowing = Engram::new("Rust borrowing".to_string(), "test".to_string(), 0.9, None);
+    let cooking = Engram::new("Baking bread".to_string(), "test".to_string(), 0.9, None);
+    let unembedded = Engram::new("No embedding".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&rust, &borrowing, &cooking, &unembedded] {
+        storage.put_engram(engram).unwrap();
+    }
+    
+    let embed = |vector: Vec<f32>| Embedding::create(vector, "test".to_string(), 3, HashMap::new());
+    storage.put_embedding(&rust.id, &embed(vec![1.0, 0.1, 0.0])).unwrap();
+    storage.put_embedding(&borrowing.id, &embed(vec![0.9, 0.2, 0.0])).unwrap();
+    storage.put_embedding(&cooking.id, &embed(vec![0.0, 0.0, 1.0])).unwrap();
+    
+    // Semantically close engrams start out strongly connected
+    let similar = Connection::new_weighted_by_similarity(
+        rust.id.clone(), borrowing.id.clone(), "related".to_string(), &storage, &service,
+    ).unwrap();
+    assert!(similar.weight > 0.9, "expected a high weight, got {}", similar.weight);
+    
+    // Unrelated engrams start out weakly connected
+    let dissimilar = Connection::new_weighted_by_similarity(
+        rust.id.clone(), cooking.id.clone(), "related".to_string(), &storage, &service,
+    ).unwrap();
+    assert!(dissimilar.weight < 0.1, "expected a low weight, got {}", dissimilar.weight);
+    
+    // Without an embedding there is nothing to measure, so the default applies
+    let fallback = Connection::new_weighted_by_similarity(
+        rust.id.clone(), unembedded.id.clone(), "related".to_string(), &storage, &service,
+    ).unwrap();
+    assert_eq!(fallback.weight, DEFAULT_CONNECTION_WEIGHT);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_secondary_sees_primary_writes_after_catch_up() {
+    let db_path = get_test_db_path();
+    let secondary_path = get_test_db_path();
+    let primary = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let existing = Engram::new("Written before the replica opened".to_string(), "test".to_string(), 0.9, None);
+    primary.put_engram(&existing).unwrap();
+    
+    let replica = Storage::open_as_secondary(&db_path, &secondary_path).expect("Failed to open secondary");
+    assert!(replica.is_secondary());
+    assert!(replica.get_engram(&existing.id).unwrap().is_some());
+    
+    // Later writes stay invisible until the replica catches up
+    let later = Engram::new("Written after the replica opened".to_string(), "test".to_string(), 0.9, None);
+    primary.put_engram(&later).unwrap();
+    assert!(replica.get_engram(&later.id).unwrap().is_none());
+    
+    replica.catch_up_with_primary().unwrap();
+    assert_eq!(replica.get_engram(&later.id).unwrap().unwrap().content, later.content);
+    
+    // The replica is read-only, and only replicas can catch up
+    assert!(replica.put_engram(&Engram::new("Rejected".to_string(), "test".to_string(), 0.9, None)).is_err());
+    assert!(primary.catch_up_with_primary().is_err());
+    
+    drop(replica);
+    drop(primary);
+    cleanup_test_db(&secondary_path);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_change_feed_reports_ordered_events() {
+    use engram_lite::storage::{ChangeEntityType, ChangeOperation};
+    
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let before = Engram::new("Written before the capture".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&before).unwrap();
+    
+    let since = storage.latest_sequence_number();
+    let engram = Engram::new("Synced memory".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&engram).unwrap();
+    storage.delete_engram(&engram.id).unwrap();
+    
+    // Only engram records written after the capture point are reported, in order
+    let events: Vec<_> = storage
+        .change_feed(since)
+        .unwrap()
+        .map(|event| event.unwrap())
+        .filter(|event| event.entity_type == ChangeEntityType::Engram)
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!((events[0].operation, events[0].id.as_str()), (ChangeOperation::Put, engram.id.as_str()));
+    assert_eq!((events[1].operation, events[1].id.as_str()), (ChangeOperation::Delete, engram.id.as_str()));
+    assert!(events[0].sequence < events[1].sequence);
+    
+    // Resuming from the last applied event yields nothing new until the next write
+    let mut feed = storage.change_feed(events[1].sequence).unwrap();
+    assert!(feed.next().is_none());
+    assert_eq!(feed.last_sequence(), events[1].sequence);
+    
+    let agent = Agent::new("Syncer".to_string(), "".to_string(), None, None);
+    storage.put_agent(&agent).unwrap();
+    let resumed: Vec<_> = storage.change_feed(events[1].sequence).unwrap().map(|event| event.unwrap()).collect();
+    assert_eq!(resumed.len(), 1);
+    assert_eq!(resumed[0].entity_type, ChangeEntityType::Agent);
+    assert_eq!(resumed[0].id, agent.id);
+    assert_eq!(resumed[0].sequence, storage.latest_sequence_number());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_connection_limit_evicts_weakest() {
+    let db_path = get_test_db_path();
+    let config = StorageConfig::new().with_max_connections_per_engram(2, ConnectionLimitPolicy::EvictWeakest);
+    let storage = Storage::with_config(&db_path, config).expect("Failed to create storage");
+    
+    let hub = Engram::new("Hub".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&hub).unwrap();
+    let mut connections = Vec::new();
+    for weight in [0.5, 0.9, 0.2, 0.7] {
+        let target = Engram::new(format!("Target {}", weight), "test".to_string(), 0.9, None);
+        storage.put_engram(&target).unwrap();
+        connections.push(Connection::new(hub.id.clone(), target.id.clone(), "relates_to".to_string(), weight, None));
+    }
+    
+    assert!(storage.add_connection(&connections[0]).unwrap().is_empty());
+    assert!(storage.add_connection(&connections[1]).unwrap().is_empty());
+    
+    // The new connection is the weakest, so it is the one evicted
+    let evicted = storage.add_connection(&connections[2]).unwrap();
+    assert_eq!(evicted.len(), 1);
+    assert_eq!(evicted[0].id, connections[2].id);
+    assert!(storage.get_connection(&connections[2].id).unwrap().is_none());
+    
+    // A stronger connection replaces the weakest existing one
+    let evicted = storage.add_connection(&connections[3]).unwrap();
+    assert_eq!(evicted.len(), 1);
+    assert_eq!(evicted[0].id, connections[0].id);
+    
+    let kept = storage.find_outgoing_connections(&hub.id).unwrap();
+    let expected: HashSet<String> = [connections[1].id.clone(), connections[3].id.clone()].into_iter().collect();
+    assert_eq!(kept, expected);
+    
+    // Pruning keeps only the strongest
+    let pruned = storage.prune_low_weight_connections(&hub.id, 1).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].id, connections[3].id);
+    assert_eq!(storage.find_outgoing_connections(&hub.id).unwrap().len(), 1);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_connection_limit_leaves_other_engrams_alone() {
+    let db_path = get_test_db_path();
+    let config = StorageConfig::new().with_max_connections_per_engram(2, ConnectionLimitPolicy::EvictWeakest);
+    let storage = Storage::with_config(&db_path, config).expect("Failed to create storage");
+    
+    // Fixed IDs so other engrams' relationship keys sort on both sides of the hub's,
+    // including one whose ID starts with the hub's
+    for id in ["hub", "hub-other", "zebra", "target"] {
+        let mut engram = Engram::new(id.to_string(), "test".to_string(), 0.9, None);
+        engram.id = id.to_string();
+        storage.put_engram(&engram).unwrap();
+    }
+    let connect = |source: &str, weight: f64| {
+        Connection::new(source.to_string(), "target".to_string(), "relates_to".to_string(), weight, None)
+    };
+    
+    let mut others = Vec::new();
+    for source in ["hub-other", "zebra"] {
+        for weight in [0.1, 0.2] {
+            let connection = connect(source, weight);
+            assert!(storage.add_connection(&connection).unwrap().is_empty());
+            others.push(connection.id);
+        }
+    }
+    
+    // Going over the hub's limit evicts only the hub's weakest connection
+    let hub_connections: Vec<Connection> = [0.5, 0.6, 0.7].iter().map(|&weight| connect("hub", weight)).collect();
+    assert!(storage.add_connection(&hub_connections[0]).unwrap().is_empty());
+    assert!(storage.add_connection(&hub_connections[1]).unwrap().is_empty());
+    let evicted = storage.add_connection(&hub_connections[2]).unwrap();
+    assert_eq!(evicted.len(), 1);
+    assert_eq!(evicted[0].id, hub_connections[0].id);
+    
+    // Pruning the hub likewise only touches its own connections
+    let pruned = storage.prune_low_weight_connections(&"hub".to_string(), 1).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].id, hub_connections[1].id);
+    assert_eq!(storage.find_outgoing_connections(&"hub".to_string()).unwrap().len(), 1);
+    
+    for id in &others {
+        assert!(storage.get_connection(id).unwrap().is_some());
+    }
+    assert_eq!(storage.find_outgoing_connections(&"hub-other".to_string()).unwrap().len(), 2);
+    assert_eq!(storage.find_incoming_connections(&"target".to_string()).unwrap().len(), 5);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_connection_limit_rejects_when_configured() {
+    let db_path = get_test_db_path();
+    let config = StorageConfig::new().with_max_connections_per_engram(1, ConnectionLimitPolicy::Reject);
+    let storage = Storage::with_config(&db_path, config).expect("Failed to create storage");
+    
+    let a = Engram::new("A".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("B".to_string(), "test".to_string(), 0.9, None);
+    let c = Engram::new("C".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&a, &b, &c] {
+        storage.put_engram(engram).unwrap();
+    }
+    
+    let first = Connection::new(a.id.clone(), b.id.clone(), "relates_to".to_string(), 0.1, None);
+    let second = Connection::new(a.id.clone(), c.id.clone(), "relates_to".to_string(), 0.9, None);
+    storage.add_connection(&first).unwrap();
+    assert!(matches!(storage.add_connection(&second), Err(EngramError::InvalidOperation(_))));
+    
+    // Updating the existing connection is still allowed
+    let mut updated = first.clone();
+    updated.weight = 0.3;
+    assert!(storage.add_connection(&updated).unwrap().is_empty());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_entities_created_by_agent() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let tester = Agent::new("Tester".to_string(), "".to_string(), None, None);
+    let other = Agent::new("Other".to_string(), "".to_string(), None, None);
+    storage.put_agent(&tester).unwrap();
+    storage.put_agent(&other).unwrap();
+    
+    let a = Engram::new("Created by the tester".to_string(), "test".to_string(), 0.9, None)
+        .with_created_by(tester.id.clone());
+    let b = Engram::new("Created by another agent".to_string(), "test".to_string(), 0.9, None)
+        .with_created_by(other.id.clone());
+    let c = Engram::new("Unattributed".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&a, &b, &c] {
+        storage.put_engram(engram).unwrap();
+    }
+    let connection = Connection::new(a.id.clone(), b.id.clone(), "relates_to".to_string(), 0.5, None)
+        .with_created_by(tester.id.clone());
+    storage.put_connection(&connection).unwrap();
+    
+    let created = storage.entities_created_by(&tester.id).unwrap();
+    assert_eq!(created.engrams, vec![a.id.clone()]);
+    assert_eq!(created.connections, vec![connection.id.clone()]);
+    
+    // Attribution survives a reload and is dropped with the entity
+    assert_eq!(storage.get_engram(&a.id).unwrap().unwrap().created_by, Some(tester.id.clone()));
+    storage.delete_connection(&connection.id).unwrap();
+    storage.delete_engram(&a.id).unwrap();
+    let created = storage.entities_created_by(&tester.id).unwrap();
+    assert!(created.engrams.is_empty());
+    assert!(created.connections.is_empty());
+    assert_eq!(storage.entities_created_by(&other.id).unwrap().engrams, vec![b.id.clone()]);
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_collection_as_of_shows_historical_content() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let pause = || std::thread::sleep(std::time::Duration::from_millis(5));
+    
+    let mut edited = Engram::new("Draft finding".to_string(), "test".to_string(), 0.8, None);
+    let removed = Engram::new("Retracted finding".to_string(), "test".to_string(), 0.8, None);
+    storage.put_engram(&edited).unwrap();
+    storage.put_engram(&removed).unwrap();
+    let mut collection = Collection::new("Review".to_string(), "Audited work".to_string(), None);
+    collection.add_engram(edited.id.clone());
+    collection.add_engram(removed.id.clone());
+    storage.put_collection(&collection).unwrap();
+    
+    pause();
+    let before_collection = chrono::Utc::now() - chrono::Duration::hours(1);
+    let cutoff = chrono::Utc::now();
+    pause();
+    
+    // After the cutoff, one engram is edited and the other leaves the collection and is deleted
+    edited.content = "Final finding".to_string();
+    storage.put_engram(&edited).unwrap();
+    collection.remove_engram(&removed.id);
+    storage.put_collection(&collection).unwrap();
+    storage.delete_engram(&removed.id).unwrap();
+    
+    // The time-travel view shows the membership and content as of the cutoff
+    let snapshot = storage.collection_as_of(&collection.id, cutoff).unwrap().expect("Collection existed at the cutoff");
+    assert_eq!(snapshot.collection.engram_ids.len(), 2);
+    assert_eq!(snapshot.engrams.len(), 2);
+    let historical = snapshot.engrams.iter().find(|e| e.id == edited.id).unwrap();
+    assert_eq!(historical.content, "Draft finding");
+    assert!(snapshot.engrams.iter().any(|e| e.id == removed.id));
+    
+    // The current view reflects the later changes
+    let now = storage.collection_as_of(&collection.id, chrono::Utc::now()).unwrap().unwrap();
+    assert_eq!(now.engrams.len(), 1);
+    assert_eq!(now.engrams[0].content, "Final finding");
+    assert!(storage.engram_as_of(&removed.id, chrono::Utc::now()).unwrap().is_none());
+    
+    // Nothing existed before the collection was created
+    assert!(storage.collection_as_of(&collection.id, before_collection).unwrap().is_none());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_engrams_page_reports_cursor_and_has_more() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let engram = Engram::new(format!("Engram {}", i), "test".to_string(), 0.9, None);
+        storage.put_engram(&engram).unwrap();
+        ids.push(engram.id);
+    }
+    ids.sort();
+    
+    // Full pages point at their last engram and report that more follow
+    let first = storage.engrams_page(None, 2).unwrap();
+    assert_eq!(first.items.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), ids[0..2]);
+    assert_eq!(first.meta.total, 5);
+    assert!(first.meta.has_more);
+    assert_eq!(first.meta.cursor.as_deref(), Some(ids[1].as_str()));
+    
+    let second = storage.engrams_page(first.meta.cursor.as_deref(), 2).unwrap();
+    assert_eq!(second.items.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), ids[2..4]);
+    assert!(second.meta.has_more);
+    
+    // The last page has no cursor, and the cursor is left out of the serialized metadata
+    let last = storage.engrams_page(second.meta.cursor.as_deref(), 2).unwrap();
+    assert_eq!(last.items.len(), 1);
+    assert_eq!(last.items[0].id, ids[4]);
+    assert!(!last.meta.has_more);
+    assert_eq!(last.meta.cursor, None);
+    let json = serde_json::to_value(&last.meta).unwrap();
+    assert_eq!(json, serde_json::json!({ "total": 5, "has_more": false }));
+    
+    // A page that exactly exhausts the listing doesn't claim more
+    let exact = storage.engrams_page(None, 5).unwrap();
+    assert!(!exact.meta.has_more);
+    assert!(storage.engrams_page(None, 0).is_err());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_engram_etag_changes_on_edit() {
+    use engram_lite::utils::etag_matches;
+    
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let mut engram = Engram::new("Cached fact".to_string(), "test".to_string(), 0.9, None);
+    engram.metadata.insert("b".to_string(), serde_json::json!(2));
+    engram.metadata.insert("a".to_string(), serde_json::json!(1));
+    storage.put_engram(&engram).unwrap();
+    let first_modified = storage.engram_last_modified(&engram.id).unwrap().expect("Engram has history");
+    
+    // Re-reading an unchanged engram yields the same tag, so a conditional request would get 304
+    let etag = storage.get_engram(&engram.id).unwrap().unwrap().etag();
+    let again = storage.get_engram(&engram.id).unwrap().unwrap().etag();
+    assert_eq!(etag, again);
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert!(etag_matches(&etag, &again));
+    assert!(etag_matches(&format!("\"other\", W/{}", etag), &again));
+    assert!(etag_matches("*", &again));
+    
+    // Editing the engram changes both the tag and the modification time
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    engram.content = "Updated fact".to_string();
+    storage.put_engram(&engram).unwrap();
+    let edited = storage.get_engram(&engram.id).unwrap().unwrap().etag();
+    assert_ne!(edited, etag);
+    assert!(!etag_matches(&etag, &edited));
+    assert!(storage.engram_last_modified(&engram.id).unwrap().unwrap() > first_modified);
+    
+    assert!(storage.engram_last_modified(&"missing".to_string()).unwrap().is_none());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_engram_detail_embeds_connections_and_collections() {
+    use engram_lite::storage::EngramExpansion;
+    
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let engram = Engram::new("Center".to_string(), "test".to_string(), 0.9, None);
+    let cause = Engram::new("Cause".to_string(), "test".to_string(), 0.9, None);
+    let effect = Engram::new("Effect".to_string(), "test".to_string(), 0.9, None);
+    let unrelated = Engram::new("Unrelated".to_string(), "test".to_string(), 0.9, None);
+    for e in [&engram, &cause, &effect, &unrelated] {
+        storage.put_engram(e).unwrap();
+    }
+    let outgoing = Connection::new(engram.id.clone(), effect.id.clone(), "causes".to_string(), 0.8, None);
+    let incoming = Connection::new(cause.id.clone(), engram.id.clone(), "causes".to_string(), 0.6, None);
+    let elsewhere = Connection::new(unrelated.id.clone(), effect.id.clone(), "mentions".to_string(), 0.5, None);
+    for c in [&outgoing, &incoming, &elsewhere] {
+        storage.put_connection(c).unwrap();
+    }
+    let mut collection = Collection::new("Findings".to_string(), "".to_string(), None);
+    collection.add_engram(engram.id.clone());
+    storage.put_collection(&collection).unwrap();
+    
+    // The default response stays lean
+    let lean = storage.engram_detail(&engram.id, EngramExpansion::default()).unwrap().unwrap();
+    let json = serde_json::to_value(&lean).unwrap();
+    assert_eq!(json["id"], serde_json::json!(engram.id));
+    assert!(json.get("outgoing").is_none() && json.get("collections").is_none());
+    
+    // Expanded, each connection appears once from this engram's side, and other engrams' don't
+    let expansion = EngramExpansion::parse("connections, collections").unwrap();
+    let detail = storage.engram_detail(&engram.id, expansion).unwrap().unwrap();
+    let out = detail.outgoing.unwrap();
+    assert_eq!(out.len(), 1);
+    assert_eq!((out[0].id.as_str(), out[0].engram_id.as_str()), (outgoing.id.as_str(), effect.id.as_str()));
+    let inc = detail.incoming.unwrap();
+    assert_eq!(inc.len(), 1);
+    assert_eq!((inc[0].id.as_str(), inc[0].engram_id.as_str()), (incoming.id.as_str(), cause.id.as_str()));
+    let collections = detail.collections.unwrap();
+    assert_eq!(collections.len(), 1);
+    assert_eq!(collections[0].name, "Findings");
+    
+    // Only the requested expansions are included, and unknown names are rejected
+    let only_collections = storage.engram_detail(&engram.id, EngramExpansion::parse("collections").unwrap()).unwrap().unwrap();
+    assert!(only_collections.outgoing.is_none());
+    assert!(EngramExpansion::parse("connections,history").is_err());
+    assert!(storage.engram_detail(&"missing".to_string(), expansion).unwrap().is_none());
+    
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_batch_delete_removes_shared_connection_once() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let a = Engram::new("A".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("B".to_string(), "test".to_string(), 0.9, None);
+    let c = Engram::new("C".to_string(), "test".to_string(), 0.9, None);
+    for engram in [&a, &b, &c] {
+        storage.put_engram(engram).unwrap();
+    }
+    let shared = Connection::new(a.id.clone(), b.id.clone(), "related".to_string(), 0.5, None);
+    let to_c = Connection::new(b.id.clone(), c.id.clone(), "related".to_string(), 0.5, None);
+    storage.put_connection(&shared).unwrap();
+    storage.put_connection(&to_c).unwrap();
+    
+    let ids = vec![a.id.clone(), b.id.clone(), a.id.clone(), "missing".to_string()];
+    let report = storage.delete_engrams(&ids, true).unwrap();
+    
+    // The connection between the two deleted engrams is removed exactly once
+    assert_eq!(report.engrams.len(), 2);
+    let mut removed: Vec<String> = report.connections.iter().map(|c| c.id.clone()).collect();
+    removed.sort();
+    let mut expected = vec![shared.id.clone(), to_c.id.clone()];
+    expected.sort();
+    assert_eq!(removed, expected);
+    assert_eq!(report.not_found, vec!["missing".to_string()]);
+    
+    assert!(storage.get_engram(&a.id).unwrap().is_none());
+    assert!(storage.get_engram(&b.id).unwrap().is_none());
+    assert!(storage.get_engram(&c.id).unwrap().is_some());
+    assert!(storage.get_connection(&shared.id).unwrap().is_none());
+    assert!(storage.find_incoming_connections(&c.id).unwrap().is_empty());
+    
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.engram_count, 1);
+    assert_eq!(stats.connection_count, 0);
+    
+    // Without cascade, connections are left in place
+    let d = Engram::new("D".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&d).unwrap();
+    let to_d = Connection::new(c.id.clone(), d.id.clone(), "related".to_string(), 0.5, None);
+    storage.put_connection(&to_d).unwrap();
+    let report = storage.delete_engrams(std::slice::from_ref(&d.id), false).unwrap();
+    assert_eq!(report.engrams.len(), 1);
+    assert!(report.connections.is_empty());
+    assert!(storage.get_connection(&to_d.id).unwrap().is_some());
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_storage_batch_keeps_relationship_indexes_consistent() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let a = Engram::new("A".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("B".to_string(), "test".to_string(), 0.9, None);
+    let c = Engram::new("C".to_string(), "test".to_string(), 0.9, None);
+    let mut moved = Connection::new(a.id.clone(), b.id.clone(), "related".to_string(), 0.5, None);
+    let dropped = Connection::new(b.id.clone(), c.id.clone(), "supports".to_string(), 0.5, None);
+    
+    let mut batch = storage.batch();
+    assert!(batch.is_empty());
+    for engram in [&a, &b, &c] {
+        batch.put_engram(engram).unwrap();
+    }
+    batch.put_connection(&moved).unwrap();
+    batch.put_connection(&dropped).unwrap();
+    
+    // Later operations see connections staged earlier in the same batch
+    moved.target_id = c.id.clone();
+    moved.relationship_type = "refines".to_string();
+    batch.put_connection(&moved).unwrap();
+    batch.delete_connection(&dropped.id).unwrap();
+    assert_eq!(batch.len(), 7);
+    
+    // Nothing is visible until commit
+    assert!(storage.get_engram(&a.id).unwrap().is_none());
+    batch.commit().unwrap();
+    
+    assert!(storage.get_engram(&c.id).unwrap().is_some());
+    assert_eq!(storage.find_outgoing_connections(&a.id).unwrap(), HashSet::from([moved.id.clone()]));
+    assert_eq!(storage.find_incoming_connections(&c.id).unwrap(), HashSet::from([moved.id.clone()]));
+    assert!(storage.find_incoming_connections(&b.id).unwrap().is_empty());
+    assert!(storage.find_outgoing_connections(&b.id).unwrap().is_empty());
+    assert!(storage.find_connections_by_type("related").unwrap().is_empty());
+    assert!(storage.find_connections_by_type("supports").unwrap().is_empty());
+    assert!(storage.get_connection(&dropped.id).unwrap().is_none());
+    
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.engram_count, 3);
+    assert_eq!(stats.connection_count, 1);
+    
+    // Deletes go through the same single write
+    let mut batch = storage.batch();
+    batch.delete_connection(&moved.id).unwrap();
+    batch.delete_engram(&a.id).unwrap();
+    batch.delete_connection(&"missing".to_string()).unwrap();
+    assert_eq!(batch.len(), 2);
+    batch.commit().unwrap();
+    
+    assert!(storage.get_engram(&a.id).unwrap().is_none());
+    assert!(storage.find_connections_by_type("refines").unwrap().is_empty());
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.engram_count, 2);
+    assert_eq!(stats.connection_count, 0);
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_bulk_reads_preserve_order_and_report_missing() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let a = Engram::new("A".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("B".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&a).unwrap();
+    storage.put_engram(&b).unwrap();
+    let connection = Connection::new(a.id.clone(), b.id.clone(), "related".to_string(), 0.5, None);
+    storage.put_connection(&connection).unwrap();
+    
+    let ids = vec![b.id.clone(), "missing".to_string(), a.id.clone(), b.id.clone()];
+    let engrams = storage.get_engrams(&ids).unwrap();
+    let contents: Vec<Option<&str>> = engrams.iter().map(|e| e.as_ref().map(|e| e.content.as_str())).collect();
+    assert_eq!(contents, vec![Some("B"), None, Some("A"), Some("B")]);
+    
+    let connections = storage.get_connections(&["missing".to_string(), connection.id.clone()]).unwrap();
+    assert!(connections[0].is_none());
+    assert_eq!(connections[1].as_ref().unwrap().id, connection.id);
+    
+    assert!(storage.get_engrams(&[]).unwrap().is_empty());
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_checkpoint_is_consistent_copy() {
+    let db_path = get_test_db_path();
+    let checkpoint_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let a = Engram::new("A".to_string(), "test".to_string(), 0.9, None);
+    let b = Engram::new("B".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&a).unwrap();
+    storage.put_engram(&b).unwrap();
+    let connection = Connection::new(a.id.clone(), b.id.clone(), "related".to_string(), 0.5, None);
+    storage.put_connection(&connection).unwrap();
+    storage.put_embedding(&a.id, &Embedding::create(vec![1.0, 0.0], "test".to_string(), 2, HashMap::new())).unwrap();
+    
+    // An existing empty directory is accepted
+    fs::create_dir_all(&checkpoint_path).unwrap();
+    storage.create_checkpoint(Path::new(&checkpoint_path)).unwrap();
+    
+    // Writes after the checkpoint do not reach it
+    storage.delete_engram(&b.id).unwrap();
+    
+    let snapshot = Storage::open_read_only(&checkpoint_path).expect("Failed to open checkpoint");
+    assert!(snapshot.get_engram(&b.id).unwrap().is_some());
+    assert_eq!(snapshot.find_outgoing_connections(&a.id).unwrap(), HashSet::from([connection.id.clone()]));
+    assert!(snapshot.get_embedding(&a.id).unwrap().is_some());
+    assert_eq!(snapshot.get_stats().unwrap().engram_count, 2);
+    
+    // The primary stays writable while the snapshot is open, and the snapshot is not
+    storage.put_engram(&b).unwrap();
+    assert!(snapshot.put_engram(&b).is_err());
+    
+    // A non-empty directory is refused
+    let result = storage.create_checkpoint(Path::new(&checkpoint_path));
+    assert!(matches!(result, Err(EngramError::StorageError(_))));
+    
+    drop(snapshot);
+    drop(storage);
+    cleanup_test_db(&db_path);
+    cleanup_test_db(&checkpoint_path);
+}
+
+#[test]
+fn test_list_engrams_paged_is_stable_under_inserts() {
+    let db_path = get_test_db_path();
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let engram = Engram::new(format!("Engram {}", i), "test".to_string(), 0.9, None);
+        storage.put_engram(&engram).unwrap();
+        ids.push(engram.id);
+    }
+    ids.sort();
+    
+    let (first, cursor) = storage.list_engrams_paged(None, 2).unwrap();
+    assert_eq!(first, ids[0..2]);
+    assert_eq!(cursor.as_deref(), Some(ids[1].as_str()));
+    
+    // Engrams inserted mid-listing never repeat or skip ones already stored
+    let mut inserted = Vec::new();
+    for i in 0..5 {
+        let engram = Engram::new(format!("Late engram {}", i), "test".to_string(), 0.9, None);
+        storage.put_engram(&engram).unwrap();
+        inserted.push(engram.id);
+    }
+    
+    let mut seen = first.clone();
+    let mut cursor = cursor;
+    while let Some(after) = cursor {
+        let (page, next) = storage.list_engrams_paged(Some(&after), 2).unwrap();
+        assert!(page.len() <= 2);
+        assert!(page.iter().all(|id| *id > after));
+        seen.extend(page);
+        cursor = next;
+    }
+    
+    let mut expected: Vec<String> = ids.iter().cloned().chain(inserted.into_iter().filter(|id| *id > ids[1])).collect();
+    expected.sort();
+    assert_eq!(seen, expected);
+    assert!(storage.list_engrams_paged(None, 0).is_err());
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_rocksdb_tuning_options() {
+    let db_path = get_test_db_path();
+    let config = StorageConfig::new()
+        .with_compression(CompressionType::Zstd)
+        .with_block_cache_size(8 * 1024 * 1024)
+        .with_bloom_filter_bits_per_key(Some(12.0))
+        .with_write_buffer_size(4 * 1024 * 1024)
+        .with_statistics(true);
+    let storage = Storage::with_config(&db_path, config).expect("Failed to create storage");
+    
+    let engram = Engram::new("Tuned".to_string(), "test".to_string(), 0.9, None);
+    storage.put_engram(&engram).unwrap();
+    assert_eq!(storage.get_engram(&engram.id).unwrap().unwrap().content, "Tuned");
+    assert!(storage.statistics().is_some());
+    drop(storage);
+    
+    // Reopening with the defaults reads the same data, without statistics
+    let storage = Storage::new(&db_path).expect("Failed to reopen storage");
+    assert!(storage.get_engram(&engram.id).unwrap().is_some());
+    assert!(storage.statistics().is_none());
+    drop(storage);
+    cleanup_test_db(&db_path);
+    
+    // Unusable values are refused before the database is opened
+    let invalid = StorageConfig::new().with_bloom_filter_bits_per_key(Some(0.0));
+    assert!(matches!(Storage::with_config(&db_path, invalid), Err(EngramError::InvalidOperation(_))));
+    assert!(Storage::with_config(&db_path, StorageConfig::new().with_block_cache_size(0)).is_err());
+    cleanup_test_db(&db_path);
+}
+
+#[test]
+fn test_persisted_score_indexes() {
+    let db_path = get_test_db_path();
+    
+    let mut high = Engram::new("High".to_string(), "test".to_string(), 0.95, None);
+    high.set_importance(0.2);
+    let mut edge = Engram::new("Edge".to_string(), "test".to_string(), 0.705, None);
+    edge.set_importance(0.9);
+    let low = Engram::new("Low".to_string(), "test".to_string(), 0.3, None);
+    
+    {
+        let storage = Storage::new(&db_path).expect("Failed to create storage");
+        storage.put_engram(&high).unwrap();
+        storage.put_engram(&low).unwrap();
+        let mut txn = storage.begin_transaction();
+        txn.put_engram(&edge).unwrap();
+        txn.commit().unwrap();
+        
+        // Scores in the same bucket as the minimum are compared exactly
+        assert_eq!(storage.find_by_min_confidence(0.7).unwrap(), HashSet::from([high.id.clone(), edge.id.clone()]));
+        assert_eq!(storage.find_by_min_confidence(0.71).unwrap(), HashSet::from([high.id.clone()]));
+        assert_eq!(storage.find_by_min_confidence(0.0).unwrap().len(), 3);
+        assert!(storage.find_by_min_confidence(1.5).unwrap().is_empty());
+        assert_eq!(storage.find_by_min_importance(0.8).unwrap(), HashSet::from([edge.id.clone()]));
+        
+        // Updates move the engram between buckets, and deletes remove it
+        let mut lowered = high.clone();
+        lowered.confidence = 0.1;
+        storage.put_engram(&lowered).unwrap();
+        assert_eq!(storage.find_by_min_confidence(0.7).unwrap(), HashSet::from([edge.id.clone()]));
+        
+        let mut batch = storage.batch();
+        batch.delete_engram(&edge.id).unwrap();
+        batch.commit().unwrap();
+        assert!(storage.find_by_min_confidence(0.7).unwrap().is_empty());
+        assert!(storage.find_by_min_importance(0.8).unwrap().is_empty());
+        assert_eq!(storage.find_by_min_confidence(0.0).unwrap().len(), 2);
+        
+        // Simulate a v5 database without the indexes
+        let metadata = storage.db.cf_handle("metadata").unwrap();
+        let keys: Vec<Box<[u8]>> = storage.db
+            .iterator_cf(metadata, rocksdb::IteratorMode::Start)
+            .map(|item| item.unwrap().0)
+            .filter(|key| key.starts_with(b"conf:") || key.starts_with(b"imp:"))
+            .collect();
+        for key in keys {
+            storage.db.delete_cf(metadata, key).unwrap();
+        }
+        storage.db.put_cf(metadata, b"schema_version", 5u32.to_le_bytes()).unwrap();
+        assert!(storage.find_by_min_confidence(0.0).unwrap().is_empty());
+    }
+    
+    // Reopening builds the indexes from the stored engrams
+    let storage = Storage::new(&db_path).expect("Failed to reopen storage");
+    assert_eq!(storage.schema_version().unwrap(), CURRENT_SCHEMA_VERSION);
+    assert_eq!(storage.find_by_min_confidence(0.0).unwrap(), HashSet::from([high.id.clone(), low.id.clone()]));
+    assert_eq!(storage.find_by_min_confidence(0.2).unwrap(), HashSet::from([low.id.clone()]));
+    
+    drop(storage);
+    cleanup_test_db(&db_path);
+}
@@ -1,7 +1,14 @@
-use engram_lite::embedding::{Embedding, EmbeddingModel, EmbeddingService};
-use engram_lite::schema::Engram;
-use engram_lite::vector_search::VectorIndex;
+use engram_lite::embedding::{DeterministicBackend, Embedding, EmbeddingModel, EmbeddingService};
+use engram_lite::schema::{Connection, Engram};
+use engram_lite::embedding::HnswIndex;
+use engram_lite::storage::{self, Storage};
+use engram_lite::index::SearchIndex;
+use engram_lite::vector_search::{
+    compute_similarity_matrix, embed_missing, estimate_tokens, exact_knn_scan, reconcile_vector_index, verify_vector_index, CombinationMethod,
+    HybridQuery, HybridSearchEngine, QueryExpander, VectorIndex,
+};
 use engram_lite::utils;
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -15,20 +22,18 @@ fn load_env() {
         let file = File::open(env_path).expect("Failed to open .env file");
         let reader = BufReader::new(file);
 
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
+        for line in reader.lines().map_while(Result::ok) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
 
-                if let Some(equal_sign_pos) = line.find('=') {
-                    let key = line[..equal_sign_pos].trim();
-                    let value = line[equal_sign_pos + 1..].trim();
-                    
-                    // Only set if not already set
-                    if env::var(key).is_err() {
-                        env::set_var(key, value);
-                    }
+            if let Some(equal_sign_pos) = line.find('=') {
+                let key = line[..equal_sign_pos].trim();
+                let value = line[equal_sign_pos + 1..].trim();
+                
+                // Only set if not already set
+                if env::var(key).is_err() {
+                    env::set_var(key, value);
                 }
             }
         }
@@ -42,7 +47,7 @@ fn test_vector_index_creation() {
     
     // Basic sanity check
     assert!(vector_index.dimensions() > 0);
-    assert_eq!(vector_index.len(), 0);
+    assert_eq!(vector_index.len().unwrap(), 0);
 }
 
 #[test]
@@ -58,7 +63,7 @@ fn test_vector_index_with_embedding_service() {
     
     // Verify dimensions match the model
     assert_eq!(vector_index.dimensions(), 1024);
-    assert_eq!(vector_index.len(), 0);
+    assert_eq!(vector_index.len().unwrap(), 0);
 }
 
 #[test]
@@ -106,7 +111,7 @@ fn test_vector_index_add_and_search() {
     vector_index.add_engram(&engram3).expect("Failed to add engram3");
     
     // Verify engrams were added
-    assert_eq!(vector_index.len(), 3);
+    assert_eq!(vector_index.len().unwrap(), 3);
     
     // Search for similar engrams
     let results = vector_index.search("fox jumping over dog", 2).expect("Search failed");
@@ -116,7 +121,7 @@ fn test_vector_index_add_and_search() {
     
     // The first two engrams should be returned as they're semantically similar
     // They should be returned in order of similarity
-    let result_ids: Vec<String> = results.iter().map(|r| r.id.clone()).collect();
+    let result_ids: Vec<String> = results.iter().map(|r| r.0.clone()).collect();
     assert!(result_ids.contains(&engram1.id));
     assert!(result_ids.contains(&engram2.id));
     
@@ -151,13 +156,13 @@ fn test_vector_index_delete() {
     vector_index.add_engram(&engram).expect("Failed to add engram");
     
     // Verify engram was added
-    assert_eq!(vector_index.len(), 1);
+    assert_eq!(vector_index.len().unwrap(), 1);
     
     // Delete the engram
-    vector_index.delete_engram(&engram_id).expect("Failed to delete engram");
+    vector_index.remove_engram(&engram_id).expect("Failed to delete engram");
     
     // Verify engram was removed
-    assert_eq!(vector_index.len(), 0);
+    assert_eq!(vector_index.len().unwrap(), 0);
     
     // Search should return empty results
     let results = vector_index.search("test content", 10).expect("Search failed");
@@ -175,7 +180,9 @@ fn test_hybrid_search() {
         return;
     }
     
-    // Create vector index
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut search_index = SearchIndex::new();
     let vector_index = VectorIndex::new();
     
     // Create test engrams with specific keywords
@@ -200,28 +207,422 @@ fn test_hybrid_search() {
         None,
     );
     
-    // Add engrams to the index
-    vector_index.add_engram(&engram1).expect("Failed to add engram1");
-    vector_index.add_engram(&engram2).expect("Failed to add engram2");
-    vector_index.add_engram(&engram3).expect("Failed to add engram3");
+    // Add engrams to storage and both indexes
+    for engram in [&engram1, &engram2, &engram3] {
+        storage.put_engram(engram).expect("Failed to store engram");
+        search_index.add_engram(engram).expect("Failed to index engram");
+        vector_index.add_engram(engram).expect("Failed to add engram");
+    }
     
-    // Perform hybrid search combining vector similarity and keyword matching
-    let results = vector_index.hybrid_search(
-        "machine learning",  // Semantic query
-        &["technology"],     // Required keywords
-        None,               // No excluded keywords
-        None,               // No minimum confidence
-        None,               // No source filter
-        10                  // Limit
-    ).expect("Hybrid search failed");
+    // Perform hybrid search combining vector similarity and keyword matching,
+    // restricted to the technology source
+    let engine = HybridSearchEngine::new(&storage, &search_index, &vector_index);
+    let query = HybridQuery::new()
+        .with_text("machine learning")
+        .with_source("technology")
+        .with_limit(10);
+    let results = engine.search(&query).expect("Hybrid search failed");
     
     // Check that we got appropriate results
-    assert!(results.len() > 0);
+    assert!(!results.is_empty());
     
     // The technology-related engram should be returned
-    assert!(results.iter().any(|r| r.id == engram3.id));
+    assert!(results.iter().any(|r| r.engram.id == engram3.id));
     
     // The animal-related engrams should not be returned even if semantically similar
-    assert!(!results.iter().any(|r| r.id == engram1.id));
-    assert!(!results.iter().any(|r| r.id == engram2.id));
-}
\ No newline at end of file
+    assert!(!results.iter().any(|r| r.engram.id == engram1.id));
+    assert!(!results.iter().any(|r| r.engram.id == engram2.id));
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_vector_index_consistency_check() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = HnswIndex::new(3);
+    
+    // Store and index two embeddings
+    for (id, vector) in [("a", vec![1.0, 0.0, 0.0]), ("b", vec![0.0, 1.0, 0.0])] {
+        let stored = storage::Embedding::create(vector.clone(), "test".to_string(), 3, Default::default());
+        storage.put_embedding(&id.to_string(), &stored).unwrap();
+        index.add(id, Embedding::new(vector, "test".to_string())).unwrap();
+    }
+    assert!(verify_vector_index(&storage, &index).unwrap().is_consistent());
+    
+    // Delete one stored embedding without updating the index, and store another without indexing it
+    storage.delete_embedding(&"a".to_string()).unwrap();
+    let stored = storage::Embedding::create(vec![0.0, 0.0, 1.0], "test".to_string(), 3, Default::default());
+    storage.put_embedding(&"c".to_string(), &stored).unwrap();
+    
+    let report = verify_vector_index(&storage, &index).unwrap();
+    assert!(!report.is_consistent());
+    assert_eq!(report.missing_from_storage, vec!["a".to_string()]);
+    assert_eq!(report.missing_from_index, vec!["c".to_string()]);
+    
+    // Reconciling fixes the drift
+    reconcile_vector_index(&storage, &mut index).unwrap();
+    assert!(verify_vector_index(&storage, &index).unwrap().is_consistent());
+    assert!(!index.contains("a"));
+    assert!(index.contains("c"));
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_exact_knn_matches_naive_scan() {
+    let vector_index = VectorIndex::new();
+    let dimensions = vector_index.get_embedding_service().get_dimensions();
+    
+    let mut embeddings = Vec::new();
+    for i in 0..40 {
+        let vector: Vec<f32> = (0..dimensions).map(|d| ((i * 7 + d) as f32 * 0.37).sin()).collect();
+        let mut engram = Engram::new(format!("engram {}", i), "test".to_string(), 0.5, None);
+        engram.metadata.insert("embedding_vector".to_string(), serde_json::json!(vector));
+        vector_index.add_engram(&engram).unwrap();
+        embeddings.push((engram.id.clone(), Embedding::new(vector, "test".to_string())));
+    }
+    
+    let query = Embedding::new((0..dimensions).map(|d| (d as f32 * 0.11).cos()).collect(), "test".to_string());
+    
+    // Naive single-threaded scan
+    let mut expected: Vec<(String, f32)> = embeddings
+        .iter()
+        .map(|(id, embedding)| (id.clone(), query.cosine_similarity(embedding).unwrap()))
+        .collect();
+    expected.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+    expected.truncate(10);
+    
+    let results = vector_index.exact_knn(&query, 10).unwrap();
+    assert_eq!(results, expected);
+    assert!(results.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    
+    // The free function agrees and skips candidates with mismatched dimensions
+    let short = Embedding::new(vec![1.0, 0.0], "test".to_string());
+    let mut candidates: Vec<(&str, &Embedding)> = embeddings.iter().map(|(id, e)| (id.as_str(), e)).collect();
+    candidates.push(("short", &short));
+    assert_eq!(exact_knn_scan(&candidates, &query, 10), expected);
+}
+
+#[test]
+fn test_retrieve_context_within_budget() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut search_index = SearchIndex::new();
+    let vector_index = VectorIndex::new();
+    
+    let contents = [
+        "The volcano erupted at dawn and covered the valley in ash",
+        "Scientists monitor volcano tremors with seismographs",
+        "A dormant volcano can reawaken after centuries of silence",
+        "Bread rises when yeast ferments the sugars in dough",
+        "The orchestra tuned their instruments before the concert",
+    ];
+    let mut volcano_ids = Vec::new();
+    for content in contents {
+        let engram = Engram::new(content.to_string(), "test".to_string(), 0.9, None);
+        storage.put_engram(&engram).unwrap();
+        search_index.add_engram(&engram).unwrap();
+        vector_index.add_engram(&engram).unwrap();
+        if content.contains("volcano") {
+            volcano_ids.push(engram.id.clone());
+        }
+    }
+    
+    let engine = HybridSearchEngine::new(&storage, &search_index, &vector_index);
+    // With max combination, keyword matches score 1.0 and vector-only matches score below it
+    let query = HybridQuery::new()
+        .with_text("volcano")
+        .with_combination_method(CombinationMethod::Max);
+    
+    // Enough budget for two of the three keyword matches, but not all three
+    let block_tokens = estimate_tokens(&format!("[{}] {}", volcano_ids[0], contents[0]));
+    let max_tokens = block_tokens * 2 + 10;
+    let retrieved = engine.retrieve(&query, max_tokens, 0.999).unwrap();
+    
+    assert!(retrieved.token_count <= max_tokens);
+    assert!(estimate_tokens(&retrieved.context) <= max_tokens);
+    assert_eq!(retrieved.sources.len(), 2);
+    for source in &retrieved.sources {
+        assert!(volcano_ids.contains(&source.engram_id), "cited a non-matching engram");
+        assert!(source.score >= 0.999);
+        assert!(retrieved.context.contains(&format!("[{}]", source.engram_id)));
+    }
+    
+    // A tiny budget yields an empty context rather than an over-budget one
+    let empty = engine.retrieve(&query, 5, 0.0).unwrap();
+    assert!(empty.sources.is_empty());
+    assert_eq!(empty.token_count, 0);
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_query_expansion_finds_synonyms() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut search_index = SearchIndex::new();
+    let vector_index = VectorIndex::new();
+    let dimensions = vector_index.get_embedding_service().get_dimensions();
+    
+    // Give the car engram a known embedding that stands in for the shared "vehicle" meaning
+    let vehicle_vector: Vec<f32> = (0..dimensions).map(|d| if d % 2 == 0 { 1.0 } else { 0.5 }).collect();
+    let mut car = Engram::new("I parked the car outside the office".to_string(), "test".to_string(), 0.9, None);
+    car.metadata.insert("embedding_vector".to_string(), serde_json::json!(vehicle_vector));
+    let bread = Engram::new("Fresh bread from the bakery".to_string(), "test".to_string(), 0.9, None);
+    
+    for engram in [&car, &bread] {
+        storage.put_engram(engram).unwrap();
+        search_index.add_engram(engram).unwrap();
+        vector_index.add_engram(engram).unwrap();
+    }
+    
+    // Without expansion the keyword search misses the synonym
+    assert!(!search_index.text_index.search("automobile").contains(&car.id));
+    
+    // With expansion, terms from the nearest engram are ORed into the query
+    let query_embedding = Embedding::new(vehicle_vector, "test".to_string());
+    let expander = QueryExpander::new(&storage, &vector_index).with_max_terms(3).with_neighbors(1);
+    let terms = expander.expand_with_embedding("automobile", &query_embedding).unwrap();
+    assert!(terms.contains(&"car".to_string()));
+    assert!(terms.len() <= 3);
+    
+    let expanded = format!("automobile {}", terms.join(" "));
+    let results = search_index.text_index.search(&expanded);
+    assert!(results.contains(&car.id));
+    assert!(!results.contains(&bread.id));
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_collection_scoped_semantic_search() {
+    let vector_index = VectorIndex::new();
+    let mut collection = engram_lite::schema::Collection::new("Scoped".to_string(), "test".to_string(), None);
+    
+    let mut all_ids = Vec::new();
+    for i in 0..12 {
+        let engram = Engram::new(format!("note number {} about topic {}", i, i % 3), "test".to_string(), 0.9, None);
+        vector_index.add_engram(&engram).unwrap();
+        if i % 2 == 0 {
+            collection.add_engram(engram.id.clone());
+        }
+        all_ids.push(engram.id);
+    }
+    vector_index.sync_collection(&collection).unwrap();
+    
+    // Scoped search never leaves the collection
+    let scoped = vector_index.semantic_search("topic 1", 3, Some(&collection.id)).unwrap();
+    assert_eq!(scoped.len(), 3);
+    assert!(scoped.iter().all(|(id, _)| collection.engram_ids.contains(id)));
+    
+    // It matches the global exact search restricted to the collection; equally
+    // similar engrams may come in either order, so compare the scores
+    let query = vector_index.get_embedding_service().embed_text("topic 1").unwrap();
+    let expected: Vec<(String, f32)> = vector_index
+        .exact_knn(&query, all_ids.len())
+        .unwrap()
+        .into_iter()
+        .filter(|(id, _)| collection.engram_ids.contains(id))
+        .take(3)
+        .collect();
+    let scoped_scores: Vec<f32> = scoped.iter().map(|(_, score)| *score).collect();
+    let expected_scores: Vec<f32> = expected.iter().map(|(_, score)| *score).collect();
+    assert_eq!(scoped_scores, expected_scores);
+    assert_eq!(scoped[0].0, expected[0].0);
+    
+    // Engrams leaving the collection drop out of the sub-index
+    let leaving = expected[0].0.clone();
+    collection.remove_engram(&leaving);
+    vector_index.sync_collection(&collection).unwrap();
+    let scoped = vector_index.semantic_search("topic 1", 6, Some(&collection.id)).unwrap();
+    assert_eq!(scoped.len(), collection.engram_ids.len());
+    assert!(scoped.iter().all(|(id, _)| *id != leaving));
+    
+    // An unknown collection yields no results
+    assert!(vector_index.semantic_search("topic 1", 3, Some(&"missing".to_string())).unwrap().is_empty());
+}
+
+#[test]
+fn test_similarity_matrix_is_symmetric() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    
+    let mut ids = Vec::new();
+    for i in 0..6 {
+        let id = format!("engram-{}", i);
+        let vector: Vec<f32> = (0..8).map(|d| ((i * 3 + d) as f32 * 0.7).sin()).collect();
+        let stored = storage::Embedding::create(vector, "test".to_string(), 8, Default::default());
+        storage.put_embedding(&id, &stored).unwrap();
+        ids.push(id);
+    }
+    
+    // Request in a non-sorted order with one engram that has no embedding
+    let requested = vec![
+        ids[3].clone(), ids[0].clone(), "missing".to_string(), ids[5].clone(), ids[1].clone(),
+    ];
+    
+    let matrix = compute_similarity_matrix(&requested, &storage, true).unwrap();
+    assert_eq!(matrix.ids, vec![ids[3].clone(), ids[0].clone(), ids[5].clone(), ids[1].clone()]);
+    assert_eq!(matrix.values.len(), 4);
+    
+    for i in 0..4 {
+        assert_eq!(matrix.values[i].len(), 4);
+        assert_eq!(matrix.values[i][i], 1.0);
+        for j in 0..4 {
+            assert_eq!(matrix.values[i][j], matrix.values[j][i]);
+        }
+    }
+    
+    // Values match a direct pairwise computation in label order
+    let a = storage.get_embedding(&ids[3]).unwrap().unwrap();
+    let b = storage.get_embedding(&ids[1]).unwrap().unwrap();
+    let a = Embedding::new(a.vector, a.model);
+    let b = Embedding::new(b.vector, b.model);
+    assert!((matrix.values[0][3] - a.cosine_similarity(&b).unwrap()).abs() < 1e-6);
+    
+    // Without skipping, a missing embedding is an error
+    assert!(compute_similarity_matrix(&requested, &storage, false).is_err());
+    
+    drop(storage);
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_similarity_floor_limits_suggestions() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let vector_index = VectorIndex::new();
+    let dimensions = vector_index.get_embedding_service().get_dimensions();
+    
+    // Engrams at known cosine similarities to the anchor: cos(theta) of a vector rotated off its axis
+    let vector_at = |similarity: f32| -> Vec<f32> {
+        let mut vector = vec![0.0; dimensions];
+        vector[0] = similarity;
+        vector[1] = (1.0 - similarity * similarity).sqrt();
+        vector
+    };
+    let engram_with = |content: &str, vector: Vec<f32>| {
+        let mut engram = Engram::new(content.to_string(), "test".to_string(), 0.9, None);
+        engram.metadata.insert("embedding_vector".to_string(), serde_json::json!(vector));
+        storage.put_engram(&engram).unwrap();
+        vector_index.add_engram(&engram).unwrap();
+        engram
+    };
+    let anchor = engram_with("anchor", vector_at(1.0));
+    let close = engram_with("close", vector_at(0.9));
+    let related = engram_with("related", vector_at(0.6));
+    let _loose = engram_with("loose", vector_at(0.35));
+    let _noise = engram_with("noise", vector_at(0.1));
+    
+    // Nothing below the floor is returned, and scores come back for further thresholding
+    let similar = vector_index.similar_to(&anchor.id, 10, Some(0.5)).unwrap();
+    assert_eq!(similar.len(), 2);
+    assert!(similar.iter().all(|(id, score)| *id != anchor.id && *score >= 0.5));
+    
+    // Lowering the floor yields more results; raising it yields strictly fewer
+    let lower = vector_index.similar_to(&anchor.id, 10, Some(0.2)).unwrap();
+    let higher = vector_index.similar_to(&anchor.id, 10, Some(0.8)).unwrap();
+    assert!(lower.len() > similar.len());
+    assert!(higher.len() < similar.len());
+    assert_eq!(higher[0].0, close.id);
+    
+    // The index default applies when no floor is given
+    let default_floor = vector_index.similar_to(&anchor.id, 10, None).unwrap();
+    assert!(default_floor.iter().all(|(_, score)| *score >= vector_index.min_similarity()));
+    
+    // Suggestions skip engrams that are already connected
+    let connection = Connection::new(anchor.id.clone(), close.id.clone(), "relates_to".to_string(), 0.9, None);
+    storage.put_connection(&connection).unwrap();
+    let suggestions = vector_index.suggest_connections(&storage, &anchor.id, 10, Some(0.5)).unwrap();
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0].0, related.id);
+    let fewer = vector_index.suggest_connections(&storage, &anchor.id, 10, Some(0.7)).unwrap();
+    assert!(fewer.is_empty());
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_embed_missing_embeds_every_engram() {
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let service = EmbeddingService::with_backend(Box::new(DeterministicBackend::new("test", 16)));
+    let mut index = HnswIndex::new(16);
+    
+    let mut engrams = Vec::new();
+    for i in 0..5 {
+        let engram = Engram::new(format!("engram number {}", i), "test".to_string(), 0.9, None);
+        storage.put_engram(&engram).unwrap();
+        engrams.push(engram);
+    }
+    
+    // One engram is already embedded and is left alone
+    let existing = storage::Embedding::create(vec![1.0; 16], "existing".to_string(), 16, HashMap::new());
+    storage.put_embedding(&engrams[0].id, &existing).unwrap();
+    
+    // Batches smaller than the backlog still cover every engram
+    assert_eq!(embed_missing(&storage, &service, &mut index, 2).unwrap(), 4);
+    for engram in &engrams {
+        assert!(storage.get_embedding(&engram.id).unwrap().is_some(), "engram {} has no embedding", engram.id);
+    }
+    assert_eq!(storage.get_embedding(&engrams[0].id).unwrap().unwrap().model, "existing");
+    assert_eq!(index.len(), 4);
+    assert!(engrams[1..].iter().all(|engram| index.contains(&engram.id)));
+    
+    // A second run finds nothing left to do
+    assert_eq!(embed_missing(&storage, &service, &mut index, 2).unwrap(), 0);
+    assert!(embed_missing(&storage, &service, &mut index, 0).is_err());
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[test]
+fn test_truncated_embeddings_search_end_to_end() {
+    let service = Arc::new(
+        EmbeddingService::with_backend(Box::new(DeterministicBackend::new("test", 1024))).with_target_dimensions(256),
+    );
+    assert_eq!(service.get_dimensions(), 256);
+    
+    // Truncated vectors are renormalized
+    let embedding = service.embed_text("The agent stores long term memories in a graph database").unwrap();
+    assert_eq!(embedding.vector.len(), 256);
+    assert_eq!(embedding.dimensions, 256);
+    let norm: f32 = embedding.vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-5, "norm was {}", norm);
+    assert_eq!(embedding.metadata.get("truncated_from").map(String::as_str), Some("1024"));
+    
+    // The vector index is sized from the service and accepts the truncated vectors
+    let vector_index = VectorIndex::with_embedding_service(service.clone());
+    assert_eq!(vector_index.dimensions(), 256);
+    
+    let contents = [
+        "The agent stores long term memories in a graph database",
+        "Bananas are a good source of potassium",
+        "Rust ownership rules prevent data races",
+    ];
+    let engrams: Vec<Engram> = contents
+        .iter()
+        .map(|content| Engram::new(content.to_string(), "test".to_string(), 0.9, None))
+        .collect();
+    for engram in &engrams {
+        vector_index.add_engram(engram).unwrap();
+    }
+    
+    let results = vector_index.search("Rust ownership rules prevent data races", 1).unwrap();
+    assert_eq!(results[0].0, engrams[2].id);
+    
+    // Embeddings written to storage have the same truncated size as the HNSW index
+    let db_path = format!("./test_db_{}", uuid::Uuid::new_v4());
+    let storage = Storage::new(&db_path).expect("Failed to create storage");
+    let mut index = HnswIndex::new(service.get_dimensions());
+    for engram in &engrams {
+        storage.put_engram(engram).unwrap();
+    }
+    assert_eq!(embed_missing(&storage, &service, &mut index, 2).unwrap(), 3);
+    for engram in &engrams {
+        assert_eq!(storage.get_embedding(&engram.id).unwrap().unwrap().dimensions, 256);
+    }
+    
+    let _ = std::fs::remove_dir_all(&db_path);
+}